@@ -0,0 +1,38 @@
+const test = require('ava')
+
+const { getAdditionalActions, removeAdditionalActions } = require('../index')
+
+const { pageReferences, resolve } = require('./helpers')
+const { form, simple } = require('./pdf')
+
+// A script run when the document closes, and a launch when page 2 opens
+const triggered = () =>
+  simple(2, {
+    catalog: '/AA << /WC << /S /JavaScript /JS (app.alert\\(1\\)) >> >>',
+    page: (index) => (index === 1 ? '/AA << /O << /S /Launch /F (calc.exe) >> >>' : ''),
+  })
+
+test('getAdditionalActions lists the document and page actions', (t) => {
+  t.deepEqual(getAdditionalActions(triggered()), [
+    { scope: 'document', trigger: 'WC', type: 'JavaScript' },
+    { scope: 'page', trigger: 'O', type: 'Launch', page: 2 },
+  ])
+  t.deepEqual(getAdditionalActions(simple(1)), [])
+})
+
+test('removeAdditionalActions removes the actions of the given scope', (t) => {
+  const pageless = removeAdditionalActions(triggered(), 'page')
+  t.deepEqual(getAdditionalActions(pageless), [{ scope: 'document', trigger: 'WC', type: 'JavaScript' }])
+  t.is(resolve(pageless, pageReferences(pageless)[1])['/AA'], undefined)
+  t.deepEqual(getAdditionalActions(removeAdditionalActions(triggered(), 'document')), [
+    { scope: 'page', trigger: 'O', type: 'Launch', page: 2 },
+  ])
+  t.deepEqual(getAdditionalActions(removeAdditionalActions(triggered(), 'all')), [])
+  t.throws(() => removeAdditionalActions(triggered(), 'annotation'), { code: 'InvalidArg' })
+})
+
+test('field actions are listed with the field name', (t) => {
+  const keystroke = form(['name'], { field: () => '/AA << /K << /S /JavaScript /JS (event.rc = true) >> >>' })
+  t.deepEqual(getAdditionalActions(keystroke), [{ scope: 'field', trigger: 'K', type: 'JavaScript', field: 'name' }])
+  t.deepEqual(getAdditionalActions(removeAdditionalActions(keystroke, 'field')), [])
+})
@@ -0,0 +1,22 @@
+const test = require('ava')
+
+const { allocatorInfo } = require('../index')
+
+// The allocator a default build picks for the platform the specs run on
+function expectedAllocator(debug) {
+  const glibc = process.platform === 'linux' && Boolean(process.report.getReport().header.glibcVersionRuntime)
+  const unix = process.platform !== 'win32'
+  if (!debug && unix && (glibc || process.platform !== 'linux') && !['arm', 'arm64'].includes(process.arch)) {
+    return 'jemalloc'
+  }
+  if (process.platform === 'win32' && process.arch === 'x64') {
+    return 'mimalloc'
+  }
+  return 'system'
+}
+
+test('allocatorInfo reports the allocator of the build for this platform', (t) => {
+  const { name, debug } = allocatorInfo()
+  t.is(typeof debug, 'boolean')
+  t.is(name, expectedAllocator(debug))
+})
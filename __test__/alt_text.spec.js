@@ -0,0 +1,39 @@
+const test = require('ava')
+
+const { getPageContent, setImageAltText } = require('../index')
+
+const { catalog, resolve } = require('./helpers')
+const { simple } = require('./pdf')
+
+// One page drawing the image Im1, object 6, below its text
+const withImage = () =>
+  simple(1, {
+    resources: () => '<< /Font << /F1 3 0 R >> /XObject << /Im1 6 0 R >> >>',
+    extra: (objects) => {
+      objects[3] = { stream: 'BT /F1 24 Tf 72 700 Td (Page 1) Tj ET\nq 100 0 0 100 72 500 cm /Im1 Do Q' }
+      objects.push({
+        dict: '/Type /XObject /Subtype /Image /Width 1 /Height 1 /ColorSpace /DeviceGray /BitsPerComponent 8',
+        stream: Buffer.from([0]),
+      })
+    },
+  })
+
+test('setImageAltText tags an untagged document with a Figure carrying the alt text', (t) => {
+  const described = setImageAltText(withImage(), [{ page: 1, imageName: 'Im1', alt: 'A black square' }])
+  const { '/MarkInfo': markInfo, '/StructTreeRoot': rootReference } = catalog(described)
+  t.deepEqual(markInfo, { '/Marked': true })
+  const root = resolve(described, rootReference)
+  const [document] = root['/K'].map((kid) => resolve(described, kid))
+  t.is(document['/S'], '/Document')
+  const [figure] = document['/K'].map((kid) => resolve(described, kid))
+  t.like(figure, { '/S': '/Figure', '/Alt': 'u:A black square', '/K': 0 })
+  t.is(resolve(described, figure['/Pg'])['/Type'], '/Page')
+  // The image is drawn as the marked content the Figure points at
+  t.regex(getPageContent(described, 1).toString('latin1'), /\/Figure <<\/MCID 0>> BDC\s+\/Im1 Do\s+EMC/)
+})
+
+test('setImageAltText throws when the page does not draw the image', (t) => {
+  t.throws(() => setImageAltText(withImage(), [{ page: 1, imageName: 'Im2', alt: 'Missing' }]), {
+    message: "Page 1 doesn't draw an image named Im2",
+  })
+})
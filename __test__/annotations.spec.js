@@ -0,0 +1,52 @@
+const test = require('ava')
+
+const { flattenAnnotations, getObject, getPageContent } = require('../index')
+
+const { resolve } = require('./helpers')
+const { pageObject, simple } = require('./pdf')
+
+const appearance = (color) => ({
+  dict: '/Type /XObject /Subtype /Form /BBox [0 0 100 20]',
+  stream: `${color} rg 0 0 100 20 re f`,
+})
+
+// A highlight with its pop-up note, a stamp, and a square without an appearance
+const annotated = () =>
+  simple(1, {
+    page: () => '/Annots [6 0 R 8 0 R 9 0 R 11 0 R]',
+    extra: (objects) =>
+      objects.push(
+        '<< /Type /Annot /Subtype /Highlight /Rect [72 690 172 710] /AP << /N 7 0 R >> /Popup 8 0 R >>',
+        appearance('1 1 0'),
+        '<< /Type /Annot /Subtype /Popup /Rect [200 600 300 700] /Parent 6 0 R >>',
+        '<< /Type /Annot /Subtype /Stamp /Rect [300 300 400 320] /AP << /N 10 0 R >> >>',
+        appearance('1 0 0'),
+        '<< /Type /Annot /Subtype /Square /Rect [0 0 10 10] >>',
+      ),
+  })
+
+// The `/Do` of every XObject the page content draws, with the matrix before it
+const drawn = (buffer) => {
+  const xObjects = resolve(buffer, getObject(buffer, pageObject(1), 0)['/Resources'])['/XObject']
+  const content = getPageContent(buffer, 1).toString('latin1')
+  const draws = [...content.matchAll(/([\d.\s-]+) cm\s+\/(\w+) Do/g)]
+  return draws.map(([, matrix, name]) => [matrix.trim(), xObjects[`/${name}`]])
+}
+
+test('flattenAnnotations draws the appearances into the page and removes the annotations', (t) => {
+  const flattened = flattenAnnotations(annotated())
+  t.deepEqual(drawn(flattened), [
+    ['1 0 0 1 72 690', '7 0 R'],
+    ['1 0 0 1 300 300', '10 0 R'],
+  ])
+  t.deepEqual(getObject(flattened, pageObject(1), 0)['/Annots'], ['11 0 R'])
+  // The highlight and its pop-up are gone
+  t.throws(() => getObject(flattened, 6, 0))
+  t.throws(() => getObject(flattened, 8, 0))
+})
+
+test('flattenAnnotations with types flattens only those annotations', (t) => {
+  const flattened = flattenAnnotations(annotated(), { types: ['Stamp'] })
+  t.deepEqual(drawn(flattened), [['1 0 0 1 300 300', '10 0 R']])
+  t.deepEqual(getObject(flattened, pageObject(1), 0)['/Annots'], ['6 0 R', '8 0 R', '11 0 R'])
+})
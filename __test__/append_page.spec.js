@@ -0,0 +1,36 @@
+const test = require('ava')
+
+const { appendPage, validate } = require('../index')
+
+const { pageReferences, pageTexts, resolve } = require('./helpers')
+const { simple } = require('./pdf')
+
+test('appendPage appends the first page of the other document last', (t) => {
+  const appended = appendPage(simple(2, { label: 'Base' }), simple(2, { label: 'Extra' }))
+  t.true(validate(appended).ok)
+  t.deepEqual(pageTexts(appended), ['Base 1', 'Base 2', 'Extra 1'])
+})
+
+test('the appended page takes the attributes it inherited along', (t) => {
+  // A 300 x 400 page whose MediaBox, Resources and Rotate come from its page tree
+  const inheriting = simple(1, {
+    label: 'Extra',
+    width: 300,
+    height: 400,
+    extra: (objects) => {
+      objects[1] = objects[1].replace(
+        ' >>',
+        ' /MediaBox [0 0 300 400] /Resources << /Font << /F1 3 0 R >> >> /Rotate 90 >>',
+      )
+      objects[4] = objects[4].replace('/MediaBox [0 0 300 400] ', '').replace('/Resources << /Font << /F1 3 0 R >> >> ', '')
+    },
+  })
+  t.is(resolve(inheriting, pageReferences(inheriting)[0])['/MediaBox'], undefined)
+  const appended = appendPage(simple(2, { label: 'Base' }), inheriting)
+  const page = resolve(appended, pageReferences(appended)[2])
+  t.deepEqual(page['/MediaBox'], [0, 0, 300, 400])
+  t.is(page['/Rotate'], 90)
+  const font = resolve(appended, resolve(appended, page['/Resources'])['/Font']['/F1'])
+  t.is(font['/BaseFont'], '/Helvetica')
+  t.deepEqual(pageTexts(appended), ['Base 1', 'Base 2', 'Extra 1'])
+})
@@ -0,0 +1,42 @@
+const test = require('ava')
+
+const { insertBlankPage, PdfPipeline } = require('../index')
+
+const { pageReferences, pageTexts, resolve } = require('./helpers')
+const { simple } = require('./pdf')
+
+const pages = (buffer) => pageReferences(buffer).map((page) => resolve(buffer, page))
+
+test('insertBlankPage adds an A3 landscape page at the position given', (t) => {
+  const inserted = insertBlankPage(simple(2), 2, { size: 'A3-landscape' })
+  t.deepEqual(pageTexts(inserted), ['Page 1', '', 'Page 2'])
+  const blank = pages(inserted)[1]
+  t.deepEqual(blank['/MediaBox'], [0, 0, 1190.55, 841.89])
+  t.is(blank['/Contents'], undefined)
+  t.is(resolve(inserted, blank['/Parent'])['/Count'], 3)
+})
+
+test('insertBlankPage takes the size and rotation of the page before it by default', (t) => {
+  const rotated = simple(2, { width: 595, height: 842, page: (index) => (index === 1 ? '/Rotate 90' : '') })
+  const inserted = insertBlankPage(rotated, 3)
+  const blank = pages(inserted)[2]
+  t.deepEqual(blank['/MediaBox'], [0, 0, 595, 842])
+  t.is(blank['/Rotate'], 90)
+  // Inserted first, it takes after the unrotated first page
+  t.is(pages(insertBlankPage(rotated, 1))[0]['/Rotate'], undefined)
+})
+
+test('insertBlankPage throws for an unknown size or a position out of range', (t) => {
+  t.throws(() => insertBlankPage(simple(1), 1, { size: 'B5' }), {
+    code: 'InvalidArg',
+    message: /^Unknown page size 'B5'/,
+  })
+  t.throws(() => insertBlankPage(simple(2), 4), { code: 'PageOutOfRange' })
+  t.throws(() => insertBlankPage(simple(2), 0), { code: 'PageOutOfRange' })
+})
+
+test('the pipeline inserts blank pages too', (t) => {
+  const inserted = new PdfPipeline(simple(1)).insertBlankPage(1, { size: 'A5' }).toBuffer()
+  t.deepEqual(pageTexts(inserted), ['', 'Page 1'])
+  t.deepEqual(pages(inserted)[0]['/MediaBox'], [0, 0, 419.53, 595.28])
+})
@@ -0,0 +1,73 @@
+const zlib = require('zlib')
+
+const test = require('ava')
+
+const { addHeaderFooter, getObject, getPageContent, setPageContent } = require('../index')
+
+const { resolve } = require('./helpers')
+const { pageObject, shownText, simple } = require('./pdf')
+
+test('getPageContent returns the operators of a page', (t) => {
+  const content = getPageContent(simple(2), 2).toString('latin1')
+  t.regex(content, /^BT\b[\s\S]*\(Page 2\) Tj[\s\S]*\bET\s*$/)
+})
+
+test('getPageContent decodes and joins the streams of a /Contents array', (t) => {
+  const split = simple(1, {
+    extra: (objects) => {
+      objects[4] = objects[4].replace('/Contents 4 0 R', '/Contents [4 0 R 6 0 R]')
+      objects[3] = { dict: '/Filter /FlateDecode', stream: zlib.deflateSync('BT /F1 24 Tf 72 700 Td (Page 1) Tj ET') }
+      objects.push({ stream: '0 0 1 rg 72 72 100 100 re f' })
+    },
+  })
+  t.is(
+    getPageContent(split, 1).toString('latin1'),
+    'BT /F1 24 Tf 72 700 Td (Page 1) Tj ET\n0 0 1 rg 72 72 100 100 re f\n',
+  )
+})
+
+// The ASCII85 encoding of `data`, with its `~>` end marker
+function ascii85(data) {
+  let encoded = ''
+  for (let index = 0; index < data.length; index += 4) {
+    const chunk = data.subarray(index, index + 4)
+    let value = Buffer.concat([chunk, Buffer.alloc(4 - chunk.length)]).readUInt32BE(0)
+    const digits = []
+    for (let digit = 0; digit < 5; digit++) {
+      digits.unshift(String.fromCharCode(33 + (value % 85)))
+      value = Math.floor(value / 85)
+    }
+    encoded += digits.slice(0, chunk.length + 1).join('')
+  }
+  return `${encoded}~>`
+}
+
+test('getPageContent and the stamps decode a chain of filters', (t) => {
+  const chained = simple(1, {
+    extra: (objects) => {
+      objects[3] = {
+        dict: '/Filter [/ASCII85Decode /FlateDecode]',
+        stream: ascii85(zlib.deflateSync('BT /F1 24 Tf 72 700 Td (Page 1) Tj ET')),
+      }
+    },
+  })
+  t.is(getPageContent(chained, 1).toString('latin1'), 'BT /F1 24 Tf 72 700 Td (Page 1) Tj ET\n')
+  t.is(shownText(getPageContent(addHeaderFooter(chained, { footer: 'Footer' }), 1)), 'Page 1Footer')
+})
+
+test('setPageContent writes back edited content, keeping the resources', (t) => {
+  const source = simple(1)
+  const content = Buffer.concat([getPageContent(source, 1), Buffer.from('1 0 0 rg 10 10 50 50 re f', 'latin1')])
+  const edited = setPageContent(source, 1, content)
+  t.regex(getPageContent(edited, 1).toString('latin1'), /\(Page 1\) Tj ET\s+1 0 0 rg 10 10 50 50 re f\s*$/)
+  t.deepEqual(getObject(edited, pageObject(1))['/Resources'], { '/Font': { '/F1': '3 0 R' } })
+})
+
+test('setPageContent compresses the content unless noCompression is set', (t) => {
+  const content = Buffer.from('0 0 1 rg 72 72 100 100 re f\n'.repeat(50), 'latin1')
+  const stream = (buffer) => resolve(buffer, getObject(buffer, pageObject(1))['/Contents']).stream
+  t.is(stream(setPageContent(simple(1), 1, content)).dict['/Filter'], '/FlateDecode')
+  const uncompressed = stream(setPageContent(simple(1), 1, content, { noCompression: true }))
+  t.is(uncompressed.dict['/Filter'], undefined)
+  t.deepEqual(uncompressed.data, content)
+})
@@ -0,0 +1,26 @@
+const test = require('ava')
+
+const { getMetadata, setDates } = require('../index')
+
+const { simple } = require('./pdf')
+
+test('setDates writes both dates, read back by getMetadata', (t) => {
+  const dated = setDates(simple(1), { creationDate: '2024-03-05T14:30:00+02:00', modDate: '2024-03-06T08:00:00Z' })
+  const { creationDate, modDate } = getMetadata(dated)
+  t.is(creationDate, '2024-03-05T14:30:00+02:00')
+  t.is(modDate, '2024-03-06T08:00:00+00:00')
+  const source = dated.toString('latin1')
+  t.true(source.includes("/CreationDate(D:20240305143000+02'00')"))
+  t.true(source.includes('/ModDate(D:20240306080000Z)'))
+})
+
+test('setDates removes a date set to null and takes a time without offset as UTC', (t) => {
+  const dated = setDates(simple(1), { creationDate: '2024-03-05T14:30:00Z' })
+  const { creationDate, modDate } = getMetadata(setDates(dated, { creationDate: null, modDate: '2024-01-02T03:04:05' }))
+  t.is(creationDate, undefined)
+  t.is(modDate, '2024-01-02T03:04:05+00:00')
+})
+
+test('setDates rejects dates that are not ISO-8601', (t) => {
+  t.throws(() => setDates(simple(1), { creationDate: 'yesterday' }), { code: 'InvalidArg' })
+})
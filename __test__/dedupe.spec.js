@@ -0,0 +1,38 @@
+const test = require('ava')
+
+const { dedupeObjects, stats, validate } = require('../index')
+
+const { pageTexts } = require('./helpers')
+const { simple } = require('./pdf')
+
+// Three pages each drawing their own copy of the same image, through their own copy of the same font
+function repeated() {
+  const image = {
+    dict: '/Type /XObject /Subtype /Image /Width 32 /Height 32 /ColorSpace /DeviceGray /BitsPerComponent 8',
+    stream: Buffer.alloc(1024, 0x80),
+  }
+  return simple(3, {
+    resources: (index) => `<< /Font << /F1 ${10 + index * 2} 0 R >> /XObject << /Im1 ${11 + index * 2} 0 R >> >>`,
+    extra: (objects) => {
+      for (let index = 0; index < 3; index++) {
+        objects.push('<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>', image)
+      }
+    },
+  })
+}
+
+test('collapses identical objects and keeps the document valid', (t) => {
+  const source = repeated()
+  const deduped = dedupeObjects(source)
+  // The copies of the font, the first page's included, and two images go, an Info dictionary comes
+  // with the Producer
+  t.is(stats(deduped).objectCount, stats(source).objectCount - 5 + 1)
+  t.true(deduped.length < source.length - 2 * 1024)
+  t.true(validate(deduped).ok)
+  t.deepEqual(pageTexts(deduped), ['Page 1', 'Page 2', 'Page 3'])
+})
+
+test('leaves documents without duplicates alone', (t) => {
+  const source = simple(2)
+  t.is(stats(dedupeObjects(source)).objectCount, stats(source).objectCount + 1)
+})
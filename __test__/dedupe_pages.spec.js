@@ -0,0 +1,28 @@
+const test = require('ava')
+
+const { dedupePages } = require('../index')
+
+const { pageTexts } = require('./helpers')
+const { simple } = require('./pdf')
+
+// Pages 2 and 4 show the same as page 1
+const repeated = () =>
+  simple(4, {
+    extra: (objects) => {
+      objects[5] = objects[3]
+      objects[9] = objects[3]
+    },
+  })
+
+test('dedupePages removes a page repeating the one before it', (t) => {
+  t.deepEqual(pageTexts(dedupePages(repeated())), ['Page 1', 'Page 3', 'Page 1'])
+})
+
+test("mode 'any' removes every repeat of an earlier page", (t) => {
+  t.deepEqual(pageTexts(dedupePages(repeated(), { mode: 'any' })), ['Page 1', 'Page 3'])
+})
+
+test('dedupePages keeps a document without repeats as it is', (t) => {
+  t.deepEqual(pageTexts(dedupePages(simple(3), { mode: 'any' })), ['Page 1', 'Page 2', 'Page 3'])
+  t.throws(() => dedupePages(simple(1), { mode: 'all' }), { code: 'InvalidArg' })
+})
@@ -0,0 +1,50 @@
+const test = require('ava')
+
+const { extractPages, getPageContent, mergePdf, PdfPipeline, probe, rotateRange } = require('../index')
+
+const { build, simple } = require('./pdf')
+
+test('a corrupt buffer throws InvalidPdf', (t) => {
+  t.throws(() => extractPages(Buffer.from('not a pdf at all'), [1]), { code: 'InvalidPdf' })
+  const truncated = simple(2).subarray(0, 200)
+  t.throws(() => mergePdf([truncated]), { code: 'InvalidPdf' })
+})
+
+test('an empty buffer throws InvalidPdf', (t) => {
+  const empty = Buffer.alloc(0)
+  t.throws(() => mergePdf([empty]), { code: 'InvalidPdf' })
+  t.throws(() => mergePdf([simple(1), { buffer: empty }]), { code: 'InvalidPdf' })
+  t.throws(() => probe(empty), { code: 'InvalidPdf' })
+  t.throws(() => getPageContent(empty, 1), { code: 'InvalidPdf' })
+  t.throws(() => new PdfPipeline(empty), { code: 'InvalidPdf' })
+})
+
+test('a page outside the document throws PageOutOfRange', (t) => {
+  const error = t.throws(() => extractPages(simple(2), [3]), { code: 'PageOutOfRange' })
+  t.regex(error.message, /3/)
+  t.throws(() => getPageContent(simple(2), 0), { code: 'PageOutOfRange' })
+  t.throws(() => rotateRange(simple(2), 2, 5, 90), { code: 'PageOutOfRange' })
+})
+
+test('a catalog without /Pages throws NoPagesRoot', (t) => {
+  const pageless = build(['<< /Type /Catalog >>'])
+  t.throws(() => mergePdf([pageless]), { code: 'NoPagesRoot' })
+})
+
+test('an encrypted document throws EncryptedNoPassword', (t) => {
+  const encrypted = simple(1, {
+    trailer: '/Encrypt 99 0 R',
+    extra: (objects) => {
+      while (objects.length < 98) {
+        objects.push('null')
+      }
+      objects.push(
+        '<< /Filter /Standard /V 2 /R 3 /Length 128 /O (0123456789abcdef0123456789abcdef) ' +
+          '/U (0123456789abcdef0123456789abcdef) /P -4 >>',
+      )
+    },
+  })
+  t.throws(() => extractPages(encrypted, [1]), { code: 'EncryptedNoPassword' })
+  // Merging can neither read encrypted sources nor encrypt the result
+  t.throws(() => mergePdf([simple(1), encrypted]), { code: 'EncryptedNoPassword' })
+})
@@ -0,0 +1,117 @@
+const test = require('ava')
+
+const { extractPages, extractVisible, getPageRotations } = require('../index')
+
+const { catalog, pageNumber, pageReferences, pageTexts, resolve, treeEntries } = require('./helpers')
+const { pageObject, simple } = require('./pdf')
+
+const link = (n, y) =>
+  `<< /Type /Annot /Subtype /Link /Rect [72 ${y} 200 ${y + 20}] /Dest [${pageObject(n)} 0 R /Fit] >>`
+
+// Page 1 links to page 2 and to page 3, the links being objects 10 and 11
+const linked = () =>
+  simple(3, {
+    page: (index) => (index === 0 ? '/Annots [10 0 R 11 0 R]' : ''),
+    extra: (objects) => objects.push(link(2, 100), link(3, 200)),
+  })
+
+const links = (buffer) => {
+  const [first] = pageReferences(buffer)
+  return resolve(buffer, resolve(buffer, first)['/Annots']).map((annotation) => resolve(buffer, annotation))
+}
+
+test('extractPages removes the links to pages left out and remaps the others', (t) => {
+  const extracted = extractPages(linked(), [1, 3])
+  t.deepEqual(pageTexts(extracted), ['Page 1', 'Page 3'])
+  const [kept, ...rest] = links(extracted)
+  t.deepEqual(rest, [])
+  t.deepEqual(kept['/Rect'], [72, 200, 200, 220])
+  t.is(pageNumber(extracted, kept['/Dest'][0]), 2)
+})
+
+test("onBrokenLink: 'keep' keeps the broken link without its destination", (t) => {
+  const extracted = extractPages(linked(), [1, 3], { onBrokenLink: 'keep' })
+  const [broken, kept] = links(extracted)
+  t.deepEqual(broken['/Rect'], [72, 100, 200, 120])
+  t.is(broken['/Dest'], undefined)
+  t.is(pageNumber(extracted, kept['/Dest'][0]), 2)
+})
+
+test('onBrokenLink rejects other values', (t) => {
+  t.throws(() => extractPages(linked(), [1], { onBrokenLink: 'drop' }), { code: 'InvalidArg' })
+})
+
+// Two tagged pages, each a paragraph of the Document element 9. Object 8 is the structure tree
+// root, 10 and 11 the paragraphs and 12 the parent tree
+const tagged = () =>
+  simple(2, {
+    catalog: '/StructTreeRoot 8 0 R /MarkInfo << /Marked true >>',
+    page: (index) => `/StructParents ${index}`,
+    extra: (objects) => {
+      objects[3] = { stream: '/P <</MCID 0>> BDC BT /F1 24 Tf 72 700 Td (Page 1) Tj ET EMC' }
+      objects[5] = { stream: '/P <</MCID 0>> BDC BT /F1 24 Tf 72 700 Td (Page 2) Tj ET EMC' }
+      objects.push('<< /Type /StructTreeRoot /K [9 0 R] /ParentTree 12 0 R /ParentTreeNextKey 2 >>')
+      objects.push('<< /Type /StructElem /S /Document /P 8 0 R /K [10 0 R 11 0 R] >>')
+      objects.push(`<< /Type /StructElem /S /P /P 9 0 R /Pg ${pageObject(1)} 0 R /K 0 >>`)
+      objects.push(`<< /Type /StructElem /S /P /P 9 0 R /Pg ${pageObject(2)} 0 R /K 0 >>`)
+      objects.push('<< /Nums [0 [10 0 R] 1 [11 0 R]] >>')
+    },
+  })
+
+test('extractPages removes the structure tree and the StructParents of the pages by default', (t) => {
+  const extracted = extractPages(tagged(), [2])
+  t.is(catalog(extracted)['/StructTreeRoot'], undefined)
+  t.is(resolve(extracted, pageReferences(extracted)[0])['/StructParents'], undefined)
+})
+
+test('preserveStructure restricts the structure tree to the extracted pages', (t) => {
+  const extracted = extractPages(tagged(), [2], { preserveStructure: true })
+  const [page] = pageReferences(extracted)
+  const root = resolve(extracted, catalog(extracted)['/StructTreeRoot'])
+  const paragraphs = resolve(extracted, root['/K'][0])['/K'].map((kid) => resolve(extracted, kid))
+  t.is(paragraphs.length, 1)
+  t.is(paragraphs[0]['/Pg'], page)
+  // The page's StructParents key still finds its paragraph in the parent tree
+  const structParents = resolve(extracted, page)['/StructParents']
+  const [[key, elements]] = treeEntries(extracted, root['/ParentTree'], '/Nums')
+  t.is(key, structParents)
+  t.deepEqual(elements.map((element) => resolve(extracted, element)), paragraphs)
+})
+
+test('extractVisible makes the CropBox the MediaBox, and keeps the MediaBox without one', (t) => {
+  const cropped = simple(3, { page: (index) => (index === 1 ? '/CropBox [50 100 350 500]' : '') })
+  const extracted = extractVisible(cropped, [2, 3])
+  const pages = pageReferences(extracted).map((page) => resolve(extracted, page))
+  t.deepEqual(
+    pages.map((page) => [page['/MediaBox'], page['/CropBox']]),
+    [
+      [[50, 100, 350, 500], undefined],
+      [[0, 0, 612, 792], undefined],
+    ],
+  )
+  t.deepEqual(pageTexts(extracted), ['Page 2', 'Page 3'])
+})
+
+test('extractVisible uses an inherited CropBox', (t) => {
+  const cropped = simple(2, {
+    extra: (objects) => (objects[1] = objects[1].replace('>>', '/CropBox [10 10 300 300] >>')),
+  })
+  const extracted = extractVisible(cropped, [1])
+  t.deepEqual(resolve(extracted, pageReferences(extracted)[0])['/MediaBox'], [10, 10, 300, 300])
+})
+
+test('extracted pages keep the rotation they resolved to in the source', (t) => {
+  // The page tree turns every page by an indirect 450, page 2 turns itself by -90
+  const rotated = simple(3, {
+    page: (index) => (index === 1 ? '/Rotate -90' : ''),
+    extra: (objects) => {
+      objects[1] = objects[1].replace(' >>', ' /Rotate 10 0 R >>')
+      objects.push('450')
+    },
+  })
+  t.deepEqual(getPageRotations(rotated), [90, 270, 90])
+  const extracted = extractPages(rotated, [3, 2])
+  t.deepEqual(getPageRotations(extracted), [90, 270])
+  t.deepEqual(pageReferences(extracted).map((reference) => resolve(extracted, reference)['/Rotate']), [90, 270])
+  t.deepEqual(pageTexts(extracted), ['Page 3', 'Page 2'])
+})
@@ -0,0 +1,42 @@
+const zlib = require('zlib')
+
+const test = require('ava')
+
+const { contentFingerprint } = require('../index')
+
+const { simple } = require('./pdf')
+
+// The same two pages with an Info dictionary, dates and a document ID
+const withMetadata = () =>
+  simple(2, {
+    trailer: '/Info 8 0 R /ID [<00112233> <44556677>]',
+    extra: (objects) =>
+      objects.push('<< /Title (Quarterly) /Author (Finance) /CreationDate (D:20240101) /ModDate (D:20240102) >>'),
+  })
+
+// The same two pages with their content compressed, the other objects in an object stream
+const recompressed = () =>
+  simple(2, {
+    objectStream: true,
+    extra: (objects) => {
+      objects.forEach((object, index) => {
+        if (object.stream) {
+          objects[index] = { dict: '/Filter /FlateDecode', stream: zlib.deflateSync(object.stream) }
+        }
+      })
+    },
+  })
+
+test('contentFingerprint ignores metadata, IDs and dates', (t) => {
+  t.regex(contentFingerprint(simple(2)), /^[0-9a-f]{64}$/)
+  t.is(contentFingerprint(withMetadata()), contentFingerprint(simple(2)))
+})
+
+test('contentFingerprint is stable across recompression and object streams', (t) => {
+  t.is(contentFingerprint(recompressed()), contentFingerprint(simple(2)))
+})
+
+test('contentFingerprint changes with the content', (t) => {
+  t.not(contentFingerprint(simple(2, { label: 'Draft' })), contentFingerprint(simple(2)))
+  t.not(contentFingerprint(simple(2, { width: 595 })), contentFingerprint(simple(2)))
+})
@@ -0,0 +1,55 @@
+const test = require('ava')
+
+const { addHeaderFooter, getPageContent, listFonts } = require('../index')
+
+const { pageReferences, resolve } = require('./helpers')
+const { simple } = require('./pdf')
+const { font: testFont } = require('./ttf')
+
+// The font the content draws `text` with, from the page resources
+function fontOf(buffer, text) {
+  const content = getPageContent(buffer, 1).toString('latin1')
+  const [, name] = new RegExp(`/(\\S+) \\d+ Tf[^(<]*[(<]${text}[)>] Tj`).exec(content)
+  const page = resolve(buffer, pageReferences(buffer)[0])
+  return resolve(buffer, resolve(buffer, resolve(buffer, page['/Resources'])['/Font'])[`/${name}`])
+}
+
+test('draws the page numbers in the chosen standard font', (t) => {
+  const stamped = addHeaderFooter(simple(1), { footer: '{page}', font: 'Courier-Bold' })
+  const font = fontOf(stamped, '1')
+  t.is(font['/BaseFont'], '/Courier-Bold')
+  t.is(font['/Encoding'], '/WinAnsiEncoding')
+})
+
+test('encodes the WinAnsi punctuation of a standard font', (t) => {
+  const stamped = addHeaderFooter(simple(1), { footer: '– {page} – €™' })
+  t.regex(getPageContent(stamped, 1).toString('latin1'), /\(\x96 1 \x96 \x80\x99\) Tj/)
+})
+
+test('rejects fonts outside the standard 14', (t) => {
+  t.throws(() => addHeaderFooter(simple(1), { footer: '{page}', font: 'Arial' }), { code: 'InvalidArg' })
+})
+
+test('embeds a subset of a TrueType font to draw non-Latin text', (t) => {
+  const file = testFont('Привет мир')
+  const stamped = addHeaderFooter(simple(1), { footer: 'мир', font: file })
+  // Two byte codes, the glyph ids in the font file
+  const font = fontOf(stamped, '000600050007')
+  t.is(font['/Subtype'], '/Type0')
+  t.is(font['/Encoding'], '/Identity-H')
+  t.truthy(font['/ToUnicode'])
+  const cidFont = resolve(stamped, resolve(stamped, font['/DescendantFonts'])[0])
+  t.regex(cidFont['/BaseFont'], /^\/[A-Z]{6}\+TestSans$/)
+  t.deepEqual(cidFont['/W'], [5, [600], 6, [600], 7, [600]])
+  // The subset keeps .notdef and the three glyphs drawn, renumbered from 1
+  const map = resolve(stamped, cidFont['/CIDToGIDMap']).stream.data
+  t.deepEqual([5, 6, 7].map((cid) => map.readUInt16BE(cid * 2)), [1, 2, 3])
+  const fontFile = resolve(stamped, resolve(stamped, cidFont['/FontDescriptor'])['/FontFile2']).stream
+  t.true(fontFile.dict['/Length1'] < file.length)
+  t.deepEqual(listFonts(stamped)[1], {
+    name: cidFont['/BaseFont'].slice(1),
+    subtype: 'Type0',
+    embedded: true,
+    subset: true,
+  })
+})
@@ -0,0 +1,170 @@
+const test = require('ava')
+
+const {
+  flattenFields,
+  getFormFields,
+  getPageContent,
+  mergePdf,
+  probe,
+  renameField,
+  setFieldReadOnly,
+  setNeedAppearances,
+} = require('../index')
+
+const { catalog, resolve } = require('./helpers')
+const { form, simple } = require('./pdf')
+
+const names = (buffer) => getFormFields(buffer).map((field) => field.name)
+
+const defaultFont = (baseFont) =>
+  `/DR << /Font << /Helv << /Type /Font /Subtype /Type1 /BaseFont /${baseFont} >> >> >>`
+
+test('flattenFields flattens only the named fields', (t) => {
+  const flattened = flattenFields(form(['date', 'comments', 'notes']), ['date', 'missing'])
+  t.deepEqual(names(flattened), ['comments', 'notes'])
+  // The appearance of the flattened field is drawn into the page at its rectangle, once
+  const content = getPageContent(flattened, 1).toString('latin1')
+  t.regex(content, /1 0 0 1 72 600 cm\s+\/\S+ Do/)
+  t.is(content.match(/ Do/g).length, 1)
+})
+
+test('setFieldReadOnly toggles bit 1 of /Ff and keeps the other bits', (t) => {
+  const source = form(['name', 'city'], { field: (index) => (index === 0 ? '/Ff 4096' : '') })
+  const locked = setFieldReadOnly(source, ['name'], true)
+  t.deepEqual(
+    getFormFields(locked).map(({ name, flags }) => [name, flags]),
+    [
+      ['name', 4096 | 1],
+      ['city', 0],
+    ],
+  )
+  const unlocked = setFieldReadOnly(locked, ['name', 'city'], false)
+  t.deepEqual(getFormFields(unlocked).map((field) => field.flags), [4096, 0])
+})
+
+test('merging forms unions the default resources and renames conflicting fonts', (t) => {
+  const merged = mergePdf([
+    form(['a'], { acroForm: defaultFont('Helvetica') }),
+    form(['b'], { acroForm: defaultFont('Courier') }),
+  ])
+  const acroForm = resolve(merged, catalog(merged)['/AcroForm'])
+  const fonts = acroForm['/DR']['/Font']
+  t.is(fonts['/Helv']['/BaseFont'], '/Helvetica')
+  t.is(fonts['/Helv_2']['/BaseFont'], '/Courier')
+  t.is(acroForm['/DA'], 'u:/Helv 0 Tf 0 g')
+  const [first, second] = acroForm['/Fields'].map((field) => resolve(merged, field))
+  // The first field inherits the form's default, the second keeps its own font under the new name
+  t.is(first['/DA'], undefined)
+  t.is(second['/DA'], 'u:/Helv_2 0 Tf 0 g')
+  t.deepEqual(names(merged), ['a', 'b'])
+})
+
+test('merging forms keeps one copy of a default font they share', (t) => {
+  const merged = mergePdf([
+    form(['a'], { acroForm: defaultFont('Helvetica') }),
+    form(['b'], { acroForm: defaultFont('Helvetica') }),
+  ])
+  const acroForm = resolve(merged, catalog(merged)['/AcroForm'])
+  t.deepEqual(Object.keys(acroForm['/DR']['/Font']), ['/Helv'])
+  t.is(resolve(merged, acroForm['/Fields'][1])['/DA'], undefined)
+})
+
+test('renameField renames a field, keeping its value', (t) => {
+  const renamed = renameField(form(['name', 'email']), 'name', 'fullName')
+  t.deepEqual(
+    getFormFields(renamed).map(({ name, value }) => [name, value]),
+    [
+      ['fullName', 'value 0'],
+      ['email', 'value 1'],
+    ],
+  )
+})
+
+test('renameField keeps renamed copies of a form apart when merging them', (t) => {
+  const copy = renameField(renameField(form(['name', 'email']), 'name', 'name2'), 'email', 'email2')
+  t.deepEqual(names(mergePdf([form(['name', 'email']), copy])), ['name', 'email', 'name2', 'email2'])
+})
+
+test('renameField throws for a missing field or a name already taken', (t) => {
+  t.throws(() => renameField(form(['name']), 'missing', 'other'), { message: "No field named 'missing'" })
+  t.throws(() => renameField(form(['name', 'email']), 'name', 'email'), {
+    message: "A field named 'email' already exists",
+  })
+})
+
+test('merging copies of a template shares their default resource fonts', (t) => {
+  const template = (index) =>
+    form([`name${index}`], {
+      acroForm: '/DR << /Font << /Helv 101 0 R /ZaDb 102 0 R >> >>',
+      extra: (objects) =>
+        objects.push(
+          '<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>',
+          '<< /Type /Font /Subtype /Type1 /BaseFont /ZapfDingbats >>',
+        ),
+    })
+  const merged = mergePdf([1, 2, 3, 4, 5].map(template))
+  const fonts = resolve(merged, resolve(merged, catalog(merged)['/AcroForm'])['/DR'])['/Font']
+  t.deepEqual(Object.keys(fonts), ['/Helv', '/ZaDb'])
+  t.like(resolve(merged, fonts['/Helv']), { '/BaseFont': '/Helvetica', '/Encoding': '/WinAnsiEncoding' })
+  t.like(resolve(merged, fonts['/ZaDb']), { '/BaseFont': '/ZapfDingbats' })
+  // One font object per face is left
+  t.is(merged.toString('latin1').split('/WinAnsiEncoding').length - 1, 1)
+  t.is(merged.toString('latin1').split('/ZapfDingbats').length - 1, 1)
+})
+
+test('setNeedAppearances sets and clears the AcroForm flag', (t) => {
+  const acroForm = (buffer) => resolve(buffer, catalog(buffer)['/AcroForm'])
+  const flagged = setNeedAppearances(form(['name', 'city']), true)
+  t.like(acroForm(flagged), { '/Fields': ['7 0 R', '9 0 R'], '/NeedAppearances': true })
+  const cleared = setNeedAppearances(flagged, false)
+  t.is(acroForm(cleared)['/NeedAppearances'], undefined)
+  t.deepEqual(names(cleared), ['name', 'city'])
+})
+
+test('setNeedAppearances gives widgets without an AcroForm one listing their fields', (t) => {
+  const orphaned = form(['name', 'city'], {
+    extra: (objects) => (objects[0] = objects[0].replace('/AcroForm 100 0 R', '')),
+  })
+  t.deepEqual(names(orphaned), [])
+  const flagged = setNeedAppearances(orphaned, true)
+  t.deepEqual(resolve(flagged, catalog(flagged)['/AcroForm']), {
+    '/Fields': ['7 0 R', '9 0 R'],
+    '/NeedAppearances': true,
+  })
+  t.deepEqual(names(flagged), ['name', 'city'])
+})
+
+// An XFA form, the template being object 7, whose AcroForm lists no fields
+const xfaOnly = () =>
+  simple(1, {
+    catalog: '/AcroForm 6 0 R',
+    extra: (objects) =>
+      objects.push('<< /Fields [] /XFA 7 0 R >>', { stream: '<xdp:xdp xmlns:xdp="http://ns.adobe.com/xdp/"/>' }),
+  })
+
+test('the AcroForm field operations throw XfaForm on an XFA only form', (t) => {
+  const document = xfaOnly()
+  t.true(probe(document).xfaOnly)
+  const error = { code: 'XfaForm', message: 'The form is XFA only (dynamic), it has no AcroForm fields to change' }
+  t.throws(() => flattenFields(document, ['name']), error)
+  t.throws(() => setFieldReadOnly(document, ['name'], true), error)
+  t.throws(() => renameField(document, 'name', 'fullName'), error)
+})
+
+test('a form with both XFA and AcroForm fields is only XFA when it needs rendering', (t) => {
+  const hybrid = form(['name'], {
+    acroForm: '/XFA 101 0 R',
+    extra: (objects) => objects.push({ stream: '<xdp:xdp xmlns:xdp="http://ns.adobe.com/xdp/"/>' }),
+  })
+  t.false(probe(hybrid).xfaOnly)
+  t.deepEqual(names(renameField(hybrid, 'name', 'fullName')), ['fullName'])
+  const dynamic = form(['name'], {
+    acroForm: '/XFA 101 0 R',
+    extra: (objects) => {
+      objects[0] = objects[0].replace(' >>', ' /NeedsRendering true >>')
+      objects.push({ stream: '<xdp:xdp xmlns:xdp="http://ns.adobe.com/xdp/"/>' })
+    },
+  })
+  t.true(probe(dynamic).xfaOnly)
+  t.throws(() => renameField(dynamic, 'name', 'fullName'), { code: 'XfaForm' })
+})
@@ -0,0 +1,44 @@
+const test = require('ava')
+
+const { addHeaderFooter, addQrCode, addTextBox, getObject, getPageContent } = require('../index')
+
+const { pageObject, simple } = require('./pdf')
+
+const original = '2 0 0 2 0 0 cm 1 0 0 rg BT /F1 12 Tf 36 350 Td (Page 1) Tj ET'
+
+// A page whose content leaves a doubled scale and a red fill set when it ends
+const leaking = () =>
+  simple(1, {
+    extra: (objects) => {
+      objects[3] = { stream: original }
+    },
+  })
+
+const stamps = {
+  addHeaderFooter: (buffer) => addHeaderFooter(buffer, { footer: 'Stamp' }),
+  addQrCode: (buffer) => addQrCode(buffer, 'https://example.com', { x: 500, y: 36, size: 72 }),
+  addTextBox: (buffer) => addTextBox(buffer, { rect: [72, 72, 300, 120], text: 'Approved' }),
+}
+
+for (const [name, stamp] of Object.entries(stamps)) {
+  test(`${name} keeps the original content as it was, its state closed before the stamp`, (t) => {
+    const stamped = stamp(leaking())
+    // The original stream is untouched, between a stream opening q and one closing Q
+    const [opening, content, ...after] = getObject(stamped, pageObject(1))['/Contents']
+    t.is(content, '4 0 R')
+    t.is(getObject(stamped, 4).stream.data.toString('latin1'), original)
+    t.regex(getObject(stamped, Number(opening.split(' ')[0])).stream.data.toString('latin1'), /^\s*q\s*$/)
+    t.true(after.length > 0)
+    // So the stamp starts from the default state and its own q/Q stay balanced
+    const page = getPageContent(stamped, 1).toString('latin1')
+    const stampContent = page.slice(page.indexOf('Q', page.indexOf(original)) + 1)
+    t.regex(stampContent, /^\s*q\b[\s\S]*\bQ\s*$/)
+    const balance = (stampContent.match(/\bq\b/g) || []).length - (stampContent.match(/\bQ\b/g) || []).length
+    t.is(balance, 0)
+  })
+}
+
+test('addTextBox draws at its rect whatever scale the page content leaves', (t) => {
+  const content = getPageContent(stamps.addTextBox(leaking()), 1).toString('latin1')
+  t.regex(content, /Q\s+q\s+1 0 0 1 0 0 cm\s+72 72 228 48 re\s+W\s+n\s+BT\s+\/\S+ 12 Tf\s+72 108 Td\s+\(Approved\) Tj/)
+})
@@ -0,0 +1,99 @@
+const test = require('ava')
+
+const { addHeaderFooter, getObject, getPageContent } = require('../index')
+
+const { pageObject, shownText, simple } = require('./pdf')
+
+test('numbers the pages in the footer', (t) => {
+  const stamped = addHeaderFooter(simple(3), { footer: 'Page {page} of {total}' })
+  for (const page of [1, 2, 3]) {
+    t.true(shownText(getPageContent(stamped, page)).includes(`Page ${page} of 3`))
+  }
+})
+
+test('pages odd stamps the odd pages and leaves the even ones untouched', (t) => {
+  const source = simple(4)
+  const stamped = addHeaderFooter(source, { footer: 'Footer {page}', pages: 'odd' })
+  t.deepEqual(
+    [1, 2, 3, 4].map((page) => shownText(getPageContent(stamped, page))),
+    ['Page 1Footer 1', 'Page 2', 'Page 3Footer 3', 'Page 4'],
+  )
+  for (const page of [2, 4]) {
+    t.deepEqual(getObject(stamped, pageObject(page)), getObject(source, pageObject(page)))
+    t.true(getPageContent(stamped, page).equals(getPageContent(source, page)))
+  }
+})
+
+test('pages takes page numbers and ranges, and rejects pages the document lacks', (t) => {
+  const stampedPages = (pages) => {
+    const stamped = addHeaderFooter(simple(4), { footer: 'F', pages })
+    return [1, 2, 3, 4].filter((page) => shownText(getPageContent(stamped, page)).endsWith('F'))
+  }
+  t.deepEqual(stampedPages('even'), [2, 4])
+  t.deepEqual(stampedPages([4, 1]), [1, 4])
+  t.deepEqual(stampedPages({ from: 2, to: 3 }), [2, 3])
+  t.deepEqual(stampedPages({ from: 3 }), [3, 4])
+  t.throws(() => stampedPages([5]), { code: 'PageOutOfRange' })
+  t.throws(() => stampedPages({ from: 3, to: 2 }), { code: 'InvalidArg' })
+  t.throws(() => stampedPages('first'), { code: 'InvalidArg' })
+})
+
+test('a range past the last page fails at once, however long', (t) => {
+  t.throws(() => addHeaderFooter(simple(2), { header: 'x', pages: { from: 1, to: 300000000 } }), {
+    code: 'PageOutOfRange',
+    message: 'Page 300000000 is out of range for 2 pages',
+  })
+  t.throws(() => addHeaderFooter(simple(2), { header: 'x', pages: { from: 4294967295 } }), {
+    code: 'PageOutOfRange',
+    message: 'Page 4294967295 is out of range for 2 pages',
+  })
+  t.throws(() => addHeaderFooter(simple(2), { header: 'x', pages: [1, 4294967295] }), { code: 'PageOutOfRange' })
+})
+
+test('registers a font and draws in the margins', (t) => {
+  const stamped = addHeaderFooter(simple(1), { header: 'Top', footer: 'Bottom', fontSize: 12, margin: 20 })
+  const content = getPageContent(stamped, 1).toString('latin1')
+  const [, font] = /\/(\S+) 12 Tf\s+\S+ 772 Td\s+\(Top\) Tj/.exec(content)
+  t.not(font, 'F1')
+  t.regex(content, new RegExp(`/${font} 12 Tf\\s+\\S+ 8 Td\\s+\\(Bottom\\) Tj`))
+})
+
+test('draws upright on a rotated page', (t) => {
+  const rotated = simple(1, { page: () => '/Rotate 90' })
+  const content = getPageContent(addHeaderFooter(rotated, { header: 'Top', margin: 20 }), 1).toString('latin1')
+  // Turned with the page, along its displayed 792 point wide top edge
+  t.regex(content, /0 1 -1 0 612 0 cm\s+BT\s+\/\S+ 10 Tf\s+\S+ 592 Td\s+\(Top\) Tj/)
+})
+
+test('coordinateSpace media measures the margins on the unrotated page', (t) => {
+  const rotated = simple(1, { page: () => '/Rotate 90' })
+  const stamped = addHeaderFooter(rotated, { header: 'Top', margin: 20, coordinateSpace: 'media' })
+  // Along the 612 point wide top edge of the media box
+  t.regex(getPageContent(stamped, 1).toString('latin1'), /1 0 0 1 0 0 cm\s+BT\s+\/\S+ 10 Tf\s+\S+ 772 Td\s+\(Top\) Tj/)
+  t.throws(() => addHeaderFooter(rotated, { header: 'Top', coordinateSpace: 'crop' }), { code: 'InvalidArg' })
+})
+
+test('replaces {date}', (t) => {
+  const stamped = addHeaderFooter(simple(1), { header: '{date}' })
+  t.regex(shownText(getPageContent(stamped, 1)), /\d{4}-\d{2}-\d{2}/)
+})
+
+test('stamps over a /Contents array, its graphics state kept apart by q and Q', (t) => {
+  // The first stream leaves a thick blue stroke set for the one after it
+  const split = simple(1, {
+    extra: (objects) => {
+      objects[4] = objects[4].replace('/Contents 4 0 R', '/Contents [4 0 R 6 0 R]')
+      objects[3] = { stream: '2 w 0 0 1 RG' }
+      objects.push({ stream: 'BT /F1 24 Tf 72 700 Td (Page 1) Tj ET' })
+    },
+  })
+  const stamped = addHeaderFooter(split, { footer: 'Stamp' })
+  // Between the stream opening q and the one closing Q, then drawing the stamp
+  const contents = getObject(stamped, pageObject(1))['/Contents']
+  t.is(contents.length, 4)
+  t.deepEqual(contents.slice(1, 3), ['4 0 R', '6 0 R'])
+  t.regex(
+    getPageContent(stamped, 1).toString('latin1'),
+    /^q\s+2 w 0 0 1 RG\s+BT \/F1 24 Tf 72 700 Td \(Page 1\) Tj ET\s+Q\s+q\b[\s\S]*\(Stamp\) Tj[\s\S]*Q\s*$/,
+  )
+})
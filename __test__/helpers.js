@@ -0,0 +1,55 @@
+// Reading back the documents the specs produce
+const { getObject, getPageContent, getTrailer, probe } = require('../index')
+
+const { shownText } = require('./pdf')
+
+// `[objNum, genNum]` of a `'12 0 R'` reference
+function referenced(reference) {
+  const [objNum, genNum] = reference.split(' ').map(Number)
+  return [objNum, genNum]
+}
+
+// The object a reference points at, or the value itself when it isn't a reference
+function resolve(buffer, value) {
+  return typeof value === 'string' && / R$/.test(value) ? getObject(buffer, ...referenced(value)) : value
+}
+
+function catalog(buffer) {
+  return resolve(buffer, getTrailer(buffer)['/Root'])
+}
+
+// The `[key, value]` entries of a name or number tree, in order
+function treeEntries(buffer, node, key = '/Names') {
+  node = resolve(buffer, node)
+  const leaves = node[key] || []
+  const entries = []
+  for (let index = 0; index + 1 < leaves.length; index += 2) {
+    entries.push([leaves[index], leaves[index + 1]])
+  }
+  for (const kid of node['/Kids'] || []) {
+    entries.push(...treeEntries(buffer, kid, key))
+  }
+  return entries
+}
+
+// The `'12 0 R'` reference of every page in order, for `pageNumber`
+function pageReferences(buffer, node = catalog(buffer)['/Pages']) {
+  const tree = resolve(buffer, node)
+  if (tree['/Type'] === '/Page') {
+    return [node]
+  }
+  return tree['/Kids'].flatMap((kid) => pageReferences(buffer, kid))
+}
+
+// 1-based number of the page a reference points at, 0 when it isn't a page of the document
+function pageNumber(buffer, reference) {
+  return pageReferences(buffer).indexOf(reference) + 1
+}
+
+// The text every page shows, in page order
+function pageTexts(buffer) {
+  const { pageCount } = probe(buffer)
+  return Array.from({ length: pageCount }, (_, index) => shownText(getPageContent(buffer, index + 1)))
+}
+
+module.exports = { referenced, resolve, catalog, treeEntries, pageReferences, pageNumber, pageTexts }
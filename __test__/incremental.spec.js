@@ -0,0 +1,47 @@
+const test = require('ava')
+
+const { addHeaderFooter, getPageContent, getTrailer, rotateRange, validate } = require('../index')
+
+const { shownText, simple } = require('./pdf')
+
+// A document certified with the DocMDP permissions `p`, the signature being object 6
+const certified = (p) =>
+  simple(1, {
+    catalog: '/Perms << /DocMDP 6 0 R >>',
+    extra: (objects) =>
+      objects.push(`<< /Type /Sig /Reference [<< /TransformMethod /DocMDP /TransformParams << /P ${p} >> >>] >>`),
+  })
+
+test('a certified document can only be updated incrementally', (t) => {
+  const source = certified(2)
+  t.throws(() => addHeaderFooter(source, { footer: 'Approved' }), { code: 'CertifiedDocument' })
+  const updated = addHeaderFooter(source, { footer: 'Approved', incremental: true })
+  // The signed bytes are kept as they are, the update follows them
+  t.true(updated.subarray(0, source.length).equals(source))
+  t.true(validate(updated).ok)
+  t.is(shownText(getPageContent(updated, 1)), 'Page 1Approved')
+})
+
+test('a document certified against any change refuses incremental updates too', (t) => {
+  t.throws(() => addHeaderFooter(certified(1), { footer: 'Approved', incremental: true }), {
+    code: 'CertifiedDocument',
+  })
+})
+
+test('an incremental update appends to a document that is not certified', (t) => {
+  const source = simple(2)
+  const updated = addHeaderFooter(source, { footer: '{page}', incremental: true })
+  t.true(updated.subarray(0, source.length).equals(source))
+  t.regex(updated.subarray(source.length).toString('latin1'), /trailer\s*<<.*\/Prev \d+/s)
+  t.is(shownText(getPageContent(updated, 2)), 'Page 22')
+})
+
+test('operations keep the bytes of the trailer /ID, rewriting the file or updating it', (t) => {
+  const source = simple(2, { trailer: '/ID [<00112233aabbccdd> (\\001\\002\\377)]' })
+  const id = ['b:00112233aabbccdd', 'b:0102ff']
+  t.deepEqual(getTrailer(source)['/ID'], id)
+  for (const incremental of [false, true]) {
+    t.deepEqual(getTrailer(rotateRange(source, 1, 1, 90, { incremental }))['/ID'], id)
+    t.deepEqual(getTrailer(addHeaderFooter(source, { footer: 'Signed', incremental }))['/ID'], id)
+  }
+})
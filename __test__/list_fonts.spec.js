@@ -0,0 +1,36 @@
+const test = require('ava')
+
+const { listFonts } = require('../index')
+
+const { simple } = require('./pdf')
+const { font } = require('./ttf')
+
+// Two pages using Helvetica, the second also ABCDEF+TestSans, embedded, through a form XObject
+const fonts = () =>
+  simple(2, {
+    resources: (index) =>
+      index === 0 ? '<< /Font << /F1 3 0 R >> >>' : '<< /Font << /F1 3 0 R >> /XObject << /Fm1 9 0 R >> >>',
+    extra: (objects) => {
+      objects.push(
+        '<< /Type /Font /Subtype /TrueType /BaseFont /ABCDEF+TestSans /FirstChar 65 /LastChar 65 ' +
+          '/Widths [600] /FontDescriptor 10 0 R >>',
+      )
+      objects.push({
+        dict: '/Type /XObject /Subtype /Form /BBox [0 0 200 30] /Resources << /Font << /F2 8 0 R >> >>',
+        stream: 'BT /F2 12 Tf (A) Tj ET',
+      })
+      objects.push(
+        '<< /Type /FontDescriptor /FontName /ABCDEF+TestSans /Flags 32 /FontBBox [0 0 600 700] ' +
+          '/ItalicAngle 0 /Ascent 800 /Descent -200 /CapHeight 700 /StemV 80 /FontFile2 11 0 R >>',
+      )
+      objects.push({ stream: font('A') })
+    },
+  })
+
+test('listFonts reports an embedded subset font and a standard font, once each', (t) => {
+  const listed = listFonts(fonts()).sort((a, b) => a.name.localeCompare(b.name))
+  t.deepEqual(listed, [
+    { name: 'ABCDEF+TestSans', subtype: 'TrueType', embedded: true, subset: true },
+    { name: 'Helvetica', subtype: 'Type1', embedded: false, subset: false },
+  ])
+})
@@ -0,0 +1,582 @@
+const test = require('ava')
+
+const {
+  getMetadata,
+  getObject,
+  getOutline,
+  getPageContent,
+  getPageRotations,
+  getTrailer,
+  mergePdf,
+  objectMap,
+  version,
+} = require('../index')
+
+const { catalog, pageNumber, pageReferences, pageTexts, resolve, treeEntries } = require('./helpers')
+const { build, pageObject, shownText, simple } = require('./pdf')
+
+test('merges the pages of every document in order', (t) => {
+  const merged = mergePdf([simple(2, { label: 'A' }), simple(1, { label: 'B' })])
+  t.deepEqual(pageTexts(merged), ['A 1', 'A 2', 'B 1'])
+})
+
+test('metadataFrom picks the document whose viewer preferences are kept', (t) => {
+  const template = simple(1, { catalog: '/ViewerPreferences << /HideToolbar false >> /PageLayout /SinglePage' })
+  const content = simple(1, { catalog: '/ViewerPreferences << /HideToolbar true /FitWindow true >> /Lang (de)' })
+  const merged = catalog(mergePdf([template, content], { metadataFrom: 1 }))
+  const preferences = resolve(merged, merged['/ViewerPreferences'])
+  t.true(preferences['/HideToolbar'])
+  t.true(preferences['/FitWindow'])
+  t.is(merged['/Lang'], 'u:de')
+  t.is(merged['/PageLayout'], undefined)
+  const first = catalog(mergePdf([template, content]))
+  t.false(resolve(first, first['/ViewerPreferences'])['/HideToolbar'])
+})
+
+test('metadataFrom must name one of the documents', (t) => {
+  t.throws(() => mergePdf([simple(1), simple(1)], { metadataFrom: 2 }), { code: 'InvalidArg' })
+})
+
+test('rotate turns the pages of one document on top of their own rotation', (t) => {
+  const scanned = simple(2, { page: (index) => (index === 0 ? '/Rotate 90' : '') })
+  const merged = mergePdf([simple(1), { buffer: scanned, rotate: 90 }])
+  t.deepEqual(getPageRotations(merged), [0, 180, 90])
+  t.deepEqual(getPageRotations(mergePdf([{ buffer: scanned, rotate: -90 }])), [0, 270])
+})
+
+test('rotate must be a multiple of 90', (t) => {
+  t.throws(() => mergePdf([{ buffer: simple(1), rotate: 45 }]), { code: 'InvalidArg' })
+})
+
+test('firstPages and lastPages pick pages from the ends of a document, clamped to its pages', (t) => {
+  const source = (label) => simple(3, { label })
+  const firsts = mergePdf(['A', 'B', 'C'].map((label) => ({ buffer: source(label), firstPages: 1 })))
+  t.deepEqual(pageTexts(firsts), ['A 1', 'B 1', 'C 1'])
+  const merged = mergePdf([
+    { buffer: source('A'), firstPages: 5 },
+    { buffer: source('B'), lastPages: 1 },
+    { buffer: source('C'), firstPages: 1, lastPages: 1 },
+    { buffer: source('D'), pages: [2], firstPages: 1 },
+  ])
+  t.deepEqual(pageTexts(merged), ['A 1', 'A 2', 'A 3', 'B 3', 'C 1', 'C 3', 'D 2'])
+})
+
+// A `simple` document whose name tree (object 20) has an `intro` destination to `page`
+function withIntro(count, page, options = {}) {
+  return simple(count, {
+    ...options,
+    catalog: `/Names << /Dests 20 0 R >> ${options.catalog || ''}`,
+    extra: (objects) => {
+      while (objects.length < 19) {
+        objects.push('null')
+      }
+      objects.push(`<< /Names [(intro) [${pageObject(page)} 0 R /Fit]] >>`)
+    },
+  })
+}
+
+test('named destinations of every document resolve in the merge, renamed when they collide', (t) => {
+  const first = withIntro(2, 2, { label: 'A' })
+  const second = withIntro(1, 1, {
+    label: 'B',
+    catalog: '/Dests << /end [5 0 R /XYZ 0 792 0] >>',
+    page: () =>
+      '/Annots [<< /Type /Annot /Subtype /Link /Rect [0 0 10 10] /Dest (intro) >> ' +
+      '<< /Type /Annot /Subtype /Link /Rect [0 0 10 10] /Dest /end >>]',
+  })
+  const { buffer, warnings } = mergePdf([first, second], { report: true })
+  t.deepEqual(warnings, ["Document 1: the named destination 'intro' was renamed to 'intro_2'"])
+  const root = catalog(buffer)
+  const destinations = Object.fromEntries(
+    treeEntries(buffer, root['/Names']['/Dests']).map(([name, destination]) => [name, resolve(buffer, destination)]),
+  )
+  t.deepEqual(Object.keys(destinations).sort(), ['u:intro', 'u:intro_2'])
+  t.is(pageNumber(buffer, destinations['u:intro'][0]), 2)
+  t.is(pageNumber(buffer, destinations['u:intro_2'][0]), 3)
+  t.is(pageNumber(buffer, resolve(buffer, root['/Dests'])['/end'][0]), 3)
+  // The link of the second document follows the rename
+  const [link] = resolve(buffer, pageReferences(buffer)[2])['/Annots']
+  t.is(resolve(buffer, link)['/Dest'], 'u:intro_2')
+})
+
+test('destinationConflicts first-wins sends the links of both documents to the earlier destination', (t) => {
+  const second = withIntro(1, 1, {
+    label: 'B',
+    page: () => '/Annots [<< /Type /Annot /Subtype /Link /Rect [0 0 10 10] /Dest (intro) >>]',
+  })
+  const { buffer, warnings } = mergePdf([withIntro(2, 2, { label: 'A' }), second], {
+    destinationConflicts: 'first-wins',
+    report: true,
+  })
+  t.deepEqual(warnings, ["Document 1: the named destination 'intro' was dropped, an earlier document defines it"])
+  const destinations = treeEntries(buffer, catalog(buffer)['/Names']['/Dests'])
+  t.deepEqual(
+    destinations.map(([name, destination]) => [name, pageNumber(buffer, resolve(buffer, destination)[0])]),
+    [['u:intro', 2]],
+  )
+  const [link] = resolve(buffer, pageReferences(buffer)[2])['/Annots']
+  t.is(resolve(buffer, link)['/Dest'], 'u:intro')
+})
+
+test('destinationConflicts error throws on a name two documents define', (t) => {
+  t.throws(() => mergePdf([withIntro(2, 2), withIntro(1, 1)], { destinationConflicts: 'error' }), {
+    message: "Document 1: the named destination 'intro' is also defined by an earlier document",
+  })
+})
+
+test("the chosen document's OpenAction follows its page into the merge", (t) => {
+  const jumping = simple(3, { label: 'B', catalog: `/OpenAction [${pageObject(2)} 0 R /Fit]` })
+  const merged = mergePdf([simple(2), jumping], { metadataFrom: 1 })
+  const [page, fit] = catalog(merged)['/OpenAction']
+  t.is(pageNumber(merged, page), 2 + 2)
+  t.is(fit, '/Fit')
+  // Another document's OpenAction isn't kept
+  t.is(catalog(mergePdf([simple(2), jumping]))['/OpenAction'], undefined)
+})
+
+test('pages keep their UserUnit, inherited or their own', (t) => {
+  const large = simple(2, {
+    label: 'Drawing',
+    extra: (objects) => (objects[1] = objects[1].replace('/Count 2', '/Count 2 /UserUnit 10')),
+    page: (index) => (index === 1 ? '/UserUnit 2.5' : ''),
+  })
+  const merged = mergePdf([simple(1), large])
+  t.deepEqual(pageReferences(merged).map((page) => resolve(merged, page)['/UserUnit']), [undefined, 10, 2.5])
+})
+
+test('report lists what the merge dropped or altered', (t) => {
+  const outlined = simple(1, {
+    catalog: '/Outlines 6 0 R /Lang (de) /PieceInfo << >>',
+    extra: (objects) => objects.push('<< /Type /Outlines /Count 0 >>'),
+  })
+  const miscounted = simple(1, { extra: (objects) => (objects[1] = objects[1].replace('/Count 1', '/Count 3')) })
+  const report = mergePdf([simple(2), outlined, Buffer.from('not a pdf'), miscounted], {
+    report: true,
+    skipInvalid: true,
+  })
+  t.deepEqual(report.warnings, [
+    'Document 2: skipped, Invalid PDF: Invalid file header',
+    'Document 1: the outlines (bookmarks) were dropped',
+    'Document 1: /Lang was dropped, catalog settings come from document 0',
+    'Document 1: the catalog entry /PieceInfo was dropped',
+    'Document 3: the page tree /Count is 3 but 1 pages were found',
+  ])
+  t.deepEqual(report.sources, [
+    { source: 0, startPage: 1, endPage: 2 },
+    { source: 1, startPage: 3, endPage: 3 },
+    { source: 3, startPage: 4, endPage: 4 },
+  ])
+  t.deepEqual(pageTexts(report.buffer), ['Page 1', 'Page 2', 'Page 1', 'Page 1'])
+})
+
+test('skipInvalid merges the documents that parse and lists the others', (t) => {
+  const sources = [simple(2, { label: 'First' }), Buffer.from('not a pdf'), simple(1, { label: 'Second' })]
+  t.throws(() => mergePdf(sources), { code: 'InvalidPdf' })
+  const { buffer, warnings } = mergePdf(sources, { skipInvalid: true, report: true })
+  t.deepEqual(pageTexts(buffer), ['First 1', 'First 2', 'Second 1'])
+  t.deepEqual(warnings, ['Document 1: skipped, Invalid PDF: Invalid file header'])
+  t.throws(() => mergePdf([Buffer.from('not a pdf')], { skipInvalid: true }), {
+    code: 'InvalidPdf',
+    message: 'None of the documents could be read',
+  })
+})
+
+test('repairInvalid rebuilds the cross-reference table of a damaged document and merges it', (t) => {
+  const intact = simple(2, { label: 'Damaged' }).toString('latin1')
+  const damaged = Buffer.from(intact.replace(/startxref\n\d+/, 'startxref\n999999'), 'latin1')
+  const sources = [simple(1, { label: 'Healthy' }), damaged, Buffer.from('not a pdf')]
+  t.throws(() => mergePdf(sources.slice(0, 2)), { code: 'InvalidPdf' })
+  const { buffer, warnings } = mergePdf(sources, { repairInvalid: true, skipInvalid: true, report: true })
+  t.deepEqual(pageTexts(buffer), ['Healthy 1', 'Damaged 1', 'Damaged 2'])
+  t.deepEqual(warnings, [
+    'Document 1: repaired, its cross-reference table was rebuilt',
+    'Document 2: skipped, Invalid PDF: Invalid file header',
+  ])
+})
+
+test('strictPageCount throws on a page tree whose /Count is wrong', (t) => {
+  const miscounted = simple(1, { extra: (objects) => (objects[1] = objects[1].replace('/Count 1', '/Count 3')) })
+  t.deepEqual(mergePdf([simple(1), miscounted], { report: true }).warnings, [
+    'Document 1: the page tree /Count is 3 but 1 pages were found',
+  ])
+  t.throws(() => mergePdf([simple(1), miscounted], { strictPageCount: true }), {
+    code: 'InvalidPdf',
+    message: 'Document 1: the page tree /Count is 3 but 1 pages were found',
+  })
+})
+
+test('maxPages and maxObjects refuse documents over the limits before merging them', (t) => {
+  // 5 pages in 16 objects
+  const sources = [simple(2), simple(3)]
+  t.throws(() => mergePdf(sources, { maxPages: 4 }), {
+    code: 'LimitExceeded',
+    message: 'The documents have 5 pages, over maxPages 4',
+  })
+  t.throws(() => mergePdf(sources, { maxObjects: 15 }), {
+    code: 'LimitExceeded',
+    message: 'The documents have 16 objects, over maxObjects 15',
+  })
+  t.is(pageTexts(mergePdf(sources, { maxPages: 5, maxObjects: 16 })).length, 5)
+})
+
+test('report maps the pages of the merge to the documents they came from', (t) => {
+  const { sources } = mergePdf([simple(2), simple(3), simple(1)], { report: true })
+  t.deepEqual(sources, [
+    { source: 0, startPage: 1, endPage: 2 },
+    { source: 1, startPage: 3, endPage: 5 },
+    { source: 2, startPage: 6, endPage: 6 },
+  ])
+})
+
+test('padEachSource starts every document on an odd page and padToEven pads the result', (t) => {
+  const [a, b, c] = [simple(3, { label: 'A' }), simple(1, { label: 'B' }), simple(2, { label: 'C' })]
+  const { buffer, warnings } = mergePdf([a, b, c], { padEachSource: true, report: true })
+  t.deepEqual(pageTexts(buffer), ['A 1', 'A 2', 'A 3', '', 'B 1', '', 'C 1', 'C 2'])
+  t.deepEqual(
+    warnings,
+    [0, 1].map((index) => `Document ${index}: a blank page was added after its odd number of pages`),
+  )
+  t.deepEqual(pageTexts(mergePdf([a, c], { padToEven: true })), ['A 1', 'A 2', 'A 3', 'C 1', 'C 2', ''])
+  t.deepEqual(pageTexts(mergePdf([a, b], { padToEven: true })), ['A 1', 'A 2', 'A 3', 'B 1'])
+})
+
+test('a clean merge reports no warnings', (t) => {
+  const { buffer, warnings } = mergePdf([simple(1), simple(1)], { report: true })
+  t.deepEqual(warnings, [])
+  t.true(Buffer.isBuffer(buffer))
+})
+
+test('merges the leaf pages of a nested page tree, with what they inherit', (t) => {
+  const page = (parent, content) => `<< /Type /Page /Parent ${parent} 0 R /Contents ${content} 0 R >>`
+  const text = (label) => ({ stream: `BT /F1 24 Tf 72 700 Td (${label}) Tj ET` })
+  const nested = build([
+    '<< /Type /Catalog /Pages 2 0 R >>',
+    '<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 4 /Resources << /Font << /F1 5 0 R >> >> >>',
+    '<< /Type /Pages /Parent 2 0 R /Kids [6 0 R 7 0 R] /Count 2 /MediaBox [0 0 300 300] >>',
+    '<< /Type /Pages /Parent 2 0 R /Kids [8 0 R 9 0 R] /Count 2 /MediaBox [0 0 400 400] /Rotate 90 >>',
+    '<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>',
+    page(3, 10),
+    page(3, 11),
+    page(4, 12),
+    page(4, 13),
+    text('Nested 1'),
+    text('Nested 2'),
+    text('Nested 3'),
+    text('Nested 4'),
+  ])
+  const merged = mergePdf([simple(1), nested])
+  t.deepEqual(pageTexts(merged), ['Page 1', 'Nested 1', 'Nested 2', 'Nested 3', 'Nested 4'])
+  const root = resolve(merged, catalog(merged)['/Pages'])
+  t.is(root['/Count'], 5)
+  t.is(root['/Kids'].length, 5)
+  t.deepEqual(
+    root['/Kids'].map((kid) => resolve(merged, kid)['/MediaBox']),
+    [[0, 0, 612, 792], [0, 0, 300, 300], [0, 0, 300, 300], [0, 0, 400, 400], [0, 0, 400, 400]],
+  )
+  t.deepEqual(getPageRotations(merged), [0, 0, 0, 90, 90])
+})
+
+// Two pages read as one article thread (object 8) of two beads (9 and 10), one per page
+const threaded = () =>
+  simple(2, {
+    catalog: '/Threads [8 0 R]',
+    page: (index) => `/B [${9 + index} 0 R]`,
+    extra: (objects) => {
+      objects.push('<< /Type /Thread /I << /Title (Story) >> /F 9 0 R >>')
+      objects.push(`<< /Type /Bead /T 8 0 R /N 10 0 R /V 10 0 R /P ${pageObject(1)} 0 R /R [72 72 300 700] >>`)
+      objects.push(`<< /Type /Bead /N 9 0 R /V 9 0 R /P ${pageObject(2)} 0 R /R [72 72 300 700] >>`)
+    },
+  })
+
+// Every object of the document, by number
+const objects = (buffer) => {
+  const found = []
+  for (let objNum = 1; objNum < getTrailer(buffer)['/Size']; objNum++) {
+    try {
+      found.push(getObject(buffer, objNum))
+    } catch (_) {
+      // A free object number
+    }
+  }
+  return found
+}
+
+test('article threads are removed with the page beads by default', (t) => {
+  const merged = mergePdf([threaded(), threaded()])
+  t.is(catalog(merged)['/Threads'], undefined)
+  t.deepEqual(
+    pageReferences(merged).map((page) => resolve(merged, page)['/B']),
+    [undefined, undefined, undefined, undefined],
+  )
+  t.false(objects(merged).some((object) => object && ['/Thread', '/Bead'].includes(object['/Type'])))
+})
+
+test('preserveThreads keeps the threads of every document, their beads on the merged pages', (t) => {
+  const merged = mergePdf([threaded(), threaded()], { preserveThreads: true })
+  const pages = pageReferences(merged)
+  const threads = catalog(merged)['/Threads']
+  t.is(threads.length, 2)
+  threads.forEach((thread, index) => {
+    const first = resolve(merged, thread)['/F']
+    const second = resolve(merged, first)['/N']
+    t.is(resolve(merged, first)['/T'], thread)
+    t.is(resolve(merged, second)['/N'], first)
+    // Each bead is listed by the page it points at
+    ;[first, second].forEach((bead, page) => {
+      const pageReference = resolve(merged, bead)['/P']
+      t.is(pageReference, pages[index * 2 + page])
+      t.deepEqual(resolve(merged, pageReference)['/B'], [bead])
+    })
+  })
+})
+
+test('tableOfContents starts with a page linking to every document, matching one bookmark each', (t) => {
+  const merged = mergePdf(
+    [
+      { buffer: simple(2, { label: 'Annual' }), title: 'Annual report' },
+      { buffer: simple(3, { label: 'Budget' }), title: 'Budget' },
+    ],
+    { tableOfContents: true },
+  )
+  t.deepEqual(pageTexts(merged).slice(1), ['Annual 1', 'Annual 2', 'Budget 1', 'Budget 2', 'Budget 3'])
+  t.is(shownText(getPageContent(merged, 1)), 'ContentsAnnual report2Budget4')
+  const links = resolve(merged, pageReferences(merged)[0])['/Annots'].map((link) => resolve(merged, link))
+  t.deepEqual(links.map((link) => pageNumber(merged, link['/Dest'][0])), [2, 4])
+  t.deepEqual(getOutline(merged), [
+    { title: 'Annual report', page: 2, children: [] },
+    { title: 'Budget', page: 4, children: [] },
+  ])
+})
+
+test('provenance records the documents and the producer in the XMP, under the highest version', (t) => {
+  const merged = mergePdf([{ buffer: simple(1, { version: '1.7' }), title: 'Annual' }, simple(1)], { provenance: true })
+  const producer = `vibes-pdf-utils ${version().crate}`
+  t.is(merged.subarray(0, 9).toString('latin1'), '%PDF-1.7\n')
+  const { stream } = resolve(merged, catalog(merged)['/Metadata'])
+  t.like(stream.dict, { '/Type': '/Metadata', '/Subtype': '/XML' })
+  const xmp = stream.data.toString('utf8')
+  t.regex(xmp, /^<\?xpacket begin="\ufeff" id="W5M0MpCehiHzreSzNTczkc9d"\?>/)
+  t.regex(xmp, /<\?xpacket end="w"\?>$/)
+  t.true(xmp.includes('<pdfu:SourceCount>2</pdfu:SourceCount>'))
+  t.true(xmp.includes('<rdf:li>Annual</rdf:li>'))
+  t.true(xmp.includes(`<pdf:Producer>${producer}</pdf:Producer>`))
+  t.is(getMetadata(merged).producer, producer)
+})
+
+test('sourceInfo records the Info title and author of every document in the XMP', (t) => {
+  // A one page document whose Info dictionary is object 6
+  const titled = (info) => simple(1, { trailer: '/Info 6 0 R', extra: (objects) => objects.push(`<< ${info} >>`) })
+  const merged = mergePdf([titled('/Title (Annual report) /Author (Finance)'), simple(1), titled('/Title (Q&A)')], {
+    sourceInfo: true,
+  })
+  const xmp = resolve(merged, catalog(merged)['/Metadata']).stream.data.toString('utf8')
+  t.true(xmp.includes('<pdfu:SourceCount>3</pdfu:SourceCount>'))
+  // The properties of every item of the sequence
+  const items = [...xmp.matchAll(/<rdf:li rdf:parseType="Resource">([^]*?)<\/rdf:li>/g)].map((item) =>
+    Object.fromEntries([...item[1].matchAll(/<pdfu:(\w+)>([^<]*)</g)].map((property) => property.slice(1))),
+  )
+  t.deepEqual(items, [
+    { Index: '0', Title: 'Annual report', Author: 'Finance' },
+    { Index: '1' },
+    { Index: '2', Title: 'Q&amp;A' },
+  ])
+})
+
+test('startObjectId numbers the merged objects from the given id', (t) => {
+  const merged = mergePdf([simple(2, { label: 'A' }), simple(1, { label: 'B' })], { startObjectId: 1000 })
+  const numbers = [...merged.toString('latin1').matchAll(/(\d+) 0 obj/g)].map((match) => Number(match[1]))
+  t.is(Math.min(...numbers), 1000)
+  t.deepEqual(numbers, Array.from({ length: numbers.length }, (_, index) => 1000 + index))
+  t.is(getTrailer(merged)['/Root'], '1000 0 R')
+  t.deepEqual(pageTexts(merged), ['A 1', 'A 2', 'B 1'])
+  t.throws(() => mergePdf([simple(1)], { startObjectId: 0 }), { code: 'InvalidArg' })
+})
+
+test('startObjectId keeps the object numbers within 8388607', (t) => {
+  for (const startObjectId of [-5, 1.5, 4294967295]) {
+    t.throws(() => mergePdf([simple(1)], { startObjectId }), {
+      code: 'InvalidArg',
+      message: `startObjectId must be an integer from 1 to 8388607, got ${startObjectId}`,
+    })
+  }
+  // The 5 objects and the information dictionary written with them don't fit
+  t.throws(() => mergePdf([simple(1)], { startObjectId: 8388603 }), {
+    code: 'LimitExceeded',
+    message: "The 5 objects of the result can't be numbered from startObjectId 8388603 without going past 8388607",
+  })
+})
+
+test('dedupePages keeps one copy of a page repeated across the documents', (t) => {
+  // Reports of two pages, the first being the same cover
+  const cover = { stream: 'BT /F1 24 Tf 72 700 Td (Cover) Tj ET' }
+  const report = (label) => ({ buffer: simple(2, { label, extra: (objects) => (objects[3] = cover) }), title: label })
+  const sources = [report('A'), report('B'), report('C')]
+  t.deepEqual(pageTexts(mergePdf(sources)), ['Cover', 'A 2', 'Cover', 'B 2', 'Cover', 'C 2'])
+  const merged = mergePdf(sources, { dedupePages: true, outlines: 'perFile', report: true })
+  t.deepEqual(pageTexts(merged.buffer), ['Cover', 'A 2', 'B 2', 'C 2'])
+  t.deepEqual(merged.warnings, ['2 duplicate pages were collapsed into their first copy'])
+  t.deepEqual(merged.sources, [
+    { source: 0, startPage: 1, endPage: 2 },
+    { source: 1, startPage: 3, endPage: 3 },
+    { source: 2, startPage: 4, endPage: 4 },
+  ])
+  // The bookmarks of the later reports go to the kept cover
+  t.deepEqual(getOutline(merged.buffer).map(({ title, page }) => [title, page]), [
+    ['A', 1],
+    ['B', 1],
+    ['C', 1],
+  ])
+})
+
+test('merged pages keep their own indexed color space and the pattern using it', (t) => {
+  // A page whose resources hold an indexed color space and a tiling pattern painting with it, objects 6 to 8
+  const colored = (lookup) =>
+    simple(1, {
+      resources: () => '<< /ColorSpace << /CS0 6 0 R >> /Pattern << /P0 8 0 R >> /ProcSet [/PDF] >>',
+      extra: (objects) =>
+        objects.push('[/Indexed /DeviceRGB 1 7 0 R]', { stream: Buffer.from(lookup, 'hex') }, {
+          dict:
+            '/Type /Pattern /PatternType 1 /PaintType 1 /TilingType 1 /BBox [0 0 10 10] /XStep 10 /YStep 10 ' +
+            '/Resources << /ColorSpace << /CS0 6 0 R >> >>',
+          stream: '/CS0 cs 1 sc 0 0 10 10 re f',
+        }),
+    })
+  const merged = mergePdf([colored('000000ff0000'), colored('0000000000ff')])
+  const colors = pageReferences(merged).map((reference) => {
+    const resources = resolve(merged, resolve(merged, reference)['/Resources'])
+    const colorSpace = resolve(merged, resources['/ColorSpace']['/CS0'])
+    const pattern = resolve(merged, resources['/Pattern']['/P0']).stream
+    // The pattern paints in the page's color space
+    t.is(pattern.dict['/Resources']['/ColorSpace']['/CS0'], resources['/ColorSpace']['/CS0'])
+    t.is(pattern.data.toString('latin1'), '/CS0 cs 1 sc 0 0 10 10 re f')
+    t.deepEqual(resources['/ProcSet'], ['/PDF'])
+    t.deepEqual(colorSpace.slice(0, 3), ['/Indexed', '/DeviceRGB', 1])
+    return resolve(merged, colorSpace[3]).stream.data.toString('hex')
+  })
+  t.deepEqual(colors, ['000000ff0000', '0000000000ff'])
+})
+
+test('normalizeToFirst redraws every page at the size of the first one', (t) => {
+  const sources = [simple(1, { width: 595, height: 842, label: 'A4' }), simple(2, { label: 'Letter' })]
+  const mediaBoxes = (buffer) => pageReferences(buffer).map((reference) => resolve(buffer, reference)['/MediaBox'])
+  const drawing = (buffer, n) => getPageContent(buffer, n).toString('latin1').split('\n')[0]
+  // Letter pages are scaled by 595 / 612 and centered vertically
+  const scaled = mergePdf(sources, { normalizeToFirst: true })
+  t.deepEqual(mediaBoxes(scaled), Array(3).fill([0, 0, 595, 842]))
+  t.deepEqual(pageTexts(scaled), ['A4 1', 'Letter 1', 'Letter 2'])
+  t.is(drawing(scaled, 1), 'BT /F1 24 Tf 72 700 Td (A4 1) Tj ET')
+  t.is(drawing(scaled, 2), `q ${595 / 612} 0 0 ${595 / 612} 0 36 cm`)
+  // or only centered
+  const centered = mergePdf(sources, { normalizeToFirst: true, normalizeFit: 'center' })
+  t.deepEqual(mediaBoxes(centered), Array(3).fill([0, 0, 595, 842]))
+  t.is(drawing(centered, 3), 'q 1 0 0 1 -8.5 25 cm')
+  t.throws(() => mergePdf(sources, { normalizeToFirst: true, normalizeFit: 'stretch' }), { code: 'InvalidArg' })
+})
+
+test('the embedded files and appearance streams of every document are merged', (t) => {
+  // Files named `name`, holding `${label} ${name}`, from object 6 on, and a `stamp` appearance after them
+  const attaching = (label, names) => {
+    const files = names.map((name, index) => `(${name}) ${6 + 2 * index} 0 R`).join(' ')
+    const stamp = `${6 + 2 * names.length} 0 R`
+    return simple(1, {
+      catalog: `/Names << /EmbeddedFiles << /Names [${files}] >> /AP << /Names [(stamp) ${stamp}] >> >>`,
+      extra: (objects) => {
+        for (const name of names) {
+          objects.push(`<< /Type /Filespec /F (${name}) /EF << /F ${objects.length + 2} 0 R >> >>`, {
+            dict: '/Type /EmbeddedFile',
+            stream: `${label} ${name}`,
+          })
+        }
+        objects.push({ dict: '/Type /XObject /Subtype /Form /BBox [0 0 10 10]', stream: `${label} stamp` })
+      },
+    })
+  }
+  // Every name of a tree with what its file or stream holds
+  const contents = (buffer, tree) =>
+    treeEntries(buffer, resolve(buffer, catalog(buffer)['/Names'])[tree]).map(([name, reference]) => {
+      const value = resolve(buffer, reference)
+      const { stream } = value['/EF'] ? resolve(buffer, value['/EF']['/F']) : value
+      return [name, stream.data.toString('latin1')]
+    })
+  const sources = [attaching('A', ['a.txt', 'shared.txt']), attaching('B', ['b.txt', 'shared.txt'])]
+  const merged = mergePdf(sources)
+  t.deepEqual(contents(merged, '/EmbeddedFiles'), [
+    ['u:a.txt', 'A a.txt'],
+    ['u:b.txt', 'B b.txt'],
+    ['u:shared.txt', 'A shared.txt'],
+    ['u:shared.txt_2', 'B shared.txt'],
+  ])
+  t.deepEqual(contents(merged, '/AP'), [
+    ['u:stamp', 'A stamp'],
+    ['u:stamp_2', 'B stamp'],
+  ])
+  // Each tree with its own policy
+  const firstWins = mergePdf(sources, { nameTreeConflicts: { EmbeddedFiles: 'first-wins' } })
+  t.deepEqual(contents(firstWins, '/EmbeddedFiles'), [
+    ['u:a.txt', 'A a.txt'],
+    ['u:b.txt', 'B b.txt'],
+    ['u:shared.txt', 'A shared.txt'],
+  ])
+  t.is(contents(firstWins, '/AP').length, 2)
+  t.throws(() => mergePdf(sources, { nameTreeConflicts: { AP: 'error' } }), {
+    message: "Document 1: the name 'stamp' of the /AP name tree is also defined by an earlier document",
+  })
+})
+
+test('remapObjectId numbers the objects of every document as the callback asks', (t) => {
+  const calls = []
+  const remapObjectId = (source, id) => {
+    calls.push([source, id])
+    return (source + 1) * 100 + id
+  }
+  const merged = mergePdf([simple(1, { label: 'A' }), simple(1, { label: 'B' })], { remapObjectId })
+  t.deepEqual(calls, [0, 1].flatMap((source) => [1, 2, 3, 4, 5].map((id) => [source, id])))
+  const ids = objectMap(merged).map(({ id }) => id)
+  // Without the second catalog and page tree root, the Info dictionary the merge adds comes last
+  t.deepEqual(ids.slice(0, -1), [101, 102, 103, 104, 105, 203, 204, 205])
+  t.true(ids[ids.length - 1] > 205)
+  t.is(getTrailer(merged)['/Root'], '101 0 R')
+  t.deepEqual(pageTexts(merged), ['A 1', 'B 1'])
+})
+
+test('remapObjectId must give distinct integers from 1 to 8388607', (t) => {
+  const sources = [simple(1), simple(1)]
+  t.throws(() => mergePdf(sources, { remapObjectId: () => 5 }), {
+    code: 'InvalidArg',
+    message: 'remapObjectId gave 5 to both object 1 of document 0 and object 2 of document 0',
+  })
+  for (const id of [0, -1, 1.5, '7', 4294967295]) {
+    t.throws(() => mergePdf(sources, { remapObjectId: () => id }), {
+      code: 'InvalidArg',
+      message: 'remapObjectId returned no integer from 1 to 8388607 for object 1 of document 0',
+    })
+  }
+  t.throws(() => mergePdf(sources, { remapObjectId: (_, id) => id, startObjectId: 10 }), { code: 'InvalidArg' })
+  // What the callback throws is thrown as it is
+  const remapObjectId = () => {
+    throw new Error('No layout for this object')
+  }
+  t.throws(() => mergePdf(sources, { remapObjectId }), { message: 'No layout for this object' })
+})
+
+test('dryRun reports the pages and version a merge gives without merging', (t) => {
+  // A bookmark on the first page of the second document
+  const bookmarked = simple(3, {
+    catalog: '/Outlines 10 0 R',
+    extra: (objects) =>
+      objects.push(
+        '<< /Type /Outlines /First 11 0 R /Last 11 0 R /Count 1 >>',
+        `<< /Title (Intro) /Parent 10 0 R /Dest [${pageObject(1)} 0 R /Fit] >>`,
+      ),
+  })
+  const sources = [simple(2, { version: '1.7' }), bookmarked, simple(1)]
+  for (const options of [{}, { padEachSource: true, padToEven: true }, { tableOfContents: true }]) {
+    const plan = mergePdf(sources, { ...options, dryRun: true })
+    const { buffer, sources: ranges, warnings } = mergePdf(sources, { ...options, report: true })
+    t.is(plan.pageCount, pageReferences(buffer).length)
+    t.is(plan.version, buffer.subarray(5, 8).toString('latin1'))
+    t.deepEqual(plan.warnings, warnings)
+    t.deepEqual(plan.sources, ranges.map((range, index) => ({ ...range, outlines: index === 1 })))
+    t.is(plan.estimatedSize, sources.reduce((size, source) => size + source.length, 0))
+  }
+})
@@ -0,0 +1,29 @@
+const crypto = require('crypto')
+
+const test = require('ava')
+
+const { mergePdfBounded } = require('../index')
+
+const { pageTexts } = require('./helpers')
+const { simple } = require('./pdf')
+
+// A one page document of about 3.7 kB, most of it an incompressible private stream
+const padded = (label) =>
+  simple(1, {
+    label,
+    page: () => '/PieceInfo << /Test << /Private 6 0 R >> >>',
+    extra: (objects) => objects.push({ stream: crypto.randomBytes(3000) }),
+  })
+
+test('mergePdfBounded starts a new part when the next document would pass the cap', (t) => {
+  const parts = mergePdfBounded([padded('A'), padded('B'), padded('C')], 8000)
+  t.deepEqual(parts.map(pageTexts), [['A 1', 'B 1'], ['C 1']])
+  t.true(parts.every((part) => part.length <= 8000))
+})
+
+test('mergePdfBounded puts a document over the cap in a part of its own, with a warning', (t) => {
+  const { buffers, warnings } = mergePdfBounded([simple(1), padded('Large')], 2000, { report: true })
+  t.deepEqual(buffers.map(pageTexts), [['Page 1'], ['Large 1']])
+  t.is(warnings.length, 1)
+  t.regex(warnings[0], /^Document 1: \d+ bytes on its own, over maxBytes 2000$/)
+})
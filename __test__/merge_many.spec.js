@@ -0,0 +1,17 @@
+const test = require('ava')
+
+const { getPageContent, mergePdf, probe } = require('../index')
+
+const { shownText, simple } = require('./pdf')
+
+test('merges 200 small files within a time budget', (t) => {
+  const buffers = Array.from({ length: 200 }, (_, index) => simple(2, { label: `Document ${index}` }))
+  const start = process.hrtime.bigint()
+  const merged = mergePdf(buffers)
+  const milliseconds = Number(process.hrtime.bigint() - start) / 1e6
+  t.log(`merged 200 files in ${Math.round(milliseconds)} ms`)
+  // Generous for a debug build on a slow machine, a merge quadratic in the files takes far longer
+  t.true(milliseconds < 30000)
+  t.is(probe(merged).pageCount, 400)
+  t.is(shownText(getPageContent(merged, 400)), 'Document 199 2')
+})
@@ -0,0 +1,42 @@
+const test = require('ava')
+
+const { getPageContent, normalizeBlack } = require('../index')
+
+const { pageReferences, resolve } = require('./helpers')
+const { simple } = require('./pdf')
+
+// A rich black rectangle, a rich black stroke at 97% black and a dark red at 90%, then the form 6
+// filling with a rich black of its own
+const printed = () =>
+  simple(1, {
+    resources: () => '<< /XObject << /X0 6 0 R >> >>',
+    extra: (objects) => {
+      objects[3] = {
+        stream:
+          '0.6 0.4 0.4 1 k 0 0 100 100 re f 0.3 0.3 0.3 0.97 K 0 0 m 10 10 l S 0.5 0 0 0.9 k 0 0 10 10 re f /X0 Do',
+      }
+      objects.push({ dict: '/Type /XObject /Subtype /Form /BBox [0 0 10 10]', stream: '0.5 0.5 0.5 1 k 0 0 5 5 re f' })
+    },
+  })
+
+// The color operators of a content stream
+const colors = (content) => content.toString('latin1').match(/^.* [kK]$/gm)
+
+test('normalizeBlack replaces rich blacks by plain black', (t) => {
+  const normalized = normalizeBlack(printed())
+  t.deepEqual(colors(getPageContent(normalized, 1)), ['0 0 0 1 k', '0 0 0 1 K', '0.5 0 0 0.9 k'])
+  const page = resolve(normalized, pageReferences(normalized)[0])
+  const form = resolve(normalized, resolve(normalized, page['/Resources'])['/XObject']['/X0'])
+  t.deepEqual(colors(form.stream.data), ['0 0 0 1 k'])
+})
+
+test('tolerance sets how far below 100% the black may be', (t) => {
+  const normalized = normalizeBlack(printed(), { tolerance: 0.1 })
+  t.deepEqual(colors(getPageContent(normalized, 1)), ['0 0 0 1 k', '0 0 0 1 K', '0 0 0 1 k'])
+  t.deepEqual(colors(getPageContent(normalizeBlack(printed(), { tolerance: 0 }), 1)), [
+    '0 0 0 1 k',
+    '0.3 0.3 0.3 0.97 K',
+    '0.5 0 0 0.9 k',
+  ])
+  t.throws(() => normalizeBlack(printed(), { tolerance: 1 }), { code: 'InvalidArg' })
+})
@@ -0,0 +1,86 @@
+const test = require('ava')
+
+const { expandObjectStreams, mergePdf, stats, validate } = require('../index')
+
+const { pageTexts } = require('./helpers')
+const { simple } = require('./pdf')
+
+test('expandObjectStreams writes every object plainly with a classic xref table', (t) => {
+  const packed = simple(2, { objectStream: true, version: '1.5' })
+  t.true(stats(packed).compressedXref)
+  const expanded = expandObjectStreams(packed)
+  const source = expanded.toString('latin1')
+  t.false(source.includes('/ObjStm'))
+  t.false(source.includes('/XRef'))
+  t.regex(source, /\nxref\n0 \d+\n/)
+  const { objectCount, streamObjectCount, compressedXref } = stats(expanded)
+  // The seven objects of the document and the Info dictionary, the two streams are the contents
+  t.deepEqual(
+    { objectCount, streamObjectCount, compressedXref },
+    { objectCount: 8, streamObjectCount: 2, compressedXref: false },
+  )
+  t.true(validate(expanded).ok)
+  t.deepEqual(pageTexts(expanded), ['Page 1', 'Page 2'])
+})
+
+test('mergePdf writes a classic xref table, even from sources with object streams', (t) => {
+  const packed = simple(2, { objectStream: true, version: '1.5' })
+  for (const options of [{}, { noObjectStreams: true }]) {
+    const merged = mergePdf([packed, simple(1)], options)
+    const source = merged.toString('latin1')
+    t.false(source.includes('/ObjStm'))
+    t.false(source.includes('/XRef'))
+    t.regex(source, /\nxref\n0 \d+\n/)
+    t.false(stats(merged).compressedXref)
+    t.deepEqual(pageTexts(merged), ['Page 1', 'Page 2', 'Page 1'])
+  }
+})
+
+// A hybrid-reference file of two pages: the classic table lists objects 1 to 6, page 2 (object 7)
+// is only in the object stream 8, which the cross-reference stream 9 of `/XRefStm` indexes
+function hybrid() {
+  const parts = ['%PDF-1.5\n']
+  const offsets = []
+  let offset = parts[0].length
+  const write = (number, body) => {
+    offsets[number] = offset
+    const text = `${number} 0 obj\n${body}\nendobj\n`
+    parts.push(text)
+    offset += text.length
+  }
+  const stream = (dict, data) => `<< ${dict} /Length ${data.length} >>\nstream\n${data}\nendstream`
+  const page = (contents) => `<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents ${contents} 0 R >>`
+  write(1, '<< /Type /Catalog /Pages 2 0 R >>')
+  write(2, '<< /Type /Pages /Kids [5 0 R 7 0 R] /Count 2 >>')
+  write(3, '<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>')
+  write(4, stream('', 'BT /F1 24 Tf 72 700 Td (Page 1) Tj ET'))
+  write(5, page(4).replace(' >>', ' /Resources << /Font << /F1 3 0 R >> >> >>'))
+  write(6, stream('', 'BT /F1 24 Tf 72 700 Td (Page 2) Tj ET'))
+  const packed = page(6).replace(' >>', ' /Resources << /Font << /F1 3 0 R >> >> >>')
+  write(8, stream('/Type /ObjStm /N 1 /First 4', `7 0 ${packed}`))
+  // Type 2 entry for object 7, type 1 for the object stream, as [1 4 2] fields
+  const entries = Buffer.alloc(14)
+  entries.writeUInt8(2, 0)
+  entries.writeUInt32BE(8, 1)
+  entries.writeUInt8(1, 7)
+  entries.writeUInt32BE(offsets[8], 8)
+  const xrefStream = offset
+  write(9, stream('/Type /XRef /Size 10 /Index [7 2] /W [1 4 2]', entries.toString('latin1')))
+  let table = 'xref\n0 10\n0000000000 65535 f \n'
+  for (let number = 1; number < 10; number++) {
+    table += number <= 6 ? `${String(offsets[number]).padStart(10, '0')} 00000 n \n` : '0000000000 00000 f \n'
+  }
+  const trailer = `trailer\n<< /Size 10 /Root 1 0 R /XRefStm ${xrefStream} >>\nstartxref\n${offset}\n%%EOF`
+  return Buffer.from(parts.join('') + table + trailer, 'latin1')
+}
+
+test('the objects only the cross-reference stream of a hybrid file indexes are read', (t) => {
+  const source = hybrid()
+  t.like(stats(source), { hybridXref: true, objectCount: 9 })
+  t.false(stats(simple(1)).hybridXref)
+  t.true(validate(source).ok)
+  t.deepEqual(pageTexts(source), ['Page 1', 'Page 2'])
+  const merged = mergePdf([source, simple(1, { label: 'Next' })])
+  t.true(validate(merged).ok)
+  t.deepEqual(pageTexts(merged), ['Page 1', 'Page 2', 'Next 1'])
+})
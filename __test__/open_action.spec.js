@@ -0,0 +1,34 @@
+const test = require('ava')
+
+const { setOpenAction } = require('../index')
+
+const { catalog, pageNumber, resolve } = require('./helpers')
+const { simple } = require('./pdf')
+
+const openAction = (buffer) => resolve(buffer, catalog(buffer)['/OpenAction'])
+
+test('setOpenAction jumps to a page, keeping the zoom by default', (t) => {
+  const opened = setOpenAction(simple(4), { page: 3 })
+  const [page, ...view] = openAction(opened)
+  t.is(pageNumber(opened, page), 3)
+  t.deepEqual(view, ['/XYZ', null, null, null])
+})
+
+test('setOpenAction sets the zoom of the page', (t) => {
+  const zoomed = (zoom) => openAction(setOpenAction(simple(4), { page: 3, zoom })).slice(1)
+  t.deepEqual(zoomed('fit'), ['/Fit'])
+  t.deepEqual(zoomed('fitWidth'), ['/FitH', null])
+  t.deepEqual(zoomed(1.5), ['/XYZ', null, null, 1.5])
+})
+
+test('setOpenAction opens a URI', (t) => {
+  const action = openAction(setOpenAction(simple(1), { url: 'https://example.com/report' }))
+  t.is(action['/S'], '/URI')
+  t.is(action['/URI'], 'u:https://example.com/report')
+})
+
+test('setOpenAction needs one page in range or a url', (t) => {
+  t.throws(() => setOpenAction(simple(4), { page: 5 }), { code: 'PageOutOfRange' })
+  t.throws(() => setOpenAction(simple(4), {}), { code: 'InvalidArg' })
+  t.throws(() => setOpenAction(simple(4), { page: 1, url: 'https://example.com' }), { code: 'InvalidArg' })
+})
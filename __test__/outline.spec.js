@@ -0,0 +1,137 @@
+const test = require('ava')
+
+const { getOutline, mergePdf, setOutline } = require('../index')
+
+const { catalog, pageNumber, resolve } = require('./helpers')
+const { pageObject, simple } = require('./pdf')
+
+// Chapter 1 on page 1 with Section 1.1 on page 2 through a GoTo action, Chapter 2 on page 3 through
+// a named destination
+const outlined = () =>
+  simple(3, {
+    catalog: '/Outlines 10 0 R /Names << /Dests 14 0 R >>',
+    extra: (objects) =>
+      objects.push(
+        '<< /Type /Outlines /First 11 0 R /Last 12 0 R /Count 3 >>',
+        `<< /Title (Chapter 1) /Parent 10 0 R /Next 12 0 R /First 13 0 R /Last 13 0 R /Count 1 ` +
+          `/Dest [${pageObject(1)} 0 R /Fit] >>`,
+        '<< /Title (Chapter 2) /Parent 10 0 R /Prev 11 0 R /Dest (ch2) >>',
+        `<< /Title (Section 1.1) /Parent 11 0 R /A << /S /GoTo /D [${pageObject(2)} 0 R /XYZ 0 792 0] >> >>`,
+        `<< /Names [(ch2) [${pageObject(3)} 0 R /Fit]] >>`,
+      ),
+  })
+
+const twoLevels = [
+  { title: 'Chapter 1', page: 1, children: [{ title: 'Section 1.1', page: 2, children: [] }] },
+  { title: 'Chapter 2', page: 3, children: [] },
+]
+
+test('getOutline reads the nested bookmarks with their pages', (t) => {
+  t.deepEqual(getOutline(outlined()), twoLevels)
+  t.deepEqual(getOutline(simple(1)), [])
+})
+
+test('getOutline leaves out the page of a bookmark pointing nowhere', (t) => {
+  const dangling = simple(1, {
+    catalog: '/Outlines 6 0 R',
+    extra: (objects) =>
+      objects.push('<< /First 7 0 R /Last 7 0 R /Count 1 >>', '<< /Title (Missing) /Parent 6 0 R /Dest (nowhere) >>'),
+  })
+  t.deepEqual(getOutline(dangling), [{ title: 'Missing', children: [] }])
+})
+
+test('setOutline writes an outline read back identically', (t) => {
+  const written = setOutline(simple(3), twoLevels)
+  t.deepEqual(getOutline(written), twoLevels)
+  const root = resolve(written, catalog(written)['/Outlines'])
+  t.is(root['/Count'], 3)
+  const first = resolve(written, root['/First'])
+  const last = resolve(written, root['/Last'])
+  t.is(first['/Next'], root['/Last'])
+  t.is(last['/Prev'], root['/First'])
+  t.is(first['/Parent'], catalog(written)['/Outlines'])
+  t.is(pageNumber(written, first['/Dest'][0]), 1)
+  const section = resolve(written, first['/First'])
+  t.is(section['/Parent'], root['/First'])
+  t.is(first['/Count'], 1)
+})
+
+test('setOutline replaces the bookmarks and an empty outline removes them', (t) => {
+  t.deepEqual(getOutline(setOutline(outlined(), [{ title: 'Only', page: 2 }])), [
+    { title: 'Only', page: 2, children: [] },
+  ])
+  t.is(catalog(setOutline(outlined(), []))['/Outlines'], undefined)
+  t.throws(() => setOutline(simple(3), [{ title: 'Beyond', page: 4 }]), { code: 'PageOutOfRange' })
+})
+
+// A bookmark nesting `depth` levels of its single child, each on page 1
+const nested = (depth) => {
+  let item = { title: `Level ${depth}`, page: 1 }
+  for (let level = depth - 1; level > 0; level--) {
+    item = { title: `Level ${level}`, page: 1, children: [item] }
+  }
+  return [item]
+}
+
+// The number of levels of an outline having a single item on each
+const depth = (items) => {
+  let levels = 0
+  for (; items.length; items = items[0].children) {
+    levels++
+  }
+  return levels
+}
+
+test('setOutline rejects bookmarks nested deeper than 256 levels', (t) => {
+  t.is(depth(getOutline(setOutline(simple(1), nested(256)))), 256)
+  t.throws(() => setOutline(simple(1), nested(5000)), {
+    code: 'InvalidArg',
+    message: 'Bookmarks can be nested at most 256 levels deep',
+  })
+})
+
+test('getOutline stops at 256 levels of a deeper outline in the document', (t) => {
+  // Level n is object 6 + n, the only child of level n - 1, and the root object 6
+  const levels = 5000
+  const deep = simple(1, {
+    catalog: '/Outlines 6 0 R',
+    extra: (objects) => {
+      objects.push('<< /Type /Outlines /First 7 0 R /Last 7 0 R >>')
+      for (let level = 1; level <= levels; level++) {
+        const children = level < levels ? `/First ${7 + level} 0 R /Last ${7 + level} 0 R` : ''
+        objects.push(`<< /Title (Level ${level}) /Parent ${5 + level} 0 R /Dest [5 0 R /Fit] ${children} >>`)
+      }
+    },
+  })
+  t.is(depth(getOutline(deep)), 256)
+  t.deepEqual(getOutline(setOutline(deep, [{ title: 'Only', page: 1 }])), [{ title: 'Only', page: 1, children: [] }])
+})
+
+// Two outlined documents around one without an outline, the outline items having no /Type
+const sources = () => {
+  const outline = setOutline(simple(2), [{ title: 'Intro', page: 1, children: [{ title: 'Detail', page: 2 }] }])
+  return [{ buffer: outline, title: 'First' }, simple(1, { label: 'X' }), { buffer: outline, title: 'Third' }]
+}
+
+test('mergePdf drops the outlines of the sources by default', (t) => {
+  for (const options of [{}, { outlines: 'drop' }]) {
+    const merged = mergePdf(sources(), options)
+    t.deepEqual(getOutline(merged), [])
+    t.is(catalog(merged)['/Outlines'], undefined)
+    t.false(merged.includes('Intro'))
+    t.false(merged.includes('Detail'))
+  }
+})
+
+test('mergePdf with outlines merge keeps every outline pointing at the merged pages', (t) => {
+  const branch = (page) => ({ title: 'Intro', page, children: [{ title: 'Detail', page: page + 1, children: [] }] })
+  t.deepEqual(getOutline(mergePdf(sources(), { outlines: 'merge' })), [branch(1), branch(4)])
+})
+
+test('mergePdf with outlines perFile writes a bookmark per source', (t) => {
+  t.deepEqual(getOutline(mergePdf(sources(), { outlines: 'perFile' })), [
+    { title: 'First', page: 1, children: [] },
+    { title: 'Document 2', page: 3, children: [] },
+    { title: 'Third', page: 4, children: [] },
+  ])
+})
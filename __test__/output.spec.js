@@ -0,0 +1,64 @@
+const fs = require('fs')
+const os = require('os')
+const path = require('path')
+
+const test = require('ava')
+
+const { extractPages, extractRangeToFile, mergePdf, mergePdfToStream, probe, validate } = require('../index')
+
+const { pageTexts } = require('./helpers')
+const { simple } = require('./pdf')
+
+function temporaryDirectory(t) {
+  const directory = fs.mkdtempSync(path.join(os.tmpdir(), 'pdf-utils-'))
+  t.teardown(() => fs.rmSync(directory, { recursive: true, force: true }))
+  return directory
+}
+
+test('outPath writes the merge to a file and returns undefined', (t) => {
+  const outPath = path.join(temporaryDirectory(t), 'merged.pdf')
+  t.is(mergePdf([simple(1), simple(2)], { outPath }), undefined)
+  const written = fs.readFileSync(outPath)
+  t.true(validate(written).ok)
+  t.is(probe(written).pageCount, 3)
+})
+
+test('outPath works the same for the other operations', (t) => {
+  const outPath = path.join(temporaryDirectory(t), 'extracted.pdf')
+  t.is(extractPages(simple(3), [2, 3], { outPath }), undefined)
+  t.is(probe(fs.readFileSync(outPath)).pageCount, 2)
+})
+
+test('extractRangeToFile writes the inclusive page range to a file', (t) => {
+  const outPath = path.join(temporaryDirectory(t), 'range.pdf')
+  t.is(extractRangeToFile(simple(6), 2, 4, outPath), undefined)
+  const written = fs.readFileSync(outPath)
+  t.true(validate(written).ok)
+  t.is(probe(written).pageCount, 3)
+  t.deepEqual(pageTexts(written), ['Page 2', 'Page 3', 'Page 4'])
+})
+
+test('extractRangeToFile throws on bad bounds and writes nothing', (t) => {
+  const directory = temporaryDirectory(t)
+  const outPath = path.join(directory, 'range.pdf')
+  t.throws(() => extractRangeToFile(simple(6), 0, 2, outPath), { code: 'InvalidArg' })
+  t.throws(() => extractRangeToFile(simple(6), 4, 2, outPath), { code: 'InvalidArg' })
+  t.throws(() => extractRangeToFile(simple(6), 5, 7, outPath), { code: 'PageOutOfRange' })
+  t.deepEqual(fs.readdirSync(directory), [])
+})
+
+test('mergePdfToStream writes the merge into a Writable', async (t) => {
+  const outPath = path.join(temporaryDirectory(t), 'streamed.pdf')
+  const writable = fs.createWriteStream(outPath)
+  await mergePdfToStream([simple(2), simple(2)], writable)
+  await new Promise((resolve, reject) => writable.end(resolve).on('error', reject))
+  const written = fs.readFileSync(outPath)
+  t.true(validate(written).ok)
+  t.is(probe(written).pageCount, 4)
+})
+
+test('mergePdfToStream rejects with the code of the failure', async (t) => {
+  const writable = fs.createWriteStream(path.join(temporaryDirectory(t), 'failed.pdf'))
+  await t.throwsAsync(mergePdfToStream([simple(1)], writable, { maxPages: 0 }), { code: 'LimitExceeded' })
+  writable.destroy()
+})
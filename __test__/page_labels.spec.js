@@ -0,0 +1,109 @@
+const test = require('ava')
+
+const { getPageLabels, mergePdf, resolveIndexToLabel, resolveLabelToIndex, setPageLabels } = require('../index')
+
+const { catalog, treeEntries } = require('./helpers')
+const { simple } = require('./pdf')
+
+const labels = (buffer, count) => Array.from({ length: count }, (_, index) => resolveIndexToLabel(buffer, index))
+
+test('setPageLabels numbers the front matter in roman numerals and the rest in arabic', (t) => {
+  const labeled = setPageLabels(simple(6), [
+    { startPage: 1, style: 'r' },
+    { startPage: 4, style: 'D' },
+  ])
+  t.deepEqual(getPageLabels(labeled), [
+    { startPage: 1, style: 'r' },
+    { startPage: 4, style: 'D' },
+  ])
+  // The number tree is keyed by 0-based page index
+  t.deepEqual(treeEntries(labeled, catalog(labeled)['/PageLabels'], '/Nums'), [
+    [0, { '/S': '/r' }],
+    [3, { '/S': '/D' }],
+  ])
+  t.deepEqual(labels(labeled, 6), ['i', 'ii', 'iii', '1', '2', '3'])
+})
+
+test('setPageLabels keeps the prefix and the first number of a range', (t) => {
+  const labeled = setPageLabels(simple(2), [{ startPage: 1, style: 'A', prefix: 'App-', start: 3 }])
+  t.deepEqual(getPageLabels(labeled), [{ startPage: 1, style: 'A', prefix: 'App-', start: 3 }])
+  t.deepEqual(labels(labeled, 2), ['App-C', 'App-D'])
+})
+
+test('setPageLabels removes the labels given no range', (t) => {
+  const labeled = setPageLabels(simple(2), [{ startPage: 1, style: 'r' }])
+  const cleared = setPageLabels(labeled, [])
+  t.deepEqual(getPageLabels(cleared), [])
+  t.is(catalog(cleared)['/PageLabels'], undefined)
+})
+
+test('setPageLabels needs the first range to start on page 1', (t) => {
+  t.throws(() => setPageLabels(simple(2), [{ startPage: 2, style: 'D' }]), {
+    message: 'The first page label range must start on page 1',
+  })
+})
+
+test('mergePdf concatenates the label ranges, rebased on the page each source starts on', (t) => {
+  const front = setPageLabels(simple(6), [
+    { startPage: 1, style: 'r' },
+    { startPage: 4, style: 'D' },
+  ])
+  const appendix = setPageLabels(simple(2), [{ startPage: 1, style: 'A', prefix: 'App-', start: 3 }])
+  const merged = mergePdf([front, appendix])
+  t.deepEqual(getPageLabels(merged), [
+    { startPage: 1, style: 'r' },
+    { startPage: 4, style: 'D' },
+    { startPage: 7, style: 'A', prefix: 'App-', start: 3 },
+  ])
+  t.deepEqual(labels(merged, 8), ['i', 'ii', 'iii', '1', '2', '3', 'App-C', 'App-D'])
+})
+
+test('mergePdf numbers the pages of an unlabeled source in decimal', (t) => {
+  const appendix = setPageLabels(simple(2), [{ startPage: 1, style: 'A' }])
+  const merged = mergePdf([simple(2), appendix])
+  t.deepEqual(getPageLabels(merged), [
+    { startPage: 1, style: 'D' },
+    { startPage: 3, style: 'A' },
+  ])
+  t.deepEqual(labels(merged, 4), ['1', '2', 'A', 'B'])
+})
+
+test('resolveLabelToIndex finds the page shown with a label, and resolveIndexToLabel the reverse', (t) => {
+  const labeled = setPageLabels(simple(6), [
+    { startPage: 1, style: 'r' },
+    { startPage: 4, style: 'D' },
+  ])
+  t.is(resolveLabelToIndex(labeled, 'iii'), 2)
+  t.is(resolveLabelToIndex(labeled, '2'), 4)
+  t.is(resolveIndexToLabel(labeled, 2), 'iii')
+  // Without labels, pages are labeled with their page number
+  t.is(resolveLabelToIndex(simple(3), '2'), 1)
+})
+
+test('resolveLabelToIndex throws for an unknown label, resolveIndexToLabel for an index out of range', (t) => {
+  const labeled = setPageLabels(simple(6), [{ startPage: 1, style: 'r' }])
+  t.throws(() => resolveLabelToIndex(labeled, 'x'), { message: "No page is labeled 'x'" })
+  t.throws(() => resolveIndexToLabel(labeled, 6), { code: 'PageOutOfRange' })
+  t.throws(() => resolveIndexToLabel(labeled, -1), { code: 'InvalidArg' })
+})
+
+test('setPageLabels only takes integer startPage and start values, start at most 100000', (t) => {
+  const ranges = [
+    [{ startPage: 1.5 }, 'startPage must be an integer from 1 to 8388607, got 1.5'],
+    [{ startPage: -1 }, 'startPage must be an integer from 1 to 8388607, got -1'],
+    [{ startPage: 1, style: 'a', start: -5 }, 'start must be an integer from 1 to 100000, got -5'],
+    [{ startPage: 1, style: 'a', start: 2.5 }, 'start must be an integer from 1 to 100000, got 2.5'],
+    [{ startPage: 1, style: 'a', start: 100001 }, 'start must be an integer from 1 to 100000, got 100001'],
+  ]
+  for (const [range, message] of ranges) {
+    t.throws(() => setPageLabels(simple(2), [range]), { code: 'InvalidArg', message })
+  }
+  const highest = setPageLabels(simple(1), [{ startPage: 1, style: 'a', start: 100000 }])
+  t.is(resolveIndexToLabel(highest, 0), 'd'.repeat(3847))
+})
+
+test('a /St out of bounds in the document numbers the range from 1', (t) => {
+  const labeled = simple(2, { catalog: '/PageLabels << /Nums [0 << /S /a /St 4294967291 >>] >>' })
+  t.deepEqual(getPageLabels(labeled), [{ startPage: 1, style: 'a' }])
+  t.deepEqual(labels(labeled, 2), ['a', 'b'])
+})
@@ -0,0 +1,65 @@
+const test = require('ava')
+
+const { fixPageTree, getDefaultMediaBox, setDefaultMediaBox, validate } = require('../index')
+
+const { catalog, pageReferences, pageTexts, resolve } = require('./helpers')
+const { simple } = require('./pdf')
+
+test('fixPageTree corrects a wrong /Count', (t) => {
+  const broken = simple(3, {
+    extra: (objects) => (objects[1] = objects[1].replace('/Count 3', '/Count 5')),
+  })
+  t.false(validate(broken).ok)
+  const fixed = fixPageTree(broken)
+  t.true(validate(fixed).ok)
+  t.is(resolve(fixed, catalog(fixed)['/Pages'])['/Count'], 3)
+  t.deepEqual(pageTexts(fixed), ['Page 1', 'Page 2', 'Page 3'])
+})
+
+test('fixPageTree repairs missing /Parent links', (t) => {
+  const broken = simple(2, {
+    extra: (objects) => (objects[4] = objects[4].replace('/Parent 2 0 R', '')),
+  })
+  const fixed = fixPageTree(broken)
+  const pages = catalog(fixed)['/Pages']
+  const kids = resolve(fixed, pages)['/Kids']
+  t.is(resolve(fixed, kids[0])['/Parent'], pages)
+})
+
+// The MediaBox of every page, inherited from the page tree when the page has none
+function mediaBoxes(buffer) {
+  const inherited = (node) => node['/MediaBox'] || inherited(resolve(buffer, node['/Parent']))
+  return pageReferences(buffer).map((reference) => inherited(resolve(buffer, reference)))
+}
+
+test('setDefaultMediaBox resizes the pages that inherit the MediaBox of the root', (t) => {
+  // Pages 1 and 3 inherit Letter from the page tree, page 2 is 200 x 300
+  const inheriting = simple(3, {
+    width: 200,
+    height: 300,
+    extra: (objects) => {
+      objects[1] = objects[1].replace(' >>', ' /MediaBox [0 0 612 792] >>')
+      ;[1, 3].forEach((n) => (objects[2 + 2 * n] = objects[2 + 2 * n].replace('/MediaBox [0 0 200 300] ', '')))
+    },
+  })
+  t.deepEqual(getDefaultMediaBox(inheriting), [0, 0, 612, 792])
+  // Given in any corner order
+  const resized = setDefaultMediaBox(inheriting, [595, 842, 0, 0])
+  t.deepEqual(getDefaultMediaBox(resized), [0, 0, 595, 842])
+  t.deepEqual(mediaBoxes(resized), [
+    [0, 0, 595, 842],
+    [0, 0, 200, 300],
+    [0, 0, 595, 842],
+  ])
+  t.deepEqual(pageTexts(resized), ['Page 1', 'Page 2', 'Page 3'])
+})
+
+test('getDefaultMediaBox is null when every page has its own', (t) => {
+  t.is(getDefaultMediaBox(simple(2)), null)
+  const resized = setDefaultMediaBox(simple(2), [0, 0, 100, 100])
+  t.deepEqual(mediaBoxes(resized), [
+    [0, 0, 612, 792],
+    [0, 0, 612, 792],
+  ])
+  t.throws(() => setDefaultMediaBox(simple(1), [0, 0, 0, 100]), { code: 'InvalidArg' })
+})
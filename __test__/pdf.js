@@ -0,0 +1,135 @@
+// Small hand-written PDFs for the specs. Objects are numbered from 1 in the order given, each
+// either the source of a PDF object or `{ dict, stream }` for a stream. With `objectStream`, the
+// objects that aren't streams are packed into an object stream, indexed by a cross-reference stream
+function build(objects, { trailer = '', version = '1.4', objectStream = false } = {}) {
+  const parts = [Buffer.from(`%PDF-${version}\n`, 'latin1')]
+  let offset = parts[0].length
+  // [type, field 2, field 3] of every object, as in a cross-reference stream
+  const entries = [[0, 0, 65535]]
+  const packed = []
+  const write = (number, object) => {
+    let body
+    if (typeof object === 'string') {
+      body = Buffer.from(`${number} 0 obj\n${object}\nendobj\n`, 'latin1')
+    } else {
+      const data = Buffer.isBuffer(object.stream) ? object.stream : Buffer.from(object.stream, 'latin1')
+      body = Buffer.concat([
+        Buffer.from(`${number} 0 obj\n<< ${object.dict || ''} /Length ${data.length} >>\nstream\n`, 'latin1'),
+        data,
+        Buffer.from('\nendstream\nendobj\n', 'latin1'),
+      ])
+    }
+    entries[number] = [1, offset, 0]
+    parts.push(body)
+    offset += body.length
+  }
+  objects.forEach((object, index) => {
+    if (objectStream && typeof object === 'string') {
+      entries[index + 1] = [2, objects.length + 1, packed.length]
+      packed.push([index + 1, object])
+    } else {
+      write(index + 1, object)
+    }
+  })
+  if (!objectStream) {
+    let xref = `xref\n0 ${objects.length + 1}\n`
+    entries.forEach(([type, position, generation]) => {
+      xref += `${String(position).padStart(10, '0')} ${String(generation).padStart(5, '0')} ${type ? 'n' : 'f'} \n`
+    })
+    xref += `trailer\n<< /Size ${objects.length + 1} /Root 1 0 R ${trailer} >>\nstartxref\n${offset}\n%%EOF`
+    parts.push(Buffer.from(xref, 'latin1'))
+    return Buffer.concat(parts)
+  }
+  let header = ''
+  let body = ''
+  packed.forEach(([number, object]) => {
+    header += `${number} ${body.length} `
+    body += `${object}\n`
+  })
+  write(objects.length + 1, {
+    dict: `/Type /ObjStm /N ${packed.length} /First ${header.length}`,
+    stream: header + body,
+  })
+  const size = objects.length + 3
+  entries[size - 1] = [1, offset, 0]
+  const table = Buffer.alloc(size * 7)
+  entries.forEach(([type, field, generation], number) => {
+    table.writeUInt8(type, number * 7)
+    table.writeUInt32BE(field, number * 7 + 1)
+    table.writeUInt16BE(generation, number * 7 + 5)
+  })
+  const xrefStart = offset
+  write(size - 1, { dict: `/Type /XRef /Size ${size} /W [1 4 2] /Root 1 0 R ${trailer}`, stream: table })
+  parts.push(Buffer.from(`startxref\n${xrefStart}\n%%EOF`, 'latin1'))
+  return Buffer.concat(parts)
+}
+
+// A document of `count` pages showing `"${label} ${n}"`. Object 1 is the catalog, 2 the page tree,
+// 3 the font, then the content and the page of every page: page n is object 3 + 2n.
+// `page(index)` adds to a page dictionary and `resources(index)` replaces its resources, `extra(objects)`
+// can append or replace objects
+function simple(count, options = {}) {
+  const { width = 612, height = 792, label = 'Page', catalog = '', page, resources, extra, ...rest } = options
+  const objects = [
+    `<< /Type /Catalog /Pages 2 0 R ${catalog} >>`,
+    null,
+    '<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>',
+  ]
+  const kids = []
+  for (let index = 0; index < count; index++) {
+    objects.push({ stream: `BT /F1 24 Tf 72 700 Td (${label} ${index + 1}) Tj ET` })
+    const contents = objects.length
+    objects.push(
+      `<< /Type /Page /Parent 2 0 R /MediaBox [0 0 ${width} ${height}] /Contents ${contents} 0 R ` +
+        `/Resources ${resources ? resources(index) : '<< /Font << /F1 3 0 R >> >>'} ${(page && page(index)) || ''} >>`,
+    )
+    kids.push(`${objects.length} 0 R`)
+  }
+  objects[1] = `<< /Type /Pages /Kids [${kids.join(' ')}] /Count ${count} >>`
+  if (extra) {
+    extra(objects)
+  }
+  return build(objects, rest)
+}
+
+// Object number of page `n` of a `simple` document
+const pageObject = (n) => 3 + 2 * n
+
+// A one page document with a text field per name, the AcroForm being object 100. `field(index)`
+// adds to a field dictionary, `acroForm` to the AcroForm and `extra(objects)` appends objects after it
+function form(names, { field, acroForm = '', extra } = {}) {
+  const fields = names.map((_, index) => `${7 + index * 2} 0 R`).join(' ')
+  return simple(1, {
+    catalog: '/AcroForm 100 0 R',
+    page: () => `/Annots [${fields}]`,
+    extra: (objects) => {
+      objects.push('null')
+      names.forEach((name, index) => {
+        const appearance = objects.length + 2
+        const y = 600 - index * 50
+        objects.push(
+          `<< /Type /Annot /Subtype /Widget /FT /Tx /T (${name}) /V (value ${index}) ` +
+            `/Rect [72 ${y} 272 ${y + 30}] /P 5 0 R /AP << /N ${appearance} 0 R >> ${field ? field(index) : ''} >>`,
+        )
+        objects.push({
+          dict: '/Type /XObject /Subtype /Form /BBox [0 0 200 30] /Resources << /Font << /F1 3 0 R >> >>',
+          stream: `BT /F1 12 Tf 2 10 Td (value ${index}) Tj ET`,
+        })
+      })
+      while (objects.length < 99) {
+        objects.push('null')
+      }
+      objects.push(`<< /Fields [${fields}] /DA (/Helv 0 Tf 0 g) ${acroForm} >>`)
+      if (extra) {
+        extra(objects)
+      }
+    },
+  })
+}
+
+// The strings a content stream shows with `Tj`, joined
+function shownText(content) {
+  return [...content.toString('latin1').matchAll(/\(((?:\\.|[^\\)])*)\)\s*Tj/g)].map((match) => match[1]).join('')
+}
+
+module.exports = { build, simple, pageObject, form, shownText }
@@ -0,0 +1,26 @@
+const test = require('ava')
+
+const { addHeaderFooter, getPageContent, getPageRotations, mergePdf, PdfPipeline, rotateRange } = require('../index')
+
+const { pageTexts } = require('./helpers')
+const { simple } = require('./pdf')
+
+const pageContents = (buffer) =>
+  pageTexts(buffer).map((_, index) => getPageContent(buffer, index + 1).toString('latin1'))
+
+test('a chain of operations gives the same pages as the separate calls', (t) => {
+  const first = simple(2, { label: 'First' })
+  const second = simple(1, { label: 'Second' })
+  const footer = { footer: 'Page {page} of {total}' }
+  const chained = new PdfPipeline(first).merge([second]).rotateRange(2, 3, 90).addHeaderFooter(footer).toBuffer()
+  const separate = addHeaderFooter(rotateRange(mergePdf([first, second]), 2, 3, 90), footer)
+  t.deepEqual(pageTexts(chained), ['First 1Page 1 of 3', 'First 2Page 2 of 3', 'Second 1Page 3 of 3'])
+  t.deepEqual(getPageRotations(chained), getPageRotations(separate))
+  t.deepEqual(pageContents(chained), pageContents(separate))
+})
+
+test('a failed step leaves the pipeline usable', (t) => {
+  const pipeline = new PdfPipeline(simple(2))
+  t.throws(() => pipeline.rotateRange(2, 5, 90), { code: 'PageOutOfRange' })
+  t.deepEqual(getPageRotations(pipeline.rotateRange(1, 1, 90).toBuffer()), [90, 0])
+})
@@ -0,0 +1,45 @@
+const test = require('ava')
+
+const { mergePdf } = require('../index')
+
+const { catalog, pageTexts, resolve, treeEntries } = require('./helpers')
+const { simple } = require('./pdf')
+
+// A portfolio with a cover page, embedding a 2 page PDF and a text file
+const portfolio = () =>
+  simple(1, {
+    label: 'Cover',
+    catalog:
+      '/Collection << /Type /Collection /View /D >> /Names << /EmbeddedFiles << /Names [(a) 6 0 R (b) 8 0 R] >> >>',
+    extra: (objects) =>
+      objects.push(
+        '<< /Type /Filespec /F (inner.pdf) /UF (inner.pdf) /EF << /F 7 0 R >> >>',
+        { dict: '/Type /EmbeddedFile /Subtype /application#2Fpdf', stream: simple(2, { label: 'Inner' }) },
+        '<< /Type /Filespec /F (notes.txt) /EF << /F 9 0 R >> >>',
+        { dict: '/Type /EmbeddedFile', stream: 'notes' },
+      ),
+  })
+
+const embeddedNames = (buffer) => {
+  const names = resolve(buffer, catalog(buffer)['/Names'])
+  return treeEntries(buffer, names['/EmbeddedFiles']).map(([name]) => name)
+}
+
+test('mergePdf keeps portfolios by default, gathering their files in one collection', (t) => {
+  const { buffer, warnings } = mergePdf([simple(1), portfolio(), portfolio()], { report: true })
+  t.deepEqual(pageTexts(buffer), ['Page 1', 'Cover 1', 'Cover 1'])
+  t.deepEqual(catalog(buffer)['/Collection'], { '/Type': '/Collection', '/View': '/D' })
+  t.deepEqual(embeddedNames(buffer), ['u:a', 'u:a_2', 'u:b', 'u:b_2'])
+  t.deepEqual(
+    warnings.filter((warning) => warning.includes('portfolio')),
+    [1, 2].map((index) => `Document ${index}: a portfolio, its 2 embedded files were added to the merged collection`),
+  )
+})
+
+test('mergePdf with portfolios flatten merges the embedded PDFs instead of the cover', (t) => {
+  const { buffer, warnings } = mergePdf([simple(1), portfolio()], { portfolios: 'flatten', report: true })
+  t.deepEqual(pageTexts(buffer), ['Page 1', 'Inner 1', 'Inner 2'])
+  t.is(catalog(buffer)['/Collection'], undefined)
+  t.deepEqual(warnings, ['Document 1: the portfolio was replaced by its 1 embedded PDFs, 1 other files were dropped'])
+  t.throws(() => mergePdf([portfolio()], { portfolios: 'unpack' }), { code: 'InvalidArg' })
+})
@@ -0,0 +1,53 @@
+const test = require('ava')
+
+const { posterize, validate } = require('../index')
+
+const { pageReferences, pageTexts, resolve } = require('./helpers')
+const { simple } = require('./pdf')
+
+// `[MediaBox, CropBox, Contents]` of every page
+const tiles = (buffer) =>
+  pageReferences(buffer).map((reference) => {
+    const page = resolve(buffer, reference)
+    return [page['/MediaBox'], page['/CropBox'], page['/Contents']]
+  })
+
+test('posterize replaces the page by a grid of tiles covering it', (t) => {
+  const poster = posterize(simple(3, { width: 1000, height: 800 }), 2, { cols: 2, rows: 2 })
+  t.true(validate(poster).ok)
+  t.deepEqual(pageTexts(poster), ['Page 1', 'Page 2', 'Page 2', 'Page 2', 'Page 2', 'Page 3'])
+  const [first, ...grid] = tiles(poster)
+  t.deepEqual(first, [[0, 0, 1000, 800], undefined, '4 0 R'])
+  // Left to right from the top row, all showing the same content
+  t.deepEqual(grid.slice(0, 4), [
+    [[0, 400, 500, 800], [0, 400, 500, 800], '6 0 R'],
+    [[500, 400, 1000, 800], [500, 400, 1000, 800], '6 0 R'],
+    [[0, 0, 500, 400], [0, 0, 500, 400], '6 0 R'],
+    [[500, 0, 1000, 400], [500, 0, 1000, 400], '6 0 R'],
+  ])
+})
+
+test('overlap makes neighbouring tiles share a margin', (t) => {
+  const poster = posterize(simple(1, { width: 1000, height: 800 }), 1, { cols: 2, rows: 2, overlap: 20 })
+  t.deepEqual(tiles(poster).map(([mediaBox]) => mediaBox), [
+    [0, 390, 510, 800],
+    [490, 390, 1000, 800],
+    [0, 0, 510, 410],
+    [490, 0, 1000, 410],
+  ])
+})
+
+test('the tiles of a rotated page follow the page as displayed', (t) => {
+  // Turned a quarter clockwise, the bottom of the MediaBox is on the left
+  const poster = posterize(simple(1, { width: 1000, height: 800, page: () => '/Rotate 90' }), 1, { cols: 2, rows: 1 })
+  const pages = pageReferences(poster).map((reference) => resolve(poster, reference))
+  t.deepEqual(pages.map((page) => [page['/MediaBox'], page['/Rotate']]), [
+    [[0, 0, 1000, 400], 90],
+    [[0, 400, 1000, 800], 90],
+  ])
+})
+
+test('posterize checks the grid and the page', (t) => {
+  t.throws(() => posterize(simple(1), 1, { cols: 0, rows: 1 }), { code: 'InvalidArg' })
+  t.throws(() => posterize(simple(1), 2, { cols: 1, rows: 1 }), { code: 'PageOutOfRange' })
+})
@@ -0,0 +1,57 @@
+const test = require('ava')
+
+const { probe } = require('../index')
+
+const { simple } = require('./pdf')
+
+// An RC4 128-bit /Encrypt dictionary with the owner password `owner`, as object 10 of a 3 page document, and
+// whose file identifier is the bytes 0 to 15. The entries open with the empty user password or with `secret`
+const id = '<000102030405060708090a0b0c0d0e0f>'
+const encrypted = (owner, user) =>
+  simple(3, {
+    version: '1.6',
+    trailer: `/Encrypt 10 0 R /ID [${id} ${id}]`,
+    extra: (objects) =>
+      objects.push(`<< /Filter /Standard /V 2 /R 3 /Length 128 /P -3904 /O <${owner}> /U <${user}> >>`),
+  })
+
+test('probe reports a plain document', (t) => {
+  t.deepEqual(probe(simple(3)), {
+    pageCount: 3,
+    encrypted: false,
+    needsPassword: false,
+    version: '1.4',
+    linearized: false,
+    xfaOnly: false,
+  })
+})
+
+test('probe opens an encrypted document with the empty user password', (t) => {
+  const document = encrypted(
+    '566fa873ee33c797cd3b904fdadf814afa34df9a38f6ed41b984e2c6da2aa6f5',
+    '6a377f14504be9f1aba99e13933760ac00000000000000000000000000000000',
+  )
+  t.deepEqual(probe(document), {
+    pageCount: 3,
+    encrypted: true,
+    needsPassword: false,
+    version: '1.6',
+    linearized: false,
+    xfaOnly: false,
+  })
+})
+
+test('probe reports that a document locked with a user password needs it', (t) => {
+  const document = encrypted(
+    '0db5855fc5326569e765906caf64e4429a4c20d6e996fdef963e9b5080f9e083',
+    '07dd1598955b4a3c04b41ce38b601e3d00000000000000000000000000000000',
+  )
+  t.deepEqual(probe(document), {
+    pageCount: 3,
+    encrypted: true,
+    needsPassword: true,
+    version: '1.6',
+    linearized: false,
+    xfaOnly: false,
+  })
+})
@@ -0,0 +1,66 @@
+const test = require('ava')
+
+const { getMetadata, mergePdf, PdfPipeline, rotateRange, setProducerInfo, version } = require('../index')
+
+const { simple } = require('./pdf')
+
+const addon = `vibes-pdf-utils ${version().crate}`
+
+// A document first written by another tool
+const authored = () =>
+  simple(1, {
+    trailer: '/Info 6 0 R',
+    extra: (objects) => objects.push('<< /Producer (Other Tool) /Creator (Word Processor) >>'),
+  })
+
+// The settings are process wide: the specs run one after the other, each restoring the defaults
+function configure(t, info) {
+  setProducerInfo(info)
+  t.teardown(() => setProducerInfo({ producer: true, application: null, creator: null }))
+}
+
+test.serial('a merge names this package as the producer', (t) => {
+  t.is(getMetadata(mergePdf([authored(), simple(1)])).producer, addon)
+})
+
+test.serial('a rewrite replaces the Producer and keeps the Creator of the document', (t) => {
+  const { producer, creator } = getMetadata(rotateRange(authored(), 1, 1, 90))
+  t.is(producer, addon)
+  t.is(creator, 'Word Processor')
+})
+
+test.serial('setProducerInfo puts the application before the package and sets an explicit Creator', (t) => {
+  configure(t, { application: 'Billing', creator: 'Invoice Service' })
+  const { producer, creator } = getMetadata(mergePdf([authored(), simple(1)]))
+  t.is(producer, `Billing (${addon})`)
+  t.is(creator, 'Invoice Service')
+})
+
+test.serial('setProducerInfo can leave the Producer alone', (t) => {
+  configure(t, { producer: false })
+  t.is(getMetadata(rotateRange(authored(), 1, 1, 90)).producer, 'Other Tool')
+})
+
+test.serial('an incremental update names the producer too', (t) => {
+  const source = authored()
+  const updated = rotateRange(source, 1, 1, 90, { incremental: true })
+  t.deepEqual(updated.subarray(0, source.length), source)
+  t.is(getMetadata(updated).producer, addon)
+  t.is(getMetadata(updated).creator, 'Word Processor')
+})
+
+test.serial('producerInfo changes the producer for its call only', (t) => {
+  const info = { application: 'Billing', creator: 'Invoice Service' }
+  const { producer, creator } = getMetadata(mergePdf([authored(), simple(1)], { producerInfo: info }))
+  t.is(producer, `Billing (${addon})`)
+  t.is(creator, 'Invoice Service')
+  t.like(getMetadata(rotateRange(authored(), 1, 1, 90)), { producer: addon, creator: 'Word Processor' })
+})
+
+test.serial('producerInfo overrides setProducerInfo, keeping the settings it omits', (t) => {
+  configure(t, { application: 'Billing', creator: 'Invoice Service' })
+  const rotated = rotateRange(authored(), 1, 1, 90, { incremental: true, producerInfo: { application: null } })
+  t.like(getMetadata(rotated), { producer: addon, creator: 'Invoice Service' })
+  const unnamed = new PdfPipeline(authored()).rotateRange(1, 1, 90).toBuffer({ producerInfo: { producer: false } })
+  t.is(getMetadata(unnamed).producer, 'Other Tool')
+})
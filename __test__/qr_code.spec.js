@@ -0,0 +1,62 @@
+const zlib = require('zlib')
+
+const test = require('ava')
+
+const { addQrCode, getPageContent } = require('../index')
+
+const { pageReferences, resolve } = require('./helpers')
+const { simple } = require('./pdf')
+
+const xObjects = (buffer, index) => {
+  const page = resolve(buffer, pageReferences(buffer)[index])
+  return resolve(buffer, resolve(buffer, page['/Resources'])['/XObject'])
+}
+
+test('addQrCode stamps a 1-bit image of the code onto the page', (t) => {
+  const stamped = addQrCode(simple(2), 'https://example.com/v/123', { page: 2, x: 500, y: 50, size: 72 })
+  t.is(xObjects(stamped, 0), undefined)
+  const images = xObjects(stamped, 1)
+  const [name] = Object.keys(images)
+  const { dict, data } = resolve(stamped, images[name]).stream
+  // A version 2 code of 25 modules, with the 4 module quiet zone on every side
+  t.like(dict, {
+    '/Subtype': '/Image',
+    '/Width': 33,
+    '/Height': 33,
+    '/ColorSpace': '/DeviceGray',
+    '/BitsPerComponent': 1,
+    '/Interpolate': false,
+  })
+  // Rows are padded to whole bytes
+  t.is(zlib.inflateSync(data).length, Math.ceil(33 / 8) * 33)
+  t.regex(getPageContent(stamped, 2).toString('latin1'), new RegExp(`72 0 0 72 500 50 cm\\s+${name} Do`))
+})
+
+// The matrix the page content is in when it draws the XObject, from the `cm` operators since the last `q`
+const drawingMatrix = (content) => {
+  const matrices = content.slice(content.lastIndexOf('q')).matchAll(/((?:[\d.-]+\s+){6})cm/g)
+  return [...matrices]
+    .map((match) => match[1].trim().split(/\s+/).map(Number))
+    .reduceRight(([a, b, c, d, e, f], [g, h, i, j, k, l]) => [
+      a * g + b * i,
+      a * h + b * j,
+      c * g + d * i,
+      c * h + d * j,
+      e * g + f * i + k,
+      e * h + f * j + l,
+    ])
+}
+
+test('addQrCode places display positions upright on a rotated page', (t) => {
+  // Displayed 792 wide and 612 high, turned a quarter clockwise
+  const rotated = simple(1, { page: () => '/Rotate 90' })
+  const topLeft = addQrCode(rotated, 'data', { x: 0, y: 540, size: 72 })
+  // The top-left of the displayed page is the bottom-left of the media box, the code turned with the page
+  t.deepEqual(drawingMatrix(getPageContent(topLeft, 1).toString('latin1')), [0, 72, -72, 0, 72, 0])
+  const media = addQrCode(rotated, 'data', { x: 0, y: 540, size: 72, coordinateSpace: 'media' })
+  t.deepEqual(drawingMatrix(getPageContent(media, 1).toString('latin1')), [72, 0, 0, 72, 0, 540])
+})
+
+test('addQrCode needs a size', (t) => {
+  t.throws(() => addQrCode(simple(1), 'data', { x: 0, y: 0, size: 0 }), { code: 'InvalidArg' })
+})
@@ -0,0 +1,103 @@
+const test = require('ava')
+
+const { getObject, getPageContent, getTrailer, objectMap, setObject, setTrailerEntry } = require('../index')
+
+const { referenced } = require('./helpers')
+const { simple } = require('./pdf')
+
+test('getObject reads the catalog', (t) => {
+  const document = simple(1)
+  const [objNum, genNum] = getTrailer(document)['/Root'].split(' ').map(Number)
+  t.deepEqual(getObject(document, objNum, genNum), { '/Type': '/Catalog', '/Pages': '2 0 R' })
+})
+
+test('getObject reads a stream with its raw data', (t) => {
+  const { stream } = getObject(simple(1), 4)
+  t.deepEqual(stream.dict, { '/Length': 37 })
+  t.is(stream.data.toString('latin1'), 'BT /F1 24 Tf 72 700 Td (Page 1) Tj ET')
+})
+
+test('getObject throws for an object the document lacks', (t) => {
+  t.throws(() => getObject(simple(1), 99), { message: "Object 99 0 doesn't exist" })
+})
+
+test('setObject replaces a dictionary', (t) => {
+  const edited = setObject(simple(1), 3, 0, { '/Type': '/Font', '/Subtype': '/Type1', '/BaseFont': '/Courier' })
+  t.is(getObject(edited, 3)['/BaseFont'], '/Courier')
+})
+
+test('setObject replaces a stream, setting its length', (t) => {
+  const content = Buffer.from('BT /F1 12 Tf (X) Tj ET', 'latin1')
+  const edited = setObject(simple(1), 4, 0, { stream: { dict: {}, data: content } })
+  t.deepEqual(getObject(edited, 4).stream.dict, { '/Length': content.length })
+  t.is(getPageContent(edited, 1).toString('latin1').trim(), content.toString('latin1'))
+})
+
+test('setObject round-trips every PDF type', (t) => {
+  const value = ['u:text', 'b:00ff', 1.5, -2, true, null, '/Name', '5 0 R', { '/Key': [] }]
+  t.deepEqual(getObject(setObject(simple(1), 20, 0, value), 20), value)
+})
+
+test('setObject requires an object number from 1 to 8388607', (t) => {
+  for (const objNum of [0, -1, 1.5, NaN, 8388608]) {
+    t.throws(() => setObject(simple(1), objNum, 0, null), {
+      code: 'InvalidArg',
+      message: `objNum must be an integer from 1 to 8388607, got ${objNum}`,
+    })
+  }
+})
+
+test('setObject adds objects at most 1024 past the highest number', (t) => {
+  t.deepEqual(getObject(setObject(simple(1), 5 + 1024, 0, 1), 5 + 1024), 1)
+  t.throws(() => setObject(simple(1), 5 + 1025, 0, 1), {
+    code: 'InvalidArg',
+    message: 'objNum 1030 is more than 1024 past the highest object number 5',
+  })
+})
+
+test('setTrailerEntry sets a custom /ID and null removes it', (t) => {
+  const id = 'b:0123456789abcdef0123456789abcdef'
+  const withId = setTrailerEntry(simple(1), '/ID', [id, id])
+  t.like(getTrailer(withId), { '/Root': '1 0 R', '/ID': [id, id] })
+  t.is(getTrailer(setTrailerEntry(withId, '/ID', null))['/ID'], undefined)
+})
+
+test('setTrailerEntry throws on a /Root that is no dictionary of the document', (t) => {
+  const message = '/Root must reference a dictionary of the document'
+  for (const root of ['9 0 R', 5]) {
+    t.throws(() => setTrailerEntry(simple(1), '/Root', root), { message })
+  }
+})
+
+test('objectMap locates the bytes of every object', (t) => {
+  const document = simple(2)
+  const map = objectMap(document)
+  t.deepEqual(map.map(({ id, type }) => [id, type]), [
+    [1, 'Catalog'],
+    [2, 'Pages'],
+    [3, 'Font'],
+    [4, 'stream'],
+    [5, 'Page'],
+    [6, 'stream'],
+    [7, 'Page'],
+  ])
+  const [root] = referenced(getTrailer(document)['/Root'])
+  const catalog = map.find(({ id }) => id === root)
+  const bytes = document.subarray(catalog.offset, catalog.offset + catalog.length).toString('latin1')
+  t.is(bytes, '1 0 obj\n<< /Type /Catalog /Pages 2 0 R  >>\nendobj')
+  for (const { offset, length, id } of map) {
+    t.regex(document.subarray(offset, offset + length).toString('latin1'), new RegExp(`^${id} 0 obj\\n[^]*endobj$`))
+  }
+})
+
+test('objectMap reports the object stream holding a compressed object', (t) => {
+  const document = simple(1, { objectStream: true, version: '1.5' })
+  const map = objectMap(document)
+  const catalog = map.find(({ type }) => type === 'Catalog')
+  const container = map.find(({ type }) => type === 'ObjStm')
+  t.like(catalog, { id: 1, container: container.id, index: 0, offset: container.offset, length: container.length })
+  const header = document.subarray(container.offset, container.offset + 40).toString('latin1')
+  t.true(header.startsWith(`${container.id} 0 obj\n<< /Type /ObjStm`))
+  // Streams can't be compressed
+  t.is(map.find(({ id }) => id === 4).container, undefined)
+})
@@ -0,0 +1,37 @@
+const test = require('ava')
+
+const { getPageContent, rewrite, stats, validate } = require('../index')
+
+const { pageTexts } = require('./helpers')
+const { simple } = require('./pdf')
+
+// Uncompressed padding in the first page content, and an orphaned stream
+const bloated = () =>
+  simple(2, {
+    objectStream: true,
+    extra: (objects) => {
+      objects[3] = { stream: `BT /F1 24 Tf 72 700 Td (Page 1) Tj ET\n${'% padding\n'.repeat(1000)}` }
+      objects.push({ stream: 'orphan '.repeat(2000) })
+    },
+  })
+
+test('rewrite shrinks a bloated document without changing its pages', (t) => {
+  const source = bloated()
+  const rewritten = rewrite(source)
+  t.true(rewritten.length < source.length / 10)
+  t.deepEqual(validate(rewritten), { ok: true, isPdf: true, issues: [] })
+  t.deepEqual(pageTexts(rewritten), ['Page 1', 'Page 2'])
+  for (const page of [1, 2]) {
+    t.true(getPageContent(rewritten, page).equals(getPageContent(source, page)))
+  }
+  t.false(stats(rewritten).compressedXref)
+})
+
+test('rewrite keeps the orphans without garbageCollect and the streams decoded with noCompression', (t) => {
+  const source = bloated()
+  const streams = (options) => stats(rewrite(source, options)).streamObjectCount
+  t.is(streams({ garbageCollect: false }), streams() + 1)
+  const decoded = rewrite(source, { noCompression: true })
+  t.true(decoded.includes('% padding\n'.repeat(1000)))
+  t.false(decoded.includes('orphan'))
+})
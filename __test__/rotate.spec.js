@@ -0,0 +1,75 @@
+const test = require('ava')
+
+const {
+  bakeRotation,
+  getObject,
+  getPageContent,
+  getPageRotations,
+  mergePdf,
+  rotateRange,
+  uniformOrientation,
+} = require('../index')
+
+const { resolve } = require('./helpers')
+const { pageObject, simple } = require('./pdf')
+
+test('getPageRotations reads the inherited rotation of every page, normalized', (t) => {
+  const rotations = ['', '/Rotate -90', '/Rotate 450', '/Rotate 90', '/Rotate 0']
+  const mixed = simple(5, {
+    page: (index) => rotations[index],
+    extra: (objects) => {
+      objects[1] = objects[1].replace(' >>', ' /Rotate 180 >>')
+    },
+  })
+  t.deepEqual(getPageRotations(mixed), [180, 270, 90, 90, 0])
+})
+
+test('rotateRange rotates only the pages of the range', (t) => {
+  const rotated = rotateRange(simple(6), 2, 4, 90)
+  t.deepEqual(getPageRotations(rotated), [0, 90, 90, 90, 0, 0])
+})
+
+test('rotateRange adds to the rotation a page already has', (t) => {
+  const rotated = rotateRange(simple(2, { page: () => '/Rotate 270' }), 1, 1, 180)
+  t.deepEqual(getPageRotations(rotated), [90, 270])
+})
+
+test('rotateRange validates its arguments', (t) => {
+  const buffer = simple(6)
+  t.throws(() => rotateRange(buffer, 4, 2, 90), { code: 'InvalidArg' })
+  t.throws(() => rotateRange(buffer, 5, 7, 90), { code: 'PageOutOfRange' })
+  t.throws(() => rotateRange(buffer, 0, 2, 90), { code: 'InvalidArg' })
+  t.throws(() => rotateRange(buffer, 1, 2, 45), { code: 'InvalidArg' })
+})
+
+test('bakeRotation turns the content and the annotations of a quarter turned page', (t) => {
+  const rotated = simple(1, {
+    page: () => '/Rotate 90 /Annots [6 0 R]',
+    extra: (objects) => objects.push('<< /Type /Annot /Subtype /Link /Rect [72 700 172 720] /Dest [5 0 R /Fit] >>'),
+  })
+  const baked = bakeRotation(rotated)
+  const page = getObject(baked, pageObject(1))
+  t.is(page['/Rotate'], 0)
+  t.deepEqual(page['/MediaBox'], [0, 0, 792, 612])
+  // (x, y) is drawn at (y, 612 - x), where the viewer displayed it before
+  t.regex(getPageContent(baked, 1).toString('latin1'), /^q 0 -1 1 0 0 612 cm\s+BT[\s\S]*ET\s+Q\s*$/)
+  t.deepEqual(resolve(baked, page['/Annots'][0])['/Rect'], [700, 440, 720, 540])
+})
+
+test('bakeRotation leaves unrotated pages alone', (t) => {
+  const baked = bakeRotation(simple(1))
+  t.deepEqual(getObject(baked, pageObject(1))['/MediaBox'], [0, 0, 612, 792])
+  t.is(getPageContent(baked, 1).toString('latin1').trim(), 'BT /F1 24 Tf 72 700 Td (Page 1) Tj ET')
+})
+
+// A portrait page, a landscape one, then both turned a quarter
+const mixed = () => {
+  const [portrait, landscape] = [simple(1), simple(1, { width: 792, height: 612 })]
+  return mergePdf([portrait, landscape, { buffer: portrait, rotate: 90 }, { buffer: landscape, rotate: 90 }])
+}
+
+test('uniformOrientation turns the pages displayed in the other orientation', (t) => {
+  t.deepEqual(getPageRotations(uniformOrientation(mixed(), 'portrait')), [0, 90, 180, 90])
+  t.deepEqual(getPageRotations(uniformOrientation(mixed(), 'landscape')), [90, 0, 90, 180])
+  t.throws(() => uniformOrientation(mixed(), 'square'), { code: 'InvalidArg' })
+})
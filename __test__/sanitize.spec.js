@@ -0,0 +1,75 @@
+const test = require('ava')
+
+const { getObject, sanitize } = require('../index')
+
+const { catalog, resolve, treeEntries } = require('./helpers')
+const { pageObject, simple } = require('./pdf')
+
+// A Windows executable, by its DOS header
+const executable = Buffer.concat([Buffer.from('MZ', 'latin1'), Buffer.alloc(62), Buffer.from('payload', 'latin1')])
+
+// One page that launches cmd.exe when opened, runs a script on the page opening and carries payload.exe
+const malicious = () =>
+  simple(1, {
+    catalog: '/OpenAction 6 0 R /Names << /EmbeddedFiles << /Names [(payload.exe) 7 0 R] >> >>',
+    page: () => '/AA << /O << /S /JavaScript /JS (app.alert\\(1\\)) >> >>',
+    extra: (objects) => {
+      objects.push('<< /S /Launch /F (cmd.exe) /Win << /F (cmd.exe) /P (/c calc) >> >>')
+      objects.push('<< /Type /Filespec /F (payload.exe) /UF (payload.exe) /EF << /F 8 0 R >> >>')
+      objects.push({ dict: '/Type /EmbeddedFile /Subtype /application#2Fx-msdownload', stream: executable })
+    },
+  })
+
+const attachments = (buffer) => {
+  const names = resolve(buffer, catalog(buffer)['/Names'])
+  return names && names['/EmbeddedFiles'] ? treeEntries(buffer, names['/EmbeddedFiles']).map(([name]) => name) : []
+}
+
+test('sanitize removes a launching OpenAction and an embedded executable', (t) => {
+  const sanitized = sanitize(malicious(), { embeddedFiles: true })
+  t.is(catalog(sanitized)['/OpenAction'], undefined)
+  t.deepEqual(attachments(sanitized), [])
+  t.false(sanitized.includes('cmd.exe'))
+  t.false(sanitized.includes(executable))
+  t.false(sanitized.includes('app.alert'))
+})
+
+test('sanitize keeps embedded files by default', (t) => {
+  const sanitized = sanitize(malicious())
+  t.is(catalog(sanitized)['/OpenAction'], undefined)
+  t.deepEqual(attachments(sanitized), ['u:payload.exe'])
+})
+
+test('sanitize leaves the passes turned off alone', (t) => {
+  const sanitized = sanitize(malicious(), { openAction: false, launchActions: false, embeddedFiles: true })
+  t.is(resolve(sanitized, catalog(sanitized)['/OpenAction'])['/S'], '/Launch')
+  t.deepEqual(attachments(sanitized), [])
+  t.false(sanitized.includes('app.alert'))
+})
+
+// Page 1 links out with a Launch (object 8), a URI (9), a GoToR (10) and a GoTo to page 2 (11)
+const links = () =>
+  simple(2, {
+    page: (index) => (index === 0 ? '/Annots [8 0 R 9 0 R 10 0 R 11 0 R]' : ''),
+    extra: (objects) => {
+      const link = (action) => `<< /Type /Annot /Subtype /Link /Rect [72 72 144 96] /A << ${action} >> >>`
+      objects.push(link('/S /Launch /F (evil.exe)'))
+      objects.push(link('/S /URI /URI (https://example.com)'))
+      objects.push(link('/S /GoToR /F (other.pdf) /D [0 /Fit]'))
+      objects.push(link(`/S /GoTo /D [${pageObject(2)} 0 R /Fit]`))
+    },
+  })
+
+const linkActions = (buffer) =>
+  getObject(buffer, pageObject(1))['/Annots'].map((annotation) => {
+    const action = resolve(buffer, annotation)['/A']
+    return action && action['/S']
+  })
+
+test('sanitize strips Launch and GoToR links, keeping URI and GoTo links', (t) => {
+  t.deepEqual(linkActions(sanitize(links())), [undefined, '/URI', undefined, '/GoTo'])
+})
+
+test('sanitize keeps GoToR links with remoteGoToActions off', (t) => {
+  t.deepEqual(linkActions(sanitize(links(), { remoteGoToActions: false })), [undefined, '/URI', '/GoToR', '/GoTo'])
+})
@@ -0,0 +1,57 @@
+const fs = require('fs')
+const os = require('os')
+const path = require('path')
+
+const test = require('ava')
+
+const { getPageContent, mergePdf, probe, rotateRange } = require('../index')
+
+const { simple } = require('./pdf')
+
+// Page 1 draws its text 50 times, enough for compression to pay off, and the document uses an
+// object stream
+const packed = () =>
+  simple(2, {
+    objectStream: true,
+    version: '1.5',
+    extra: (objects) => (objects[3] = { stream: 'BT /F1 24 Tf 72 700 Td (Page 1) Tj ET\n'.repeat(50) }),
+  })
+
+test('noObjectStreams and noCompression write a classic xref table and readable streams', (t) => {
+  const merged = mergePdf([packed()], { noObjectStreams: true, noCompression: true })
+  const source = merged.toString('latin1')
+  t.false(source.includes('/ObjStm'))
+  t.false(source.includes('/XRef'))
+  t.regex(source, /\nxref\n0 \d+\n/)
+  t.false(source.includes('/FlateDecode'))
+  t.true(source.includes('BT /F1 24 Tf 72 700 Td (Page 1) Tj ET'))
+})
+
+test('streams are compressed by default, the content staying the same', (t) => {
+  const merged = mergePdf([packed()])
+  const source = merged.toString('latin1')
+  t.true(source.includes('/FlateDecode'))
+  t.false(source.includes('(Page 1) Tj'))
+  t.deepEqual(getPageContent(merged, 1), getPageContent(mergePdf([packed()], { noCompression: true }), 1))
+})
+
+// The page tree node is typed `/ObjStm`, which lopdf reads but never writes back: the written
+// document loses its pages
+const brokenTree = () =>
+  simple(2, { extra: (objects) => (objects[1] = objects[1].replace('/Type /Pages', '/Type /ObjStm')) })
+
+test('verify throws instead of returning a document that lost its page tree', (t) => {
+  t.throws(() => rotateRange(brokenTree(), 1, 1, 90), {
+    code: 'GenericFailure',
+    message: /The written document is broken: its catalog or page tree is missing/,
+  })
+  t.is(probe(rotateRange(brokenTree(), 1, 1, 90, { verify: false })).pageCount, 0)
+})
+
+test('verify removes the file it found broken', (t) => {
+  const directory = fs.mkdtempSync(path.join(os.tmpdir(), 'pdf-utils-'))
+  t.teardown(() => fs.rmSync(directory, { recursive: true, force: true }))
+  const outPath = path.join(directory, 'rotated.pdf')
+  t.throws(() => rotateRange(brokenTree(), 1, 1, 90, { outPath }), { code: 'GenericFailure' })
+  t.false(fs.existsSync(outPath))
+})
@@ -0,0 +1,49 @@
+const fs = require('fs')
+const os = require('os')
+const path = require('path')
+
+const test = require('ava')
+
+const { getPageRotations, splitPdfToFiles } = require('../index')
+
+const { pageTexts } = require('./helpers')
+const { simple } = require('./pdf')
+
+function temporaryDirectory(t) {
+  const directory = fs.mkdtempSync(path.join(os.tmpdir(), 'pdf-utils-'))
+  t.teardown(() => fs.rmSync(directory, { recursive: true, force: true }))
+  return directory
+}
+
+test('splitPdfToFiles writes every page to a file of its own, in page order', (t) => {
+  const outDir = temporaryDirectory(t)
+  const written = splitPdfToFiles(simple(3), outDir)
+  t.deepEqual(written, [1, 2, 3].map((n) => path.join(outDir, `page-${n}.pdf`)))
+  t.deepEqual(fs.readdirSync(outDir).sort(), ['page-1.pdf', 'page-2.pdf', 'page-3.pdf'])
+  t.deepEqual(written.map((file) => pageTexts(fs.readFileSync(file))), [['Page 1'], ['Page 2'], ['Page 3']])
+})
+
+test('splitPdfToFiles creates the directory and names the files from the template', (t) => {
+  const outDir = path.join(temporaryDirectory(t), 'nested', 'parts')
+  const written = splitPdfToFiles(simple(2), outDir, 'report-{n}-of-2.pdf')
+  t.deepEqual(written.map((file) => path.basename(file)), ['report-1-of-2.pdf', 'report-2-of-2.pdf'])
+  t.true(written.every((file) => fs.existsSync(file)))
+})
+
+test('splitPdfToFiles rejects templates reaching outside outDir', (t) => {
+  const root = temporaryDirectory(t)
+  const outDir = path.join(root, 'parts')
+  for (const template of ['../escape-{n}.pdf', path.join(root, 'absolute-{n}.pdf'), 'sub/page-{n}.pdf']) {
+    t.throws(() => splitPdfToFiles(simple(2), outDir, template), { code: 'InvalidArg', message: /must be a file name/ })
+  }
+  t.deepEqual(fs.readdirSync(root), [])
+})
+
+test('splitPdfToFiles keeps the inherited rotation of every page', (t) => {
+  const rotated = simple(2, {
+    page: (index) => (index === 1 ? '/Rotate 180' : ''),
+    extra: (objects) => (objects[1] = objects[1].replace(' >>', ' /Rotate 90 >>')),
+  })
+  const written = splitPdfToFiles(rotated, temporaryDirectory(t))
+  t.deepEqual(written.map((file) => getPageRotations(fs.readFileSync(file))), [[90], [180]])
+})
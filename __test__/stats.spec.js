@@ -0,0 +1,25 @@
+const test = require('ava')
+
+const { stats } = require('../index')
+
+const { build, simple } = require('./pdf')
+
+test('stats counts the objects of a known file', (t) => {
+  t.deepEqual(stats(simple(2)), {
+    objectCount: 7,
+    maxObjectId: 7,
+    streamObjectCount: 2,
+    compressedXref: false,
+    hybridXref: false,
+  })
+})
+
+test('stats reports the highest object number', (t) => {
+  const objects = ['<< /Type /Catalog /Pages 2 0 R >>', '<< /Type /Pages /Kids [] /Count 0 >>']
+  while (objects.length < 11) {
+    objects.push('null')
+  }
+  objects.push('<< /Note (last) >>')
+  const { maxObjectId } = stats(build(objects))
+  t.is(maxObjectId, 12)
+})
@@ -0,0 +1,35 @@
+const fs = require('fs')
+const os = require('os')
+const path = require('path')
+const { Readable } = require('stream')
+
+const test = require('ava')
+
+const { mergePdf, mergePdfFromStreams } = require('../index')
+
+const { pageTexts } = require('./helpers')
+const { simple } = require('./pdf')
+
+test('mergePdfFromStreams merges file read streams like the buffers', async (t) => {
+  const directory = fs.mkdtempSync(path.join(os.tmpdir(), 'pdf-utils-'))
+  t.teardown(() => fs.rmSync(directory, { recursive: true, force: true }))
+  const buffers = [simple(2, { label: 'First' }), simple(1, { label: 'Second' })]
+  const paths = buffers.map((buffer, index) => {
+    const file = path.join(directory, `${index}.pdf`)
+    fs.writeFileSync(file, buffer)
+    return file
+  })
+  // Small chunks, so every document arrives in several
+  const merged = await mergePdfFromStreams(paths.map((file) => fs.createReadStream(file, { highWaterMark: 64 })))
+  t.deepEqual(pageTexts(merged), ['First 1', 'First 2', 'Second 1'])
+  t.deepEqual(merged, mergePdf(buffers))
+})
+
+test('mergePdfFromStreams rejects when a stream fails', async (t) => {
+  const failing = new Readable({
+    read() {
+      this.destroy(new Error('connection reset'))
+    },
+  })
+  await t.throwsAsync(mergePdfFromStreams([Readable.from([simple(1)]), failing]), { message: 'connection reset' })
+})
@@ -0,0 +1,51 @@
+const test = require('ava')
+
+const { mergePdf } = require('../index')
+
+const { catalog, pageNumber, pageTexts, resolve } = require('./helpers')
+const { pageObject, simple } = require('./pdf')
+
+// Two tagged pages, each a paragraph (10 and 11) of the Document element 9 under the structure
+// tree root 8, with a bookmark per paragraph reaching it through a structure destination: the
+// first by its own /SD, the second by that of its GoTo action
+const tagged = (label) =>
+  simple(2, {
+    label,
+    catalog: '/StructTreeRoot 8 0 R /MarkInfo << /Marked true >> /Outlines 13 0 R',
+    page: (index) => `/StructParents ${index}`,
+    extra: (objects) => {
+      objects[3] = { stream: `/P <</MCID 0>> BDC BT /F1 24 Tf 72 700 Td (${label} 1) Tj ET EMC` }
+      objects[5] = { stream: `/P <</MCID 0>> BDC BT /F1 24 Tf 72 700 Td (${label} 2) Tj ET EMC` }
+      objects.push(
+        '<< /Type /StructTreeRoot /K [9 0 R] /ParentTree 12 0 R /ParentTreeNextKey 2 >>',
+        '<< /Type /StructElem /S /Document /P 8 0 R /K [10 0 R 11 0 R] >>',
+        `<< /Type /StructElem /S /P /P 9 0 R /Pg ${pageObject(1)} 0 R /K 0 >>`,
+        `<< /Type /StructElem /S /P /P 9 0 R /Pg ${pageObject(2)} 0 R /K 0 >>`,
+        '<< /Nums [0 [10 0 R] 1 [11 0 R]] >>',
+        '<< /Type /Outlines /First 14 0 R /Last 15 0 R /Count 2 >>',
+        `<< /Title (${label} 1) /Parent 13 0 R /Next 15 0 R /SD [10 0 R] /Dest [${pageObject(1)} 0 R /Fit] >>`,
+        `<< /Title (${label} 2) /Parent 13 0 R /Prev 14 0 R ` +
+          `/A << /S /GoTo /SD [11 0 R] /D [${pageObject(2)} 0 R /Fit] >> >>`,
+      )
+    },
+  })
+
+test('merged bookmarks keep their structure destinations, into the combined structure tree', (t) => {
+  const merged = mergePdf([tagged('A'), tagged('B')], { outlines: 'merge' })
+  t.deepEqual(pageTexts(merged), ['A 1', 'A 2', 'B 1', 'B 2'])
+  const root = resolve(merged, catalog(merged)['/StructTreeRoot'])
+  const items = [resolve(merged, resolve(merged, catalog(merged)['/Outlines'])['/First'])]
+  while (items[items.length - 1]['/Next']) {
+    items.push(resolve(merged, items[items.length - 1]['/Next']))
+  }
+  t.deepEqual(items.map((item) => item['/Title']), ['u:A 1', 'u:A 2', 'u:B 1', 'u:B 2'])
+  items.forEach((item, index) => {
+    const [element] = item['/A']['/SD']
+    const paragraph = resolve(merged, element)
+    // The paragraph shown on the bookmark's page, in the tree of its document
+    t.is(pageNumber(merged, paragraph['/Pg']), index + 1)
+    t.is(pageNumber(merged, item['/A']['/D'][0]), index + 1)
+    t.is(root['/K'][Math.floor(index / 2)], paragraph['/P'])
+    t.true(resolve(merged, paragraph['/P'])['/K'].includes(element))
+  })
+})
@@ -0,0 +1,71 @@
+const zlib = require('zlib')
+
+const test = require('ava')
+
+const { getObject, getPageContent, subsetFonts } = require('../index')
+
+const { resolve } = require('./helpers')
+const { simple } = require('./pdf')
+const { font } = require('./ttf')
+
+const alphabet = 'ABCDEFGHIJKLMNOPQRSTUVWXYZ'
+
+// A page showing "AC" in TestSans, embedded whole with the 26 letters. Object 6 is the Type 0
+// font, 7 its descendant, 8 the descriptor, 9 the font program, 10 the ToUnicode CMap and 11 the CIDSet
+const sparse = () =>
+  simple(1, {
+    resources: () => '<< /Font << /F1 3 0 R /F2 6 0 R >> >>',
+    extra: (objects) => {
+      objects[3] = { stream: 'BT /F2 24 Tf 72 700 Td <00010003> Tj ET' }
+      objects.push(
+        '<< /Type /Font /Subtype /Type0 /BaseFont /TestSans /Encoding /Identity-H ' +
+          '/DescendantFonts [7 0 R] /ToUnicode 10 0 R >>',
+      )
+      const widths = Array(alphabet.length).fill(600).join(' ')
+      objects.push(
+        '<< /Type /Font /Subtype /CIDFontType2 /BaseFont /TestSans /CIDToGIDMap /Identity ' +
+          `/CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> /W [1 [${widths}]] ` +
+          '/FontDescriptor 8 0 R >>',
+      )
+      objects.push(
+        '<< /Type /FontDescriptor /FontName /TestSans /Flags 32 /FontBBox [0 0 600 700] /ItalicAngle 0 ' +
+          '/Ascent 800 /Descent -200 /CapHeight 700 /StemV 80 /CIDSet 11 0 R /FontFile2 9 0 R >>',
+      )
+      const program = font(alphabet)
+      objects.push({ dict: `/Length1 ${program.length}`, stream: program })
+      const ranges = [...alphabet]
+        .map((c, index) => `<${(index + 1).toString(16).padStart(4, '0')}> <00${c.charCodeAt(0).toString(16)}>`)
+        .join('\n')
+      objects.push({
+        stream:
+          '/CIDInit /ProcSet findresource begin 12 dict begin begincmap /CMapName /TestSans-UTF16 def ' +
+          `1 begincodespacerange <0000> <FFFF> endcodespacerange 26 beginbfchar\n${ranges}\nendbfchar ` +
+          'endcmap CMapName currentdict /CMap defineresource pop end end',
+      })
+      objects.push({ stream: Buffer.from([0x7f, 0xff, 0xff, 0xc0]) })
+    },
+  })
+
+const data = ({ stream }) => (stream.dict['/Filter'] === '/FlateDecode' ? zlib.inflateSync(stream.data) : stream.data)
+
+test('subsetFonts shrinks a sparsely used font to the glyphs drawn', (t) => {
+  const source = sparse()
+  const subset = subsetFonts(source)
+  t.true(data(getObject(subset, 9)).length < data(getObject(source, 9)).length)
+  // The fonts and the descriptor get the tag of the subset
+  const cidFont = getObject(subset, 7)
+  t.regex(cidFont['/BaseFont'], /^\/[A-Z]{6}\+TestSans$/)
+  t.is(getObject(subset, 6)['/BaseFont'], cidFont['/BaseFont'])
+  t.is(getObject(subset, 8)['/FontName'], cidFont['/BaseFont'])
+  t.is(getObject(subset, 8)['/CIDSet'], undefined)
+  t.deepEqual(cidFont['/W'], [1, [600], 3, [600]])
+  // CIDs 1 and 3 point at the renumbered glyphs 1 and 2
+  t.deepEqual([...data(resolve(subset, cidFont['/CIDToGIDMap']))], [0, 0, 0, 1, 0, 0, 0, 2])
+})
+
+test('subsetFonts keeps the codes the page shows and their Unicode mapping', (t) => {
+  const subset = subsetFonts(sparse())
+  t.regex(getPageContent(subset, 1).toString('latin1'), /<00010003> Tj/)
+  t.deepEqual(getObject(subset, 6)['/ToUnicode'], '10 0 R')
+  t.regex(data(getObject(subset, 10)).toString('latin1'), /<0003> <0043>/)
+})
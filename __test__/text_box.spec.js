@@ -0,0 +1,53 @@
+const test = require('ava')
+
+const { addTextBox, getObject, getPageContent } = require('../index')
+
+const { pageObject, shownText, simple } = require('./pdf')
+
+const paragraph = 'Copies of this report may only be shared inside the company.\nQuestions go to the finance team.'
+
+// The [x, y] position and the text of every line the box shows
+const lines = (content) =>
+  [...content.toString('latin1').matchAll(/([\d.]+) ([\d.]+) Td\s*\(((?:\\.|[^\\)])*)\) Tj/g)]
+    .map(([, x, y, text]) => [Number(x), Number(y), text])
+    .filter(([, , text]) => !text.startsWith('Page '))
+
+test('wraps a paragraph at word boundaries and breaks at explicit newlines', (t) => {
+  const content = getPageContent(addTextBox(simple(1), { rect: [72, 600, 272, 700], text: paragraph, fontSize: 12 }), 1)
+  t.deepEqual(lines(content), [
+    [72, 688, 'Copies of this report may only be'],
+    [72, 673.6, 'shared inside the company.'],
+    [72, 659.2, 'Questions go to the finance team.'],
+  ])
+  t.regex(content.toString('latin1'), /72 600 200 100 re\s+W\s+n/)
+})
+
+test('align places every line against the right edge or around the centre', (t) => {
+  const edges = (align) =>
+    lines(getPageContent(addTextBox(simple(1), { rect: [72, 600, 272, 700], text: paragraph, fontSize: 12, align }), 1))
+  const right = edges('right').map(([x]) => x)
+  const center = edges('center').map(([x]) => x)
+  right.forEach((x, index) => {
+    t.true(x > 72)
+    // A centred line starts half way between the left edge and where the right aligned one starts
+    t.true(Math.abs(center[index] - (72 + x) / 2) < 0.01)
+  })
+  t.true(new Set(right).size > 1)
+})
+
+test('leaves out the lines that overflow the box', (t) => {
+  const content = getPageContent(addTextBox(simple(1), { rect: [72, 660, 272, 700], text: paragraph, fontSize: 12 }), 1)
+  t.deepEqual(lines(content).map(([, , text]) => text), [
+    'Copies of this report may only be',
+    'shared inside the company.',
+  ])
+})
+
+test('stamps only the page it is given', (t) => {
+  const source = simple(2)
+  const stamped = addTextBox(source, { page: 2, rect: [72, 500, 272, 700], text: paragraph, fontSize: 12 })
+  t.deepEqual(getObject(stamped, pageObject(1)), getObject(source, pageObject(1)))
+  t.true(getPageContent(stamped, 1).equals(getPageContent(source, 1)))
+  t.is(lines(getPageContent(stamped, 2)).length, 3)
+  t.true(shownText(getPageContent(stamped, 2)).startsWith('Page 2'))
+})
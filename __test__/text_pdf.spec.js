@@ -0,0 +1,19 @@
+const test = require('ava')
+
+const { getPageContent, probe, textToPdf } = require('../index')
+
+// Words 66.72pt wide in 12pt Helvetica, six to a 451pt wide line of A4 between 72pt margins
+const words = (count) => Array(count).fill('0123456789').join(' ')
+
+test('textToPdf wraps the text to the page width', (t) => {
+  const lines = getPageContent(textToPdf(words(7)), 1).toString('latin1').match(/\(.*\) Tj/g)
+  t.deepEqual(lines, [`(${words(6)}) Tj`, `(${words(1)}) Tj`])
+})
+
+test('textToPdf starts a new page when the lines fill one', (t) => {
+  // 48 lines of 14.4pt fit on a page
+  t.is(probe(textToPdf(words(6 * 48))).pageCount, 1)
+  t.is(probe(textToPdf(words(6 * 48 + 1))).pageCount, 2)
+  t.is(probe(textToPdf(Array(97).fill('line').join('\n'))).pageCount, 3)
+  t.is(probe(textToPdf('first\fsecond')).pageCount, 2)
+})
@@ -0,0 +1,44 @@
+const test = require('ava')
+
+const { renderThumbnails } = require('../index')
+
+const { simple } = require('./pdf')
+
+const PNG_SIGNATURE = Buffer.from([0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a])
+
+// Width and height from the IHDR chunk that follows the signature
+const dimensions = (png) => [png.readUInt32BE(16), png.readUInt32BE(20)]
+
+test('renders the pages to PNG at one pixel per point by default', (t) => {
+  const thumbnails = renderThumbnails(simple(2, { width: 300, height: 400 }))
+  t.is(thumbnails.length, 2)
+  for (const png of thumbnails) {
+    t.true(png.subarray(0, 8).equals(PNG_SIGNATURE))
+    t.deepEqual(dimensions(png), [300, 400])
+  }
+})
+
+test('renders the chosen pages at the chosen resolution', (t) => {
+  const [png, ...rest] = renderThumbnails(simple(3), { dpi: 36, pages: [2] })
+  t.deepEqual(rest, [])
+  t.deepEqual(dimensions(png), [306, 396])
+  t.throws(() => renderThumbnails(simple(3), { pages: [4] }), { code: 'PageOutOfRange' })
+  t.throws(() => renderThumbnails(simple(1), { dpi: 0 }), { code: 'InvalidArg' })
+})
+
+test('dpi must be finite and give images of 1 to 16384 pixels per side', (t) => {
+  for (const dpi of [NaN, Infinity, -1]) {
+    t.throws(() => renderThumbnails(simple(1), { dpi }), { code: 'InvalidArg' })
+  }
+  for (const dpi of [1e5, 0.01]) {
+    t.throws(() => renderThumbnails(simple(1), { dpi }), {
+      code: 'LimitExceeded',
+      message: /images are 1 to 16384 pixels per side/,
+    })
+  }
+  // 1500 dpi fits the 612 point wide page, not the 792 point high one
+  t.throws(() => renderThumbnails(simple(1), { dpi: 1500 }), {
+    code: 'LimitExceeded',
+    message: 'Page 1 would be 12750 x 16500 pixels at 1500 dpi, images are 1 to 16384 pixels per side',
+  })
+})
@@ -0,0 +1,34 @@
+const { PassThrough, Readable } = require('stream')
+
+const test = require('ava')
+
+const { mergePdf, mergePdfFromStreams, mergePdfToStream } = require('../index')
+
+const { simple } = require('./pdf')
+
+// Takes seconds to merge, far longer than the timeouts below
+const large = () => Array.from({ length: 10 }, () => simple(50))
+
+const timeout = { code: 'Timeout', message: 'The merge ran past its timeoutMs' }
+
+test('the asynchronous merges reject with Timeout past timeoutMs', async (t) => {
+  const streams = large().map((buffer) => Readable.from([buffer]))
+  await t.throwsAsync(mergePdfFromStreams(streams, { timeoutMs: 1 }), timeout)
+  await t.throwsAsync(mergePdfToStream(large(), new PassThrough(), { timeoutMs: 1 }), timeout)
+})
+
+test('mergePdf throws Timeout past timeoutMs and merges within it', (t) => {
+  t.throws(() => mergePdf(large(), { timeoutMs: 1 }), timeout)
+  t.true(mergePdf([simple(1)], { timeoutMs: 60000 }).length > 0)
+  t.throws(() => mergePdf([simple(1)], { timeoutMs: 0 }), { code: 'InvalidArg' })
+})
+
+test('timeoutMs must be a positive finite duration', (t) => {
+  for (const timeoutMs of [-1, NaN, Infinity]) {
+    t.throws(() => mergePdf([simple(1)], { timeoutMs }), { code: 'InvalidArg', message: /must be positive and finite/ })
+  }
+  t.throws(() => mergePdf([simple(1)], { timeoutMs: 1e300 }), {
+    code: 'InvalidArg',
+    message: 'timeoutMs 1e300 is too long',
+  })
+})
@@ -0,0 +1,41 @@
+const test = require('ava')
+
+const { flattenTransparency, getObject, getPageContent } = require('../index')
+
+const { pageObject, simple } = require('./pdf')
+
+const image = '/Type /XObject /Subtype /Image /Width 1 /Height 1 /ColorSpace /DeviceGray /BitsPerComponent 8'
+
+// A page painting a half transparent, soft masked image in a transparency group. Object 6 is the
+// graphics state, 7 the image and 8 its soft mask
+const overlay = () =>
+  simple(1, {
+    page: () => '/Group << /S /Transparency /CS /DeviceRGB >>',
+    resources: () => '<< /Font << /F1 3 0 R >> /ExtGState << /GS1 6 0 R >> /XObject << /Im1 7 0 R >> >>',
+    extra: (objects) => {
+      objects[3] = { stream: 'BT /F1 24 Tf 72 700 Td (Page 1) Tj ET\nq /GS1 gs 100 0 0 100 72 500 cm /Im1 Do Q' }
+      objects.push('<< /Type /ExtGState /CA 0.5 /ca 0.5 /BM /Multiply /SMask /None /OP true /op true >>')
+      objects.push({
+        dict: `${image} /SMask 8 0 R`,
+        stream: Buffer.from([0]),
+      })
+      objects.push({
+        dict: image,
+        stream: Buffer.from([128]),
+      })
+    },
+  })
+
+test('flattenTransparency removes alpha, blend modes and soft masks, keeping overprint', (t) => {
+  const flattened = flattenTransparency(overlay())
+  t.deepEqual(getObject(flattened, 6), { '/Type': '/ExtGState', '/OP': true, '/op': true })
+  t.is(getObject(flattened, 7).stream.dict['/SMask'], undefined)
+  t.is(getObject(flattened, pageObject(1))['/Group'], undefined)
+  // The page still paints the image, now opaque
+  t.regex(getPageContent(flattened, 1).toString('latin1'), /\/GS1 gs[\s\S]*\/Im1 Do/)
+})
+
+test('flattenTransparency drops the soft mask image', (t) => {
+  const flattened = flattenTransparency(overlay())
+  t.throws(() => getObject(flattened, 8), { message: "Object 8 0 doesn't exist" })
+})
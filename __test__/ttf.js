@@ -0,0 +1,101 @@
+// A hand-written TrueType font for the specs, named `TestSans`. Glyph 0 is .notdef, then every
+// character given gets a square glyph of its own, `width` units wide on a 1000 unit em
+function font(characters, { width = 600 } = {}) {
+  const codes = [...new Set([...characters].map((c) => c.codePointAt(0)))].sort((a, b) => a - b)
+  const glyphCount = codes.length + 1
+
+  const glyph = Buffer.alloc(36)
+  // One contour of four on-curve points around (50, 0) - (width - 50, 700)
+  ;[1, 50, 0, width - 50, 700, 3, 0].forEach((value, index) => glyph.writeInt16BE(value, index * 2))
+  glyph.fill(0x01, 14, 18)
+  ;[50, width - 100, 0, 100 - width].forEach((value, index) => glyph.writeInt16BE(value, 18 + index * 2))
+  ;[0, 0, 700, 0].forEach((value, index) => glyph.writeInt16BE(value, 26 + index * 2))
+  const glyf = Buffer.concat(Array(glyphCount).fill(glyph))
+
+  // Short offsets, halved
+  const loca = Buffer.alloc(glyphCount * 2 + 2)
+  for (let index = 0; index <= glyphCount; index++) {
+    loca.writeUInt16BE((index * glyph.length) / 2, index * 2)
+  }
+
+  const head = Buffer.alloc(54)
+  head.writeUInt32BE(0x00010000, 0)
+  head.writeUInt32BE(0x00010000, 4)
+  head.writeUInt32BE(0x5f0f3cf5, 12)
+  head.writeUInt16BE(0x000b, 16)
+  head.writeUInt16BE(1000, 18)
+  ;[50, 0, width - 50, 700].forEach((value, index) => head.writeInt16BE(value, 36 + index * 2))
+  head.writeUInt16BE(8, 46)
+  head.writeInt16BE(2, 48)
+
+  const hhea = Buffer.alloc(36)
+  hhea.writeUInt32BE(0x00010000, 0)
+  hhea.writeInt16BE(800, 4)
+  hhea.writeInt16BE(-200, 6)
+  hhea.writeUInt16BE(width, 10)
+  hhea.writeInt16BE(50, 12)
+  hhea.writeInt16BE(50, 14)
+  hhea.writeInt16BE(width - 50, 16)
+  hhea.writeInt16BE(1, 18)
+  hhea.writeUInt16BE(glyphCount, 34)
+
+  const maxp = Buffer.alloc(32)
+  maxp.writeUInt32BE(0x00010000, 0)
+  maxp.writeUInt16BE(glyphCount, 4)
+  maxp.writeUInt16BE(4, 6)
+  maxp.writeUInt16BE(1, 8)
+  maxp.writeUInt16BE(2, 14)
+
+  const hmtx = Buffer.alloc(glyphCount * 4)
+  for (let index = 0; index < glyphCount; index++) {
+    hmtx.writeUInt16BE(width, index * 4)
+    hmtx.writeInt16BE(50, index * 4 + 2)
+  }
+
+  // A format 4 subtable with a segment per character, mapped through idDelta, and the final 0xFFFF
+  const segments = [...codes.map((code, index) => [code, index + 1 - code]), [0xffff, 1]]
+  const subtable = Buffer.alloc(16 + segments.length * 8)
+  const searchRange = 2 ** Math.floor(Math.log2(segments.length)) * 2
+  ;[4, subtable.length, 0, segments.length * 2, searchRange, Math.log2(searchRange / 2)].forEach((value, index) =>
+    subtable.writeUInt16BE(value, index * 2),
+  )
+  subtable.writeUInt16BE(segments.length * 2 - searchRange, 12)
+  segments.forEach(([code, delta], index) => {
+    subtable.writeUInt16BE(code, 14 + index * 2)
+    subtable.writeUInt16BE(code, 16 + segments.length * 2 + index * 2)
+    subtable.writeUInt16BE((delta + 0x10000) % 0x10000, 16 + segments.length * 4 + index * 2)
+  })
+  const cmap = Buffer.concat([Buffer.from([0, 0, 0, 1, 0, 3, 0, 1, 0, 0, 0, 12]), subtable])
+
+  // The PostScript name, in UTF-16BE for the Windows platform
+  const postScriptName = Buffer.from('TestSans', 'utf16le').swap16()
+  const name = Buffer.alloc(18)
+  ;[0, 1, 18, 3, 1, 0x409, 6, postScriptName.length, 0].forEach((value, index) => name.writeUInt16BE(value, index * 2))
+
+  const post = Buffer.alloc(32)
+  post.writeUInt32BE(0x00030000, 0)
+
+  const tables = { cmap, glyf, head, hhea, hmtx, loca, maxp, name: Buffer.concat([name, postScriptName]), post }
+  const tags = Object.keys(tables).sort()
+  const directory = Buffer.alloc(12 + tags.length * 16)
+  const tableSearchRange = 2 ** Math.floor(Math.log2(tags.length)) * 16
+  directory.writeUInt32BE(0x00010000, 0)
+  ;[tags.length, tableSearchRange, Math.log2(tableSearchRange / 16), tags.length * 16 - tableSearchRange].forEach(
+    (value, index) => directory.writeUInt16BE(value, 4 + index * 2),
+  )
+  const parts = [directory]
+  let offset = directory.length
+  tags.forEach((tag, index) => {
+    const data = tables[tag]
+    directory.write(tag.padEnd(4), 12 + index * 16, 'latin1')
+    directory.writeUInt32BE(offset, 20 + index * 16)
+    directory.writeUInt32BE(data.length, 24 + index * 16)
+    // Tables start on a 4 byte boundary
+    const padded = Buffer.concat([data, Buffer.alloc((4 - (data.length % 4)) % 4)])
+    parts.push(padded)
+    offset += padded.length
+  })
+  return Buffer.concat(parts)
+}
+
+module.exports = { font }
@@ -0,0 +1,47 @@
+// Type-checked by `yarn typecheck`, never run: the declarations must accept the calls below, with
+// the result types given, and reject the lines marked `@ts-expect-error`
+import {
+  extractPages,
+  FormField,
+  getFormFields,
+  mergePdf,
+  MergePlan,
+  MergeReport,
+  PdfErrorCode,
+  PdfPipeline,
+  probe,
+  ProbeResult,
+  splitPdfToFiles,
+} from '../index'
+
+declare const buffer: Buffer
+
+const expectType = <T>(_value: T) => undefined
+
+expectType<Buffer>(mergePdf([buffer, { buffer, rotate: 90, title: 'Annex' }]))
+expectType<Buffer>(mergePdf([buffer], { metadataFrom: 0, outlines: 'merge', noCompression: true }))
+expectType<undefined>(mergePdf([buffer], { outPath: 'merged.pdf' }))
+expectType<Required<MergeReport>>(mergePdf([buffer], { report: true }))
+expectType<MergePlan>(mergePdf([buffer], { dryRun: true }))
+// @ts-expect-error a report written to a file has no buffer
+expectType<Buffer>(mergePdf([buffer], { outPath: 'merged.pdf', report: true }).buffer)
+// @ts-expect-error unknown outline mode
+mergePdf([buffer], { outlines: 'all' })
+
+expectType<Buffer>(extractPages(buffer, [1, 3]))
+expectType<undefined>(extractPages(buffer, [1], { outPath: 'first.pdf' }))
+// @ts-expect-error pages are numbers
+extractPages(buffer, ['1'])
+
+expectType<string[]>(splitPdfToFiles(buffer, 'out', 'page-{n}.pdf'))
+expectType<ProbeResult>(probe(buffer))
+expectType<FormField[]>(getFormFields(buffer))
+
+const pipeline: PdfPipeline = new PdfPipeline(buffer).merge([buffer]).rotateRange(1, 2, 90).dedupeObjects()
+expectType<Buffer>(pipeline.toBuffer({ noObjectStreams: true }))
+expectType<void>(pipeline.toFile('out.pdf'))
+
+const code: PdfErrorCode = 'PageOutOfRange'
+// @ts-expect-error not one of the codes
+const invalid: PdfErrorCode = 'Oops'
+expectType<PdfErrorCode[]>([code, invalid])
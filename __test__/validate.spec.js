@@ -0,0 +1,47 @@
+const test = require('ava')
+
+const { tryLoad, validate } = require('../index')
+
+const { build, simple } = require('./pdf')
+
+test('a sound document has no issues', (t) => {
+  t.deepEqual(validate(simple(2)), { ok: true, isPdf: true, issues: [] })
+})
+
+test('lists the problems of a broken document instead of throwing', (t) => {
+  const broken = build([
+    '<< /Type /Catalog /Pages 2 0 R >>',
+    '<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 5 >>',
+    '<< /Type /Page /Parent 2 0 R /Contents 9 0 R >>',
+    '<< /Type /Page /Parent 2 0 R /MediaBox [0 0 10 10] >>',
+  ])
+  t.deepEqual(validate(broken), {
+    ok: false,
+    isPdf: true,
+    issues: [
+      '3 0 R references the missing object 9 0 R',
+      'The Pages node 2 0 R has /Count 5 but 2 pages',
+      'Page 1 (3 0 R) has no valid /MediaBox',
+    ],
+  })
+  t.deepEqual(validate(build(['<< /Type /Catalog >>'])).issues, ['The catalog 1 0 R has no /Pages'])
+})
+
+test('tells data that is not a PDF apart', (t) => {
+  const { ok, isPdf } = validate(Buffer.from('hello'))
+  t.false(ok)
+  t.false(isPdf)
+})
+
+test('tryLoad tells openable documents apart without throwing', (t) => {
+  t.true(tryLoad(simple(2)))
+  // The start of a JFIF file
+  t.false(tryLoad(Buffer.from([0xff, 0xd8, 0xff, 0xe0, 0x00, 0x10, 0x4a, 0x46, 0x49, 0x46, 0x00])))
+  t.false(tryLoad(simple(2).subarray(0, 200)))
+  t.false(tryLoad(build(['<< /Type /Catalog /Pages 2 0 R >>', '<< /Type /Pages /Kids [] /Count 0 >>'])))
+})
+
+test('an empty buffer is not a PDF', (t) => {
+  t.false(tryLoad(Buffer.alloc(0)))
+  t.deepEqual(validate(Buffer.alloc(0)), { ok: false, isPdf: false, issues: ['Invalid PDF: Invalid file header'] })
+})
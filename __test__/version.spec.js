@@ -0,0 +1,20 @@
+const fs = require('fs')
+const path = require('path')
+
+const test = require('ava')
+
+const { version } = require('../index')
+
+const { version: packageVersion } = require('../package.json')
+
+// The version of lopdf the lockfile pins
+const lockedLopdf = () =>
+  fs.readFileSync(path.join(__dirname, '..', 'Cargo.lock'), 'utf8').match(/name = "lopdf"\nversion = "([^"]+)"/)[1]
+
+test('version reports the version of the package', (t) => {
+  t.is(version().crate, packageVersion)
+})
+
+test('version reports the linked lopdf', (t) => {
+  t.is(version().lopdf, lockedLopdf())
+})
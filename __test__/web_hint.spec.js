@@ -0,0 +1,49 @@
+const test = require('ava')
+
+const { getTrailer, objectMap, probe, setWebOptimizedHint, validate } = require('../index')
+
+const { pageReferences, pageTexts, referenced, resolve } = require('./helpers')
+const { simple } = require('./pdf')
+
+// The linearization dictionary written first, as its source
+const linearization = (buffer) => buffer.toString('latin1').match(/^%PDF-1\.\d\n1 0 obj\n(<<[^]*?>>)\nendobj\n/)[1]
+
+test('setWebOptimizedHint writes the catalog and the first page objects ahead of the rest', (t) => {
+  const hinted = setWebOptimizedHint(simple(3), true)
+  t.true(validate(hinted).ok)
+  t.true(probe(hinted).linearized)
+  t.deepEqual(pageTexts(hinted), ['Page 1', 'Page 2', 'Page 3'])
+  const offsets = new Map(objectMap(hinted).map(({ id, offset }) => [id, offset]))
+  const offset = (reference) => offsets.get(referenced(reference)[0])
+  const [first, ...others] = pageReferences(hinted)
+  const page = resolve(hinted, first)
+  // In the order the first page reaches them
+  const front = [getTrailer(hinted)['/Root'], first, page['/Contents'], page['/Resources']['/Font']['/F1']]
+  t.deepEqual(front.map(offset), [...front.map(offset)].sort((a, b) => a - b))
+  const rest = [resolve(hinted, getTrailer(hinted)['/Root'])['/Pages'], ...others]
+  t.true(Math.min(...rest.map(offset)) > Math.max(...front.map(offset)))
+  t.true(Math.min(...front.map(offset)) > offsets.get(1))
+})
+
+test('the linearization dictionary locates the end of the first page and the xref table', (t) => {
+  const hinted = setWebOptimizedHint(simple(3), true)
+  const dictionary = linearization(hinted)
+  const entry = (key) => Number(dictionary.match(new RegExp(`/${key} (\\d+)`))[1])
+  t.notRegex(dictionary, /\/H/)
+  t.is(entry('L'), hinted.length)
+  t.is(entry('N'), 3)
+  t.is(`${entry('O')} 0 R`, pageReferences(hinted)[0])
+  // The first page's section ends where the page tree, the first object after it, starts
+  const pagesRoot = referenced(resolve(hinted, getTrailer(hinted)['/Root'])['/Pages'])[0]
+  t.is(entry('E'), objectMap(hinted).find(({ id }) => id === pagesRoot).offset)
+  // and `/T` is the end of line before the first cross-reference entry
+  t.is(hinted.subarray(entry('T') - 9, entry('T') + 21).toString('latin1'), 'xref\n0 12\n0000000000 65535 f \n')
+})
+
+test('setWebOptimizedHint with false removes the marker', (t) => {
+  const plain = setWebOptimizedHint(setWebOptimizedHint(simple(2), true), false)
+  t.false(probe(plain).linearized)
+  t.true(validate(plain).ok)
+  t.deepEqual(pageTexts(plain), ['Page 1', 'Page 2'])
+  t.throws(() => setWebOptimizedHint(simple(1), true, { incremental: true }), { code: 'InvalidArg' })
+})
@@ -0,0 +1,80 @@
+const path = require('path')
+const { Worker } = require('worker_threads')
+
+const test = require('ava')
+
+// Merges `merges` pairs of documents labelled after the worker and sends back the page texts of
+// every merge. Only the workers load the addon, which must survive them exiting
+const worker = `
+const { parentPort, workerData } = require('worker_threads')
+const { mergePdf } = require(workerData.index)
+const { pageTexts } = require(workerData.helpers)
+const { simple } = require(workerData.pdf)
+const texts = []
+for (let run = 0; run < workerData.merges; run++) {
+  texts.push(pageTexts(mergePdf([simple(2, { label: workerData.label }), simple((run % 3) + 1, { label: 'Run' })])))
+}
+parentPort.postMessage(texts)
+`
+
+// Calls `setProducerInfo` with `info` when given, then merges `merges` times with the save options
+// `options` and sends back the package name with the producer of every merge
+const producerWorker = `
+const { parentPort, workerData } = require('worker_threads')
+const { getMetadata, mergePdf, setProducerInfo, version } = require(workerData.index)
+const { simple } = require(workerData.pdf)
+if (workerData.info) {
+  setProducerInfo(workerData.info)
+}
+const producers = []
+for (let run = 0; run < workerData.merges; run++) {
+  producers.push(getMetadata(mergePdf([simple(1), simple(1)], workerData.options)).producer)
+}
+parentPort.postMessage({ addon: 'vibes-pdf-utils ' + version().crate, producers })
+`
+
+// Runs `script` in a worker with `data` and resolves to the message it sent
+const runScript = (script, data) =>
+  new Promise((resolve, reject) => {
+    const [index, helpers, pdf] = ['../index', 'helpers', 'pdf'].map((file) => path.join(__dirname, file))
+    const thread = new Worker(script, { eval: true, workerData: { index, helpers, pdf, ...data } })
+    let message
+    thread.once('message', (sent) => (message = sent))
+    thread.once('error', reject)
+    thread.once('exit', (code) => (code === 0 ? resolve(message) : reject(new Error(`The worker exited with ${code}`))))
+  })
+
+const run = (label, merges) => runScript(worker, { label, merges })
+
+test('mergePdf runs concurrently in worker threads', async (t) => {
+  const labels = Array.from({ length: 8 }, (_, index) => `Worker ${index}`)
+  const results = await Promise.all(labels.map((label) => run(label, 20)))
+  results.forEach((texts, index) => {
+    t.is(texts.length, 20)
+    texts.forEach((pages, run) => {
+      const runs = Array.from({ length: (run % 3) + 1 }, (_, page) => `Run ${page + 1}`)
+      t.deepEqual(pages, [`${labels[index]} 1`, `${labels[index]} 2`, ...runs])
+    })
+  })
+})
+
+// Only workers load the addon, so the settings are restored from one
+const restoreProducerInfo = () =>
+  runScript(producerWorker, { info: { producer: true, application: null, creator: null }, merges: 0 })
+
+test.serial('setProducerInfo in one worker changes the producer of the others', async (t) => {
+  t.teardown(restoreProducerInfo)
+  await runScript(producerWorker, { info: { application: 'Worker A' }, merges: 0 })
+  const { addon, producers } = await runScript(producerWorker, { merges: 5 })
+  t.deepEqual(producers, Array(5).fill(`Worker A (${addon})`))
+})
+
+test.serial('producerInfo names the producer of each worker', async (t) => {
+  const labels = ['Worker A', 'Worker B']
+  const results = await Promise.all(
+    labels.map((application) => runScript(producerWorker, { options: { producerInfo: { application } }, merges: 20 })),
+  )
+  results.forEach(({ addon, producers }, index) => {
+    t.deepEqual(producers, Array(20).fill(`${labels[index]} (${addon})`))
+  })
+})
@@ -0,0 +1,75 @@
+const test = require('ava')
+
+const { attachXmp, detachXmp, pdfaHints, validate } = require('../index')
+
+const { catalog, resolve } = require('./helpers')
+const { simple } = require('./pdf')
+
+// A one page document whose XMP packet, object 6, has the given `rdf:Description`
+const described = (description) =>
+  simple(1, {
+    catalog: '/Metadata 6 0 R',
+    extra: (objects) =>
+      objects.push({
+        dict: '/Type /Metadata /Subtype /XML',
+        stream: Buffer.from(
+          '<?xpacket begin="\ufeff" id="W5M0MpCehiHzreSzNTczkc9d"?>\n' +
+            '<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">' +
+            `${description}</rdf:RDF></x:xmpmeta>\n<?xpacket end="w"?>`,
+        ),
+      }),
+  })
+
+test('pdfaHints reports the part and conformance a PDF/A-2b file claims', (t) => {
+  const archival = described(
+    '<rdf:Description rdf:about="" xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/">' +
+      '<pdfaid:part>2</pdfaid:part><pdfaid:conformance>B</pdfaid:conformance></rdf:Description>',
+  )
+  t.deepEqual(pdfaHints(archival), { claimsPdfA: true, part: '2', conformance: 'B' })
+})
+
+test('pdfaHints reads the properties written as attributes under another prefix', (t) => {
+  const archival = described(`<rdf:Description rdf:about="" xmlns:id='http://www.aiim.org/pdfa/ns/id/' id:part="4"/>`)
+  t.deepEqual(pdfaHints(archival), { claimsPdfA: true, part: '4' })
+})
+
+test('pdfaHints reports no claim without the schema', (t) => {
+  t.deepEqual(pdfaHints(simple(1)), { claimsPdfA: false })
+  const plain = described('<rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/"/>')
+  t.deepEqual(pdfaHints(plain), { claimsPdfA: false })
+})
+
+test('detachXmp takes the packet out and attachXmp puts the edited one back', (t) => {
+  const source = described('<rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/"/>')
+  const { pdf, xmp } = detachXmp(source)
+  t.regex(xmp, /^<\?xpacket begin="\ufeff"/)
+  t.true(xmp.includes('xmlns:dc="http://purl.org/dc/elements/1.1/"/>'))
+  t.is(catalog(pdf)['/Metadata'], undefined)
+  t.true(validate(pdf).ok)
+  const edited = xmp.replace(
+    '/>',
+    ' xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/"><pdfaid:part>3</pdfaid:part></rdf:Description>',
+  )
+  const attached = attachXmp(pdf, edited)
+  const { stream } = resolve(attached, catalog(attached)['/Metadata'])
+  t.like(stream.dict, { '/Type': '/Metadata', '/Subtype': '/XML' })
+  t.is(stream.data.toString('utf8'), edited)
+  t.deepEqual(pdfaHints(attached), { claimsPdfA: true, part: '3' })
+  t.is(detachXmp(attached).xmp, edited)
+})
+
+test('detachXmp and attachXmp keep the original bytes with incremental', (t) => {
+  const source = described('<rdf:Description rdf:about=""/>')
+  const { pdf, xmp } = detachXmp(source, { incremental: true })
+  t.true(pdf.subarray(0, source.length).equals(source))
+  t.is(catalog(pdf)['/Metadata'], undefined)
+  const attached = attachXmp(pdf, xmp, { incremental: true })
+  t.true(attached.subarray(0, pdf.length).equals(pdf))
+  t.is(detachXmp(attached).xmp, xmp)
+})
+
+test('detachXmp reports no packet for a document without one', (t) => {
+  const { pdf, xmp } = detachXmp(simple(1))
+  t.is(xmp, null)
+  t.true(validate(pdf).ok)
+})
@@ -1,5 +1,19 @@
 extern crate napi_build;
 
+use std::fs;
+
+/// Version of a package in Cargo.lock, the one linked into the addon
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+  let mut lines = lock.lines();
+  lines.find(|line| *line == format!("name = \"{}\"", package))?;
+  let version = lines.next()?.strip_prefix("version = \"")?.strip_suffix('"')?;
+  Some(version.to_owned())
+}
+
 fn main() {
   napi_build::setup();
+  println!("cargo:rerun-if-changed=Cargo.lock");
+  let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+  let lopdf = locked_version(&lock, "lopdf").unwrap_or_else(|| "unknown".to_owned());
+  println!("cargo:rustc-env=LOPDF_VERSION={}", lopdf);
 }
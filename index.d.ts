@@ -1 +1,1124 @@
-export const mergePdf: (buffers: Buffer[]) => Buffer
+/*
+ * Every function works on its own copy of the input buffers and keeps no state between calls, so they
+ * can be called concurrently from Node worker threads. The exception is `setProducerInfo`, whose
+ * settings are shared by the whole process: a worker calling it changes the Producer and Creator
+ * written by every other worker. Workers naming their own use the `producerInfo` save option instead.
+ */
+
+/** Value of the `code` property on errors thrown by this package */
+export type PdfErrorCode = 'InvalidPdf' | 'EncryptedNoPassword' | 'NoPagesRoot' | 'PageOutOfRange' | 'CertifiedDocument' | 'XfaForm' | 'LimitExceeded' | 'Timeout' | 'InvalidArg' | 'GenericFailure'
+
+/** Serialization settings, for tools that need a stable uncompressed layout (e.g. signing) */
+export interface SaveOptions {
+  /** Drop the object and cross-reference streams of the source, every object is written in a classic xref table */
+  noObjectStreams?: boolean
+  /** Write the streams decoded instead of compressing them. Images and streams with unsupported filters are kept as is */
+  noCompression?: boolean
+  /**
+   * Parse the written document back and throw a `GenericFailure` when it doesn't, or lost its catalog
+   * or some pages, instead of returning a broken file. Defaults to `true`, `false` skips the extra parse.
+   */
+  verify?: boolean
+  /**
+   * Changes to the `setProducerInfo` settings for this call only, the properties it omits keep the
+   * settings of the process. Safe in worker threads, unlike `setProducerInfo`
+   */
+  producerInfo?: ProducerInfo
+}
+
+export interface OutputOptions extends SaveOptions {
+  /** Write the result to this path instead of returning it, the call then returns `undefined` */
+  outPath?: string
+  /**
+   * Append the changes to the original bytes as an incremental update instead of rewriting the file.
+   * Required for certified documents, whose certification a rewrite would break. `noObjectStreams` doesn't apply.
+   */
+  incremental?: boolean
+}
+
+/** Options with `outPath` set, for the overloads writing straight to a file */
+type ToFile<T> = T & { outPath: string }
+
+/** A paper size, case-insensitive, portrait unless it ends in `-landscape` */
+export type PageSizeName = `${'A3' | 'A4' | 'A5' | 'Letter' | 'Legal'}${'' | '-portrait' | '-landscape'}`
+
+export interface MergeOptions extends Omit<OutputOptions, 'incremental'> {
+  /** Index of the document whose catalog settings (Lang, ViewerPreferences, Metadata, PageLayout, OpenAction) are kept */
+  metadataFrom?: number
+  /** Return `{ buffer, warnings }` listing what the merge dropped or altered, instead of the buffer */
+  report?: boolean
+  /**
+   * Leave out the documents that fail to parse, listing them in the report warnings, instead of
+   * throwing. The merge still throws when none of the documents can be read
+   */
+  skipInvalid?: boolean
+  /**
+   * Rebuild the cross-reference table of the documents that fail to parse or show no pages, from the
+   * objects found in the file, before giving up on them. The report lists the repaired documents.
+   * Combines with `skipInvalid` for the documents beyond repair
+   */
+  repairInvalid?: boolean
+  /**
+   * Keep the article threads of every document. By default they are removed along with the page
+   * beads pointing into them, since the merged catalog could only keep one document's threads
+   */
+  preserveThreads?: boolean
+  /**
+   * Start with pages listing every document with a link to its first page. Titles come from
+   * `MergeSource.title`. Implies `outlines: 'perFile'` unless `outlines` is set
+   */
+  tableOfContents?: boolean
+  /**
+   * The bookmarks of the result: none (`drop`, the default), the outlines of all documents one
+   * after the other pointing at the merged pages (`merge`), or one bookmark per document to its
+   * first page, titled as in the table of contents (`perFile`). Merged bookmarks keep their
+   * structure destinations, the structure trees of tagged documents being combined
+   */
+  outlines?: 'drop' | 'merge' | 'perFile'
+  /** Size of the table of contents pages, the size of the first document's first page by default */
+  tableOfContentsPageSize?: PageSizeName
+  /**
+   * Throw when the `/Count` of a document's page tree doesn't match the pages found in it, instead
+   * of listing it in the report warnings. The merge uses the pages found either way
+   */
+  strictPageCount?: boolean
+  /**
+   * Replace the XMP metadata with a packet recording the number of merged documents, their
+   * titles when given and the producer, which is also set in the Info dictionary. The output
+   * header always takes the highest PDF version of the inputs
+   */
+  provenance?: boolean
+  /**
+   * Also record the Info title and author of every document in the provenance, which it implies,
+   * as a `pdfu:SourceInfo` sequence
+   */
+  sourceInfo?: boolean
+  /**
+   * What to do with a named destination an earlier document already defines: `rename` the later
+   * one along with its document's links (default), keep the earlier one so the links of both
+   * documents jump to it (`first-wins`), or throw (`error`)
+   */
+  destinationConflicts?: 'rename' | 'first-wins' | 'error'
+  /**
+   * What to do, per catalog name tree, with a name an earlier document already defined, as for
+   * `destinationConflicts` (`rename` by default). The trees listed here are merged from every
+   * document, embedded files and appearance streams included; others come from the last document
+   */
+  nameTreeConflicts?: Partial<
+    Record<
+      | 'AP'
+      | 'JavaScript'
+      | 'Pages'
+      | 'Templates'
+      | 'IDS'
+      | 'URLS'
+      | 'EmbeddedFiles'
+      | 'AlternatePresentations'
+      | 'Renditions',
+      'rename' | 'first-wins' | 'error'
+    >
+  >
+  /**
+   * What to do with portfolios, documents whose catalog `/Collection` makes their embedded files
+   * the content. `preserve` (default) keeps their cover pages and makes the result a portfolio of
+   * all their files; `flatten` merges the PDFs they embed in place of the cover pages, dropping
+   * the other files. Either way the report lists the portfolios met
+   */
+  portfolios?: 'preserve' | 'flatten'
+  /**
+   * Add a blank page after every document (and table of contents) with an odd number of pages, so
+   * each one starts on the front of a sheet when printed duplex
+   */
+  padEachSource?: boolean
+  /**
+   * Add a blank page at the end when the documents add up to an odd number of pages, and after the
+   * table of contents when it has an odd number, so the result is even
+   */
+  padToEven?: boolean
+  /** Fail with `LimitExceeded` before merging when the documents have more pages in total */
+  maxPages?: number
+  /** Fail with `LimitExceeded` before merging when the documents have more objects in total */
+  maxObjects?: number
+  /**
+   * Fail with `Timeout` once this many milliseconds have passed since the call, checked between
+   * documents and pages. Mostly for the asynchronous merges, so a pathological input can't hold a
+   * thread indefinitely. Anything but a positive finite number throws `InvalidArg`
+   */
+  timeoutMs?: number
+  /**
+   * Number the objects of the result from this value instead of 1, for embedding them into a
+   * larger document without collisions. The numbers stay within 8388607, the limit of the PDF
+   * reference: a result going past it throws `LimitExceeded`
+   */
+  startObjectId?: number
+  /**
+   * Choose the number of every object of the result instead of numbering them in order: called
+   * with the index of the document and the number of the object in it, returns its number in the
+   * result. The numbers must be distinct integers from 1 to 8388607, objects the merge adds come
+   * after the highest. Only `mergePdf` supports it, and not along with `startObjectId`
+   */
+  remapObjectId?: (source: number, id: number) => number
+  /**
+   * Keep one copy of the pages that display the same, e.g. a cover repeated in every report, the
+   * bookmarks and links to the others going to it. Blank pages from `padEachSource` are kept.
+   */
+  dedupePages?: boolean
+  /**
+   * Redraw every page on the MediaBox of the first page, upright, e.g. to bind A4 and Letter
+   * reports into one booklet. Annotations and page boxes follow the content
+   */
+  normalizeToFirst?: boolean
+  /**
+   * With `normalizeToFirst`, scale the content of the other pages to fit, keeping its proportions
+   * (`scale`, the default), or keep its size (`center`). Either way it is centered
+   */
+  normalizeFit?: 'scale' | 'center'
+}
+
+export interface MergeReport {
+  /** Absent when the result was written to `outPath` */
+  buffer?: Buffer
+  /** One message per dropped or altered item, prefixed with the index of the input document */
+  warnings: string[]
+  /** The 1-based inclusive output pages of every input document with pages, in order */
+  sources: MergeSourceRange[]
+}
+
+export interface MergeSourceRange {
+  /** Index of the input document */
+  source: number
+  startPage: number
+  endPage: number
+}
+
+/** What `mergePdf` would produce, from `dryRun` */
+export interface MergePlan {
+  /** Pages of the result, before any `dedupePages` collapses */
+  pageCount: number
+  /** The `%PDF-` header version of the result */
+  version: string
+  /** The bytes of the inputs, which the result is usually close to */
+  estimatedSize: number
+  /**
+   * What reading the documents shows would be dropped or altered. Renamed destinations and names
+   * and the other warnings of combining the documents only come from a real merge
+   */
+  warnings: string[]
+  sources: Array<MergeSourceRange & { outlines: boolean }>
+}
+
+export interface MergeSource {
+  buffer: Buffer
+  /** Clockwise rotation (multiple of 90) added to every page of this document */
+  rotate?: number
+  /** Title of the document in the table of contents, `Document <n>` by default, and in the provenance */
+  title?: string
+  /** 1-based pages to merge, in this order. Wins over `firstPages` and `lastPages` */
+  pages?: number[]
+  /** Merge only the first pages of the document, as many as it has at most */
+  firstPages?: number
+  /** Merge only the last pages of the document, as many as it has at most. Combines with `firstPages` */
+  lastPages?: number
+}
+
+export const mergePdf: {
+  /** Read the documents and report what the merge would give, without merging or writing anything */
+  (buffers: Array<Buffer | MergeSource>, options: MergeOptions & { dryRun: true }): MergePlan
+  (buffers: Array<Buffer | MergeSource>, options: ToFile<MergeOptions> & { report: true }): Omit<MergeReport, 'buffer'>
+  (buffers: Array<Buffer | MergeSource>, options: MergeOptions & { report: true }): Required<MergeReport>
+  (buffers: Array<Buffer | MergeSource>, options: ToFile<MergeOptions>): undefined
+  (buffers: Array<Buffer | MergeSource>, options?: MergeOptions): Buffer
+}
+
+/** Drain the Readables (which must emit Buffers), then merge them off the JS thread */
+export const mergePdfFromStreams: (
+  streams: NodeJS.ReadableStream[],
+  options?: Omit<MergeOptions, 'outPath' | 'report'>,
+) => Promise<Buffer>
+
+/**
+ * Merge the documents in order into as many parts as needed to keep each under `maxBytes`. A
+ * document over the cap on its own makes a part by itself, with a warning in the report
+ */
+export const mergePdfBounded: {
+  (
+    buffers: Array<Buffer | MergeSource>,
+    maxBytes: number,
+    options: Omit<MergeOptions, 'outPath'> & { report: true },
+  ): { buffers: Buffer[]; warnings: string[] }
+  (buffers: Array<Buffer | MergeSource>, maxBytes: number, options?: Omit<MergeOptions, 'outPath'>): Buffer[]
+}
+
+/**
+ * Merge on the threadpool and write the result to `writable` in chunks, the stream is left open.
+ * The chunks are sent as they're written, so `verify` doesn't apply.
+ */
+export const mergePdfToStream: (
+  buffers: Array<Buffer | MergeSource>,
+  writable: NodeJS.WritableStream,
+  options?: Omit<MergeOptions, 'outPath' | 'report'>,
+) => Promise<void>
+
+/** The Latin standard 14 fonts, readers provide them so nothing is embedded */
+export type StandardFontName =
+  | 'Helvetica'
+  | 'Helvetica-Bold'
+  | 'Helvetica-Oblique'
+  | 'Helvetica-BoldOblique'
+  | 'Times-Roman'
+  | 'Times-Bold'
+  | 'Times-Italic'
+  | 'Times-BoldItalic'
+  | 'Courier'
+  | 'Courier-Bold'
+  | 'Courier-Oblique'
+  | 'Courier-BoldOblique'
+
+/** Options shared by the operations drawing text */
+export interface TextOptions {
+  /**
+   * A standard font, or a TrueType/OpenType font file to embed for text outside Windows-1252
+   * (Latin-1 with quotes, dashes and the euro sign). A TrueType font is embedded as a subset of the
+   * glyphs drawn. Defaults to Helvetica
+   */
+  font?: StandardFontName | Buffer
+}
+
+/** Pages a stamping operation applies to, 1-based. A range without `to` runs to the last page */
+export type PageSelector = 'all' | 'odd' | 'even' | number[] | { from: number; to?: number }
+
+export interface HeaderFooterOptions extends OutputOptions, TextOptions {
+  /** Header text, supports the `{page}`, `{total}` and `{date}` placeholders */
+  header?: string
+  /** Footer text, supports the `{page}`, `{total}` and `{date}` placeholders */
+  footer?: string
+  /** Defaults to 10 */
+  fontSize?: number
+  /** Distance from the top/bottom page edge in points, defaults to 36 */
+  margin?: number
+  /** The page edges the margins are measured from, those of the page as displayed by default */
+  coordinateSpace?: CoordinateSpace
+  /** Pages to stamp, defaults to every page. `{page}` and `{total}` still count every page */
+  pages?: PageSelector
+}
+
+export const addHeaderFooter: {
+  (buffer: Buffer, options: ToFile<HeaderFooterOptions>): undefined
+  (buffer: Buffer, options: HeaderFooterOptions): Buffer
+}
+
+export interface TextToPdfOptions extends Omit<OutputOptions, 'incremental'>, TextOptions {
+  /** Defaults to A4 */
+  pageSize?: PageSizeName
+  /** Defaults to 12 */
+  fontSize?: number
+  /** Distance of the text from every page edge in points, defaults to 72 */
+  margin?: number
+}
+
+/**
+ * Lay out plain text on as many pages as it takes, wrapping lines to the page width. Line breaks
+ * are kept, tabs are four spaces and a form feed starts a new page
+ */
+export const textToPdf: {
+  (text: string, options: ToFile<TextToPdfOptions>): undefined
+  (text: string, options?: TextToPdfOptions): Buffer
+}
+
+/** Collapse byte-identical indirect objects into one, rewriting all references */
+export const dedupeObjects: {
+  (buffer: Buffer, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, options?: OutputOptions): Buffer
+}
+
+export interface DedupePagesOptions extends OutputOptions {
+  /** Remove only pages repeating the page right before them (default), or any repeated page */
+  mode?: 'consecutive' | 'any'
+}
+
+/** Remove pages identical to an earlier page, links to them point at the kept copy */
+export const dedupePages: {
+  (buffer: Buffer, options: ToFile<DedupePagesOptions>): undefined
+  (buffer: Buffer, options?: DedupePagesOptions): Buffer
+}
+
+export interface FormField {
+  /** Fully qualified field name, e.g. `address.city` */
+  name: string
+  /** Field type: `Tx`, `Btn`, `Ch` or `Sig` */
+  type: string
+  /** Field flags (`/Ff`) */
+  flags: number
+  value?: string
+}
+
+export const getFormFields: (buffer: Buffer) => FormField[]
+
+/**
+ * Render the named fields into the page content and remove them from the form, unknown names are
+ * ignored. Throws `XfaForm` on an XFA only form, as do `setFieldReadOnly` and `renameField`
+ */
+export const flattenFields: {
+  (buffer: Buffer, fieldNames: string[], options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, fieldNames: string[], options?: OutputOptions): Buffer
+}
+
+/** Toggle the read-only flag (bit 1 of `/Ff`) of the named fields */
+export const setFieldReadOnly: {
+  (buffer: Buffer, fieldNames: string[], readOnly: boolean, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, fieldNames: string[], readOnly: boolean, options?: OutputOptions): Buffer
+}
+
+/**
+ * Rename a field, or a group and every field under it, e.g. to keep the fields of several copies
+ * of a form apart before merging them. The new name keeps the parent of the old one, only its last
+ * part changes. Throws when the field doesn't exist or the new name is taken
+ */
+export const renameField: {
+  (buffer: Buffer, oldName: string, newName: string, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, oldName: string, newName: string, options?: OutputOptions): Buffer
+}
+
+/**
+ * Set or clear the AcroForm `/NeedAppearances` flag, so viewers redraw the fields from their
+ * values after they were filled by another tool. A form missing its `/AcroForm` gets one listing
+ * the fields of the widgets on the pages
+ */
+export const setNeedAppearances: {
+  (buffer: Buffer, value: boolean, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, value: boolean, options?: OutputOptions): Buffer
+}
+
+export interface FlattenAnnotationsOptions extends OutputOptions {
+  /** `/Subtype`s of the annotations to flatten, such as `Highlight` or `Stamp`, all of them by default */
+  types?: string[]
+}
+
+/**
+ * Draw the normal appearance of the annotations into the page content and remove them with their
+ * pop-up notes, so markups become part of the page. Form field widgets (see `flattenFields`) and
+ * annotations without an appearance stream are left as they are
+ */
+export const flattenAnnotations: {
+  (buffer: Buffer, options: ToFile<FlattenAnnotationsOptions>): undefined
+  (buffer: Buffer, options?: FlattenAnnotationsOptions): Buffer
+}
+
+export interface ValidationResult {
+  ok: boolean
+  /**
+   * Whether the data has a `%PDF-` header in its first kilobyte, telling a damaged PDF (`ok`
+   * false) from something that isn't a PDF at all
+   */
+  isPdf: boolean
+  /** Human readable description of every structural problem found */
+  issues: string[]
+}
+
+/** Check the structure of a PDF (trailer, catalog, page tree, references, MediaBox) without throwing */
+export const validate: (buffer: Buffer) => ValidationResult
+
+/**
+ * Whether the data is a PDF the other operations can open: it parses, isn't encrypted and has
+ * pages. Never throws, a cheap gate before heavier work
+ */
+export const tryLoad: (buffer: Buffer) => boolean
+
+export interface ProbeResult {
+  pageCount: number
+  encrypted: boolean
+  /**
+   * Whether opening the document takes a password, false when it is encrypted with an empty user
+   * password as readers open without asking
+   */
+  needsPassword: boolean
+  /** Version of the header, e.g. `1.7` */
+  version: string
+  linearized: boolean
+  /**
+   * Whether the form is XFA only (dynamic): the AcroForm field operations throw `XfaForm` on it as
+   * it has no AcroForm fields to change
+   */
+  xfaOnly: boolean
+}
+
+/** Answer the usual pre-flight questions in one parse, encrypted documents included */
+export const probe: (buffer: Buffer) => ProbeResult
+
+/** Recompute the `/Count` of every page tree node and repair missing or wrong `/Parent` links */
+export const fixPageTree: {
+  (buffer: Buffer, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, options?: OutputOptions): Buffer
+}
+
+/** The `/MediaBox` of the root page tree node, inherited by pages without their own, or `null` */
+export const getDefaultMediaBox: (buffer: Buffer) => [number, number, number, number] | null
+
+/**
+ * Set the `/MediaBox` of the root page tree node, `[llx, lly, urx, ury]` in points, resizing the
+ * pages that inherit it. Pages with their own MediaBox keep it
+ */
+export const setDefaultMediaBox: {
+  (buffer: Buffer, box: number[], options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, box: number[], options?: OutputOptions): Buffer
+}
+
+export interface PdfStats {
+  /** Number of indirect objects, including those stored in object streams */
+  objectCount: number
+  maxObjectId: number
+  streamObjectCount: number
+  /** Whether the file uses a cross-reference stream or object streams */
+  compressedXref: boolean
+  /**
+   * Whether the file is hybrid-reference, a classic table with a cross-reference stream for the
+   * objects older readers don't see. Those objects are read either way
+   */
+  hybridXref: boolean
+}
+
+export const stats: (buffer: Buffer) => PdfStats
+
+export interface ObjectLocation {
+  id: number
+  gen: number
+  /** Byte offset of the `N G obj` header, or of the object stream holding the object */
+  offset: number
+  /** Bytes up to and including `endobj` */
+  length: number
+  /** `/Type` of a dictionary or stream, e.g. `Catalog`, otherwise the kind of object, e.g. `array` */
+  type: string
+  /** For an object stored in an object stream, the number of that stream */
+  container?: number
+  /** For an object stored in an object stream, its position in the stream */
+  index?: number
+}
+
+/**
+ * Where each object sits in the file, by object number, as the newest cross-reference section
+ * locates it: for diagnosing damaged files, or for computing byte ranges
+ */
+export const objectMap: (buffer: Buffer) => ObjectLocation[]
+
+export interface FontInfo {
+  /** `/BaseFont`, subset tag included */
+  name: string
+  /** `Type1`, `TrueType`, `Type0`, `Type3`, ... */
+  subtype: string
+  /** Whether the font program is in the file, false when any use of the font lacks it */
+  embedded: boolean
+  /** Whether the name has the `ABCDEF+` tag of a subset font */
+  subset: boolean
+}
+
+/** List the fonts the pages use, directly or through forms, one entry per base font */
+export const listFonts: (buffer: Buffer) => FontInfo[]
+
+/**
+ * Shrink embedded fonts to the glyphs the pages and annotation appearances draw. Applies to Type 0
+ * fonts with a TrueType program and an Identity CMap, which covers most CJK fonts. Simple fonts,
+ * form default resource fonts and fonts whose subset wouldn't be smaller are left as they are
+ */
+export const subsetFonts: {
+  (buffer: Buffer, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, options?: OutputOptions): Buffer
+}
+
+/** Add a clockwise rotation (multiple of 90) to the 1-based inclusive page range `from`..`to` */
+export const rotateRange: {
+  (buffer: Buffer, from: number, to: number, degrees: number, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, from: number, to: number, degrees: number, options?: OutputOptions): Buffer
+}
+
+/** The `/Rotate` of every page, inherited values included, as 0, 90, 180 or 270 */
+export const getPageRotations: (buffer: Buffer) => number[]
+
+/**
+ * Apply the `/Rotate` of every page to its content, for tools that ignore it: the page displays
+ * the same with `/Rotate 0`, its MediaBox sides swapped for quarter turns. Annotations move with
+ * the content
+ */
+export const bakeRotation: {
+  (buffer: Buffer, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, options?: OutputOptions): Buffer
+}
+
+/**
+ * Add a quarter turn clockwise to the `/Rotate` of the pages displayed in the other orientation,
+ * without scaling. Square pages and pages already in the `target` orientation are left alone
+ */
+export const uniformOrientation: {
+  (buffer: Buffer, target: 'portrait' | 'landscape', options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, target: 'portrait' | 'landscape', options?: OutputOptions): Buffer
+}
+
+export interface ExtractOptions extends OutputOptions {
+  /**
+   * What to do with links to pages that weren't extracted: `remove` the link (default)
+   * or `keep` it without a destination
+   */
+  onBrokenLink?: 'remove' | 'keep'
+  /**
+   * Keep the tags of the extracted pages, restricting the structure tree to their content. By
+   * default the structure tree is removed, since it would point at the pages left out
+   */
+  preserveStructure?: boolean
+}
+
+/**
+ * Keep only the given 1-based pages, in the given order. Every page displays as in the source:
+ * what it inherits is copied onto it, its `/Rotate` as the angle it resolves to
+ */
+export const extractPages: {
+  (buffer: Buffer, pages: number[], options: ToFile<ExtractOptions>): undefined
+  (buffer: Buffer, pages: number[], options?: ExtractOptions): Buffer
+}
+
+/**
+ * Keep only the given 1-based pages like `extractPages`, each trimmed to its visible area: the
+ * CropBox becomes the MediaBox. Pages without a CropBox keep their MediaBox
+ */
+export const extractVisible: {
+  (buffer: Buffer, pages: number[], options: ToFile<ExtractOptions>): undefined
+  (buffer: Buffer, pages: number[], options?: ExtractOptions): Buffer
+}
+
+/**
+ * Keep the 1-based inclusive page range `from`..`to` like `extractPages`, written straight to
+ * `outPath` without building the output in memory
+ */
+export const extractRangeToFile: (
+  buffer: Buffer,
+  from: number,
+  to: number,
+  outPath: string,
+  options?: Omit<ExtractOptions, keyof OutputOptions> & SaveOptions,
+) => void
+
+/**
+ * Append the first page of `page` to the end of `base`, keeping everything else of `base`. The
+ * objects the page uses are renumbered and the attributes it inherits copied onto it; the rest of
+ * `page` (outline, form, catalog) is left behind.
+ */
+export const appendPage: {
+  (base: Buffer, page: Buffer, options: ToFile<OutputOptions>): undefined
+  (base: Buffer, page: Buffer, options?: OutputOptions): Buffer
+}
+
+export interface BlankPageOptions extends OutputOptions {
+  /**
+   * Size of the new page. By default it takes the size and rotation of the page before it, or of
+   * the first page when inserted first
+   */
+  size?: PageSizeName
+}
+
+/**
+ * Insert an empty page that becomes the 1-based page `position`, from 1 to one past the last page.
+ * Throws `InvalidArg` for an unknown size name
+ */
+export const insertBlankPage: {
+  (buffer: Buffer, position: number, options: ToFile<BlankPageOptions>): undefined
+  (buffer: Buffer, position: number, options?: BlankPageOptions): Buffer
+}
+
+export interface PosterOptions {
+  /** Tiles across and down, at least 1 */
+  cols: number
+  rows: number
+  /** Points every tile shares with its neighbours, as a margin for gluing. 0 by default */
+  overlap?: number
+}
+
+/**
+ * Replace the 1-based page by `cols` × `rows` pages each showing a tile of it, left to right from
+ * the top row as displayed, to print a poster on several sheets. The tiles share the page's
+ * content through their own MediaBox and CropBox; the first keeps its annotations and the links
+ * and bookmarks to it
+ */
+export const posterize: {
+  (buffer: Buffer, page: number, options: ToFile<PosterOptions & OutputOptions>): undefined
+  (buffer: Buffer, page: number, options: PosterOptions & OutputOptions): Buffer
+}
+
+export interface SplitOptions extends SaveOptions {
+  /** What to do with links to the other pages, as for `extractPages` */
+  onBrokenLink?: 'remove' | 'keep'
+  /** Restrict the structure tree to each page instead of removing it, as for `extractPages` */
+  preserveStructure?: boolean
+}
+
+/**
+ * Write every page to a document of its own in `outDir`, which is created when missing. `{n}` in
+ * `template` (default `'page-{n}.pdf'`) is replaced with the 1-based page number. The template is
+ * a file name, one with directories or `..` throws `InvalidArg`. Returns the written paths, in page order
+ */
+export const splitPdfToFiles: (buffer: Buffer, outDir: string, template?: string, options?: SplitOptions) => string[]
+
+export interface OpenAction {
+  /** 1-based page to jump to when the document is opened */
+  page?: number
+  /** URI to open when the document is opened, instead of a page */
+  url?: string
+  /** Magnification for `page`, 1 is 100%, or fit the page/its width. Keeps the viewer's zoom by default */
+  zoom?: number | 'fit' | 'fitWidth'
+}
+
+/** Set the catalog `/OpenAction` to jump to a page or open a URI when the document is opened */
+export const setOpenAction: {
+  (buffer: Buffer, action: OpenAction, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, action: OpenAction, options?: OutputOptions): Buffer
+}
+
+/**
+ * Unpack PDF 1.5 object streams into plain indirect objects and write a classic xref table,
+ * for tools that can't read compressed cross-references
+ */
+export const expandObjectStreams: {
+  (buffer: Buffer, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, options?: OutputOptions): Buffer
+}
+
+/**
+ * With `value`, write a `/Linearized` dictionary first, then the catalog and the objects of the
+ * first page, then the rest, so viewers that go by the marker start rendering early. This is only
+ * a hint: there are no hint tables and the file keeps a single cross-reference table, so it isn't
+ * full Fast Web View. Without `value`, remove the marker and the hint stream of a linearized file.
+ * `incremental` is only supported without `value`
+ */
+export const setWebOptimizedHint: {
+  (buffer: Buffer, value: boolean, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, value: boolean, options?: OutputOptions): Buffer
+}
+
+export interface RewriteOptions extends Omit<OutputOptions, 'incremental'> {
+  /** Drop the objects nothing refers to and renumber the others without gaps, defaults to true */
+  garbageCollect?: boolean
+}
+
+/**
+ * Write the document again without changing its content: streams compressed (unless
+ * `noCompression`), unused objects dropped and a classic xref table. Shrinks files with orphaned
+ * or uncompressed objects
+ */
+export const rewrite: {
+  (buffer: Buffer, options: ToFile<RewriteOptions>): undefined
+  (buffer: Buffer, options?: RewriteOptions): Buffer
+}
+
+/**
+ * How stamp positions are measured: from the bottom-left of the page as displayed, with the stamp
+ * upright even on rotated pages (`display`, the default), or in the page's own coordinates,
+ * ignoring `/Rotate` (`media`)
+ */
+export type CoordinateSpace = 'display' | 'media'
+
+export interface QrCodeOptions extends OutputOptions {
+  /** 1-based page to stamp, a shorthand for `pages: [page]` */
+  page?: number
+  /** Pages to stamp, defaults to every page */
+  pages?: PageSelector
+  /** Lower-left corner in points, measured in `coordinateSpace` */
+  x: number
+  y: number
+  coordinateSpace?: CoordinateSpace
+  /** Side length in points, including the quiet zone */
+  size: number
+}
+
+/** Stamp a QR code encoding `data` onto the page(s), as a crisp 1-bit image */
+export const addQrCode: {
+  (buffer: Buffer, data: string, options: ToFile<QrCodeOptions>): undefined
+  (buffer: Buffer, data: string, options: QrCodeOptions): Buffer
+}
+
+export interface TextBoxOptions extends OutputOptions, TextOptions {
+  /** 1-based page to stamp, a shorthand for `pages: [page]` */
+  page?: number
+  /** Pages to stamp, defaults to every page */
+  pages?: PageSelector
+  /** `[llx, lly, urx, ury]` of the box in points, measured in `coordinateSpace` */
+  rect: [number, number, number, number]
+  coordinateSpace?: CoordinateSpace
+  /** Broken at its newlines and between words to fit the width of the box */
+  text: string
+  /** Defaults to 12, lines are 1.2 times apart */
+  fontSize?: number
+  /** Defaults to `left` */
+  align?: 'left' | 'center' | 'right'
+}
+
+/**
+ * Stamp text wrapped into a box onto the page(s), from its top edge down. What doesn't fit is
+ * clipped: a word wider than the box is cut at its edge, lines below it are left out
+ */
+export const addTextBox: {
+  (buffer: Buffer, options: ToFile<TextBoxOptions>): undefined
+  (buffer: Buffer, options: TextBoxOptions): Buffer
+}
+
+/** Entries of the document Info dictionary, dates as ISO-8601 strings. Absent entries are omitted */
+export interface Metadata {
+  title?: string
+  author?: string
+  subject?: string
+  keywords?: string
+  creator?: string
+  producer?: string
+  creationDate?: string
+  modDate?: string
+}
+
+export const getMetadata: (buffer: Buffer) => Metadata
+
+export interface PdfaHints {
+  /** Whether the XMP metadata declares a PDF/A part */
+  claimsPdfA: boolean
+  /** `pdfaid:part`, e.g. `1` to `4` */
+  part?: string
+  /** `pdfaid:conformance`, e.g. `A`, `B` or `U`, absent for PDF/A-4 without a level */
+  conformance?: string
+}
+
+/**
+ * What the XMP `pdfaid` schema claims about PDF/A conformance, for sorting archival input. The
+ * file itself isn't checked against the standard
+ */
+export const pdfaHints: (buffer: Buffer) => PdfaHints
+
+export interface DetachedXmp {
+  /** The document without its XMP metadata, absent when it was written to `outPath` */
+  pdf?: Buffer
+  /** The XMP packet, `null` when the document had none */
+  xmp: string | null
+}
+
+/**
+ * Take the XMP metadata out of the document, to edit it with other tools and put it back with
+ * `attachXmp`. With `incremental: true` the original bytes are kept and only the change is appended
+ */
+export const detachXmp: (buffer: Buffer, options?: OutputOptions) => DetachedXmp
+
+/** Make `xmp` the document's XMP metadata, stored uncompressed, replacing the current packet */
+export const attachXmp: {
+  (buffer: Buffer, xmp: string, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, xmp: string, options?: OutputOptions): Buffer
+}
+
+export interface DatesOptions extends OutputOptions {
+  /** ISO-8601 date, or `null` to remove it. Without an offset the time is taken as UTC */
+  creationDate?: string | null
+  /** ISO-8601 date, or `null` to remove it. Without an offset the time is taken as UTC */
+  modDate?: string | null
+}
+
+/** Write the Info dictionary dates, creating the dictionary when missing */
+export const setDates: {
+  (buffer: Buffer, options: ToFile<DatesOptions>): undefined
+  (buffer: Buffer, options: DatesOptions): Buffer
+}
+
+export interface OutlineItem {
+  title: string
+  /** 1-based target page, absent when the bookmark doesn't point at a page of the document */
+  page?: number
+  children: OutlineItem[]
+}
+
+/**
+ * Read the bookmarks, resolving explicit and named destinations to page numbers. Bookmarks nested
+ * deeper than 256 levels are left out
+ */
+export const getOutline: (buffer: Buffer) => OutlineItem[]
+
+/** An item to write with `setOutline`, `children` defaults to none */
+export type OutlineInput = Omit<OutlineItem, 'children'> & { children?: OutlineInput[] }
+
+/**
+ * Replace the bookmarks, each pointing at its 1-based page. An empty outline removes them. Bookmarks
+ * nest at most 256 levels deep, deeper ones throw `InvalidArg`
+ */
+export const setOutline: {
+  (buffer: Buffer, outline: OutlineInput[], options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, outline: OutlineInput[], options?: OutputOptions): Buffer
+}
+
+export interface ThumbnailOptions {
+  /**
+   * Resolution of the images, defaults to 72 (one pixel per point). A page is rendered 1 to 16384
+   * pixels per side, throwing `LimitExceeded` at a resolution taking it out of these bounds
+   */
+  dpi?: number
+  /** 1-based pages to render, defaults to every page */
+  pages?: number[]
+}
+
+/**
+ * Render pages to PNG images on a white background, in the order of `pages`.
+ * Throws when the addon was built without the `thumbnails` feature.
+ */
+export const renderThumbnails: (buffer: Buffer, options?: ThumbnailOptions) => Buffer[]
+
+export interface ImageAltText {
+  /** 1-based page drawing the image */
+  page: number
+  /** Name of the image in the page `/XObject` resources, e.g. `Im1` */
+  imageName: string
+  /** Description read by screen readers */
+  alt: string
+}
+
+/**
+ * Describe images through `Figure` elements of the structure tree, tagging the document when it isn't.
+ * Throws when a page doesn't draw the named image.
+ */
+export const setImageAltText: {
+  (buffer: Buffer, altTexts: ImageAltText[], options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, altTexts: ImageAltText[], options?: OutputOptions): Buffer
+}
+
+export interface SanitizeOptions extends OutputOptions {
+  /** Remove JavaScript actions and document-level scripts, defaults to true */
+  javascript?: boolean
+  /** Remove the catalog `/OpenAction`, defaults to true */
+  openAction?: boolean
+  /** Remove the `/AA` actions run on document, page and field events, defaults to true */
+  additionalActions?: boolean
+  /** Remove `Launch` actions, and `URI` actions that run without a click, defaults to true */
+  launchActions?: boolean
+  /** Remove `GoToR` and `GoToE` actions, which open other PDF files, defaults to true. `URI` and `GoTo` links are kept */
+  remoteGoToActions?: boolean
+  /** Remove embedded files, file attachment annotations and associated files, defaults to false */
+  embeddedFiles?: boolean
+}
+
+/** Remove active content from an untrusted document in one pass */
+export const sanitize: {
+  (buffer: Buffer, options: ToFile<SanitizeOptions>): undefined
+  (buffer: Buffer, options?: SanitizeOptions): Buffer
+}
+
+/**
+ * Where additional actions are defined: the catalog (`WC`, `WS`, `DS`, `WP`, `DP`), the pages
+ * (`O`, `C`) or the form fields and their widgets (`K`, `F`, `V`, `C`, `E`, `X`, `D`, `U`, ...)
+ */
+export type AdditionalActionScope = 'document' | 'page' | 'field'
+
+export interface AdditionalAction {
+  scope: AdditionalActionScope
+  /** Key of the action in the `/AA` dictionary, the event that runs it */
+  trigger: string
+  /** Action type (`/S`), e.g. `JavaScript` or `Launch` */
+  type: string
+  /** 1-based page, for page actions */
+  page?: number
+  /** Fully qualified field name, for field actions */
+  field?: string
+}
+
+/** The `/AA` actions run automatically on document, page and field events, in document order */
+export const getAdditionalActions: (buffer: Buffer) => AdditionalAction[]
+
+/** Remove the `/AA` actions of one scope, or of all of them */
+export const removeAdditionalActions: {
+  (buffer: Buffer, scope: AdditionalActionScope | 'all', options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, scope: AdditionalActionScope | 'all', options?: OutputOptions): Buffer
+}
+
+export interface PageLabelRange {
+  /** 1-based first page of the range, which runs up to the next range */
+  startPage: number
+  /** Decimal, lowercase or uppercase roman, lowercase or uppercase letters. Absent, pages only get the prefix */
+  style?: 'D' | 'r' | 'R' | 'a' | 'A'
+  prefix?: string
+  /** Number of the range's first page, an integer from 1 to 100000 defaulting to 1 */
+  start?: number
+}
+
+/** Read the page label ranges, ordered by first page */
+export const getPageLabels: (buffer: Buffer) => PageLabelRange[]
+
+/** Replace the page labels. The first range starts on page 1, an empty list removes the labels */
+export const setPageLabels: {
+  (buffer: Buffer, labels: PageLabelRange[], options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, labels: PageLabelRange[], options?: OutputOptions): Buffer
+}
+
+/**
+ * 0-based index of the first page displayed with `label`, like `'iv'` or `'A-3'`. Pages without a
+ * label range are labeled with their page number. Throws when no page has the label
+ */
+export const resolveLabelToIndex: (buffer: Buffer, label: string) => number
+
+/** Label displayed for the page at the 0-based `index` */
+export const resolveIndexToLabel: (buffer: Buffer, index: number) => string
+
+/**
+ * A PDF object in the notation of qpdf's JSON output: names are `'/Name'` strings, strings
+ * `'u:text'` or `'b:<hex bytes>'`, references `'12 0 R'`, dictionary keys keep their `/`
+ */
+export type PdfValue =
+  | null
+  | boolean
+  | number
+  | string
+  | PdfValue[]
+  | { [key: string]: PdfValue }
+  | { stream: { dict: { [key: string]: PdfValue }; data: Buffer } }
+
+/** Read an indirect object, a stream's `data` is its raw (still encoded) content */
+export const getObject: (buffer: Buffer, objNum: number, genNum?: number) => PdfValue
+
+/** Read the trailer dictionary, whose `/Root` and `/Info` reference the catalog and the Info dictionary */
+export const getTrailer: (buffer: Buffer) => { [key: string]: PdfValue }
+
+/**
+ * Replace or add an indirect object, `null` deletes it. A stream's `/Length` is set from its data. `objNum` is an
+ * integer from 1 to 8388607, a new object at most 1024 past the highest object number
+ */
+export const setObject: {
+  (buffer: Buffer, objNum: number, genNum: number, value: PdfValue, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, objNum: number, genNum: number, value: PdfValue, options?: OutputOptions): Buffer
+}
+
+/**
+ * Set a trailer entry such as `'/ID'`, `null` removes it. Throws when `/Root` wouldn't reference a
+ * dictionary of the document. `/Size` is always rewritten from the objects
+ */
+export const setTrailerEntry: {
+  (buffer: Buffer, key: string, value: PdfValue, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, key: string, value: PdfValue, options?: OutputOptions): Buffer
+}
+
+/**
+ * Remove transparency for printers that don't support it: graphics state alpha, blend modes and
+ * soft masks, image soft masks, transparency groups and annotation opacity. Transparent objects
+ * are painted opaque rather than composited, overprint settings are kept
+ */
+export const flattenTransparency: {
+  (buffer: Buffer, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, options?: OutputOptions): Buffer
+}
+
+export interface NormalizeBlackOptions extends OutputOptions {
+  /** How far below 100% the black of a rich black may be, 0.05 by default */
+  tolerance?: number
+}
+
+/**
+ * Replace rich blacks, CMYK blacks mixed with cyan, magenta or yellow, by plain `0 0 0 1` black so
+ * black text and rules don't show registration errors in print. Only colors set with `k`/`K` in
+ * the page contents, forms and tiling patterns are changed
+ */
+export const normalizeBlack: {
+  (buffer: Buffer, options: ToFile<NormalizeBlackOptions>): undefined
+  (buffer: Buffer, options?: NormalizeBlackOptions): Buffer
+}
+
+/** Keeps one parsed document across several operations, only parsing and serializing once */
+export class PdfPipeline {
+  constructor(buffer: Buffer)
+  /** Append documents after the current one, `metadataFrom: 0` refers to the pipeline document */
+  merge(
+    buffers: Array<Buffer | MergeSource>,
+    options?: Omit<MergeOptions, keyof OutputOptions | 'report' | 'remapObjectId'>,
+  ): this
+  addHeaderFooter(options: Omit<HeaderFooterOptions, keyof OutputOptions>): this
+  dedupeObjects(): this
+  flattenFields(fieldNames: string[]): this
+  setFieldReadOnly(fieldNames: string[], readOnly: boolean): this
+  renameField(oldName: string, newName: string): this
+  setNeedAppearances(value: boolean): this
+  flattenAnnotations(options?: Omit<FlattenAnnotationsOptions, keyof OutputOptions>): this
+  rotateRange(from: number, to: number, degrees: number): this
+  bakeRotation(): this
+  uniformOrientation(target: 'portrait' | 'landscape'): this
+  extractPages(pages: number[], options?: Omit<ExtractOptions, keyof OutputOptions>): this
+  extractVisible(pages: number[], options?: Omit<ExtractOptions, keyof OutputOptions>): this
+  appendPage(page: Buffer): this
+  insertBlankPage(position: number, options?: Omit<BlankPageOptions, keyof OutputOptions>): this
+  posterize(page: number, options: PosterOptions): this
+  fixPageTree(): this
+  setDefaultMediaBox(box: number[]): this
+  setOpenAction(action: OpenAction): this
+  addQrCode(data: string, options: Omit<QrCodeOptions, keyof OutputOptions>): this
+  addTextBox(options: Omit<TextBoxOptions, keyof OutputOptions>): this
+  setDates(options: Omit<DatesOptions, keyof OutputOptions>): this
+  setOutline(outline: OutlineInput[]): this
+  setImageAltText(altTexts: ImageAltText[]): this
+  sanitize(options?: Omit<SanitizeOptions, keyof OutputOptions>): this
+  removeAdditionalActions(scope: AdditionalActionScope | 'all'): this
+  setPageLabels(labels: PageLabelRange[]): this
+  setObject(objNum: number, genNum: number, value: PdfValue): this
+  setTrailerEntry(key: string, value: PdfValue): this
+  flattenTransparency(): this
+  normalizeBlack(options?: Omit<NormalizeBlackOptions, keyof OutputOptions>): this
+  subsetFonts(): this
+  setPageContent(pageNumber: number, content: Buffer): this
+  toBuffer(options?: SaveOptions): Buffer
+  toFile(path: string, options?: SaveOptions): void
+}
+
+export interface AllocatorInfo {
+  /**
+   * Global allocator of the build: `'jemalloc'` on glibc x86_64 release builds, `'mimalloc'` on
+   * Windows x86_64 and `'system'` elsewhere or with the `system-allocator` feature
+   */
+  name: 'jemalloc' | 'mimalloc' | 'system'
+  /** Whether the addon is a debug build */
+  debug: boolean
+}
+
+/** Report the global allocator the addon was built with */
+export const allocatorInfo: () => AllocatorInfo
+
+/**
+ * The content stream operators of the 1-based page, with the filters of every `/Contents` stream
+ * decoded and the streams joined by newlines
+ */
+export const getPageContent: (buffer: Buffer, pageNumber: number) => Buffer
+
+/**
+ * Replace the content of the 1-based page with `content`, compressed on save unless
+ * `noCompression` is set. The page keeps its `/Resources`, so the fonts and images it names can
+ * still be used
+ */
+export const setPageContent: {
+  (buffer: Buffer, pageNumber: number, content: Buffer, options: ToFile<OutputOptions>): undefined
+  (buffer: Buffer, pageNumber: number, content: Buffer, options?: OutputOptions): Buffer
+}
+
+export interface VersionInfo {
+  /** Version of the addon, the same as the npm package */
+  crate: string
+  /** Version of lopdf linked into the addon */
+  lopdf: string
+}
+
+/** Versions compiled into the addon, to report along with bugs */
+export const version: () => VersionInfo
+
+/**
+ * SHA-256 (hex) of what the pages display: their content, resources, geometry and annotations.
+ * Metadata, document IDs, dates, object numbers and stream compression don't change it, so it
+ * tells whether two files show the same pages
+ */
+export const contentFingerprint: (buffer: Buffer) => string
+
+export interface ProducerInfo {
+  /** Write `/Producer` on every rewrite, on by default */
+  producer?: boolean
+  /** Name of the calling application, written in `/Producer` before this package, or `null` to stop */
+  application?: string | null
+  /** Written into `/Creator` on every rewrite, or `null` to leave `/Creator` alone again (the default) */
+  creator?: string | null
+}
+
+/**
+ * Change what every operation rewriting a document puts in its Info dictionary, for the whole
+ * process: the settings are shared by all worker threads, so one worker calling this changes what
+ * the others write. Set `producerInfo` in the save options to change them for a single call.
+ * Omitted properties keep their setting. Incremental updates append the Info dictionary along with
+ * the objects they change, and leave the document as it is when nothing changed
+ */
+export const setProducerInfo: (info: ProducerInfo) => void
@@ -0,0 +1,28 @@
+// Fails when the napi exports registered in `init` and the declarations in index.d.ts drift apart
+const fs = require('fs')
+const path = require('path')
+
+const root = path.join(__dirname, '..')
+
+// Functions are registered with `create_named_method`, classes with `set_named_property`
+const registered = new Set(
+  [
+    ...fs.readFileSync(path.join(root, 'src', 'lib.rs'), 'utf8').matchAll(/exports\.(?:create_named_method|set_named_property)\("(\w+)"/g),
+  ].map(([, name]) => name),
+)
+const declared = new Set(
+  [...fs.readFileSync(path.join(root, 'index.d.ts'), 'utf8').matchAll(/^export (?:const|class) (\w+)/gm)].map(
+    ([, name]) => name,
+  ),
+)
+
+const missing = [...registered].filter((name) => !declared.has(name))
+const stale = [...declared].filter((name) => !registered.has(name))
+
+if (missing.length || stale.length) {
+  if (missing.length) console.error(`Exported but not declared in index.d.ts: ${missing.join(', ')}`)
+  if (stale.length) console.error(`Declared in index.d.ts but not exported: ${stale.join(', ')}`)
+  process.exit(1)
+}
+
+console.info(`index.d.ts declares all ${registered.size} exports`)
@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+use crate::dedupe::{same_object, stays_distinct};
+use crate::fingerprint::Canonical;
+use crate::form::root_fields;
+use crate::names::unique_name;
+use crate::utils::replace_references;
+
+/// Interactive forms collected from all merged documents
+#[derive(Default)]
+pub struct AcroForms {
+  /// Whether any document has an `AcroForm` at all
+  present: bool,
+  fields: Vec<Object>,
+  /// Default resources (`/DR`) by category (`Font`, `XObject`, ...)
+  resources: BTreeMap<Vec<u8>, Dictionary>,
+  /// Default appearance (`/DA`) of the first document defining one
+  default_appearance: Option<Object>,
+  need_appearances: bool,
+  sig_flags: i64,
+  /// The objects reached from the default resources merged so far, by the digest of their value
+  shared: BTreeMap<[u8; 32], ObjectId>,
+}
+
+/// Replace the `/Name` operands of a default appearance string
+fn rename_fonts(appearance: &[u8], renamed: &BTreeMap<Vec<u8>, Vec<u8>>) -> Vec<u8> {
+  appearance
+      .split(|byte| byte.is_ascii_whitespace())
+      .filter(|token| !token.is_empty())
+      .map(|token| match token.strip_prefix(b"/").and_then(|name| renamed.get(name)) {
+        Some(new_name) => [b"/".as_slice(), new_name].concat(),
+        None => token.to_vec(),
+      })
+      .collect::<Vec<_>>()
+      .join(&b' ')
+}
+
+impl AcroForms {
+  /// Collect the form of a (renumbered) document. Default resource names colliding with an
+  /// earlier document are renamed, along with the `/DA` strings using them.
+  /// Returns the renamed fonts.
+  pub fn add_document(&mut self, document: &mut Document) -> BTreeMap<Vec<u8>, Vec<u8>> {
+    let (acro_form_id, acro_form) = match document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"AcroForm"))
+        .and_then(|acro_form| document.dereference(acro_form))
+        .and_then(|(id, acro_form)| acro_form.as_dict().map(|acro_form| (id, acro_form.clone())))
+    {
+      Ok(acro_form) => acro_form,
+      Err(_) => return BTreeMap::new(),
+    };
+    self.present = true;
+    let field_ids = root_fields(document);
+    // Replaced by the merged form
+    if let Some(id) = acro_form_id {
+      document.objects.remove(&id);
+    }
+    let mut renamed_fonts = BTreeMap::new();
+    let mut resources = Object::Dictionary(
+      acro_form
+          .get(b"DR")
+          .and_then(|resources| document.dereference(resources))
+          .and_then(|(_, resources)| resources.as_dict())
+          .cloned()
+          .unwrap_or_default(),
+    );
+    self.share_resources(document, &mut resources);
+    let resources = resources.as_dict().cloned().unwrap_or_default();
+    for (category, entries) in resources.iter() {
+      let entries = match document.dereference(entries).and_then(|(_, entries)| entries.as_dict()) {
+        Ok(entries) => entries,
+        Err(_) => continue,
+      };
+      let merged = self.resources.entry(category.clone()).or_default();
+      for (name, value) in entries.iter() {
+        let name = match merged.get(name) {
+          Ok(existing) if !same_object(existing, value) => {
+            let new_name = unique_name(name, |candidate| merged.has(candidate) || entries.has(candidate));
+            if category == b"Font" {
+              renamed_fonts.insert(name.clone(), new_name.clone());
+            }
+            new_name
+          }
+          _ => name.clone(),
+        };
+        merged.set(name, value.clone());
+      }
+    }
+    if !renamed_fonts.is_empty() {
+      for object in document.objects.values_mut() {
+        if let Ok(dictionary) = object.as_dict_mut() {
+          if let Ok(Object::String(appearance, _)) = dictionary.get_mut(b"DA") {
+            *appearance = rename_fonts(appearance, &renamed_fonts);
+          }
+        }
+      }
+    }
+    let default_appearance = acro_form.get(b"DA").ok().map(|appearance| match appearance {
+      Object::String(appearance, format) => Object::String(rename_fonts(appearance, &renamed_fonts), format.clone()),
+      _ => appearance.clone(),
+    });
+    match (&self.default_appearance, default_appearance) {
+      (None, default_appearance) => self.default_appearance = default_appearance,
+      // Fields inherit `/DA` from the form, keep this document's default on its own fields
+      (Some(merged), Some(default_appearance)) if !same_object(merged, &default_appearance) => {
+        for field_id in field_ids.iter() {
+          if let Ok(field) = document.get_object_mut(*field_id).and_then(Object::as_dict_mut) {
+            if !field.has(b"DA") {
+              field.set("DA", default_appearance.clone());
+            }
+          }
+        }
+      }
+      _ => {}
+    }
+    self.fields.extend(field_ids.into_iter().map(Object::Reference));
+    self.need_appearances |= acro_form
+        .get(b"NeedAppearances")
+        .and_then(Object::as_bool)
+        .unwrap_or(false);
+    self.sig_flags |= acro_form.get(b"SigFlags").and_then(Object::as_i64).unwrap_or(0);
+    renamed_fonts
+  }
+
+  /// Point the default resources of a document, and everything else using them, at the equal
+  /// objects an earlier document brought, so forms made from one template share their fonts
+  fn share_resources(&mut self, document: &mut Document, resources: &mut Object) {
+    let mut canonical = Canonical::new(document);
+    let mut roots = vec![];
+    if let Ok(categories) = resources.as_dict() {
+      for (_, entries) in categories.iter() {
+        if let Ok((_, Object::Dictionary(entries))) = document.dereference(entries) {
+          roots.extend(entries.iter().filter_map(|(_, value)| value.as_reference().ok()));
+        }
+      }
+    }
+    for root in roots {
+      canonical.digest(root);
+    }
+    let digests = canonical.into_digests().into_iter().collect::<BTreeMap<_, _>>();
+    let mut replace = BTreeMap::new();
+    for (id, digest) in digests {
+      let distinct = match document.objects.get(&id) {
+        Some(object) => stays_distinct(object),
+        None => true,
+      };
+      if distinct {
+        continue;
+      }
+      match self.shared.get(&digest) {
+        Some(&shared_id) => {
+          replace.insert(id, shared_id);
+        }
+        None => {
+          self.shared.insert(digest, id);
+        }
+      }
+    }
+    if replace.is_empty() {
+      return;
+    }
+    for id in replace.keys() {
+      document.objects.remove(id);
+    }
+    for object in document.objects.values_mut() {
+      replace_references(object, &replace);
+    }
+    replace_references(resources, &replace);
+  }
+
+  /// Write the merged form into the catalog of the merged document
+  pub fn apply(&self, document: &mut Document, catalog: &mut Dictionary) {
+    if !self.present {
+      return;
+    }
+    let mut acro_form = Dictionary::new();
+    acro_form.set("Fields", self.fields.clone());
+    if !self.resources.is_empty() {
+      let mut resources = Dictionary::new();
+      for (category, entries) in self.resources.iter() {
+        resources.set(category.clone(), entries.clone());
+      }
+      acro_form.set("DR", resources);
+    }
+    if let Some(ref default_appearance) = self.default_appearance {
+      acro_form.set("DA", default_appearance.clone());
+    }
+    if self.need_appearances {
+      acro_form.set("NeedAppearances", true);
+    }
+    if self.sig_flags != 0 {
+      acro_form.set("SigFlags", self.sig_flags);
+    }
+    catalog.set("AcroForm", document.add_object(acro_form));
+  }
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeSet;
+
+use lopdf::{Document, Object, ObjectId};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status};
+
+use crate::error::OrThrow;
+use crate::form::collect_fields;
+use crate::utils::{buffer_value, load_document, output, output_update};
+
+/// Where an `/AA` additional-actions dictionary is defined
+#[derive(Clone, Copy, PartialEq)]
+pub enum ActionScope {
+  /// The catalog, run when the document is closed, saved or printed
+  Document,
+  /// A page, run when it is opened or closed
+  Page,
+  /// A form field or one of its widgets, run on keystrokes, focus and mouse events
+  Field,
+}
+
+impl ActionScope {
+  fn name(self) -> &'static str {
+    match self {
+      ActionScope::Document => "document",
+      ActionScope::Page => "page",
+      ActionScope::Field => "field",
+    }
+  }
+
+  /// The scope filter of `removeAdditionalActions`, `None` for `all`
+  pub fn filter_from_js(scope: &str) -> Result<Option<Self>> {
+    match scope {
+      "all" => Ok(None),
+      "document" => Ok(Some(ActionScope::Document)),
+      "page" => Ok(Some(ActionScope::Page)),
+      "field" => Ok(Some(ActionScope::Field)),
+      other => Err(Error::new(
+        Status::InvalidArg,
+        format!("scope must be 'all', 'document', 'page' or 'field', got '{}'", other),
+      )),
+    }
+  }
+}
+
+/// An object carrying an `/AA` entry
+struct ActionOwner {
+  id: ObjectId,
+  scope: ActionScope,
+  /// 1-based page, for page actions
+  page: Option<u32>,
+  /// Fully qualified name, for field actions
+  field: Option<String>,
+}
+
+/// Every object that can carry additional actions: the catalog, the pages, and the terminal
+/// fields with their widgets
+fn action_owners(document: &Document) -> Vec<ActionOwner> {
+  let mut owners = vec![];
+  if let Ok(catalog_id) = document.trailer.get(b"Root").and_then(Object::as_reference) {
+    owners.push(ActionOwner {
+      id: catalog_id,
+      scope: ActionScope::Document,
+      page: None,
+      field: None,
+    });
+  }
+  for (page_number, page_id) in document.get_pages() {
+    owners.push(ActionOwner {
+      id: page_id,
+      scope: ActionScope::Page,
+      page: Some(page_number),
+      field: None,
+    });
+  }
+  let mut visited = BTreeSet::new();
+  for field in collect_fields(document) {
+    for id in std::iter::once(field.id).chain(field.widgets) {
+      if visited.insert(id) {
+        owners.push(ActionOwner {
+          id,
+          scope: ActionScope::Field,
+          page: None,
+          field: Some(field.name.clone()),
+        });
+      }
+    }
+  }
+  owners
+}
+
+#[js_function(1)]
+pub fn get_additional_actions(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  let mut result = ctx.env.create_array_with_length(0)?;
+  let mut index = 0;
+  for owner in action_owners(&document) {
+    let actions = document
+        .get_dictionary(owner.id)
+        .and_then(|dictionary| dictionary.get(b"AA"))
+        .and_then(|actions| document.dereference(actions))
+        .and_then(|(_, actions)| actions.as_dict());
+    let actions = match actions {
+      Ok(actions) => actions,
+      Err(_) => continue,
+    };
+    for (trigger, action) in actions.iter() {
+      let action_type = document
+          .dereference(action)
+          .and_then(|(_, action)| action.as_dict())
+          .and_then(|action| action.get(b"S"))
+          .and_then(Object::as_name_str)
+          .unwrap_or("");
+      let mut item = ctx.env.create_object()?;
+      item.set_named_property("scope", ctx.env.create_string(owner.scope.name())?)?;
+      item.set_named_property("trigger", ctx.env.create_string(&String::from_utf8_lossy(trigger))?)?;
+      item.set_named_property("type", ctx.env.create_string(action_type)?)?;
+      if let Some(page) = owner.page {
+        item.set_named_property("page", ctx.env.create_uint32(page)?)?;
+      }
+      if let Some(ref field) = owner.field {
+        item.set_named_property("field", ctx.env.create_string(field)?)?;
+      }
+      result.set_element(index, item)?;
+      index += 1;
+    }
+  }
+  Ok(result)
+}
+
+#[js_function(3)]
+pub fn remove_additional_actions(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let scope = ActionScope::filter_from_js(&ctx.get::<String>(1)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  remove_additional_actions_in(&mut document, scope);
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Remove the `/AA` entries of `scope`, or of every scope
+pub fn remove_additional_actions_in(document: &mut Document, scope: Option<ActionScope>) {
+  for owner in action_owners(document) {
+    if scope.is_some_and(|scope| scope != owner.scope) {
+      continue;
+    }
+    if let Ok(dictionary) = document.get_object_mut(owner.id).and_then(Object::as_dict_mut) {
+      dictionary.remove(b"AA");
+    }
+  }
+}
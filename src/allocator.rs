@@ -0,0 +1,43 @@
+// `#[js_function(0)]` expands to a zero-length argument array
+#![allow(clippy::zero_repeat_side_effects)]
+
+use napi::{CallContext, JsObject, Result};
+
+#[cfg(all(
+  unix,
+  not(target_env = "musl"),
+  not(target_arch = "aarch64"),
+  not(target_arch = "arm"),
+  not(debug_assertions),
+  not(feature = "system-allocator")
+))]
+#[global_allocator]
+static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
+
+#[cfg(all(windows, target_arch = "x86_64", not(feature = "system-allocator")))]
+#[global_allocator]
+static ALLOC: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+/// Name of the global allocator this build was compiled with
+const ALLOCATOR: &str = if cfg!(all(
+  unix,
+  not(target_env = "musl"),
+  not(target_arch = "aarch64"),
+  not(target_arch = "arm"),
+  not(debug_assertions),
+  not(feature = "system-allocator")
+)) {
+  "jemalloc"
+} else if cfg!(all(windows, target_arch = "x86_64", not(feature = "system-allocator"))) {
+  "mimalloc"
+} else {
+  "system"
+};
+
+#[js_function(0)]
+pub fn allocator_info(ctx: CallContext) -> Result<JsObject> {
+  let mut info = ctx.env.create_object()?;
+  info.set_named_property("name", ctx.env.create_string(ALLOCATOR)?)?;
+  info.set_named_property("debug", ctx.env.get_boolean(cfg!(debug_assertions))?)?;
+  Ok(info)
+}
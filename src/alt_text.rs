@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+
+use lopdf::content::Operation;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status};
+
+use crate::error::{self, ErrorCode, OrThrow, PdfError};
+use crate::names::{number_tree, number_tree_entries};
+use crate::page;
+use crate::utils::{buffer_value, encode_text_string, load_document, output, output_update};
+
+/// Alternate text for the image drawn under `image_name` on a page
+pub struct AltText {
+  /// 1-based page
+  page: u32,
+  image_name: Vec<u8>,
+  alt: String,
+}
+
+impl AltText {
+  pub fn from_js(alt_text: JsObject) -> Result<Self> {
+    let page = alt_text.get_named_property::<u32>("page")?;
+    if page == 0 {
+      return Err(Error::new(Status::InvalidArg, "page is 1-based".to_owned()));
+    }
+    Ok(AltText {
+      page,
+      image_name: alt_text.get_named_property::<String>("imageName")?.into_bytes(),
+      alt: alt_text.get_named_property::<String>("alt")?,
+    })
+  }
+}
+
+/// Read the `{ page, imageName, alt }[]` argument
+pub fn alt_texts_from_js(alt_texts: JsObject) -> Result<Vec<AltText>> {
+  (0..alt_texts.get_array_length()?)
+      .map(|index| AltText::from_js(alt_texts.get_element::<JsObject>(index)?))
+      .collect()
+}
+
+#[js_function(3)]
+pub fn set_image_alt_text(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let alt_texts = alt_texts_from_js(ctx.get::<JsObject>(1)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  set_image_alt_text_in(&mut document, &alt_texts).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// The parts of the structure tree figures are added to
+struct StructTree {
+  root_id: ObjectId,
+  /// Element new figures are added under: the top `Document` element, or the root itself
+  container_id: ObjectId,
+  /// `ParentTree` entries, a page's entry lists the element of each of its MCIDs
+  parent_tree: BTreeMap<i64, Object>,
+  next_key: i64,
+}
+
+impl StructTree {
+  /// Read the structure tree, creating a root with a `Document` element when the document isn't tagged
+  fn load(document: &mut Document, catalog_id: ObjectId) -> error::Result<Self> {
+    let root_id = document
+        .get_dictionary(catalog_id)?
+        .get(b"StructTreeRoot")
+        .and_then(Object::as_reference)
+        .ok();
+    let root_id = match root_id {
+      Some(root_id) if document.get_dictionary(root_id).is_ok() => root_id,
+      _ => {
+        let root_id = document.new_object_id();
+        let mut element = Dictionary::new();
+        element.set("Type", Object::Name(b"StructElem".to_vec()));
+        element.set("S", Object::Name(b"Document".to_vec()));
+        element.set("P", root_id);
+        element.set("K", Vec::<Object>::new());
+        let element_id = document.add_object(element);
+        let mut root = Dictionary::new();
+        root.set("Type", Object::Name(b"StructTreeRoot".to_vec()));
+        root.set("K", vec![Object::Reference(element_id)]);
+        document.objects.insert(root_id, Object::Dictionary(root));
+        let catalog = document.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?;
+        catalog.set("StructTreeRoot", root_id);
+        let mut mark_info = Dictionary::new();
+        mark_info.set("Marked", true);
+        catalog.set("MarkInfo", mark_info);
+        root_id
+      }
+    };
+    let root = document.get_dictionary(root_id)?;
+    let kids = match root.get(b"K") {
+      Ok(Object::Array(kids)) => kids.clone(),
+      Ok(kid) => vec![kid.clone()],
+      Err(_) => vec![],
+    };
+    let is_document = |id: ObjectId| {
+      document.get_dictionary(id).and_then(|kid| kid.get(b"S")).and_then(Object::as_name).ok() == Some(b"Document")
+    };
+    let container_id = match kids.as_slice() {
+      [Object::Reference(kid_id)] if is_document(*kid_id) => *kid_id,
+      _ => root_id,
+    };
+    let parent_tree = match root.get(b"ParentTree") {
+      Ok(tree) => number_tree_entries(document, tree).into_iter().collect::<BTreeMap<_, _>>(),
+      Err(_) => BTreeMap::new(),
+    };
+    let next_key = root
+        .get(b"ParentTreeNextKey")
+        .and_then(Object::as_i64)
+        .unwrap_or(0)
+        .max(parent_tree.keys().next_back().map_or(0, |key| key + 1));
+    Ok(StructTree {
+      root_id,
+      container_id,
+      parent_tree,
+      next_key,
+    })
+  }
+
+  /// Add a `Figure` element for marked content `mcid` of the page and return its id
+  fn add_figure(&mut self, document: &mut Document, page_id: ObjectId, mcid: i64, alt: &str) -> error::Result<ObjectId> {
+    let mut element = Dictionary::new();
+    element.set("Type", Object::Name(b"StructElem".to_vec()));
+    element.set("S", Object::Name(b"Figure".to_vec()));
+    element.set("P", self.container_id);
+    element.set("Pg", page_id);
+    element.set("K", mcid);
+    element.set("Alt", encode_text_string(alt));
+    let element_id = document.add_object(element);
+    let container = document.get_object_mut(self.container_id).and_then(Object::as_dict_mut)?;
+    let mut kids = match container.get(b"K") {
+      Ok(Object::Array(kids)) => kids.clone(),
+      Ok(kid) => vec![kid.clone()],
+      Err(_) => vec![],
+    };
+    kids.push(Object::Reference(element_id));
+    container.set("K", kids);
+    Ok(element_id)
+  }
+
+  fn store(self, document: &mut Document) -> error::Result<()> {
+    let root = document.get_object_mut(self.root_id).and_then(Object::as_dict_mut)?;
+    root.set("ParentTree", number_tree(&self.parent_tree));
+    root.set("ParentTreeNextKey", self.next_key);
+    Ok(())
+  }
+}
+
+/// MCID of a `BDC` operation with an inline property list
+fn marked_content_id(operation: &Operation) -> Option<i64> {
+  match operation.operands.get(1) {
+    Some(Object::Dictionary(properties)) => properties.get(b"MCID").and_then(Object::as_i64).ok(),
+    _ => None,
+  }
+}
+
+/// Tag the images of one page. An image already inside tagged marked content gets the `/Alt` on
+/// its element, an untagged one is wrapped in a new `Figure` marked content sequence.
+fn tag_page(
+  document: &mut Document, tree: &mut StructTree, page_id: ObjectId, page_number: u32, alt_texts: &[&AltText],
+) -> error::Result<()> {
+  let content = page::page_content(document, page_id)?;
+  let key = match document.get_dictionary(page_id)?.get(b"StructParents").and_then(Object::as_i64) {
+    Ok(key) => key,
+    Err(_) => {
+      let key = tree.next_key;
+      tree.next_key += 1;
+      document.get_object_mut(page_id).and_then(Object::as_dict_mut)?.set("StructParents", key);
+      key
+    }
+  };
+  let mut parents = match tree.parent_tree.get(&key).map(|parents| document.dereference(parents)) {
+    Some(Ok((_, Object::Array(parents)))) => parents.clone(),
+    _ => vec![],
+  };
+  let mut next_mcid = content
+      .operations
+      .iter()
+      .filter(|operation| operation.operator == "BDC")
+      .filter_map(marked_content_id)
+      .max()
+      .map_or(0, |mcid| mcid + 1)
+      .max(parents.len() as i64);
+
+  let mut found = vec![false; alt_texts.len()];
+  let mut rewritten = false;
+  // MCIDs of the enclosing marked content sequences
+  let mut marked = vec![];
+  let mut operations = Vec::with_capacity(content.operations.len());
+  for operation in content.operations {
+    match operation.operator.as_str() {
+      "BDC" => marked.push(marked_content_id(&operation)),
+      "BMC" => marked.push(None),
+      "EMC" => {
+        marked.pop();
+      }
+      _ => {}
+    }
+    let image_name = match (operation.operator.as_str(), operation.operands.first()) {
+      ("Do", Some(Object::Name(name))) => name.clone(),
+      _ => {
+        operations.push(operation);
+        continue;
+      }
+    };
+    let index = match alt_texts.iter().position(|alt_text| alt_text.image_name == image_name) {
+      Some(index) => index,
+      None => {
+        operations.push(operation);
+        continue;
+      }
+    };
+    found[index] = true;
+    let alt = &alt_texts[index].alt;
+    let tagged = marked.iter().rev().find_map(|mcid| *mcid);
+    let element_id = tagged
+        .and_then(|mcid| parents.get(mcid as usize))
+        .and_then(|element| element.as_reference().ok())
+        .filter(|element_id| document.get_dictionary(*element_id).is_ok());
+    match (tagged, element_id) {
+      (Some(_), Some(element_id)) => {
+        document
+            .get_object_mut(element_id)
+            .and_then(Object::as_dict_mut)?
+            .set("Alt", encode_text_string(alt));
+        operations.push(operation);
+      }
+      (Some(mcid), None) => {
+        let element_id = tree.add_figure(document, page_id, mcid, alt)?;
+        parents.resize(parents.len().max(mcid as usize + 1), Object::Null);
+        parents[mcid as usize] = element_id.into();
+        operations.push(operation);
+      }
+      (None, _) => {
+        let mcid = next_mcid;
+        next_mcid += 1;
+        let element_id = tree.add_figure(document, page_id, mcid, alt)?;
+        parents.resize(mcid as usize + 1, Object::Null);
+        parents[mcid as usize] = element_id.into();
+        let mut properties = Dictionary::new();
+        properties.set("MCID", mcid);
+        operations.push(Operation::new(
+          "BDC",
+          vec![Object::Name(b"Figure".to_vec()), Object::Dictionary(properties)],
+        ));
+        operations.push(operation);
+        operations.push(Operation::new("EMC", vec![]));
+        rewritten = true;
+      }
+    }
+  }
+  if let Some(index) = found.iter().position(|found| !found) {
+    return Err(PdfError::new(
+      ErrorCode::GenericFailure,
+      format!(
+        "Page {} doesn't draw an image named {}",
+        page_number,
+        String::from_utf8_lossy(&alt_texts[index].image_name)
+      ),
+    ));
+  }
+  if rewritten {
+    let content = lopdf::content::Content { operations }.encode()?;
+    page::set_content(document, page_id, content)?;
+  }
+  tree.parent_tree.insert(key, Object::Array(parents));
+  Ok(())
+}
+
+/// Attach alternate text to images through the structure tree, tagging the document when needed
+pub fn set_image_alt_text_in(document: &mut Document, alt_texts: &[AltText]) -> error::Result<()> {
+  let page_numbers = alt_texts.iter().map(|alt_text| alt_text.page).collect::<Vec<_>>();
+  let page_ids = page::page_ids(document, &page_numbers)?;
+  let catalog_id = document
+      .trailer
+      .get(b"Root")
+      .and_then(Object::as_reference)
+      .map_err(|_| PdfError::new(ErrorCode::InvalidPdf, "The trailer has no /Root"))?;
+  let mut by_page: BTreeMap<u32, (ObjectId, Vec<&AltText>)> = BTreeMap::new();
+  for (alt_text, page_id) in alt_texts.iter().zip(page_ids) {
+    by_page.entry(alt_text.page).or_insert_with(|| (page_id, vec![])).1.push(alt_text);
+  }
+  if by_page.is_empty() {
+    return Ok(());
+  }
+  let mut tree = StructTree::load(document, catalog_id)?;
+  for (page_number, (page_id, alt_texts)) in by_page {
+    tag_page(document, &mut tree, page_id, page_number, &alt_texts)?;
+  }
+  tree.store(document)
+}
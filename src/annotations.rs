@@ -0,0 +1,70 @@
+use lopdf::{Document, Object};
+use napi::{CallContext, JsBuffer, JsObject, JsUnknown, Result};
+
+use crate::error::{self, OrThrow};
+use crate::form::{annotation_ids, draw_appearance, normal_appearance, remove_from_array};
+use crate::utils::{buffer_value, load_document, output, output_update};
+
+#[derive(Default)]
+pub struct FlattenAnnotationsOptions {
+  /// `/Subtype`s to flatten, all of them when unset
+  types: Option<Vec<String>>,
+}
+
+impl FlattenAnnotationsOptions {
+  pub fn from_js(options: &Option<JsObject>) -> Result<Self> {
+    match options {
+      Some(options) => Ok(FlattenAnnotationsOptions {
+        types: options.get_named_property::<Option<Vec<String>>>("types")?,
+      }),
+      None => Ok(FlattenAnnotationsOptions::default()),
+    }
+  }
+}
+
+#[js_function(2)]
+pub fn flatten_annotations(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let options = ctx.get::<Option<JsObject>>(1)?;
+  let flatten = FlattenAnnotationsOptions::from_js(&options)?;
+  let output = output(&options)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  flatten_annotations_in(&mut document, &flatten).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Draw the normal appearance of the annotations into the page content and remove them, along with
+/// their pop-up notes. Widgets belong to form fields, which `flattenFields` handles, and annotations
+/// without an appearance have nothing to draw, both are left as they are.
+pub fn flatten_annotations_in(document: &mut Document, options: &FlattenAnnotationsOptions) -> error::Result<()> {
+  for page_id in document.get_pages().into_values() {
+    for annotation_id in annotation_ids(document, page_id) {
+      let annotation = match document.get_dictionary(annotation_id) {
+        Ok(annotation) => annotation,
+        Err(_) => continue,
+      };
+      let subtype = annotation
+          .get(b"Subtype")
+          .and_then(Object::as_name_str)
+          .unwrap_or("")
+          .to_owned();
+      let selected = match &options.types {
+        Some(types) => types.contains(&subtype),
+        None => true,
+      };
+      if !selected || subtype == "Widget" || subtype == "Popup" {
+        continue;
+      }
+      if normal_appearance(document, annotation_id).is_none() {
+        continue;
+      }
+      let popup_id = annotation.get(b"Popup").and_then(Object::as_reference).ok();
+      draw_appearance(document, page_id, annotation_id)?;
+      for id in std::iter::once(annotation_id).chain(popup_id) {
+        remove_from_array(document, page_id, b"Annots", id)?;
+        document.objects.remove(&id);
+      }
+    }
+  }
+  Ok(())
+}
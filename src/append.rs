@@ -0,0 +1,138 @@
+use std::collections::BTreeSet;
+
+use lopdf::{Dictionary, Document, Object};
+use napi::{CallContext, JsBuffer, JsObject, JsUnknown, Result};
+
+use crate::error::{self, ErrorCode, OrThrow, PdfError};
+use crate::extract::{extract_pages_in, BrokenLink};
+use crate::page;
+use crate::page_size::page_size;
+use crate::page_tree::fix_page_tree_in;
+use crate::utils::{buffer_value, collect_references, load_document, output, output_update};
+
+#[js_function(3)]
+pub fn append_page(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let page = buffer_value(ctx.get::<JsBuffer>(1)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  let source = load_document(&page).or_throw(ctx.env)?;
+  append_page_in(&mut document, source).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Append the first page of `source` to the end of `document`, with the objects it uses renumbered
+/// after those of `document`. The rest of `source` (outline, form, catalog) is left behind.
+pub fn append_page_in(document: &mut Document, mut source: Document) -> error::Result<()> {
+  if source.get_pages().is_empty() {
+    return Err(PdfError::new(ErrorCode::PageOutOfRange, "The appended document has no pages"));
+  }
+  // Drops the other pages, the links to them and the structure tree, and copies inherited attributes
+  extract_pages_in(&mut source, &[1], BrokenLink::Remove, false)?;
+  source.renumber_objects_with(document.max_id + 1);
+  let page_id = source.get_pages()[&1];
+  let source_catalog_id = source.trailer.get(b"Root").and_then(Object::as_reference)?;
+  let source_pages_id = source.catalog()?.get(b"Pages").and_then(Object::as_reference)?;
+  let pages_id = document
+      .catalog()?
+      .get(b"Pages")
+      .and_then(Object::as_reference)
+      .map_err(|_| PdfError::new(ErrorCode::NoPagesRoot, "Pages root not found"))?;
+  // The page now sits under the root of `document`, whose inheritable attributes must not apply to it
+  let media_box = page::media_box(&source, page_id);
+  let rotation = page::rotation(&source, page_id);
+  let root = document.get_dictionary(pages_id)?.clone();
+  let page_dictionary = source.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+  if !page_dictionary.has(b"MediaBox") {
+    page_dictionary.set("MediaBox", media_box.iter().map(|&value| value.into()).collect::<Vec<Object>>());
+  }
+  if !page_dictionary.has(b"Resources") && root.has(b"Resources") {
+    page_dictionary.set("Resources", Dictionary::new());
+  }
+  if !page_dictionary.has(b"Rotate") && root.has(b"Rotate") {
+    page_dictionary.set("Rotate", rotation);
+  }
+  if !page_dictionary.has(b"CropBox") && root.has(b"CropBox") {
+    let media_box = page_dictionary.get(b"MediaBox")?.clone();
+    page_dictionary.set("CropBox", media_box);
+  }
+  page_dictionary.set("Parent", pages_id);
+  // Copy what the page uses, without climbing back to the catalog or the page tree
+  let mut copied = BTreeSet::new();
+  let mut pending = vec![page_id];
+  while let Some(id) = pending.pop() {
+    if id == source_catalog_id || id == source_pages_id || !copied.insert(id) {
+      continue;
+    }
+    if let Some(object) = source.objects.remove(&id) {
+      collect_references(&object, &mut pending);
+      document.objects.insert(id, object);
+    }
+  }
+  document.max_id = document.max_id.max(source.max_id);
+  let root = document.get_object_mut(pages_id).and_then(Object::as_dict_mut)?;
+  let count = root.get(b"Count").and_then(Object::as_i64).unwrap_or(0);
+  root.set("Count", count + 1);
+  match root.get_mut(b"Kids") {
+    Ok(Object::Array(kids)) => kids.push(Object::Reference(page_id)),
+    _ => root.set("Kids", vec![Object::Reference(page_id)]),
+  }
+  Ok(())
+}
+
+#[js_function(3)]
+pub fn insert_blank_page(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let position = ctx.get::<u32>(1)?;
+  let options = ctx.get::<Option<JsObject>>(2)?;
+  let size = blank_page_size_from_js(&options)?;
+  let output = output(&options)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  insert_blank_page_in(&mut document, position, size).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Read the `size` option of `insertBlankPage`, a page size name
+pub fn blank_page_size_from_js(options: &Option<JsObject>) -> Result<Option<(f64, f64)>> {
+  match options {
+    Some(options) => options.get_named_property::<Option<String>>("size")?.map(|name| page_size(&name)).transpose(),
+    None => Ok(None),
+  }
+}
+
+/// Insert an empty page that becomes the 1-based page `position`, up to one past the last page. It
+/// takes the width and height of `size`, or else the size and rotation of the page before it (the
+/// first page when inserted first), and sits in the page tree next to its neighbour.
+pub fn insert_blank_page_in(document: &mut Document, position: u32, size: Option<(f64, f64)>) -> error::Result<()> {
+  let page_count = document.get_pages().len() as u32;
+  if position == 0 || position > page_count + 1 {
+    return Err(PdfError::new(
+      ErrorCode::PageOutOfRange,
+      format!("Page {} is out of range for inserting into {} pages", position, page_count),
+    ));
+  }
+  let like = page::page_ids(document, &[(position - 1).max(1)])?[0];
+  let mut blank = page::blank_page(document, like);
+  if let Some((width, height)) = size {
+    blank.set("MediaBox", vec![0.into(), 0.into(), width.into(), height.into()]);
+    blank.remove(b"Rotate");
+  }
+  // Before the page now at `position`, or after the last page
+  let (neighbour, after) = match page::page_ids(document, &[position]) {
+    Ok(page_ids) => (page_ids[0], 0),
+    Err(_) => (like, 1),
+  };
+  let parent_id = document.get_dictionary(neighbour)?.get(b"Parent").and_then(Object::as_reference)?;
+  blank.set("Parent", parent_id);
+  let blank_id = document.add_object(blank);
+  let parent = document.get_object_mut(parent_id).and_then(Object::as_dict_mut)?;
+  let mut kids = parent.get(b"Kids").and_then(Object::as_array).cloned().unwrap_or_default();
+  let index = kids
+      .iter()
+      .position(|kid| kid.as_reference().ok() == Some(neighbour))
+      .ok_or_else(|| PdfError::new(ErrorCode::InvalidPdf, "The page isn't among the kids of its parent"))?;
+  kids.insert(index + after, Object::Reference(blank_id));
+  parent.set("Kids", kids);
+  // Counts the new page in every node above it
+  fix_page_tree_in(document)
+}
@@ -0,0 +1,93 @@
+use lopdf::content::{Content, Operation};
+use lopdf::{Document, Object};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status};
+
+use crate::error::{self, OrThrow};
+use crate::filters;
+use crate::page;
+use crate::utils::{buffer_value, load_document, output, output_update};
+
+/// How far from 100% the black of a rich black may be, by default
+const DEFAULT_TOLERANCE: f64 = 0.05;
+
+#[js_function(2)]
+pub fn normalize_black(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let options = ctx.get::<Option<JsObject>>(1)?;
+  let tolerance = tolerance_from_js(&options)?;
+  let output = output(&options)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  normalize_black_in(&mut document, tolerance).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Read the `tolerance` option, 0.05 by default
+pub fn tolerance_from_js(options: &Option<JsObject>) -> Result<f64> {
+  let tolerance = match options {
+    Some(options) => options.get_named_property::<Option<f64>>("tolerance")?.unwrap_or(DEFAULT_TOLERANCE),
+    None => DEFAULT_TOLERANCE,
+  };
+  if !(0.0..1.0).contains(&tolerance) {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("tolerance must be at least 0 and below 1, got {}", tolerance),
+    ));
+  }
+  Ok(tolerance)
+}
+
+/// Replace the `k`/`K` rich blacks of `content`, a black within `tolerance` of 100% mixed with
+/// cyan, magenta or yellow, by plain black. Returns whether any was replaced.
+fn normalize_operations(content: &mut Content, tolerance: f64) -> bool {
+  let mut replaced = false;
+  for operation in content.operations.iter_mut() {
+    if operation.operator != "k" && operation.operator != "K" {
+      continue;
+    }
+    let values = operation.operands.iter().map(|value| value.as_float().ok()).collect::<Option<Vec<f64>>>();
+    let (cyan, magenta, yellow, black) = match values.as_deref() {
+      Some(&[cyan, magenta, yellow, black]) => (cyan, magenta, yellow, black),
+      _ => continue,
+    };
+    if black >= 1.0 - tolerance && (cyan > 0.0 || magenta > 0.0 || yellow > 0.0) {
+      *operation = Operation::new(&operation.operator, vec![0.into(), 0.into(), 0.into(), 1.into()]);
+      replaced = true;
+    }
+  }
+  replaced
+}
+
+/// Turn the rich blacks set with `k` and `K` into plain black, in the page contents and in the
+/// form XObjects and tiling patterns, appearance streams included. Colors set through a color
+/// space with `sc`/`scn` are left alone.
+pub fn normalize_black_in(document: &mut Document, tolerance: f64) -> error::Result<()> {
+  for page_id in document.get_pages().into_values() {
+    let mut content = match page::page_content(document, page_id) {
+      Ok(content) => content,
+      Err(_) => continue,
+    };
+    if normalize_operations(&mut content, tolerance) {
+      page::set_content(document, page_id, content.encode()?)?;
+    }
+  }
+  for object in document.objects.values_mut() {
+    let stream = match object {
+      Object::Stream(stream) => stream,
+      _ => continue,
+    };
+    let is_form = stream.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Form");
+    let is_tiling = stream.dict.get(b"PatternType").and_then(Object::as_i64).ok() == Some(1);
+    if !is_form && !is_tiling {
+      continue;
+    }
+    let data = filters::decoded_content(stream);
+    let mut content = match Content::decode(&data) {
+      Ok(content) => content,
+      Err(_) => continue,
+    };
+    if normalize_operations(&mut content, tolerance) {
+      stream.set_plain_content(content.encode()?);
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,35 @@
+use lopdf::Document;
+use napi::{CallContext, JsBuffer, JsObject, JsUnknown, Result};
+
+use crate::error::{self, OrThrow};
+use crate::page::{page_content_data, page_ids, set_content};
+use crate::utils::{buffer_value, load_document, output, output_update};
+
+/// The operators of a 1-based page, its `/Contents` streams decoded and joined
+#[js_function(2)]
+pub fn get_page_content(ctx: CallContext) -> Result<JsBuffer> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let page_number = ctx.get::<u32>(1)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  let page_id = page_ids(&document, &[page_number]).or_throw(ctx.env)?[0];
+  let data = page_content_data(&document, page_id).or_throw(ctx.env)?;
+  Ok(ctx.env.create_buffer_with_data(data)?.into_raw())
+}
+
+#[js_function(4)]
+pub fn set_page_content(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let page_number = ctx.get::<u32>(1)?;
+  let content = buffer_value(ctx.get::<JsBuffer>(2)?)?.to_vec();
+  let output = output(&ctx.get::<Option<JsObject>>(3)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  set_page_content_in(&mut document, page_number, content).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Replace the content of a 1-based page with a single stream of `content`. The page keeps its
+/// `/Resources`, the new operators can use the fonts and images it already names.
+pub fn set_page_content_in(document: &mut Document, page_number: u32, content: Vec<u8>) -> error::Result<()> {
+  let page_id = page_ids(document, &[page_number])?[0];
+  set_content(document, page_id, content)
+}
@@ -0,0 +1,213 @@
+//! Just enough MD5, RC4 and AES-128 to check the passwords of the standard security handler, lopdf
+//! having no encryption support. Only ever used to compare public hashes of the file, never to
+//! protect anything.
+
+use std::convert::TryInto;
+
+pub fn md5(data: &[u8]) -> [u8; 16] {
+  const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+  // The constants are the integer part of 2^32 * |sin(i + 1)|
+  let constants = (0..64)
+      .map(|index| ((index as f64 + 1.0).sin().abs() * 4_294_967_296.0) as u32)
+      .collect::<Vec<_>>();
+  let mut message = data.to_vec();
+  message.push(0x80);
+  while message.len() % 64 != 56 {
+    message.push(0);
+  }
+  message.extend_from_slice(&(data.len() as u64).wrapping_mul(8).to_le_bytes());
+  let mut state = [0x6745_2301_u32, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+  for chunk in message.chunks(64) {
+    let words = chunk
+        .chunks(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    let [mut a, mut b, mut c, mut d] = state;
+    for index in 0..64 {
+      let (mixed, word) = match index / 16 {
+        0 => ((b & c) | (!b & d), index),
+        1 => ((d & b) | (!d & c), (5 * index + 1) % 16),
+        2 => (b ^ c ^ d, (3 * index + 5) % 16),
+        _ => (c ^ (b | !d), (7 * index) % 16),
+      };
+      let rotated = a
+          .wrapping_add(mixed)
+          .wrapping_add(constants[index])
+          .wrapping_add(words[word])
+          .rotate_left(SHIFTS[index / 16 * 4 + index % 4]);
+      a = d;
+      d = c;
+      c = b;
+      b = b.wrapping_add(rotated);
+    }
+    for (value, add) in state.iter_mut().zip([a, b, c, d]) {
+      *value = value.wrapping_add(add);
+    }
+  }
+  let mut digest = [0; 16];
+  for (bytes, value) in digest.chunks_mut(4).zip(state) {
+    bytes.copy_from_slice(&value.to_le_bytes());
+  }
+  digest
+}
+
+pub fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+  let mut table = (0..=255).collect::<Vec<u8>>();
+  let mut j = 0_u8;
+  for i in 0..256 {
+    j = j.wrapping_add(table[i]).wrapping_add(key[i % key.len()]);
+    table.swap(i, j as usize);
+  }
+  let (mut i, mut j) = (0_u8, 0_u8);
+  data
+      .iter()
+      .map(|byte| {
+        i = i.wrapping_add(1);
+        j = j.wrapping_add(table[i as usize]);
+        table.swap(i as usize, j as usize);
+        byte ^ table[table[i as usize].wrapping_add(table[j as usize]) as usize]
+      })
+      .collect()
+}
+
+/// The AES S-box, from the multiplicative inverse in GF(2^8) and the affine transformation
+fn s_box() -> [u8; 256] {
+  let mut s_box = [0x63; 256];
+  // `p` walks the field by multiplying by 3, `q` by dividing by 3, so `q` is the inverse of `p`
+  let (mut p, mut q) = (1_u8, 1_u8);
+  loop {
+    p ^= (p << 1) ^ if p & 0x80 != 0 { 0x1b } else { 0 };
+    q ^= q << 1;
+    q ^= q << 2;
+    q ^= q << 4;
+    if q & 0x80 != 0 {
+      q ^= 0x09;
+    }
+    s_box[p as usize] = q ^ q.rotate_left(1) ^ q.rotate_left(2) ^ q.rotate_left(3) ^ q.rotate_left(4) ^ 0x63;
+    if p == 1 {
+      break;
+    }
+  }
+  s_box
+}
+
+/// Multiply by 2 in GF(2^8)
+fn double(byte: u8) -> u8 {
+  (byte << 1) ^ if byte & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+/// AES-128 in CBC mode, `data` being a multiple of the block size
+pub fn aes128_cbc_encrypt(key: &[u8; 16], iv: &[u8; 16], data: &[u8]) -> Vec<u8> {
+  let s_box = s_box();
+  let mut round_keys = [[0_u8; 16]; 11];
+  round_keys[0] = *key;
+  let mut constant = 1_u8;
+  for round in 1..11 {
+    let previous = round_keys[round - 1];
+    let mut word = [previous[13], previous[14], previous[15], previous[12]].map(|byte| s_box[byte as usize]);
+    word[0] ^= constant;
+    constant = double(constant);
+    for column in 0..4 {
+      for row in 0..4 {
+        word[row] ^= previous[column * 4 + row];
+        round_keys[round][column * 4 + row] = word[row];
+      }
+    }
+  }
+  let mut encrypted = Vec::with_capacity(data.len());
+  let mut block = *iv;
+  for chunk in data.chunks(16) {
+    for (byte, input) in block.iter_mut().zip(chunk) {
+      *byte ^= input;
+    }
+    for (round, round_key) in round_keys.iter().enumerate() {
+      if round > 0 {
+        let substituted = block.map(|byte| s_box[byte as usize]);
+        // Row `r` of the column-major state shifts `r` columns left
+        for index in 0..16 {
+          block[index] = substituted[(index + 4 * (index % 4)) % 16];
+        }
+        if round < 10 {
+          for column in block.chunks_mut(4) {
+            let [a, b, c, d] = [column[0], column[1], column[2], column[3]];
+            column[0] = double(a) ^ double(b) ^ b ^ c ^ d;
+            column[1] = a ^ double(b) ^ double(c) ^ c ^ d;
+            column[2] = a ^ b ^ double(c) ^ double(d) ^ d;
+            column[3] = double(a) ^ a ^ b ^ c ^ double(d);
+          }
+        }
+      }
+      for (byte, key) in block.iter_mut().zip(round_key) {
+        *byte ^= key;
+      }
+    }
+    encrypted.extend_from_slice(&block);
+  }
+  encrypted
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+  }
+
+  fn unhex(text: &str) -> Vec<u8> {
+    (0..text.len()).step_by(2).map(|index| u8::from_str_radix(&text[index..index + 2], 16).unwrap()).collect()
+  }
+
+  #[test]
+  fn md5_matches_the_rfc_1321_test_suite() {
+    let suite = [
+      ("", "d41d8cd98f00b204e9800998ecf8427e"),
+      ("a", "0cc175b9c0f1b6a831c399e269772661"),
+      ("abc", "900150983cd24fb0d6963f7d28e17f72"),
+      ("message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+      ("abcdefghijklmnopqrstuvwxyz", "c3fcd3d76192e4007dfb496cca67e13b"),
+      ("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789", "d174ab98d277d9f5a5611c2c9f419d9f"),
+      (
+        "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+        "57edf4a22be3c955ac49da2e2107b67a",
+      ),
+    ];
+    for (message, digest) in suite.iter() {
+      assert_eq!(hex(&md5(message.as_bytes())), *digest, "{:?}", message);
+    }
+  }
+
+  #[test]
+  fn rc4_matches_known_keystreams() {
+    let vectors = [
+      ("Key", "Plaintext", "bbf316e8d940af0ad3"),
+      ("Wiki", "pedia", "1021bf0420"),
+      ("Secret", "Attack at dawn", "45a01f645fc35b383552544b9bf5"),
+    ];
+    for (key, plaintext, ciphertext) in vectors.iter() {
+      assert_eq!(hex(&rc4(key.as_bytes(), plaintext.as_bytes())), *ciphertext);
+      // The same keystream decrypts
+      assert_eq!(rc4(key.as_bytes(), &unhex(ciphertext)), plaintext.as_bytes());
+    }
+  }
+
+  #[test]
+  fn aes128_matches_the_fips_197_example() {
+    // Appendix C.1, a single block, for which CBC with a zero IV is the cipher itself
+    let key = unhex("000102030405060708090a0b0c0d0e0f").try_into().unwrap();
+    let encrypted = aes128_cbc_encrypt(&key, &[0; 16], &unhex("00112233445566778899aabbccddeeff"));
+    assert_eq!(hex(&encrypted), "69c4e0d86a7b0430d8cdb78070b4c55a");
+  }
+
+  #[test]
+  fn aes128_cbc_chains_the_blocks() {
+    // F.2.1 of NIST SP 800-38A, its first two blocks
+    let key = unhex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap();
+    let iv = unhex("000102030405060708090a0b0c0d0e0f").try_into().unwrap();
+    let plaintext = unhex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+    assert_eq!(
+      hex(&aes128_cbc_encrypt(&key, &iv, &plaintext)),
+      "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2"
+    );
+  }
+}
@@ -0,0 +1,410 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use sha2::{Digest, Sha256};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status};
+
+use crate::error::{self, OrThrow};
+use crate::fingerprint::Canonical;
+use crate::form::{annotation_ids, remove_from_array};
+use crate::page;
+use crate::page_tree::fix_page_tree_in;
+use crate::utils::{buffer_value, collect_references, load_document, output, output_update, replace_references};
+
+/// Object types that must stay distinct even when byte-identical,
+/// e.g. two blank pages are still two pages
+pub const DISTINCT_TYPES: [&str; 4] = ["Catalog", "Pages", "Page", "Annot"];
+
+/// Whether an object must stay distinct even when byte-identical: one of `DISTINCT_TYPES`, an
+/// annotation without `/Type` (a `/Subtype` and a `/Rect`), or a node of a tree such as a form
+/// field, an outline item or a structure element, which `/Parent` or `/P` links to its place
+pub fn stays_distinct(object: &Object) -> bool {
+  if object.type_name().is_ok_and(|type_name| DISTINCT_TYPES.contains(&type_name)) {
+    return true;
+  }
+  match object {
+    Object::Dictionary(dictionary) => {
+      (dictionary.has(b"Subtype") && dictionary.has(b"Rect")) || dictionary.has(b"Parent") || dictionary.has(b"P")
+    }
+    _ => false,
+  }
+}
+
+/// Where `serialize_object` writes, a buffer or a hasher
+pub trait Sink {
+  fn put(&mut self, bytes: &[u8]);
+}
+
+impl Sink for Vec<u8> {
+  fn put(&mut self, bytes: &[u8]) {
+    self.extend_from_slice(bytes);
+  }
+}
+
+impl Sink for Sha256 {
+  fn put(&mut self, bytes: &[u8]) {
+    self.update(bytes);
+  }
+}
+
+#[js_function(2)]
+pub fn dedupe_objects(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(1)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  dedupe_document(&mut document);
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Which repeated pages `dedupePages` removes
+#[derive(Clone, Copy, PartialEq)]
+pub enum DuplicatePages {
+  /// Only a page identical to the page right before it
+  Consecutive,
+  /// Any page identical to an earlier page
+  Any,
+}
+
+#[js_function(2)]
+pub fn dedupe_pages(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let options = ctx.get::<Option<JsObject>>(1)?;
+  let mode = match options {
+    Some(ref options) => match options.get_named_property::<Option<String>>("mode")?.as_deref() {
+      None | Some("consecutive") => DuplicatePages::Consecutive,
+      Some("any") => DuplicatePages::Any,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("mode must be 'consecutive' or 'any', got '{}'", other),
+        ))
+      }
+    },
+    None => DuplicatePages::Consecutive,
+  };
+  let output = output(&options)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  dedupe_pages_in(&mut document, mode).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Canonical form of what a page displays. Expects identical objects to be collapsed already,
+/// so identical content and resources are the very same references.
+fn page_fingerprint(document: &Document, page_id: ObjectId) -> Vec<u8> {
+  let mut out = vec![];
+  if let Ok(page) = document.get_dictionary(page_id) {
+    let mut page = page.clone();
+    page.remove(b"Parent");
+    page.remove(b"Annots");
+    serialize_object(&Object::Dictionary(page), &mut out);
+  }
+  let media_box = page::media_box(document, page_id)
+      .iter()
+      .map(|&value| value.into())
+      .collect::<Vec<Object>>();
+  serialize_object(&Object::Array(media_box), &mut out);
+  serialize_object(&page::rotation(document, page_id).into(), &mut out);
+  // Annotations are never collapsed, compare them without their back-reference to the page
+  for annotation_id in annotation_ids(document, page_id) {
+    if let Ok(annotation) = document.get_dictionary(annotation_id) {
+      let mut annotation = annotation.clone();
+      annotation.remove(b"P");
+      serialize_object(&Object::Dictionary(annotation), &mut out);
+    }
+  }
+  out
+}
+
+/// Remove pages identical to an earlier one, pointing links to them at the kept copy.
+/// Returns the number of removed pages.
+pub fn dedupe_pages_in(document: &mut Document, mode: DuplicatePages) -> error::Result<usize> {
+  dedupe_document(document);
+  let mut kept: HashMap<Vec<u8>, ObjectId> = HashMap::new();
+  let mut previous: Option<Vec<u8>> = None;
+  let mut replace = BTreeMap::new();
+  for page_id in document.get_pages().into_values() {
+    let fingerprint = page_fingerprint(document, page_id);
+    let duplicate_of = match mode {
+      DuplicatePages::Consecutive if previous.as_ref() != Some(&fingerprint) => None,
+      _ => kept.get(&fingerprint).copied(),
+    };
+    match duplicate_of {
+      Some(kept_id) => {
+        replace.insert(page_id, kept_id);
+      }
+      None => {
+        kept.insert(fingerprint.clone(), page_id);
+      }
+    }
+    previous = Some(fingerprint);
+  }
+  for page_id in replace.keys() {
+    if let Ok(parent_id) = document
+        .get_dictionary(*page_id)
+        .and_then(|page| page.get(b"Parent"))
+        .and_then(Object::as_reference)
+    {
+      remove_from_array(document, parent_id, b"Kids", *page_id)?;
+    }
+    document.objects.remove(page_id);
+  }
+  if replace.is_empty() {
+    return Ok(0);
+  }
+  for object in document.objects.values_mut() {
+    replace_references(object, &replace);
+  }
+  fix_page_tree_in(document)?;
+  // Annotations of the removed pages are left unreferenced
+  document.prune_objects();
+  document.renumber_objects();
+  Ok(replace.len())
+}
+
+/// Pages of `page_ids` that display the same as an earlier one of them, mapped to that first copy.
+/// Pages are compared by value, so copies from different documents match; `distinct` pages never do.
+pub fn duplicate_pages(
+  document: &Document, page_ids: &[ObjectId], distinct: &BTreeSet<ObjectId>,
+) -> BTreeMap<ObjectId, ObjectId> {
+  let mut canonical = Canonical::new(document);
+  let mut first_copies = HashMap::new();
+  let mut duplicates = BTreeMap::new();
+  for &page_id in page_ids.iter().filter(|page_id| !distinct.contains(page_id)) {
+    match first_copies.entry(canonical.digest(page_id)) {
+      Entry::Occupied(entry) => {
+        duplicates.insert(page_id, *entry.get());
+      }
+      Entry::Vacant(entry) => {
+        entry.insert(page_id);
+      }
+    }
+  }
+  duplicates
+}
+
+/// Whether two objects have the same canonical form, compared in place
+pub fn same_object(a: &Object, b: &Object) -> bool {
+  match (a, b) {
+    (Object::Null, Object::Null) => true,
+    (Object::Boolean(a), Object::Boolean(b)) => a == b,
+    (Object::Integer(a), Object::Integer(b)) => a == b,
+    (Object::Real(a), Object::Real(b)) => a.to_bits() == b.to_bits(),
+    (Object::Name(a), Object::Name(b)) => a == b,
+    (Object::String(a, _), Object::String(b, _)) => a == b,
+    (Object::Array(a), Object::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_object(a, b)),
+    (Object::Dictionary(a), Object::Dictionary(b)) => same_dictionary(a, b),
+    (Object::Stream(a), Object::Stream(b)) => same_dictionary(&a.dict, &b.dict) && a.content == b.content,
+    (Object::Reference(a), Object::Reference(b)) => a == b,
+    _ => false,
+  }
+}
+
+/// Same entries in the same order, as `serialize_object` writes them
+fn same_dictionary(a: &Dictionary, b: &Dictionary) -> bool {
+  a.len() == b.len()
+      && a
+          .iter()
+          .zip(b.iter())
+          .all(|((a_key, a_value), (b_key, b_value))| a_key == b_key && same_object(a_value, b_value))
+}
+
+/// Write a canonical byte form of an object, used as the identity of the object
+pub fn serialize_object<S: Sink>(object: &Object, out: &mut S) {
+  match object {
+    Object::Null => out.put(b"null"),
+    Object::Boolean(value) => out.put(if *value { b"true" } else { b"false" }),
+    Object::Integer(value) => out.put(format!("i{}", value).as_bytes()),
+    Object::Real(value) => out.put(format!("f{}", value).as_bytes()),
+    Object::Name(name) => {
+      out.put(format!("/{}:", name.len()).as_bytes());
+      out.put(name);
+    }
+    Object::String(string, _) => {
+      out.put(format!("({}:", string.len()).as_bytes());
+      out.put(string);
+    }
+    Object::Array(array) => {
+      out.put(b"[");
+      for item in array {
+        serialize_object(item, out);
+        out.put(b" ");
+      }
+      out.put(b"]");
+    }
+    Object::Dictionary(dictionary) => serialize_dictionary(dictionary, out),
+    Object::Stream(stream) => {
+      serialize_dictionary(&stream.dict, out);
+      out.put(format!("stream{}:", stream.content.len()).as_bytes());
+      out.put(&stream.content);
+    }
+    Object::Reference((id, generation)) => out.put(format!("r{} {}", id, generation).as_bytes()),
+  }
+}
+
+fn serialize_dictionary<S: Sink>(dictionary: &Dictionary, out: &mut S) {
+  out.put(b"<<");
+  for (key, value) in dictionary.iter() {
+    out.put(format!("/{}:", key.len()).as_bytes());
+    out.put(key);
+    out.put(b" ");
+    serialize_object(value, out);
+    out.put(b" ");
+  }
+  out.put(b">>");
+}
+
+/// SHA-256 of the canonical form of an object, streams hashed in place instead of copied
+fn object_digest(object: &Object) -> [u8; 32] {
+  let mut hasher = Sha256::new();
+  serialize_object(object, &mut hasher);
+  hasher.finalize().into()
+}
+
+/// Collapse byte-identical objects into one and rewrite references to them.
+/// Runs until nothing changes, as merging objects can make their referrers identical too.
+/// Objects are keyed by a digest of their canonical form, computed again only for the objects
+/// whose references changed, and compared in full when the digests match.
+/// Returns the number of removed objects.
+pub fn dedupe_document(document: &mut Document) -> usize {
+  let mut removed = 0;
+  let mut digests: HashMap<ObjectId, [u8; 32]> = HashMap::new();
+  loop {
+    let mut first_copies: HashMap<[u8; 32], Vec<ObjectId>> = HashMap::new();
+    let mut replace: BTreeMap<ObjectId, ObjectId> = BTreeMap::new();
+    for (object_id, object) in document.objects.iter() {
+      if stays_distinct(object) {
+        continue;
+      }
+      let digest = *digests.entry(*object_id).or_insert_with(|| object_digest(object));
+      let copies = first_copies.entry(digest).or_default();
+      match copies.iter().find(|id| same_object(&document.objects[*id], object)) {
+        Some(existing) => {
+          replace.insert(*object_id, *existing);
+        }
+        None => copies.push(*object_id),
+      }
+    }
+    if replace.is_empty() {
+      break;
+    }
+    // Every pass removes at least one object, so reference cycles can't loop forever
+    for object_id in replace.keys() {
+      document.objects.remove(object_id);
+      digests.remove(object_id);
+    }
+    for (object_id, object) in document.objects.iter_mut() {
+      let mut references = vec![];
+      collect_references(object, &mut references);
+      if references.iter().any(|id| replace.contains_key(id)) {
+        replace_references(object, &replace);
+        digests.remove(object_id);
+      }
+    }
+    for (_, value) in document.trailer.iter_mut() {
+      replace_references(value, &replace);
+    }
+    removed += replace.len();
+  }
+  if removed > 0 {
+    document.renumber_objects();
+  }
+  removed
+}
+
+#[cfg(test)]
+mod tests {
+  use lopdf::{Dictionary, Object, Stream};
+
+  use super::*;
+  use crate::test_utils;
+
+  fn image(data: &[u8]) -> Object {
+    let mut dictionary = Dictionary::new();
+    dictionary.set("Type", Object::Name(b"XObject".to_vec()));
+    dictionary.set("Subtype", Object::Name(b"Image".to_vec()));
+    Object::Stream(Stream::new(dictionary, data.to_vec()))
+  }
+
+  #[test]
+  fn collapses_identical_objects_and_their_referrers() {
+    let mut document = test_utils::document(2);
+    let images = (0..2).map(|_| document.add_object(image(&[7; 64]))).collect::<Vec<_>>();
+    document.add_object(image(&[8; 64]));
+    for (&page_id, &image_id) in document.get_pages().values().zip(images.iter()) {
+      let mut xobjects = Dictionary::new();
+      xobjects.set("Im1", image_id);
+      let mut resources = Dictionary::new();
+      resources.set("XObject", xobjects);
+      let resources_id = document.add_object(resources);
+      document.get_object_mut(page_id).and_then(Object::as_dict_mut).unwrap().set("Resources", resources_id);
+    }
+    let before = document.objects.len();
+    // The second image, then the resources that only differed by it
+    assert_eq!(dedupe_document(&mut document), 2);
+    assert_eq!(document.objects.len(), before - 2);
+    let resources = document
+        .get_pages()
+        .values()
+        .map(|&page_id| document.get_dictionary(page_id).unwrap().get(b"Resources").unwrap().as_reference().unwrap())
+        .collect::<BTreeSet<_>>();
+    assert_eq!(resources.len(), 1);
+    // The two page contents, the shared image and the different one
+    let streams = document.objects.values().filter(|object| object.as_stream().is_ok()).count();
+    assert_eq!(streams, 4);
+  }
+
+  #[test]
+  fn terminates_on_reference_cycles() {
+    let mut document = test_utils::document(1);
+    let a = document.new_object_id();
+    let b = document.new_object_id();
+    let link = |to: ObjectId| {
+      let mut dictionary = Dictionary::new();
+      dictionary.set("Next", to);
+      Object::Dictionary(dictionary)
+    };
+    document.objects.insert(a, link(b));
+    document.objects.insert(b, link(a));
+    let c = document.add_object(Object::Null);
+    document.objects.insert(c, link(c));
+    let d = document.add_object(Object::Null);
+    document.objects.insert(d, link(c));
+    // `d` is a copy of `c`, which then points at itself; the `a`/`b` pair differs by its references
+    assert_eq!(dedupe_document(&mut document), 1);
+  }
+
+  #[test]
+  fn keeps_annotations_and_tree_nodes_apart() {
+    let mut document = test_utils::document(1);
+    let page_id = *document.get_pages().values().next().unwrap();
+    let mut annotation = Dictionary::new();
+    annotation.set("Subtype", Object::Name(b"Square".to_vec()));
+    annotation.set("Rect", vec![0.into(), 0.into(), 10.into(), 10.into()]);
+    let mut field = Dictionary::new();
+    field.set("T", Object::string_literal("kid"));
+    field.set("Parent", page_id);
+    for object in [annotation, field] {
+      document.add_object(object.clone());
+      document.add_object(object);
+    }
+    assert_eq!(dedupe_document(&mut document), 0);
+  }
+
+  #[test]
+  fn compares_objects_like_their_serialization() {
+    let mut a = Dictionary::new();
+    a.set("A", 1);
+    a.set("B", vec![Object::Real(0.5), Object::string_literal("x")]);
+    let mut b = a.clone();
+    assert!(same_object(&Object::Dictionary(a.clone()), &Object::Dictionary(b.clone())));
+    assert_eq!(object_digest(&Object::Dictionary(a.clone())), object_digest(&Object::Dictionary(b.clone())));
+    b.set("A", 2);
+    assert!(!same_object(&Object::Dictionary(a.clone()), &Object::Dictionary(b.clone())));
+    let mut bytes = vec![];
+    serialize_object(&Object::Dictionary(a.clone()), &mut bytes);
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    assert_eq!(object_digest(&Object::Dictionary(a)), <[u8; 32]>::from(hasher.finalize()));
+  }
+}
@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use lopdf::{Dictionary, Document, Object};
+use napi::{Error, JsObject, Result, Status};
+
+use crate::error::{self, ErrorCode, PdfError};
+use crate::names::{name_tree, name_tree_entries, unique_name};
+
+/// What to do with a named destination already defined by an earlier document
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum DestinationConflicts {
+  /// Rename the later destination, along with the links and actions of its document
+  #[default]
+  Rename,
+  /// Keep the earlier destination, the links of the later document then jump to it
+  FirstWins,
+  /// Fail the merge
+  Error,
+}
+
+impl DestinationConflicts {
+  /// Read the `destinationConflicts` option, `rename` by default
+  pub fn from_js(options: &JsObject) -> Result<Self> {
+    let value = options.get_named_property::<Option<String>>("destinationConflicts")?;
+    Self::parse("destinationConflicts", value.as_deref())
+  }
+
+  /// Read the value of the `option` policy, `rename` when unset
+  pub fn parse(option: &str, value: Option<&str>) -> Result<Self> {
+    match value {
+      None | Some("rename") => Ok(DestinationConflicts::Rename),
+      Some("first-wins") => Ok(DestinationConflicts::FirstWins),
+      Some("error") => Ok(DestinationConflicts::Error),
+      Some(other) => Err(Error::new(
+        Status::InvalidArg,
+        format!("{} must be 'rename', 'first-wins' or 'error', got '{}'", option, other),
+      )),
+    }
+  }
+}
+
+/// The names of a document that an earlier document already defined, with their new name, or
+/// `None` when the later destination was dropped
+pub type Conflicts = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// Named destinations collected from all merged documents
+#[derive(Default)]
+pub struct Destinations {
+  conflicts: DestinationConflicts,
+  /// Entries of the `/Names /Dests` name tree, keyed by string
+  tree: BTreeMap<Vec<u8>, Object>,
+  /// Entries of the catalog `/Dests` dictionary, keyed by name
+  dictionary: BTreeMap<Vec<u8>, Object>,
+}
+
+/// Add `entries` to `merged`, handling the keys already used by an earlier document as
+/// `conflicts` says. The keys are all checked before anything is added.
+pub fn merge_entries(
+  merged: &mut BTreeMap<Vec<u8>, Object>,
+  entries: Vec<(Vec<u8>, Object)>,
+  conflicts: DestinationConflicts,
+) -> std::result::Result<Conflicts, Vec<u8>> {
+  if conflicts == DestinationConflicts::Error {
+    if let Some((key, _)) = entries.iter().find(|(key, _)| merged.contains_key(key)) {
+      return Err(key.clone());
+    }
+  }
+  let own_keys = entries.iter().map(|(key, _)| key.clone()).collect::<BTreeSet<_>>();
+  let mut collided = BTreeMap::new();
+  for (key, value) in entries {
+    let key = if merged.contains_key(&key) {
+      if conflicts == DestinationConflicts::FirstWins {
+        collided.insert(key, None);
+        continue;
+      }
+      let new_key = unique_name(&key, |candidate| {
+        merged.contains_key(candidate) || own_keys.contains(candidate)
+      });
+      collided.insert(key, Some(new_key.clone()));
+      new_key
+    } else {
+      key
+    };
+    merged.insert(key, value);
+  }
+  Ok(collided)
+}
+
+/// The new names among `collided`
+fn renamed(collided: &Conflicts) -> BTreeMap<Vec<u8>, Vec<u8>> {
+  collided
+      .iter()
+      .filter_map(|(key, new_key)| Some((key.clone(), new_key.clone()?)))
+      .collect()
+}
+
+/// Point `/Dest` entries and `GoTo` actions of the document at the renamed destinations
+fn rename_references(
+  document: &mut Document,
+  renamed_strings: &BTreeMap<Vec<u8>, Vec<u8>>,
+  renamed_names: &BTreeMap<Vec<u8>, Vec<u8>>,
+) {
+  fn rename(destination: &mut Object, renamed_strings: &BTreeMap<Vec<u8>, Vec<u8>>, renamed_names: &BTreeMap<Vec<u8>, Vec<u8>>) {
+    match destination {
+      Object::String(name, _) => {
+        if let Some(new_name) = renamed_strings.get(name) {
+          *name = new_name.clone();
+        }
+      }
+      Object::Name(name) => {
+        if let Some(new_name) = renamed_names.get(name) {
+          *name = new_name.clone();
+        }
+      }
+      _ => {}
+    }
+  }
+  fn visit(object: &mut Object, renamed_strings: &BTreeMap<Vec<u8>, Vec<u8>>, renamed_names: &BTreeMap<Vec<u8>, Vec<u8>>) {
+    let dictionary = match object {
+      Object::Dictionary(dictionary) => dictionary,
+      Object::Stream(stream) => &mut stream.dict,
+      Object::Array(array) => {
+        for item in array.iter_mut() {
+          visit(item, renamed_strings, renamed_names);
+        }
+        return;
+      }
+      _ => return,
+    };
+    if let Ok(destination) = dictionary.get_mut(b"Dest") {
+      rename(destination, renamed_strings, renamed_names);
+    }
+    if dictionary.get(b"S").and_then(Object::as_name).ok() == Some(b"GoTo") {
+      if let Ok(destination) = dictionary.get_mut(b"D") {
+        rename(destination, renamed_strings, renamed_names);
+      }
+    }
+    for (_, value) in dictionary.iter_mut() {
+      visit(value, renamed_strings, renamed_names);
+    }
+  }
+  for object in document.objects.values_mut() {
+    visit(object, renamed_strings, renamed_names);
+  }
+}
+
+impl Destinations {
+  pub fn new(conflicts: DestinationConflicts) -> Self {
+    Destinations {
+      conflicts,
+      ..Destinations::default()
+    }
+  }
+
+  /// Collect the named destinations of a (renumbered) document, the `index`-th of the merge.
+  /// Names colliding with an earlier document are renamed along with the links and actions using
+  /// them, or dropped, or fail the merge, depending on the policy. Returns the colliding names.
+  pub fn add_document(&mut self, document: &mut Document, index: usize) -> error::Result<Conflicts> {
+    let catalog = match document.catalog() {
+      Ok(catalog) => catalog,
+      Err(_) => return Ok(BTreeMap::new()),
+    };
+    let tree_entries = catalog
+        .get(b"Names")
+        .and_then(|names| document.dereference(names))
+        .and_then(|(_, names)| names.as_dict())
+        .and_then(|names| names.get(b"Dests"))
+        .map(|dests| name_tree_entries(document, dests))
+        .unwrap_or_default();
+    let dictionary_entries = catalog
+        .get(b"Dests")
+        .and_then(|dests| document.dereference(dests))
+        .and_then(|(_, dests)| dests.as_dict())
+        .map(|dests| dests.iter().map(|(key, value)| (key.clone(), value.clone())).collect())
+        .unwrap_or_default();
+    let conflict = |name: Vec<u8>| {
+      PdfError::new(
+        ErrorCode::GenericFailure,
+        format!(
+          "Document {}: the named destination '{}' is also defined by an earlier document",
+          index,
+          String::from_utf8_lossy(&name)
+        ),
+      )
+    };
+    let collided_strings = merge_entries(&mut self.tree, tree_entries, self.conflicts).map_err(conflict)?;
+    let collided_names = merge_entries(&mut self.dictionary, dictionary_entries, self.conflicts).map_err(conflict)?;
+    let (renamed_strings, renamed_names) = (renamed(&collided_strings), renamed(&collided_names));
+    if !renamed_strings.is_empty() || !renamed_names.is_empty() {
+      rename_references(document, &renamed_strings, &renamed_names);
+    }
+    Ok(collided_strings.into_iter().chain(collided_names).collect())
+  }
+
+  /// Write the merged destinations into the catalog of the merged document
+  pub fn apply(&self, document: &mut Document, catalog: &mut Dictionary) {
+    if !self.tree.is_empty() {
+      let mut names = catalog
+          .get(b"Names")
+          .and_then(|names| document.dereference(names))
+          .and_then(|(_, names)| names.as_dict())
+          .cloned()
+          .unwrap_or_default();
+      names.set("Dests", document.add_object(name_tree(&self.tree)));
+      catalog.set("Names", names);
+    }
+    if !self.dictionary.is_empty() {
+      let mut dests = Dictionary::new();
+      for (key, value) in self.dictionary.iter() {
+        dests.set(key.clone(), value.clone());
+      }
+      catalog.set("Dests", document.add_object(dests));
+    }
+  }
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+
+use napi::{Env, JsError, Status};
+
+/// Error kinds surfaced to JS as the `code` property of the thrown error
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+  /// The buffer couldn't be parsed as a PDF
+  InvalidPdf,
+  /// The document is encrypted and can't be opened without a password
+  EncryptedNoPassword,
+  /// The document has no `Pages` root to collect pages from
+  NoPagesRoot,
+  /// A page number is outside of the document
+  PageOutOfRange,
+  /// The document is certified and the operation would invalidate the certification
+  CertifiedDocument,
+  /// The form is XFA only, its fields aren't AcroForm fields the operation could change
+  XfaForm,
+  /// The input is over a `maxPages` or `maxObjects` limit, a thumbnail would be too large or empty, or
+  /// the objects numbered past `utils::MAX_OBJECT_ID`
+  LimitExceeded,
+  /// The operation ran past its `timeoutMs`
+  Timeout,
+  /// Any other failure while processing or writing the document
+  GenericFailure,
+}
+
+impl AsRef<str> for ErrorCode {
+  fn as_ref(&self) -> &str {
+    match self {
+      ErrorCode::InvalidPdf => "InvalidPdf",
+      ErrorCode::EncryptedNoPassword => "EncryptedNoPassword",
+      ErrorCode::NoPagesRoot => "NoPagesRoot",
+      ErrorCode::PageOutOfRange => "PageOutOfRange",
+      ErrorCode::CertifiedDocument => "CertifiedDocument",
+      ErrorCode::XfaForm => "XfaForm",
+      ErrorCode::LimitExceeded => "LimitExceeded",
+      ErrorCode::Timeout => "Timeout",
+      ErrorCode::GenericFailure => "GenericFailure",
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct PdfError {
+  pub code: ErrorCode,
+  pub message: String,
+}
+
+impl PdfError {
+  pub fn new<M: Into<String>>(code: ErrorCode, message: M) -> Self {
+    PdfError {
+      code,
+      message: message.into(),
+    }
+  }
+
+  /// Convert into a napi error carrying the JS error object, so the code survives a promise rejection
+  pub fn into_napi(self, env: &Env) -> napi::Error {
+    JsError::from(napi::Error::new(self.code, self.message)).into_unknown(*env).into()
+  }
+
+  /// Throw the error into JS with its code and return the pending exception
+  pub fn throw(self, env: &Env) -> napi::Error {
+    let error = napi::Error::new(self.code, self.message.clone());
+    unsafe { JsError::from(error).throw_into(env.raw()) };
+    napi::Error::new(Status::PendingException, self.message)
+  }
+}
+
+impl fmt::Display for PdfError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.message)
+  }
+}
+
+impl From<lopdf::Error> for PdfError {
+  fn from(err: lopdf::Error) -> Self {
+    PdfError::new(ErrorCode::GenericFailure, format!("{}", err))
+  }
+}
+
+impl From<std::io::Error> for PdfError {
+  fn from(err: std::io::Error) -> Self {
+    PdfError::new(ErrorCode::GenericFailure, format!("{}", err))
+  }
+}
+
+pub type Result<T> = std::result::Result<T, PdfError>;
+
+/// Convert a crate result into a napi result at the JS boundary
+pub trait OrThrow<T> {
+  fn or_throw(self, env: &Env) -> napi::Result<T>;
+}
+
+impl<T> OrThrow<T> for Result<T> {
+  fn or_throw(self, env: &Env) -> napi::Result<T> {
+    self.map_err(|err| err.throw(env))
+  }
+}
@@ -0,0 +1,210 @@
+use std::collections::BTreeSet;
+
+use lopdf::{Document, Object, ObjectId};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUndefined, JsUnknown, Result, Status};
+
+use crate::error::{self, ErrorCode, OrThrow, PdfError};
+use crate::form::{annotation_ids, remove_from_array};
+use crate::page;
+use crate::structure;
+use crate::utils::{buffer_value, load_document, output_document, output_update, Output};
+
+/// What to do with links pointing at a page that wasn't extracted
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum BrokenLink {
+  /// Remove the link annotation
+  #[default]
+  Remove,
+  /// Keep the annotation but drop its destination, so clicking it does nothing
+  Keep,
+}
+
+impl BrokenLink {
+  /// Read the `onBrokenLink` option, `remove` by default
+  pub fn from_js(options: &JsObject) -> Result<Self> {
+    match options.get_named_property::<Option<String>>("onBrokenLink")?.as_deref() {
+      None | Some("remove") => Ok(BrokenLink::Remove),
+      Some("keep") => Ok(BrokenLink::Keep),
+      Some(other) => Err(Error::new(
+        Status::InvalidArg,
+        format!("onBrokenLink must be 'remove' or 'keep', got '{}'", other),
+      )),
+    }
+  }
+}
+
+pub struct ExtractOptions {
+  pub on_broken_link: BrokenLink,
+  /// Restrict the structure tree to the extracted pages instead of removing it
+  pub preserve_structure: bool,
+  pub output: Output,
+}
+
+impl ExtractOptions {
+  pub fn from_js(options: Option<JsObject>) -> Result<Self> {
+    let mut extract_options = ExtractOptions {
+      on_broken_link: BrokenLink::Remove,
+      preserve_structure: false,
+      output: Output::default(),
+    };
+    if let Some(options) = options {
+      extract_options.on_broken_link = BrokenLink::from_js(&options)?;
+      extract_options.preserve_structure =
+          options.get_named_property::<Option<bool>>("preserveStructure")?.unwrap_or(false);
+      extract_options.output = Output::from_js(&options)?;
+    }
+    Ok(extract_options)
+  }
+}
+
+#[js_function(3)]
+pub fn extract_pages(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let page_numbers = ctx.get::<Vec<u32>>(1)?;
+  let options = ExtractOptions::from_js(ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  extract_pages_in(&mut document, &page_numbers, options.on_broken_link, options.preserve_structure)
+      .or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &options.output)
+}
+
+#[js_function(3)]
+pub fn extract_visible(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let page_numbers = ctx.get::<Vec<u32>>(1)?;
+  let options = ExtractOptions::from_js(ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  extract_visible_in(&mut document, &page_numbers, &options).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &options.output)
+}
+
+#[js_function(5)]
+pub fn extract_range_to_file(ctx: CallContext) -> Result<JsUndefined> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let from = ctx.get::<u32>(1)?;
+  let to = ctx.get::<u32>(2)?;
+  if from == 0 || from > to {
+    return Err(Error::new(Status::InvalidArg, format!("Invalid page range {}-{}", from, to)));
+  }
+  let out_path = ctx.get::<String>(3)?;
+  let options = ExtractOptions::from_js(ctx.get::<Option<JsObject>>(4)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  let page_numbers = (from..=to).collect::<Vec<_>>();
+  extract_pages_in(&mut document, &page_numbers, options.on_broken_link, options.preserve_structure)
+      .or_throw(ctx.env)?;
+  // Written by `Document::save` straight to the file, without an intermediate buffer
+  output_document(ctx.env, &mut document, Some(out_path), options.output.save)?;
+  ctx.env.get_undefined()
+}
+
+/// Extract the pages, each trimmed to its visible area: the CropBox, clipped to the MediaBox as
+/// viewers do, becomes the MediaBox. Pages without a CropBox keep their MediaBox.
+pub fn extract_visible_in(
+  document: &mut Document, page_numbers: &[u32], options: &ExtractOptions,
+) -> error::Result<()> {
+  extract_pages_in(document, page_numbers, options.on_broken_link, options.preserve_structure)?;
+  for page_id in document.get_pages().into_values() {
+    let crop_box = match page::inherited_attribute(document, page_id, b"CropBox").and_then(page::rect_from_object) {
+      Some(crop_box) => crop_box,
+      None => continue,
+    };
+    let media_box = page::media_box(document, page_id);
+    let visible = [
+      crop_box[0].max(media_box[0]),
+      crop_box[1].max(media_box[1]),
+      crop_box[2].min(media_box[2]),
+      crop_box[3].min(media_box[3]),
+    ];
+    // A CropBox outside the MediaBox leaves nothing visible, such a page keeps its MediaBox
+    if visible[0] >= visible[2] || visible[1] >= visible[3] {
+      continue;
+    }
+    let page = document.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+    page.set("MediaBox", visible.iter().map(|&value| value.into()).collect::<Vec<Object>>());
+    page.remove(b"CropBox");
+  }
+  Ok(())
+}
+
+/// Explicit destination array of a link annotation, from `/Dest` or a `GoTo` action
+fn link_target(document: &Document, annotation_id: ObjectId) -> Option<ObjectId> {
+  let annotation = document.get_dictionary(annotation_id).ok()?;
+  let destination = match annotation.get(b"Dest") {
+    Ok(destination) => destination,
+    Err(_) => {
+      let (_, action) = document.dereference(annotation.get(b"A").ok()?).ok()?;
+      let action = action.as_dict().ok()?;
+      if action.get(b"S").and_then(Object::as_name).ok() != Some(b"GoTo") {
+        return None;
+      }
+      action.get(b"D").ok()?
+    }
+  };
+  let (_, destination) = document.dereference(destination).ok()?;
+  destination.as_array().ok()?.first()?.as_reference().ok()
+}
+
+/// Keep only the given 1-based pages, in the given order, under a single `Pages` node. The
+/// structure tree is restricted to the kept pages with `preserve_structure`, otherwise removed.
+pub fn extract_pages_in(
+  document: &mut Document, page_numbers: &[u32], on_broken_link: BrokenLink, preserve_structure: bool,
+) -> error::Result<()> {
+  let mut kept = vec![];
+  for page_id in page::page_ids(document, page_numbers)? {
+    if !kept.contains(&page_id) {
+      kept.push(page_id);
+    }
+  }
+  let all_pages = document.get_pages().into_values().collect::<BTreeSet<_>>();
+  let kept_pages = kept.iter().copied().collect::<BTreeSet<_>>();
+  let pages_id = document
+      .catalog()?
+      .get(b"Pages")
+      .and_then(Object::as_reference)
+      .map_err(|_| PdfError::new(ErrorCode::NoPagesRoot, "Pages root not found"))?;
+  for page_id in kept.iter() {
+    page::copy_inherited_attributes(document, *page_id)?;
+    // Written as the angle it resolves to, an inherited `/Rotate` may be an indirect or unusual
+    // value (`-90`, `450`) that not every reader resolves the same
+    let rotate = page::rotation(document, *page_id);
+    let page = document.get_object_mut(*page_id).and_then(Object::as_dict_mut)?;
+    page.set("Parent", pages_id);
+    if page.has(b"Rotate") {
+      page.set("Rotate", rotate);
+    }
+    for annotation_id in annotation_ids(document, *page_id) {
+      let broken = match link_target(document, annotation_id) {
+        Some(target) => all_pages.contains(&target) && !kept_pages.contains(&target),
+        None => false,
+      };
+      if !broken {
+        continue;
+      }
+      match on_broken_link {
+        BrokenLink::Remove => remove_from_array(document, *page_id, b"Annots", annotation_id)?,
+        BrokenLink::Keep => {
+          let annotation = document.get_object_mut(annotation_id).and_then(Object::as_dict_mut)?;
+          annotation.remove(b"Dest");
+          annotation.remove(b"A");
+        }
+      }
+    }
+  }
+  let root = document.get_object_mut(pages_id).and_then(Object::as_dict_mut)?;
+  root.set("Kids", kept.iter().map(|id| Object::Reference(*id)).collect::<Vec<_>>());
+  root.set("Count", kept.len() as i64);
+  // Structure elements of the removed pages would point at them
+  if preserve_structure {
+    structure::restrict_structure(document, &kept_pages)?;
+  } else {
+    structure::strip_structure(document)?;
+  }
+  // Outline entries would keep the removed pages alive
+  document
+      .get_object_mut(document.trailer.get(b"Root").and_then(Object::as_reference)?)
+      .and_then(Object::as_dict_mut)?
+      .remove(b"Outlines");
+  document.prune_objects();
+  document.renumber_objects();
+  Ok(())
+}
@@ -0,0 +1,215 @@
+//! Decoding of the whole filter chain of a stream. lopdf only decodes Flate and LZW, and gives up
+//! on a chain holding anything else, such as the `[/ASCII85Decode /FlateDecode]` of content
+//! written as text.
+
+use lopdf::{Dictionary, Object, Stream};
+
+/// The `/DecodeParms` of each filter of the chain: one dictionary for a single filter, an array
+/// with an entry (possibly `null`) per filter otherwise
+fn filter_params(stream: &Stream, count: usize) -> Vec<Option<Dictionary>> {
+  match stream.dict.get(b"DecodeParms") {
+    Ok(Object::Dictionary(params)) => vec![Some(params.clone())],
+    Ok(Object::Array(params)) => params.iter().map(|params| params.as_dict().ok().cloned()).collect(),
+    _ => vec![None; count],
+  }
+}
+
+fn ascii_hex_decode(data: &[u8]) -> Option<Vec<u8>> {
+  let mut digits = vec![];
+  for &byte in data {
+    match byte {
+      b'>' => break,
+      byte if byte.is_ascii_whitespace() => continue,
+      byte => digits.push((byte as char).to_digit(16)? as u8),
+    }
+  }
+  // A missing last digit is 0
+  if digits.len() % 2 == 1 {
+    digits.push(0);
+  }
+  Some(digits.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect())
+}
+
+/// The 32-bit value of five base-85 digits, `None` past `u32::MAX`
+fn base85_value(group: &[u8]) -> Option<u32> {
+  group.iter().try_fold(0_u32, |value, &digit| value.checked_mul(85)?.checked_add(digit as u32))
+}
+
+fn ascii85_decode(data: &[u8]) -> Option<Vec<u8>> {
+  let data = data.strip_prefix(b"<~").unwrap_or(data);
+  let mut decoded = vec![];
+  let mut group = Vec::with_capacity(5);
+  for &byte in data {
+    match byte {
+      b'~' => break,
+      b'z' if group.is_empty() => decoded.extend_from_slice(&[0; 4]),
+      b'!'..=b'u' => {
+        group.push(byte - b'!');
+        if group.len() == 5 {
+          let value = base85_value(&group)?;
+          decoded.extend_from_slice(&value.to_be_bytes());
+          group.clear();
+        }
+      }
+      byte if byte.is_ascii_whitespace() => continue,
+      _ => return None,
+    }
+  }
+  // A final partial group of n digits, padded with `u`, gives n - 1 bytes
+  if group.len() == 1 {
+    return None;
+  }
+  if !group.is_empty() {
+    let length = group.len() - 1;
+    group.resize(5, b'u' - b'!');
+    let value = base85_value(&group)?;
+    decoded.extend_from_slice(&value.to_be_bytes()[..length]);
+  }
+  Some(decoded)
+}
+
+fn run_length_decode(data: &[u8]) -> Option<Vec<u8>> {
+  let mut decoded = vec![];
+  let mut index = 0;
+  while let Some(&length) = data.get(index) {
+    match length {
+      128 => break,
+      0..=127 => {
+        let run = data.get(index + 1..index + 2 + length as usize)?;
+        decoded.extend_from_slice(run);
+        index += 2 + length as usize;
+      }
+      _ => {
+        let byte = *data.get(index + 1)?;
+        decoded.extend(std::iter::repeat_n(byte, 257 - length as usize));
+        index += 2;
+      }
+    }
+  }
+  Some(decoded)
+}
+
+/// Flate and LZW are left to lopdf, which applies their predictors
+fn lopdf_decode(filter: &str, params: Option<Dictionary>, data: Vec<u8>) -> Option<Vec<u8>> {
+  let mut dictionary = Dictionary::new();
+  dictionary.set("Filter", Object::Name(filter.as_bytes().to_vec()));
+  if let Some(params) = params {
+    dictionary.set("DecodeParms", params);
+  }
+  Stream::new(dictionary, data).decompressed_content().ok()
+}
+
+/// The data of a stream with every filter of its chain applied, or `None` when one of them can't
+/// be decoded (the image filters, or damaged data). A stream without filters is returned as is.
+pub fn decode(stream: &Stream) -> Option<Vec<u8>> {
+  let filters = match stream.dict.get(b"Filter") {
+    Ok(Object::Name(name)) => vec![name.clone()],
+    Ok(Object::Array(names)) => names
+        .iter()
+        .map(|name| name.as_name().ok().map(<[u8]>::to_vec))
+        .collect::<Option<Vec<_>>>()?,
+    Ok(_) => return None,
+    Err(_) => return Some(stream.content.clone()),
+  };
+  let params = filter_params(stream, filters.len());
+  let mut data = stream.content.clone();
+  for (index, filter) in filters.iter().enumerate() {
+    let params = params.get(index).cloned().flatten();
+    data = match filter.as_slice() {
+      b"ASCIIHexDecode" | b"AHx" => ascii_hex_decode(&data)?,
+      b"ASCII85Decode" | b"A85" => ascii85_decode(&data)?,
+      b"RunLengthDecode" | b"RL" => run_length_decode(&data)?,
+      b"FlateDecode" | b"Fl" => lopdf_decode("FlateDecode", params, data)?,
+      b"LZWDecode" | b"LZW" => lopdf_decode("LZWDecode", params, data)?,
+      _ => return None,
+    };
+  }
+  Some(data)
+}
+
+/// `decode`, falling back to the data as stored when a filter can't be decoded
+pub fn decoded_content(stream: &Stream) -> Vec<u8> {
+  decode(stream).unwrap_or_else(|| stream.content.clone())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const CONTENT: &[u8] = b"BT /F1 24 Tf 72 700 Td (Page 1) Tj ET";
+
+  fn ascii85_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![];
+    for chunk in data.chunks(4) {
+      let mut group = [0; 4];
+      group[..chunk.len()].copy_from_slice(chunk);
+      let mut value = u32::from_be_bytes(group);
+      let mut digits = [0; 5];
+      for digit in digits.iter_mut().rev() {
+        *digit = (value % 85) as u8 + b'!';
+        value /= 85;
+      }
+      encoded.extend_from_slice(&digits[..chunk.len() + 1]);
+    }
+    encoded.extend_from_slice(b"~>");
+    encoded
+  }
+
+  fn ascii_hex_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded: Vec<u8> = data.iter().flat_map(|byte| format!("{:02X}", byte).into_bytes()).collect();
+    encoded.push(b'>');
+    encoded
+  }
+
+  fn filtered(filters: &[&str], content: Vec<u8>) -> Stream {
+    let mut dictionary = Dictionary::new();
+    let filters = filters.iter().map(|filter| Object::Name(filter.as_bytes().to_vec())).collect::<Vec<_>>();
+    dictionary.set("Filter", filters);
+    Stream::new(dictionary, content)
+  }
+
+  /// `content` compressed with Flate by lopdf, which keeps it only when it gets smaller
+  fn flate(content: &[u8]) -> Vec<u8> {
+    let mut stream = Stream::new(Dictionary::new(), content.to_vec());
+    stream.compress().unwrap();
+    assert!(stream.dict.has(b"Filter"));
+    stream.content
+  }
+
+  #[test]
+  fn decodes_every_filter_of_a_chain_in_order() {
+    let content = CONTENT.repeat(4);
+    let stream = filtered(&["ASCII85Decode", "FlateDecode"], ascii85_encode(&flate(&content)));
+    assert_eq!(decoded_content(&stream), content);
+    let stream = filtered(&["AHx", "A85"], ascii_hex_encode(&ascii85_encode(CONTENT)));
+    assert_eq!(decoded_content(&stream), CONTENT);
+  }
+
+  #[test]
+  fn decodes_a_single_filter_given_as_a_name() {
+    let mut dictionary = Dictionary::new();
+    dictionary.set("Filter", Object::Name(b"ASCIIHexDecode".to_vec()));
+    assert_eq!(decoded_content(&Stream::new(dictionary, ascii_hex_encode(CONTENT))), CONTENT);
+  }
+
+  #[test]
+  fn ascii85_reads_z_and_a_final_partial_group() {
+    assert_eq!(ascii85_decode(b"<~z87cURD]~>").unwrap(), b"\0\0\0\0Hello");
+    assert_eq!(ascii85_decode(&ascii85_encode(b"PDF")).unwrap(), b"PDF");
+    assert_eq!(ascii85_decode(b"s8W-\"~>"), None);
+  }
+
+  #[test]
+  fn run_length_expands_literal_and_repeated_runs() {
+    let stream = filtered(&["RunLengthDecode"], vec![2, b'a', b'b', b'c', 254, b'x', 128, b'!']);
+    assert_eq!(decoded_content(&stream), b"abcxxx");
+  }
+
+  #[test]
+  fn falls_back_to_the_stored_data_on_a_filter_it_cannot_decode() {
+    let stream = filtered(&["ASCII85Decode", "DCTDecode"], ascii85_encode(CONTENT));
+    assert_eq!(decode(&stream), None);
+    assert_eq!(decoded_content(&stream), stream.content);
+    assert_eq!(decoded_content(&Stream::new(Dictionary::new(), CONTENT.to_vec())), CONTENT);
+  }
+}
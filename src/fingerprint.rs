@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use napi::{CallContext, JsBuffer, JsString, Result};
+use sha2::{Digest, Sha256};
+
+use crate::error::OrThrow;
+use crate::filters;
+use crate::page;
+use crate::utils::{buffer_value, load_document};
+
+/// Keys that describe or locate an object rather than what it draws: back-pointers, dates,
+/// metadata streams and tagging
+const IGNORED_KEYS: [&[u8]; 10] = [
+  b"Parent",
+  b"P",
+  b"M",
+  b"CreationDate",
+  b"ModDate",
+  b"LastModified",
+  b"Metadata",
+  b"PieceInfo",
+  b"StructParents",
+  b"StructParent",
+];
+
+/// Stream keys that only describe how the data is stored
+const ENCODING_KEYS: [&[u8]; 4] = [b"Length", b"Filter", b"DecodeParms", b"DL"];
+
+/// Page attributes taken with their inherited value
+const PAGE_ATTRIBUTES: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+#[js_function(1)]
+pub fn content_fingerprint(ctx: CallContext) -> Result<JsString> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  ctx.env.create_string(&content_fingerprint_of(&document))
+}
+
+/// Hashes objects by value, so the numbering of objects and whether equal objects are shared
+/// doesn't matter: a reference is written as the digest of what it points to
+pub struct Canonical<'a> {
+  document: &'a Document,
+  digests: HashMap<ObjectId, [u8; 32]>,
+  /// Objects being hashed, a reference back to one of them is written as its depth
+  stack: Vec<ObjectId>,
+}
+
+impl<'a> Canonical<'a> {
+  pub fn new(document: &'a Document) -> Self {
+    Canonical {
+      document,
+      digests: HashMap::new(),
+      stack: vec![],
+    }
+  }
+
+  /// The digests computed so far, of the objects hashed and of every object they reach
+  pub fn into_digests(self) -> HashMap<ObjectId, [u8; 32]> {
+    self.digests
+  }
+
+  fn write_bytes(out: &mut Vec<u8>, tag: u8, bytes: &[u8]) {
+    out.push(tag);
+    out.extend_from_slice(format!("{}:", bytes.len()).as_bytes());
+    out.extend_from_slice(bytes);
+  }
+
+  fn write_dictionary(&mut self, out: &mut Vec<u8>, dictionary: &Dictionary, skipped: &[&[u8]]) {
+    let mut entries = dictionary
+        .iter()
+        .filter(|(key, _)| !IGNORED_KEYS.contains(&key.as_slice()) && !skipped.contains(&key.as_slice()))
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|(key, _)| *key);
+    out.extend_from_slice(b"<<");
+    for (key, value) in entries {
+      Self::write_bytes(out, b'/', key);
+      self.write(out, value);
+    }
+    out.extend_from_slice(b">>");
+  }
+
+  fn write(&mut self, out: &mut Vec<u8>, object: &Object) {
+    match object {
+      Object::Null => out.extend_from_slice(b"null"),
+      Object::Boolean(value) => out.extend_from_slice(if *value { b"true" } else { b"false" }),
+      // Integral reals are the same number, writers differ in how they print them
+      Object::Integer(value) => out.extend_from_slice(format!("n{} ", *value as f64).as_bytes()),
+      Object::Real(value) => out.extend_from_slice(format!("n{} ", value).as_bytes()),
+      Object::Name(name) => Self::write_bytes(out, b'/', name),
+      Object::String(string, _) => Self::write_bytes(out, b'(', string),
+      Object::Array(array) => {
+        out.push(b'[');
+        for item in array {
+          self.write(out, item);
+        }
+        out.push(b']');
+      }
+      Object::Dictionary(dictionary) => self.write_dictionary(out, dictionary, &[]),
+      Object::Stream(stream) => {
+        self.write_dictionary(out, &stream.dict, &ENCODING_KEYS);
+        let data = filters::decoded_content(stream);
+        Self::write_bytes(out, b's', &data);
+      }
+      Object::Reference(id) => {
+        if let Some(depth) = self.stack.iter().position(|ancestor| ancestor == id) {
+          out.extend_from_slice(format!("^{} ", depth).as_bytes());
+          return;
+        }
+        let digest = self.digest(*id);
+        Self::write_bytes(out, b'@', &digest);
+      }
+    }
+  }
+
+  pub fn digest(&mut self, id: ObjectId) -> [u8; 32] {
+    if let Some(digest) = self.digests.get(&id) {
+      return *digest;
+    }
+    let mut out = vec![];
+    self.stack.push(id);
+    match self.document.get_object(id) {
+      Ok(object) => self.write(&mut out, object),
+      Err(_) => out.extend_from_slice(b"null"),
+    }
+    self.stack.pop();
+    let digest = Sha256::digest(&out).into();
+    self.digests.insert(id, digest);
+    digest
+  }
+}
+
+/// SHA-256 (hex) of what the pages display: their content, resources, geometry and annotations,
+/// objects compared by value. Metadata, document IDs, dates and the way streams are compressed or
+/// objects numbered don't change it.
+pub fn content_fingerprint_of(document: &Document) -> String {
+  let mut canonical = Canonical::new(document);
+  let mut out = vec![];
+  for page_id in document.get_pages().into_values() {
+    let mut page = match document.get_dictionary(page_id) {
+      Ok(page) => page.clone(),
+      Err(_) => continue,
+    };
+    for key in PAGE_ATTRIBUTES.iter() {
+      if let Some(value) = page::inherited_attribute(document, page_id, key) {
+        page.set(key.to_vec(), value.clone());
+      }
+    }
+    canonical.stack.push(page_id);
+    canonical.write_dictionary(&mut out, &page, &[]);
+    canonical.stack.pop();
+  }
+  Sha256::digest(&out).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,505 @@
+use std::collections::BTreeMap;
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use napi::{Error, JsBuffer, JsObject, JsString, JsUnknown, Result, Status, ValueType};
+use subsetter::GlyphRemapper;
+use ttf_parser::{name_id, Face, GlyphId};
+
+use crate::error::{self, ErrorCode, PdfError};
+use crate::fonts;
+use crate::utils::buffer_value;
+
+/// The standard 14 fonts with a Latin character set, readers supply them so nothing is embedded
+#[derive(Clone, Copy, PartialEq)]
+pub enum StandardFont {
+  Helvetica,
+  HelveticaBold,
+  HelveticaOblique,
+  HelveticaBoldOblique,
+  TimesRoman,
+  TimesBold,
+  TimesItalic,
+  TimesBoldItalic,
+  Courier,
+  CourierBold,
+  CourierOblique,
+  CourierBoldOblique,
+}
+
+const STANDARD_FONTS: [StandardFont; 12] = [
+  StandardFont::Helvetica,
+  StandardFont::HelveticaBold,
+  StandardFont::HelveticaOblique,
+  StandardFont::HelveticaBoldOblique,
+  StandardFont::TimesRoman,
+  StandardFont::TimesBold,
+  StandardFont::TimesItalic,
+  StandardFont::TimesBoldItalic,
+  StandardFont::Courier,
+  StandardFont::CourierBold,
+  StandardFont::CourierOblique,
+  StandardFont::CourierBoldOblique,
+];
+
+impl StandardFont {
+  /// The PostScript name, used as `/BaseFont`
+  pub fn base_font(self) -> &'static str {
+    match self {
+      StandardFont::Helvetica => "Helvetica",
+      StandardFont::HelveticaBold => "Helvetica-Bold",
+      StandardFont::HelveticaOblique => "Helvetica-Oblique",
+      StandardFont::HelveticaBoldOblique => "Helvetica-BoldOblique",
+      StandardFont::TimesRoman => "Times-Roman",
+      StandardFont::TimesBold => "Times-Bold",
+      StandardFont::TimesItalic => "Times-Italic",
+      StandardFont::TimesBoldItalic => "Times-BoldItalic",
+      StandardFont::Courier => "Courier",
+      StandardFont::CourierBold => "Courier-Bold",
+      StandardFont::CourierOblique => "Courier-Oblique",
+      StandardFont::CourierBoldOblique => "Courier-BoldOblique",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    STANDARD_FONTS.iter().copied().find(|font| font.base_font() == name)
+  }
+
+  /// Advance width (1/1000 em) of a WinAnsi character code
+  fn width(self, code: u8) -> u16 {
+    let widths = match self {
+      // Slanted variants share the metrics of the upright face
+      StandardFont::Helvetica | StandardFont::HelveticaOblique => &HELVETICA_WIDTHS,
+      StandardFont::HelveticaBold | StandardFont::HelveticaBoldOblique => &HELVETICA_BOLD_WIDTHS,
+      StandardFont::TimesRoman => &TIMES_ROMAN_WIDTHS,
+      StandardFont::TimesBold => &TIMES_BOLD_WIDTHS,
+      StandardFont::TimesItalic => &TIMES_ITALIC_WIDTHS,
+      StandardFont::TimesBoldItalic => &TIMES_BOLD_ITALIC_WIDTHS,
+      // Courier is monospaced
+      _ => return COURIER_WIDTH,
+    };
+    match code {
+      0x20..=0x7e => widths[(code - 0x20) as usize],
+      _ => DEFAULT_WIDTH,
+    }
+  }
+}
+
+/// The font of drawn text
+pub enum Font {
+  Standard(StandardFont),
+  /// A TrueType or OpenType font file, embedded as a CID font so any script it covers can be drawn
+  Embedded(Vec<u8>),
+}
+
+impl Font {
+  /// Read the `font` property of an options object, either a standard font name or a font file,
+  /// defaulting to Helvetica
+  pub fn from_js(options: &JsObject) -> Result<Self> {
+    let font = options.get_named_property::<JsUnknown>("font")?;
+    match font.get_type()? {
+      ValueType::Undefined => Ok(Font::Standard(StandardFont::Helvetica)),
+      ValueType::String => {
+        let name = unsafe { font.cast::<JsString>() }.into_utf8()?.into_owned()?;
+        StandardFont::from_name(&name).map(Font::Standard).ok_or_else(|| {
+          Error::new(
+            Status::InvalidArg,
+            format!("Unknown font '{}', expected a standard font such as Helvetica or Times-Bold", name),
+          )
+        })
+      }
+      _ if font.is_buffer()? => {
+        let data = buffer_value(unsafe { font.cast::<JsBuffer>() })?.to_vec();
+        if let Err(err) = Face::parse(&data, 0) {
+          return Err(Error::new(
+            Status::InvalidArg,
+            format!("font is not a valid TrueType/OpenType font: {}", err),
+          ));
+        }
+        Ok(Font::Embedded(data))
+      }
+      _ => Err(Error::new(
+        Status::InvalidArg,
+        "font must be a standard font name or a font file Buffer".to_owned(),
+      )),
+    }
+  }
+}
+
+/// Helvetica advance widths (1/1000 em) for the printable ASCII range 32..=126
+const HELVETICA_WIDTHS: [u16; 95] = [
+  278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, // space - /
+  556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, // 0 - ?
+  1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, // @ - O
+  667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, // P - _
+  333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, // ` - o
+  556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // p - ~
+];
+
+/// Helvetica-Bold advance widths for the same range
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+  278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, // space - /
+  556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, // 0 - ?
+  975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778, // @ - O
+  667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556, // P - _
+  333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611, // ` - o
+  611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584, // p - ~
+];
+
+/// Times-Roman advance widths for the same range
+const TIMES_ROMAN_WIDTHS: [u16; 95] = [
+  250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278, // space - /
+  500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444, // 0 - ?
+  921, 722, 667, 667, 722, 611, 556, 722, 722, 333, 389, 722, 611, 889, 722, 722, // @ - O
+  556, 722, 667, 556, 611, 722, 722, 944, 722, 722, 611, 333, 278, 333, 469, 500, // P - _
+  333, 444, 500, 444, 500, 444, 333, 500, 500, 278, 278, 500, 278, 778, 500, 500, // ` - o
+  500, 500, 333, 389, 278, 500, 500, 722, 500, 500, 444, 480, 200, 480, 541, // p - ~
+];
+
+/// Times-Bold advance widths for the same range
+const TIMES_BOLD_WIDTHS: [u16; 95] = [
+  250, 333, 555, 500, 500, 1000, 833, 278, 333, 333, 500, 570, 250, 333, 250, 278, // space - /
+  500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500, // 0 - ?
+  930, 722, 667, 722, 722, 667, 611, 778, 778, 389, 500, 778, 667, 944, 722, 778, // @ - O
+  611, 778, 722, 556, 667, 722, 722, 1000, 722, 722, 667, 333, 278, 333, 581, 500, // P - _
+  333, 500, 556, 444, 556, 444, 333, 500, 556, 278, 333, 556, 278, 833, 556, 500, // ` - o
+  556, 556, 444, 389, 333, 556, 500, 722, 500, 500, 444, 394, 220, 394, 520, // p - ~
+];
+
+/// Times-Italic advance widths for the same range
+const TIMES_ITALIC_WIDTHS: [u16; 95] = [
+  250, 333, 420, 500, 500, 833, 778, 214, 333, 333, 500, 675, 250, 333, 250, 278, // space - /
+  500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 675, 675, 675, 500, // 0 - ?
+  920, 611, 611, 667, 722, 611, 611, 722, 722, 333, 444, 667, 556, 833, 667, 722, // @ - O
+  611, 722, 611, 500, 556, 722, 611, 833, 611, 556, 556, 389, 278, 389, 422, 500, // P - _
+  333, 500, 500, 444, 500, 444, 278, 500, 500, 278, 278, 444, 278, 722, 500, 500, // ` - o
+  500, 500, 389, 389, 278, 500, 444, 667, 444, 444, 389, 400, 275, 400, 541, // p - ~
+];
+
+/// Times-BoldItalic advance widths for the same range
+const TIMES_BOLD_ITALIC_WIDTHS: [u16; 95] = [
+  250, 389, 555, 500, 500, 833, 778, 278, 333, 333, 500, 570, 250, 333, 250, 278, // space - /
+  500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500, // 0 - ?
+  832, 667, 667, 667, 722, 667, 667, 722, 778, 389, 500, 667, 611, 889, 722, 722, // @ - O
+  611, 722, 667, 556, 611, 722, 667, 889, 667, 611, 611, 333, 278, 333, 570, 500, // P - _
+  333, 500, 500, 444, 500, 444, 333, 500, 556, 278, 278, 500, 278, 778, 556, 500, // ` - o
+  500, 500, 389, 389, 278, 556, 444, 667, 500, 444, 389, 348, 220, 348, 570, // p - ~
+];
+
+/// Advance width of every Courier character
+const COURIER_WIDTH: u16 = 600;
+
+/// Width used for characters outside the metrics tables
+const DEFAULT_WIDTH: u16 = 556;
+
+/// The characters WinAnsiEncoding places at 0x80 - 0x9f, where Latin-1 has control codes. The
+/// codes 0x81, 0x8d, 0x8f, 0x90 and 0x9d are unused.
+const WIN_ANSI_EXTRAS: [(char, u8); 27] = [
+  ('\u{20ac}', 0x80), // €
+  ('\u{201a}', 0x82), // ‚
+  ('\u{0192}', 0x83), // ƒ
+  ('\u{201e}', 0x84), // „
+  ('\u{2026}', 0x85), // …
+  ('\u{2020}', 0x86), // †
+  ('\u{2021}', 0x87), // ‡
+  ('\u{02c6}', 0x88), // ˆ
+  ('\u{2030}', 0x89), // ‰
+  ('\u{0160}', 0x8a), // Š
+  ('\u{2039}', 0x8b), // ‹
+  ('\u{0152}', 0x8c), // Œ
+  ('\u{017d}', 0x8e), // Ž
+  ('\u{2018}', 0x91), // ‘
+  ('\u{2019}', 0x92), // ’
+  ('\u{201c}', 0x93), // “
+  ('\u{201d}', 0x94), // ”
+  ('\u{2022}', 0x95), // •
+  ('\u{2013}', 0x96), // –
+  ('\u{2014}', 0x97), // —
+  ('\u{02dc}', 0x98), // ˜
+  ('\u{2122}', 0x99), // ™
+  ('\u{0161}', 0x9a), // š
+  ('\u{203a}', 0x9b), // ›
+  ('\u{0153}', 0x9c), // œ
+  ('\u{017e}', 0x9e), // ž
+  ('\u{0178}', 0x9f), // Ÿ
+];
+
+/// Encode text for a WinAnsiEncoding simple font, replacing unsupported characters with `?`
+pub fn encode_text(text: &str) -> Vec<u8> {
+  text
+      .chars()
+      .map(|c| match c as u32 {
+        code @ 0x20..=0x7e | code @ 0xa0..=0xff => code as u8,
+        _ => WIN_ANSI_EXTRAS
+            .iter()
+            .find(|(extra, _)| *extra == c)
+            .map_or(b'?', |&(_, code)| code),
+      })
+      .collect()
+}
+
+/// Width of the encoded text in points
+pub fn text_width(font: StandardFont, encoded: &[u8], font_size: f64) -> f64 {
+  let units: f64 = encoded.iter().map(|&code| font.width(code) as f64).sum();
+  units * font_size / 1000.0
+}
+
+/// A `Font` ready to draw with, the file of an embedded font parsed once for all the text
+enum ParsedFont<'a> {
+  Standard(StandardFont),
+  Embedded(&'a [u8], Box<Face<'a>>),
+}
+
+/// A font used to draw text into one document. The font object is reserved up front so content can
+/// reference it, and written by `finish` once the glyphs used by embedded fonts are known.
+pub struct FontWriter<'a> {
+  font: ParsedFont<'a>,
+  id: ObjectId,
+  /// Glyphs drawn with an embedded font and the character each one stands for
+  glyphs: BTreeMap<u16, char>,
+}
+
+impl<'a> FontWriter<'a> {
+  pub fn new(document: &mut Document, font: &'a Font) -> error::Result<Self> {
+    let font = match font {
+      Font::Standard(font) => ParsedFont::Standard(*font),
+      Font::Embedded(data) => ParsedFont::Embedded(data, Box::new(parse_face(data)?)),
+    };
+    Ok(FontWriter {
+      font,
+      id: document.new_object_id(),
+      glyphs: BTreeMap::new(),
+    })
+  }
+
+  /// The font object to register in the page resources
+  pub fn id(&self) -> ObjectId {
+    self.id
+  }
+
+  /// Encode text as the string operand of `Tj`, returning it with its width in points
+  pub fn encode(&mut self, text: &str, font_size: f64) -> error::Result<(Object, f64)> {
+    let face = match &self.font {
+      ParsedFont::Standard(font) => {
+        let encoded = encode_text(text);
+        let width = text_width(*font, &encoded, font_size);
+        return Ok((Object::string_literal(encoded), width));
+      }
+      ParsedFont::Embedded(_, face) => face,
+    };
+    let mut encoded = vec![];
+    let mut units = 0.0;
+    for c in text.chars() {
+      // Characters missing from the font draw as .notdef
+      let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+      if glyph.0 != 0 {
+        self.glyphs.entry(glyph.0).or_insert(c);
+      }
+      encoded.extend_from_slice(&glyph.0.to_be_bytes());
+      units += glyph_width(face, glyph);
+    }
+    // Glyph ids are binary, a hex string keeps them safe from line ending normalization
+    Ok((Object::String(encoded, StringFormat::Hexadecimal), units * font_size / 1000.0))
+  }
+
+  /// Write the font object, along with the descriptor and font file of an embedded font
+  pub fn finish(self, document: &mut Document) -> error::Result<()> {
+    let font = match &self.font {
+      ParsedFont::Standard(font) => {
+        let mut dictionary = Dictionary::new();
+        dictionary.set("Type", Object::Name(b"Font".to_vec()));
+        dictionary.set("Subtype", Object::Name(b"Type1".to_vec()));
+        dictionary.set("BaseFont", Object::Name(font.base_font().as_bytes().to_vec()));
+        dictionary.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
+        dictionary
+      }
+      ParsedFont::Embedded(data, face) => embedded_font(document, data, face, &self.glyphs),
+    };
+    document.objects.insert(self.id, Object::Dictionary(font));
+    Ok(())
+  }
+}
+
+fn parse_face(data: &[u8]) -> error::Result<Face<'_>> {
+  Face::parse(data, 0).map_err(|err| PdfError::new(ErrorCode::GenericFailure, format!("Invalid font: {}", err)))
+}
+
+/// Advance width of a glyph in 1/1000 em
+fn glyph_width(face: &Face, glyph: GlyphId) -> f64 {
+  face.glyph_hor_advance(glyph).unwrap_or(0) as f64 * 1000.0 / face.units_per_em() as f64
+}
+
+/// Scale font units to the 1/1000 em of PDF glyph space
+fn scale(face: &Face, value: i16) -> Object {
+  ((value as f64 * 1000.0 / face.units_per_em() as f64).round() as i64).into()
+}
+
+/// Build a Type0 font with Identity-H encoding, so the two-byte codes written by `encode` are glyph
+/// ids of the font file. A TrueType file is embedded as a subset of the drawn glyphs, which
+/// `CIDToGIDMap` maps the codes to.
+fn embedded_font(document: &mut Document, data: &[u8], face: &Face, glyphs: &BTreeMap<u16, char>) -> Dictionary {
+  // The PostScript name may only hold printable ASCII without delimiters
+  let base_font = face
+      .names()
+      .into_iter()
+      .filter(|name| name.name_id == name_id::POST_SCRIPT_NAME)
+      .find_map(|name| name.to_string())
+      .map(|name| {
+        name
+            .chars()
+            .filter(|c| c.is_ascii_graphic() && !"()<>[]{}/%#".contains(*c))
+            .collect::<String>()
+      })
+      .filter(|name| !name.is_empty())
+      .unwrap_or_else(|| "EmbeddedFont".to_owned());
+  let is_cff = face.tables().cff.is_some();
+  let kept = glyphs.keys().copied().collect::<Vec<_>>();
+  let remapper = GlyphRemapper::new_from_glyphs_sorted(&kept);
+  // The glyphs of a CFF font are CIDs too, keep its program whole rather than renumber them
+  let subset = match subsetter::subset(data, 0, &remapper) {
+    Ok(subset) if !is_cff && subset.len() < data.len() => Some(subset),
+    _ => None,
+  };
+  let base_font = match subset {
+    Some(_) => Object::Name(format!("{}+{}", fonts::subset_tag(&kept), base_font).into_bytes()),
+    None => Object::Name(base_font.into_bytes()),
+  };
+
+  let mut font_file = Stream::new(Dictionary::new(), vec![]);
+  if is_cff {
+    font_file.dict.set("Subtype", Object::Name(b"OpenType".to_vec()));
+  }
+  let cid_to_gid_map = match subset {
+    Some(subset) => {
+      font_file.dict.set("Length1", subset.len() as i64);
+      font_file.set_content(subset);
+      let mut map = vec![0; (kept.last().copied().unwrap_or(0) as usize + 1) * 2];
+      for &glyph in kept.iter() {
+        let new_glyph = remapper.get(glyph).unwrap_or(0);
+        map[glyph as usize * 2..glyph as usize * 2 + 2].copy_from_slice(&new_glyph.to_be_bytes());
+      }
+      Object::Reference(document.add_object(Stream::new(Dictionary::new(), map)))
+    }
+    None => {
+      if !is_cff {
+        font_file.dict.set("Length1", data.len() as i64);
+      }
+      font_file.set_content(data.to_vec());
+      Object::Name(b"Identity".to_vec())
+    }
+  };
+  let font_file_id = document.add_object(font_file);
+
+  let bounding_box = face.global_bounding_box();
+  // Symbolic, as glyphs are addressed by id rather than through a standard encoding
+  let mut flags = 4;
+  if face.is_monospaced() {
+    flags |= 1;
+  }
+  if face.is_italic() {
+    flags |= 64;
+  }
+  let mut descriptor = Dictionary::new();
+  descriptor.set("Type", Object::Name(b"FontDescriptor".to_vec()));
+  descriptor.set("FontName", base_font.clone());
+  descriptor.set("Flags", flags);
+  descriptor.set(
+    "FontBBox",
+    vec![
+      scale(face, bounding_box.x_min),
+      scale(face, bounding_box.y_min),
+      scale(face, bounding_box.x_max),
+      scale(face, bounding_box.y_max),
+    ],
+  );
+  descriptor.set("ItalicAngle", face.italic_angle().unwrap_or(0.0) as f64);
+  descriptor.set("Ascent", scale(face, face.ascender()));
+  descriptor.set("Descent", scale(face, face.descender()));
+  descriptor.set("CapHeight", scale(face, face.capital_height().unwrap_or_else(|| face.ascender())));
+  // Not stored in font files, readers only use it as a hint
+  descriptor.set("StemV", 80);
+  descriptor.set(if is_cff { "FontFile3" } else { "FontFile2" }, font_file_id);
+  let descriptor_id = document.add_object(descriptor);
+
+  let mut widths = vec![];
+  for &glyph in glyphs.keys() {
+    widths.push((glyph as i64).into());
+    widths.push(vec![(glyph_width(face, GlyphId(glyph)).round() as i64).into()].into());
+  }
+  let mut system_info = Dictionary::new();
+  system_info.set("Registry", Object::string_literal("Adobe"));
+  system_info.set("Ordering", Object::string_literal("Identity"));
+  system_info.set("Supplement", 0);
+  let mut cid_font = Dictionary::new();
+  cid_font.set("Type", Object::Name(b"Font".to_vec()));
+  let subtype: &[u8] = if is_cff { b"CIDFontType0" } else { b"CIDFontType2" };
+  cid_font.set("Subtype", Object::Name(subtype.to_vec()));
+  cid_font.set("BaseFont", base_font.clone());
+  cid_font.set("CIDSystemInfo", system_info);
+  cid_font.set("FontDescriptor", descriptor_id);
+  cid_font.set("W", widths);
+  if !is_cff {
+    cid_font.set("CIDToGIDMap", cid_to_gid_map);
+  }
+  let cid_font_id = document.add_object(cid_font);
+
+  let to_unicode_id = document.add_object(Stream::new(Dictionary::new(), to_unicode_cmap(glyphs)));
+
+  let mut font = Dictionary::new();
+  font.set("Type", Object::Name(b"Font".to_vec()));
+  font.set("Subtype", Object::Name(b"Type0".to_vec()));
+  font.set("BaseFont", base_font);
+  font.set("Encoding", Object::Name(b"Identity-H".to_vec()));
+  font.set("DescendantFonts", vec![cid_font_id.into()]);
+  font.set("ToUnicode", to_unicode_id);
+  font
+}
+
+/// A CMap mapping the glyph ids back to text, so drawn text can be searched and copied
+fn to_unicode_cmap(glyphs: &BTreeMap<u16, char>) -> Vec<u8> {
+  let mut cmap = String::from(
+    "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+     /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+     /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+     1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+  );
+  let entries = glyphs.iter().collect::<Vec<_>>();
+  // A bfchar block holds at most 100 entries
+  for chunk in entries.chunks(100) {
+    cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+    for (glyph, c) in chunk {
+      let mut utf16 = [0; 2];
+      let hex = c
+          .encode_utf16(&mut utf16)
+          .iter()
+          .map(|unit| format!("{:04X}", unit))
+          .collect::<String>();
+      cmap.push_str(&format!("<{:04X}> <{}>\n", glyph, hex));
+    }
+    cmap.push_str("endbfchar\n");
+  }
+  cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+  cmap.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn encodes_latin_1_and_the_win_ansi_punctuation() {
+    assert_eq!(encode_text("Café"), b"Caf\xe9");
+    assert_eq!(encode_text("“5 €” – ‘ok’…"), b"\x935 \x80\x94 \x96 \x91ok\x92\x85");
+    assert_eq!(encode_text("Œuvre™ Šž Ÿ"), b"\x8cuvre\x99 \x8a\x9e \x9f");
+  }
+
+  #[test]
+  fn replaces_what_win_ansi_lacks() {
+    assert_eq!(encode_text("日本\u{81}\n"), b"????");
+  }
+
+  #[test]
+  fn measures_with_the_metrics_of_the_font() {
+    assert_eq!(text_width(StandardFont::Courier, b"abc", 10.0), 18.0);
+    assert_eq!(text_width(StandardFont::Helvetica, b"Hi", 10.0), (722.0 + 222.0) / 100.0);
+  }
+}
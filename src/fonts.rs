@@ -0,0 +1,520 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use napi::{CallContext, JsBuffer, JsObject, JsUnknown, Result};
+use subsetter::GlyphRemapper;
+
+use crate::error::OrThrow;
+use crate::utils::{buffer_value, load_document, output, output_update};
+use crate::{filters, form, page};
+
+/// A font the document uses, as reported by `listFonts`
+pub struct FontInfo {
+  pub name: String,
+  pub subtype: String,
+  /// Whether every use of the font carries its program
+  pub embedded: bool,
+  pub subset: bool,
+}
+
+/// Whether a `BaseFont` has the `ABCDEF+` tag of a subset font
+pub fn is_subset_name(name: &str) -> bool {
+  let bytes = name.as_bytes();
+  bytes.len() > 7 && bytes[6] == b'+' && bytes[..6].iter().all(u8::is_ascii_uppercase)
+}
+
+/// The font dictionaries used by the pages, through their resources and those of the forms they
+/// draw, with their id when they are indirect objects
+pub fn used_fonts(document: &Document) -> Vec<(Option<ObjectId>, &Dictionary)> {
+  fn walk<'a>(
+    document: &'a Document, resources: &'a Object, visited: &mut BTreeSet<ObjectId>,
+    fonts: &mut Vec<(Option<ObjectId>, &'a Dictionary)>,
+  ) {
+    let resources = match document.dereference(resources) {
+      Ok((_, Object::Dictionary(resources))) => resources,
+      _ => return,
+    };
+    let entries = |category: &[u8]| match resources.get(category).and_then(|entries| document.dereference(entries)) {
+      Ok((_, Object::Dictionary(entries))) => entries.iter().map(|(_, entry)| entry).collect::<Vec<_>>(),
+      _ => vec![],
+    };
+    for font in entries(b"Font") {
+      let id = font.as_reference().ok();
+      if id.is_some_and(|id| !visited.insert(id)) {
+        continue;
+      }
+      if let Ok((_, Object::Dictionary(font))) = document.dereference(font) {
+        fonts.push((id, font));
+        // Type 3 glyphs are content streams with resources of their own
+        if let Ok(resources) = font.get(b"Resources") {
+          walk(document, resources, visited, fonts);
+        }
+      }
+    }
+    for xobject in entries(b"XObject") {
+      let id = match xobject.as_reference() {
+        Ok(id) if visited.insert(id) => id,
+        _ => continue,
+      };
+      if let Ok(Object::Stream(stream)) = document.get_object(id) {
+        if let Ok(resources) = stream.dict.get(b"Resources") {
+          walk(document, resources, visited, fonts);
+        }
+      }
+    }
+  }
+  let mut visited = BTreeSet::new();
+  let mut fonts = vec![];
+  for page_id in document.get_pages().into_values() {
+    if let Some(resources) = page::inherited_attribute(document, page_id, b"Resources") {
+      walk(document, resources, &mut visited, &mut fonts);
+    }
+  }
+  fonts
+}
+
+/// The font descriptor of a simple font, or of the descendant of a Type 0 font
+fn font_descriptor<'a>(document: &'a Document, font: &'a Dictionary) -> Option<&'a Dictionary> {
+  let font = match font.get(b"DescendantFonts").and_then(|fonts| document.dereference(fonts)) {
+    Ok((_, Object::Array(fonts))) => document.dereference(fonts.first()?).ok()?.1.as_dict().ok()?,
+    _ => font,
+  };
+  document.dereference(font.get(b"FontDescriptor").ok()?).ok()?.1.as_dict().ok()
+}
+
+/// Whether the font program is in the document. Type 3 glyphs always are.
+fn is_embedded(document: &Document, font: &Dictionary) -> bool {
+  if font.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Type3") {
+    return true;
+  }
+  font_descriptor(document, font).is_some_and(|descriptor| {
+    [b"FontFile".as_ref(), b"FontFile2", b"FontFile3"].iter().any(|key| descriptor.has(key))
+  })
+}
+
+/// The fonts used by the pages, one entry per base font
+pub fn list_fonts_in(document: &Document) -> Vec<FontInfo> {
+  let mut fonts: BTreeMap<String, FontInfo> = BTreeMap::new();
+  for (_, font) in used_fonts(document) {
+    let subtype = font.get(b"Subtype").and_then(Object::as_name_str).unwrap_or("").to_owned();
+    // Type 3 fonts have no `BaseFont`, their `Name` is optional
+    let name = font
+        .get(b"BaseFont")
+        .or_else(|_| font.get(b"Name"))
+        .and_then(Object::as_name)
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .unwrap_or_default();
+    let embedded = is_embedded(document, font);
+    let info = fonts.entry(name.clone()).or_insert_with(|| FontInfo {
+      subset: is_subset_name(&name),
+      name,
+      subtype,
+      embedded,
+    });
+    info.embedded &= embedded;
+  }
+  fonts.into_values().collect()
+}
+
+#[js_function(1)]
+pub fn list_fonts(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  let fonts = list_fonts_in(&document);
+  let mut result = ctx.env.create_array_with_length(fonts.len())?;
+  for (index, font) in fonts.iter().enumerate() {
+    let mut item = ctx.env.create_object()?;
+    item.set_named_property("name", ctx.env.create_string(&font.name)?)?;
+    item.set_named_property("subtype", ctx.env.create_string(&font.subtype)?)?;
+    item.set_named_property("embedded", ctx.env.get_boolean(font.embedded)?)?;
+    item.set_named_property("subset", ctx.env.get_boolean(font.subset)?)?;
+    result.set_element(index as u32, item)?;
+  }
+  Ok(result)
+}
+
+/// Entry `name` of a resource category (`Font`, `XObject`, ...)
+fn resource<'a>(document: &'a Document, resources: Option<&'a Dictionary>, category: &[u8], name: &[u8]) -> Option<&'a Object> {
+  let entries = document.dereference(resources?.get(category).ok()?).ok()?.1.as_dict().ok()?;
+  entries.get(name).ok()
+}
+
+/// Collects the two-byte codes drawn with each font, following the forms content draws
+struct CodeScanner<'a> {
+  document: &'a Document,
+  codes: BTreeMap<ObjectId, BTreeSet<u16>>,
+  visited_forms: BTreeSet<ObjectId>,
+}
+
+impl<'a> CodeScanner<'a> {
+  fn scan(&mut self, data: &[u8], resources: Option<&'a Dictionary>, mut font: Option<ObjectId>) {
+    let content = match Content::decode(data) {
+      Ok(content) => content,
+      Err(_) => return,
+    };
+    for operation in content.operations.iter() {
+      let strings = match operation.operator.as_str() {
+        "Tf" => {
+          font = operation
+              .operands
+              .first()
+              .and_then(|name| name.as_name().ok())
+              .and_then(|name| resource(self.document, resources, b"Font", name))
+              .and_then(|font| font.as_reference().ok());
+          continue;
+        }
+        "Tj" | "'" | "\"" => operation.operands.last().into_iter().collect::<Vec<_>>(),
+        "TJ" => match operation.operands.first() {
+          Some(Object::Array(items)) => items.iter().collect(),
+          _ => continue,
+        },
+        "Do" => {
+          let form = operation
+              .operands
+              .first()
+              .and_then(|name| name.as_name().ok())
+              .and_then(|name| resource(self.document, resources, b"XObject", name))
+              .and_then(|form| form.as_reference().ok());
+          if let Some(form_id) = form {
+            self.scan_form(form_id, resources, font);
+          }
+          continue;
+        }
+        _ => continue,
+      };
+      if let Some(font_id) = font {
+        let codes = self.codes.entry(font_id).or_default();
+        for string in strings {
+          if let Object::String(bytes, _) = string {
+            codes.extend(bytes.chunks(2).map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])));
+          }
+        }
+      }
+    }
+  }
+
+  /// Scan a form XObject, which inherits the caller's resources when it has none and the current font
+  fn scan_form(&mut self, form_id: ObjectId, resources: Option<&'a Dictionary>, font: Option<ObjectId>) {
+    if !self.visited_forms.insert(form_id) {
+      return;
+    }
+    let document = self.document;
+    if let Ok(Object::Stream(form)) = document.get_object(form_id) {
+      if form.dict.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Form") {
+        return;
+      }
+      let form_resources = form
+          .dict
+          .get(b"Resources")
+          .and_then(|resources| document.dereference(resources))
+          .and_then(|(_, resources)| resources.as_dict())
+          .ok()
+          .or(resources);
+      let data = filters::decoded_content(form);
+      self.scan(&data, form_resources, font);
+    }
+  }
+}
+
+/// Codes drawn with each font by the pages and the appearance streams of their annotations
+fn used_codes(document: &Document) -> BTreeMap<ObjectId, BTreeSet<u16>> {
+  let mut scanner = CodeScanner {
+    document,
+    codes: BTreeMap::new(),
+    visited_forms: BTreeSet::new(),
+  };
+  for page_id in document.get_pages().into_values() {
+    let resources = page::inherited_attribute(document, page_id, b"Resources")
+        .and_then(|resources| document.dereference(resources).ok())
+        .and_then(|(_, resources)| resources.as_dict().ok());
+    if let Ok(content) = page::page_content(document, page_id) {
+      if let Ok(data) = content.encode() {
+        scanner.scan(&data, resources, None);
+      }
+    }
+    for annotation_id in form::annotation_ids(document, page_id) {
+      let appearances = document
+          .get_dictionary(annotation_id)
+          .and_then(|annotation| annotation.get(b"AP"))
+          .and_then(|appearances| document.dereference(appearances))
+          .and_then(|(_, appearances)| appearances.as_dict());
+      for (_, appearance) in appearances.into_iter().flat_map(|appearances| appearances.iter()) {
+        // An appearance is a stream, or a dictionary of streams by appearance state
+        match document.dereference(appearance) {
+          Ok((_, Object::Dictionary(states))) => {
+            for state_id in states.iter().filter_map(|(_, state)| state.as_reference().ok()) {
+              scanner.scan_form(state_id, resources, None);
+            }
+          }
+          Ok((Some(appearance_id), Object::Stream(_))) => scanner.scan_form(appearance_id, resources, None),
+          _ => {}
+        }
+      }
+    }
+  }
+  scanner.codes
+}
+
+/// A TrueType font program subsetFonts can rewrite, with the fonts using it
+struct SubsetTarget {
+  /// Type 0 fonts with an Identity encoding, whose codes are CIDs
+  font_ids: BTreeSet<ObjectId>,
+  cid_font_ids: BTreeSet<ObjectId>,
+  descriptor_id: ObjectId,
+  /// Set when some use of the program can't be scanned, e.g. a non-Identity encoding
+  excluded: bool,
+}
+
+/// Font programs of the Type 0 fonts with a `CIDFontType2` descendant, by font file id
+fn subset_targets(document: &Document) -> BTreeMap<ObjectId, SubsetTarget> {
+  let mut targets: BTreeMap<ObjectId, SubsetTarget> = BTreeMap::new();
+  for (font_id, object) in document.objects.iter() {
+    let font = match object {
+      Object::Dictionary(font) if font.type_is(b"Font") => font,
+      _ => continue,
+    };
+    if font.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Type0") {
+      continue;
+    }
+    let cid_font_id = match font.get(b"DescendantFonts").and_then(|fonts| document.dereference(fonts)) {
+      Ok((_, Object::Array(fonts))) => match fonts.first().map(Object::as_reference) {
+        Some(Ok(cid_font_id)) => cid_font_id,
+        _ => continue,
+      },
+      _ => continue,
+    };
+    let cid_font = match document.get_dictionary(cid_font_id) {
+      Ok(cid_font) if cid_font.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"CIDFontType2") => cid_font,
+      _ => continue,
+    };
+    let descriptor_id = match cid_font.get(b"FontDescriptor").and_then(Object::as_reference) {
+      Ok(descriptor_id) => descriptor_id,
+      Err(_) => continue,
+    };
+    let file_id = match document
+        .get_dictionary(descriptor_id)
+        .and_then(|descriptor| descriptor.get(b"FontFile2"))
+        .and_then(Object::as_reference)
+    {
+      Ok(file_id) => file_id,
+      Err(_) => continue,
+    };
+    let identity = matches!(
+      font.get(b"Encoding").and_then(Object::as_name),
+      Ok(b"Identity-H") | Ok(b"Identity-V")
+    );
+    let target = targets.entry(file_id).or_insert_with(|| SubsetTarget {
+      font_ids: BTreeSet::new(),
+      cid_font_ids: BTreeSet::new(),
+      descriptor_id,
+      excluded: false,
+    });
+    target.font_ids.insert(*font_id);
+    target.cid_font_ids.insert(cid_font_id);
+    target.excluded |= !identity || target.descriptor_id != descriptor_id;
+  }
+  // Form fields are typed into with the default resources, any glyph may be needed
+  let form_fonts = document
+      .catalog()
+      .and_then(|catalog| catalog.get(b"AcroForm"))
+      .and_then(|acro_form| document.dereference(acro_form))
+      .and_then(|(_, acro_form)| acro_form.as_dict())
+      .and_then(|acro_form| acro_form.get(b"DR"))
+      .and_then(|resources| document.dereference(resources))
+      .and_then(|(_, resources)| resources.as_dict())
+      .and_then(|resources| resources.get(b"Font"))
+      .and_then(|fonts| document.dereference(fonts))
+      .and_then(|(_, fonts)| fonts.as_dict())
+      .map(|fonts| fonts.iter().filter_map(|(_, font)| font.as_reference().ok()).collect::<BTreeSet<_>>())
+      .unwrap_or_default();
+  for target in targets.values_mut() {
+    target.excluded |= target.cid_font_ids.len() > 1 || !target.font_ids.is_disjoint(&form_fonts);
+  }
+  targets.retain(|_, target| !target.excluded);
+  targets
+}
+
+/// Glyph of each CID, from a `CIDToGIDMap` stream or the identity mapping
+fn cid_to_gid(document: &Document, cid_font: &Dictionary) -> Option<Vec<u16>> {
+  match cid_font.get(b"CIDToGIDMap").and_then(|map| document.dereference(map)) {
+    Ok((_, Object::Stream(map))) => {
+      let data = filters::decoded_content(map);
+      Some(data.chunks(2).map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])).collect())
+    }
+    // `/Identity`, which is also the default
+    _ => None,
+  }
+}
+
+/// The `W` widths of the given CIDs
+fn cid_widths(document: &Document, widths: &Object, cids: &BTreeSet<u16>) -> BTreeMap<u16, Object> {
+  let mut result = BTreeMap::new();
+  let items = match document.dereference(widths) {
+    Ok((_, Object::Array(items))) => items,
+    _ => return result,
+  };
+  let mut index = 0;
+  while let Some(first) = items.get(index).and_then(|first| first.as_i64().ok()) {
+    match (items.get(index + 1), items.get(index + 2)) {
+      // `c [w1 w2 ...]`
+      (Some(Object::Array(run)), _) => {
+        for (offset, width) in run.iter().enumerate() {
+          if let Ok(cid) = u16::try_from(first + offset as i64) {
+            if cids.contains(&cid) {
+              result.insert(cid, width.clone());
+            }
+          }
+        }
+        index += 2;
+      }
+      // `c_first c_last w`
+      (Some(last), Some(width)) => {
+        let last = last.as_i64().unwrap_or(first);
+        let range = u16::try_from(first).unwrap_or(u16::MAX)..=u16::try_from(last).unwrap_or(u16::MAX);
+        for cid in cids.range(range) {
+          result.insert(*cid, width.clone());
+        }
+        index += 3;
+      }
+      _ => break,
+    }
+  }
+  result
+}
+
+/// A subset tag derived from the kept glyphs, so different subsets of a font get different names
+pub fn subset_tag(glyphs: &[u16]) -> String {
+  // FNV-1a
+  let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+  for glyph in glyphs {
+    for byte in glyph.to_be_bytes() {
+      hash ^= byte as u64;
+      hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+  }
+  (0..6)
+      .map(|index| (b'A' + ((hash >> (index * 5)) % 26) as u8) as char)
+      .collect()
+}
+
+fn tagged_name(document: &Document, id: ObjectId, key: &[u8], tag: &str) -> Option<Object> {
+  let name = document.get_dictionary(id).ok()?.get(key).ok()?.as_name_str().ok()?;
+  if is_subset_name(name) {
+    return None;
+  }
+  Some(Object::Name(format!("{}+{}", tag, name).into_bytes()))
+}
+
+/// Rewrite one font program with only the glyphs of `cids`. Returns false, leaving the document
+/// alone, when the program can't be read or the subset isn't smaller.
+fn subset_font(document: &mut Document, file_id: ObjectId, target: &SubsetTarget, cids: &BTreeSet<u16>) -> bool {
+  let cid_font_id = match target.cid_font_ids.iter().next() {
+    Some(cid_font_id) => *cid_font_id,
+    None => return false,
+  };
+  let (data, map, widths) = {
+    let file = match document.get_object(file_id) {
+      Ok(Object::Stream(file)) => file,
+      _ => return false,
+    };
+    let data = match filters::decode(file) {
+      Some(data) => data,
+      None => return false,
+    };
+    let cid_font = match document.get_dictionary(cid_font_id) {
+      Ok(cid_font) => cid_font,
+      Err(_) => return false,
+    };
+    let widths = cid_font.get(b"W").map(|widths| cid_widths(document, widths, cids)).ok();
+    (data, cid_to_gid(document, cid_font), widths)
+  };
+  let glyph = |cid: u16| match &map {
+    Some(map) => map.get(cid as usize).copied().unwrap_or(0),
+    None => cid,
+  };
+  let glyphs = cids.iter().map(|cid| glyph(*cid)).collect::<Vec<_>>();
+  let remapper = GlyphRemapper::new_from_glyphs_sorted(&glyphs);
+  let subset = match subsetter::subset(&data, 0, &remapper) {
+    Ok(subset) if subset.len() < data.len() => subset,
+    _ => return false,
+  };
+  let tag = subset_tag(&glyphs);
+
+  let mut new_map = vec![0; (*cids.iter().next_back().unwrap_or(&0) as usize + 1) * 2];
+  for cid in cids {
+    let new_glyph = remapper.get(glyph(*cid)).unwrap_or(0);
+    new_map[*cid as usize * 2..*cid as usize * 2 + 2].copy_from_slice(&new_glyph.to_be_bytes());
+  }
+  let map_id = document.add_object(Stream::new(Dictionary::new(), new_map));
+  let mut file_dictionary = Dictionary::new();
+  file_dictionary.set("Length1", subset.len() as i64);
+  document.objects.insert(file_id, Object::Stream(Stream::new(file_dictionary, subset)));
+
+  let font_name = tagged_name(document, cid_font_id, b"BaseFont", &tag);
+  let descriptor_name = tagged_name(document, target.descriptor_id, b"FontName", &tag);
+  if let Ok(descriptor) = document.get_object_mut(target.descriptor_id).and_then(Object::as_dict_mut) {
+    // The CIDs present in the program, stale once glyphs are removed
+    descriptor.remove(b"CIDSet");
+    if let Some(name) = descriptor_name {
+      descriptor.set("FontName", name);
+    }
+  }
+  if let Ok(cid_font) = document.get_object_mut(cid_font_id).and_then(Object::as_dict_mut) {
+    cid_font.set("CIDToGIDMap", map_id);
+    if let Some(widths) = widths {
+      let mut items = Vec::with_capacity(widths.len() * 2);
+      for (cid, width) in widths {
+        items.push((cid as i64).into());
+        items.push(vec![width].into());
+      }
+      cid_font.set("W", items);
+    }
+    if let Some(name) = font_name {
+      cid_font.set("BaseFont", name);
+    }
+  }
+  for font_id in target.font_ids.iter() {
+    if let Some(name) = tagged_name(document, *font_id, b"BaseFont", &tag) {
+      if let Ok(font) = document.get_object_mut(*font_id).and_then(Object::as_dict_mut) {
+        font.set("BaseFont", name);
+      }
+    }
+  }
+  true
+}
+
+#[js_function(2)]
+pub fn subset_fonts(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(1)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  subset_fonts_in(&mut document);
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Shrink the embedded TrueType programs of Type 0 fonts to the glyphs the pages and annotation
+/// appearances draw. Codes stay the same, the `CIDToGIDMap` points them at the renumbered glyphs
+/// and `W` only keeps their widths. Simple fonts are left alone, as are the fonts of the form
+/// default resources and those used with a non-Identity CMap.
+pub fn subset_fonts_in(document: &mut Document) {
+  let targets = subset_targets(document);
+  let codes = used_codes(document);
+  let mut subset = false;
+  for (file_id, target) in targets.iter() {
+    let mut cids = target
+        .font_ids
+        .iter()
+        .filter_map(|font_id| codes.get(font_id))
+        .flatten()
+        .copied()
+        .collect::<BTreeSet<u16>>();
+    cids.insert(0);
+    subset |= subset_font(document, *file_id, target, &cids);
+  }
+  if subset {
+    // Replaced `CIDToGIDMap` streams
+    document.prune_objects();
+  }
+}
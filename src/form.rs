@@ -0,0 +1,571 @@
+use std::collections::BTreeSet;
+
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use napi::{CallContext, JsBuffer, JsObject, JsUnknown, Result};
+
+use crate::error::{self, ErrorCode, OrThrow, PdfError};
+use crate::page;
+use crate::utils::{buffer_value, decode_text_string, encode_text_string, load_document, output, output_update};
+
+/// A terminal form field and the widget annotations displaying it
+pub struct Field {
+  pub id: ObjectId,
+  /// Fully qualified name, e.g. `address.city`
+  pub name: String,
+  /// Field (or `AcroForm.Fields`) array the field is listed in
+  pub parent: Option<ObjectId>,
+  pub widgets: Vec<ObjectId>,
+}
+
+/// Id of the `AcroForm` dictionary when it's an indirect object
+fn acro_form_id(document: &Document) -> Option<ObjectId> {
+  document
+      .catalog()
+      .ok()?
+      .get(b"AcroForm")
+      .and_then(Object::as_reference)
+      .ok()
+}
+
+/// Root field references listed in `/AcroForm /Fields`
+pub fn root_fields(document: &Document) -> Vec<ObjectId> {
+  document
+      .catalog()
+      .ok()
+      .and_then(|catalog| catalog.get(b"AcroForm").ok())
+      .and_then(|acro_form| document.dereference(acro_form).ok())
+      .and_then(|(_, acro_form)| acro_form.as_dict().ok())
+      .and_then(|acro_form| acro_form.get(b"Fields").ok())
+      .and_then(|fields| document.dereference(fields).ok())
+      .and_then(|(_, fields)| fields.as_array().ok())
+      .map(|fields| fields.iter().filter_map(|field| field.as_reference().ok()).collect())
+      .unwrap_or_default()
+}
+
+/// Whether the form is only described by its XFA stream, the `/AcroForm` listing no fields or the
+/// catalog's `/NeedsRendering` asking viewers to lay the pages out from the XFA
+pub fn is_xfa_only(document: &Document) -> bool {
+  let catalog = match document.catalog() {
+    Ok(catalog) => catalog,
+    Err(_) => return false,
+  };
+  let has_xfa = catalog
+      .get(b"AcroForm")
+      .and_then(|acro_form| document.dereference(acro_form))
+      .and_then(|(_, acro_form)| acro_form.as_dict())
+      .is_ok_and(|acro_form| acro_form.has(b"XFA"));
+  let needs_rendering = catalog.get(b"NeedsRendering").and_then(Object::as_bool).unwrap_or(false);
+  has_xfa && (needs_rendering || root_fields(document).is_empty())
+}
+
+/// Fail on XFA only forms, whose fields the AcroForm operations would silently leave alone
+fn refuse_xfa_only(document: &Document) -> error::Result<()> {
+  if is_xfa_only(document) {
+    return Err(PdfError::new(
+      ErrorCode::XfaForm,
+      "The form is XFA only (dynamic), it has no AcroForm fields to change",
+    ));
+  }
+  Ok(())
+}
+
+/// Walk the field tree and collect every terminal field
+pub fn collect_fields(document: &Document) -> Vec<Field> {
+  fn walk(
+    document: &Document,
+    field_id: ObjectId,
+    parent: Option<ObjectId>,
+    prefix: Option<&str>,
+    visited: &mut BTreeSet<ObjectId>,
+    fields: &mut Vec<Field>,
+  ) {
+    if !visited.insert(field_id) {
+      return;
+    }
+    let dictionary = match document.get_dictionary(field_id) {
+      Ok(dictionary) => dictionary,
+      Err(_) => return,
+    };
+    let name = match dictionary.get(b"T").and_then(Object::as_str) {
+      Ok(partial) => {
+        let partial = decode_text_string(partial);
+        match prefix {
+          Some(prefix) => format!("{}.{}", prefix, partial),
+          None => partial,
+        }
+      }
+      Err(_) => prefix.unwrap_or_default().to_owned(),
+    };
+    let kids = dictionary
+        .get(b"Kids")
+        .and_then(Object::as_array)
+        .map(|kids| kids.iter().filter_map(|kid| kid.as_reference().ok()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    // Kids with a `/T` are child fields, the others are widgets of this field
+    let (children, widgets): (Vec<ObjectId>, Vec<ObjectId>) = kids.into_iter().partition(|kid| {
+      document
+          .get_dictionary(*kid)
+          .map(|kid| kid.has(b"T"))
+          .unwrap_or(false)
+    });
+    for child in children.iter() {
+      walk(document, *child, Some(field_id), Some(&name), visited, fields);
+    }
+    if children.is_empty() || !widgets.is_empty() {
+      let widgets = if widgets.is_empty() && dictionary.has(b"Rect") {
+        // Field and widget merged into a single dictionary
+        vec![field_id]
+      } else {
+        widgets
+      };
+      fields.push(Field {
+        id: field_id,
+        name,
+        parent,
+        widgets,
+      });
+    }
+  }
+  let mut visited = BTreeSet::new();
+  let mut fields = vec![];
+  for field_id in root_fields(document) {
+    walk(document, field_id, None, None, &mut visited, &mut fields);
+  }
+  fields
+}
+
+/// Field attribute that may be inherited from ancestor fields (`FT`, `Ff`, `V`, `DA`)
+pub fn inherited_field_attribute<'a>(document: &'a Document, field_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+  page::inherited_attribute(document, field_id, key)
+}
+
+#[js_function(1)]
+pub fn get_form_fields(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  let fields = collect_fields(&document);
+  let mut result = ctx.env.create_array_with_length(fields.len())?;
+  for (index, field) in fields.iter().enumerate() {
+    let mut item = ctx.env.create_object()?;
+    item.set_named_property("name", ctx.env.create_string(&field.name)?)?;
+    let field_type = inherited_field_attribute(&document, field.id, b"FT")
+        .and_then(|value| value.as_name_str().ok())
+        .unwrap_or("");
+    item.set_named_property("type", ctx.env.create_string(field_type)?)?;
+    let flags = inherited_field_attribute(&document, field.id, b"Ff")
+        .and_then(|value| value.as_i64().ok())
+        .unwrap_or(0);
+    item.set_named_property("flags", ctx.env.create_int64(flags)?)?;
+    match inherited_field_attribute(&document, field.id, b"V") {
+      Some(Object::String(value, _)) => {
+        item.set_named_property("value", ctx.env.create_string(&decode_text_string(value))?)?
+      }
+      Some(Object::Name(value)) => {
+        item.set_named_property("value", ctx.env.create_string(&String::from_utf8_lossy(value))?)?
+      }
+      _ => {}
+    }
+    result.set_element(index as u32, item)?;
+  }
+  Ok(result)
+}
+
+#[js_function(3)]
+pub fn flatten_fields(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let field_names = ctx.get::<Vec<String>>(1)?;
+  let output = output(&ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  flatten_fields_in(&mut document, &field_names).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Page displaying a widget, from its `/P` entry or by searching the pages' `/Annots`
+fn widget_page(document: &Document, widget_id: ObjectId) -> Option<ObjectId> {
+  let pages = document.get_pages();
+  if let Ok(page_id) = document
+      .get_dictionary(widget_id)
+      .and_then(|widget| widget.get(b"P"))
+      .and_then(Object::as_reference)
+  {
+    if pages.values().any(|id| *id == page_id) {
+      return Some(page_id);
+    }
+  }
+  pages.into_values().find(|page_id| annotation_ids(document, *page_id).contains(&widget_id))
+}
+
+/// References listed in the `/Annots` array of a page
+pub fn annotation_ids(document: &Document, page_id: ObjectId) -> Vec<ObjectId> {
+  document
+      .get_dictionary(page_id)
+      .ok()
+      .and_then(|page| page.get(b"Annots").ok())
+      .and_then(|annots| document.dereference(annots).ok())
+      .and_then(|(_, annots)| annots.as_array().ok())
+      .map(|annots| annots.iter().filter_map(|annot| annot.as_reference().ok()).collect())
+      .unwrap_or_default()
+}
+
+/// Remove `item` from the array stored under `key` of a dictionary, following a reference to the array
+pub fn remove_from_array(document: &mut Document, owner_id: ObjectId, key: &[u8], item: ObjectId) -> error::Result<()> {
+  let array = match document.get_dictionary(owner_id)?.get(key) {
+    Ok(Object::Reference(array_id)) => {
+      let array_id = *array_id;
+      document.get_object_mut(array_id)?
+    }
+    Ok(_) => document.get_object_mut(owner_id)?.as_dict_mut()?.get_mut(key)?,
+    Err(_) => return Ok(()),
+  };
+  if let Ok(array) = array.as_array_mut() {
+    array.retain(|value| value.as_reference().ok() != Some(item));
+  }
+  Ok(())
+}
+
+/// Normal appearance stream of an annotation, honoring the `/AS` state for multi-state widgets
+pub fn normal_appearance(document: &Document, annotation_id: ObjectId) -> Option<ObjectId> {
+  let annotation = document.get_dictionary(annotation_id).ok()?;
+  let appearance = annotation.get(b"AP").ok()?;
+  let (_, appearance) = document.dereference(appearance).ok()?;
+  let normal = appearance.as_dict().ok()?.get(b"N").ok()?;
+  match normal {
+    Object::Reference(id) => match document.get_object(*id).ok()? {
+      Object::Stream(_) => Some(*id),
+      Object::Dictionary(states) => {
+        let state = annotation.get(b"AS").and_then(Object::as_name).ok()?;
+        states.get(state).and_then(Object::as_reference).ok()
+      }
+      _ => None,
+    },
+    Object::Dictionary(states) => {
+      let state = annotation.get(b"AS").and_then(Object::as_name).ok()?;
+      states.get(state).and_then(Object::as_reference).ok()
+    }
+    _ => None,
+  }
+}
+
+/// Draw the normal appearance of an annotation into the page content at its `/Rect`
+pub fn draw_appearance(document: &mut Document, page_id: ObjectId, annotation_id: ObjectId) -> error::Result<()> {
+  let annotation = document.get_dictionary(annotation_id)?;
+  // Hidden (bit 2) annotations are never rendered
+  let flags = annotation.get(b"F").and_then(Object::as_i64).unwrap_or(0);
+  if flags & 2 != 0 {
+    return Ok(());
+  }
+  let rect = match annotation.get(b"Rect").ok().and_then(page::rect_from_object) {
+    Some(rect) => rect,
+    None => return Ok(()),
+  };
+  let appearance_id = match normal_appearance(document, annotation_id) {
+    Some(id) => id,
+    None => return Ok(()),
+  };
+  let appearance = document.get_object_mut(appearance_id)?.as_stream_mut()?;
+  appearance.dict.set("Type", Object::Name(b"XObject".to_vec()));
+  appearance.dict.set("Subtype", Object::Name(b"Form".to_vec()));
+  let bbox = appearance
+      .dict
+      .get(b"BBox")
+      .ok()
+      .and_then(page::rect_from_object)
+      .unwrap_or(rect);
+  let matrix = appearance
+      .dict
+      .get(b"Matrix")
+      .and_then(Object::as_array)
+      .ok()
+      .and_then(|values| values.iter().map(|value| value.as_float().ok()).collect::<Option<Vec<f64>>>())
+      .filter(|values| values.len() == 6)
+      .unwrap_or_else(|| vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+  // Map the transformed bounding box onto the annotation rectangle
+  let corners = [
+    (bbox[0], bbox[1]),
+    (bbox[2], bbox[1]),
+    (bbox[0], bbox[3]),
+    (bbox[2], bbox[3]),
+  ]
+  .iter()
+  .map(|&(x, y)| {
+    (
+      matrix[0] * x + matrix[2] * y + matrix[4],
+      matrix[1] * x + matrix[3] * y + matrix[5],
+    )
+  })
+  .collect::<Vec<_>>();
+  let min_x = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min);
+  let max_x = corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max);
+  let min_y = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min);
+  let max_y = corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max);
+  let scale_x = if max_x > min_x { (rect[2] - rect[0]) / (max_x - min_x) } else { 1.0 };
+  let scale_y = if max_y > min_y { (rect[3] - rect[1]) / (max_y - min_y) } else { 1.0 };
+  let name = page::add_resource(document, page_id, b"XObject", "FlatAP", appearance_id)?;
+  let content = Content {
+    operations: vec![
+      Operation::new(
+        "cm",
+        vec![
+          scale_x.into(),
+          0.into(),
+          0.into(),
+          scale_y.into(),
+          (rect[0] - scale_x * min_x).into(),
+          (rect[1] - scale_y * min_y).into(),
+        ],
+      ),
+      Operation::new("Do", vec![Object::Name(name)]),
+    ],
+  }
+  .encode()?;
+  page::append_content(document, page_id, content)
+}
+
+/// Make the catalog's `/AcroForm` an indirect object, creating an empty one when missing
+pub fn ensure_acro_form(document: &mut Document) -> error::Result<ObjectId> {
+  if let Some(acro_form_id) = acro_form_id(document) {
+    return Ok(acro_form_id);
+  }
+  let acro_form = match document.catalog()?.get(b"AcroForm") {
+    Ok(Object::Dictionary(acro_form)) => acro_form.clone(),
+    _ => {
+      let mut acro_form = Dictionary::new();
+      acro_form.set("Fields", Vec::<Object>::new());
+      acro_form
+    }
+  };
+  let acro_form_id = document.add_object(acro_form);
+  let root_id = document.trailer.get(b"Root")?.as_reference()?;
+  document
+      .get_object_mut(root_id)?
+      .as_dict_mut()?
+      .set("AcroForm", acro_form_id);
+  Ok(acro_form_id)
+}
+
+/// Remove a field reference from its parent's `/Kids` or from `/AcroForm /Fields`
+fn detach_field(document: &mut Document, field: &Field) -> error::Result<()> {
+  match field.parent {
+    Some(parent_id) => remove_from_array(document, parent_id, b"Kids", field.id),
+    None => {
+      let acro_form_id = ensure_acro_form(document)?;
+      remove_from_array(document, acro_form_id, b"Fields", field.id)
+    }
+  }
+}
+
+/// Render the named fields into the page content and remove them from the form.
+/// Unknown names are ignored.
+pub fn flatten_fields_in(document: &mut Document, field_names: &[String]) -> error::Result<()> {
+  refuse_xfa_only(document)?;
+  let fields = collect_fields(document)
+      .into_iter()
+      .filter(|field| field_names.contains(&field.name))
+      .collect::<Vec<_>>();
+  for field in fields.iter() {
+    for widget_id in field.widgets.iter() {
+      if let Some(page_id) = widget_page(document, *widget_id) {
+        draw_appearance(document, page_id, *widget_id)?;
+        remove_from_array(document, page_id, b"Annots", *widget_id)?;
+      }
+    }
+    detach_field(document, field)?;
+    for widget_id in field.widgets.iter() {
+      document.objects.remove(widget_id);
+    }
+    document.objects.remove(&field.id);
+  }
+  Ok(())
+}
+
+/// `/Ff` bit 1: the field can't be changed by the user
+const READ_ONLY_FLAG: i64 = 1;
+
+#[js_function(4)]
+pub fn set_field_read_only(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let field_names = ctx.get::<Vec<String>>(1)?;
+  let read_only = ctx.get::<bool>(2)?;
+  let output = output(&ctx.get::<Option<JsObject>>(3)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  set_field_read_only_in(&mut document, &field_names, read_only).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Toggle the read-only flag of the named fields, keeping their other flag bits
+pub fn set_field_read_only_in(document: &mut Document, field_names: &[String], read_only: bool) -> error::Result<()> {
+  refuse_xfa_only(document)?;
+  let fields = collect_fields(document)
+      .into_iter()
+      .filter(|field| field_names.contains(&field.name))
+      .collect::<Vec<_>>();
+  for field in fields {
+    // `/Ff` may be inherited, write the effective value on the field itself
+    let flags = inherited_field_attribute(document, field.id, b"Ff")
+        .and_then(|value| value.as_i64().ok())
+        .unwrap_or(0);
+    let flags = if read_only {
+      flags | READ_ONLY_FLAG
+    } else {
+      flags & !READ_ONLY_FLAG
+    };
+    document.get_object_mut(field.id)?.as_dict_mut()?.set("Ff", flags);
+  }
+  Ok(())
+}
+
+/// Id and fully qualified name of every field node, intermediate ones included
+fn named_fields(document: &Document) -> Vec<(ObjectId, String)> {
+  fn walk(
+    document: &Document, field_id: ObjectId, prefix: Option<&str>, visited: &mut BTreeSet<ObjectId>,
+    fields: &mut Vec<(ObjectId, String)>,
+  ) {
+    if !visited.insert(field_id) {
+      return;
+    }
+    let dictionary = match document.get_dictionary(field_id) {
+      Ok(dictionary) => dictionary,
+      Err(_) => return,
+    };
+    // Kids without a `/T` are widgets
+    let partial = match dictionary.get(b"T").and_then(Object::as_str) {
+      Ok(partial) => decode_text_string(partial),
+      Err(_) => return,
+    };
+    let name = match prefix {
+      Some(prefix) => format!("{}.{}", prefix, partial),
+      None => partial,
+    };
+    if let Ok(kids) = dictionary.get(b"Kids").and_then(Object::as_array) {
+      for kid in kids.iter().filter_map(|kid| kid.as_reference().ok()) {
+        walk(document, kid, Some(&name), visited, fields);
+      }
+    }
+    fields.push((field_id, name));
+  }
+  let mut visited = BTreeSet::new();
+  let mut fields = vec![];
+  for field_id in root_fields(document) {
+    walk(document, field_id, None, &mut visited, &mut fields);
+  }
+  fields
+}
+
+#[js_function(4)]
+pub fn rename_field(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let old_name = ctx.get::<String>(1)?;
+  let new_name = ctx.get::<String>(2)?;
+  let output = output(&ctx.get::<Option<JsObject>>(3)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  rename_field_in(&mut document, &old_name, &new_name).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Rename a field, or a group of fields and everything under it, by changing its partial name `/T`.
+/// Fields and widgets reference each other by object, so nothing else needs updating. The new name
+/// keeps the parent of the old one, only the last part of the name changes.
+pub fn rename_field_in(document: &mut Document, old_name: &str, new_name: &str) -> error::Result<()> {
+  refuse_xfa_only(document)?;
+  let fields = named_fields(document);
+  let field_id = fields
+      .iter()
+      .find(|(_, name)| name == old_name)
+      .map(|(id, _)| *id)
+      .ok_or_else(|| PdfError::new(ErrorCode::GenericFailure, format!("No field named '{}'", old_name)))?;
+  let parent = |name: &str| name.rsplit_once('.').map(|(parent, _)| parent.to_owned());
+  if parent(old_name) != parent(new_name) {
+    return Err(PdfError::new(
+      ErrorCode::GenericFailure,
+      format!(
+        "'{}' and '{}' have different parents, only the last part of a field name can be renamed",
+        old_name, new_name
+      ),
+    ));
+  }
+  let partial = new_name.rsplit('.').next().unwrap_or(new_name);
+  if partial.is_empty() {
+    return Err(PdfError::new(ErrorCode::GenericFailure, "A field name can't be empty"));
+  }
+  let taken = fields
+      .iter()
+      .any(|(id, name)| *id != field_id && (name == new_name || name.starts_with(&format!("{}.", new_name))));
+  if taken {
+    return Err(PdfError::new(
+      ErrorCode::GenericFailure,
+      format!("A field named '{}' already exists", new_name),
+    ));
+  }
+  document
+      .get_object_mut(field_id)
+      .and_then(Object::as_dict_mut)?
+      .set("T", encode_text_string(partial));
+  Ok(())
+}
+
+#[js_function(3)]
+pub fn set_need_appearances(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let value = ctx.get::<bool>(1)?;
+  let output = output(&ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  set_need_appearances_in(&mut document, value).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Set or clear `/NeedAppearances`, which asks viewers to draw the fields from their values.
+/// A form without its `/AcroForm` gets one, listing the fields of the widgets found on the pages.
+pub fn set_need_appearances_in(document: &mut Document, value: bool) -> error::Result<()> {
+  let has_acro_form = document.catalog()?.has(b"AcroForm");
+  if !has_acro_form && !value {
+    return Ok(());
+  }
+  let acro_form_id = ensure_acro_form(document)?;
+  if !has_acro_form {
+    let fields = widget_root_fields(document).into_iter().map(Object::Reference).collect::<Vec<_>>();
+    document
+        .get_object_mut(acro_form_id)
+        .and_then(Object::as_dict_mut)?
+        .set("Fields", fields);
+  }
+  let acro_form = document.get_object_mut(acro_form_id).and_then(Object::as_dict_mut)?;
+  if value {
+    acro_form.set("NeedAppearances", true);
+  } else {
+    acro_form.remove(b"NeedAppearances");
+  }
+  Ok(())
+}
+
+/// Root fields of the widget annotations of the pages, following their `/Parent` chains
+fn widget_root_fields(document: &Document) -> Vec<ObjectId> {
+  let mut roots = vec![];
+  for page_id in document.get_pages().into_values() {
+    for annotation_id in annotation_ids(document, page_id) {
+      let subtype = document
+          .get_dictionary(annotation_id)
+          .and_then(|annotation| annotation.get(b"Subtype"))
+          .and_then(Object::as_name);
+      if subtype.ok() != Some(b"Widget") {
+        continue;
+      }
+      let mut field_id = annotation_id;
+      let mut visited = BTreeSet::new();
+      while let Ok(parent_id) = document
+          .get_dictionary(field_id)
+          .and_then(|field| field.get(b"Parent"))
+          .and_then(Object::as_reference)
+      {
+        if !visited.insert(parent_id) {
+          break;
+        }
+        field_id = parent_id;
+      }
+      if !roots.contains(&field_id) {
+        roots.push(field_id);
+      }
+    }
+  }
+  roots
+}
@@ -0,0 +1,97 @@
+use lopdf::content::{Content, Operation};
+use lopdf::{Document, Object};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status};
+
+use crate::error::{self, OrThrow};
+use crate::font::{Font, FontWriter};
+use crate::page;
+use crate::utils::{buffer_value, load_document, output_update, Output};
+
+pub struct HeaderFooterOptions {
+  header: Option<String>,
+  footer: Option<String>,
+  font: Font,
+  font_size: f64,
+  /// Distance of the text from the top/bottom edge of the page, in `coordinate_space`
+  margin: f64,
+  coordinate_space: page::CoordinateSpace,
+  pages: page::PageSelector,
+  output: Output,
+}
+
+impl HeaderFooterOptions {
+  pub fn from_js(options: JsObject) -> Result<Self> {
+    let font_size = options.get_named_property::<Option<f64>>("fontSize")?.unwrap_or(10.0);
+    if font_size <= 0.0 {
+      return Err(Error::new(Status::InvalidArg, "fontSize must be positive".to_owned()));
+    }
+    Ok(HeaderFooterOptions {
+      header: options.get_named_property::<Option<String>>("header")?,
+      footer: options.get_named_property::<Option<String>>("footer")?,
+      font: Font::from_js(&options)?,
+      font_size,
+      margin: options.get_named_property::<Option<f64>>("margin")?.unwrap_or(36.0),
+      coordinate_space: page::CoordinateSpace::from_js(&options)?,
+      pages: page::PageSelector::from_js(&options)?,
+      output: Output::from_js(&options)?,
+    })
+  }
+}
+
+#[js_function(2)]
+pub fn add_header_footer(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let options = HeaderFooterOptions::from_js(ctx.get::<JsObject>(1)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  add_header_footer_to(&mut document, &options).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &options.output)
+}
+
+/// Replace `{page}`, `{total}` and `{date}` in a header/footer template
+fn expand_placeholders(template: &str, page_number: usize, total: usize, date: &str) -> String {
+  template
+      .replace("{page}", &page_number.to_string())
+      .replace("{total}", &total.to_string())
+      .replace("{date}", date)
+}
+
+pub fn add_header_footer_to(document: &mut Document, options: &HeaderFooterOptions) -> error::Result<()> {
+  if options.header.is_none() && options.footer.is_none() {
+    return Ok(());
+  }
+  // `{page}` and `{total}` count every page, not only the selected ones
+  let total = document.get_pages().len();
+  let pages = options.pages.select(document)?;
+  let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+  let mut font = FontWriter::new(document, &options.font)?;
+  for (page_number, page_id) in pages {
+    let font_name = page::add_resource(document, page_id, b"Font", "FHF", font.id())?;
+    // In display space, the default, rotated pages still read upright
+    let ((width, height), matrix) = options.coordinate_space.frame(document, page_id);
+    let mut operations = vec![Operation::new("cm", matrix.iter().map(|&value| value.into()).collect())];
+    let lines = [
+      (&options.header, height - options.margin),
+      (&options.footer, options.margin - options.font_size),
+    ];
+    for (template, baseline) in lines.iter() {
+      if let Some(template) = template {
+        let text = expand_placeholders(template, page_number as usize, total, &date);
+        let (encoded, text_width) = font.encode(&text, options.font_size)?;
+        let x = (width - text_width) / 2.0;
+        operations.extend(vec![
+          Operation::new("BT", vec![]),
+          Operation::new(
+            "Tf",
+            vec![Object::Name(font_name.clone()), options.font_size.into()],
+          ),
+          Operation::new("Td", vec![x.into(), (*baseline).into()]),
+          Operation::new("Tj", vec![encoded]),
+          Operation::new("ET", vec![]),
+        ]);
+      }
+    }
+    let content = Content { operations }.encode()?;
+    page::append_content(document, page_id, content)?;
+  }
+  font.finish(document)
+}
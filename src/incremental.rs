@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+
+use lopdf::{Document, Object};
+
+use crate::dedupe::same_object;
+use crate::error::{ErrorCode, PdfError, Result};
+use crate::object_streams::XREF_STREAM_KEYS;
+use crate::producer;
+use crate::utils::{load_document, write_indirect_object, write_object, SaveOptions};
+
+/// The access permissions (`/P` of the DocMDP transform) when the document is certified:
+/// 1 allows no change, 2 form filling and signing, 3 also annotations
+pub fn docmdp_permissions(document: &Document) -> Option<i64> {
+  let perms = document
+      .catalog()
+      .ok()?
+      .get(b"Perms")
+      .and_then(|perms| document.dereference(perms))
+      .and_then(|(_, perms)| perms.as_dict())
+      .ok()?;
+  let signature = perms
+      .get(b"DocMDP")
+      .and_then(|signature| document.dereference(signature))
+      .and_then(|(_, signature)| signature.as_dict())
+      .ok()?;
+  let references = match signature.get(b"Reference").and_then(|references| document.dereference(references)) {
+    Ok((_, Object::Array(references))) => references.clone(),
+    _ => vec![],
+  };
+  let params = references
+      .iter()
+      .filter_map(|reference| document.dereference(reference).and_then(|(_, reference)| reference.as_dict()).ok())
+      .find(|reference| matches!(reference.get(b"TransformMethod"), Ok(Object::Name(name)) if name == b"DocMDP"))
+      .and_then(|reference| reference.get(b"TransformParams").ok())
+      .and_then(|params| document.dereference(params).and_then(|(_, params)| params.as_dict()).ok());
+  // P defaults to 2 when the signature doesn't say
+  Some(params.and_then(|params| params.get(b"P").and_then(Object::as_i64).ok()).unwrap_or(2))
+}
+
+/// Object and cross-reference streams are containers, their objects were unpacked on load
+fn is_container(object: &Object) -> bool {
+  match object {
+    Object::Stream(stream) => stream.dict.type_is(b"ObjStm") || stream.dict.type_is(b"XRef"),
+    _ => false,
+  }
+}
+
+/// Offset of the last cross-reference section, from the final `startxref`
+fn last_startxref(bytes: &[u8]) -> Option<usize> {
+  let keyword = b"startxref";
+  let start = bytes.windows(keyword.len()).rposition(|window| window == keyword)? + keyword.len();
+  let digits = bytes[start..]
+      .iter()
+      .skip_while(|byte| byte.is_ascii_whitespace())
+      .take_while(|byte| byte.is_ascii_digit())
+      .map(|&byte| byte as char)
+      .collect::<String>();
+  digits.parse().ok()
+}
+
+/// Serialize the objects changed since `source` was parsed and append them to it with a new
+/// cross-reference section, so the original bytes and any signature over them stay intact.
+/// The original objects are kept as they are, so `noObjectStreams` has no effect. The producer is
+/// only written when there is something to update.
+pub fn save_incremental(source: &[u8], document: &mut Document, save: SaveOptions) -> Result<Vec<u8>> {
+  if docmdp_permissions(document) == Some(1) {
+    return Err(PdfError::new(
+      ErrorCode::CertifiedDocument,
+      "The document is certified with no changes allowed",
+    ));
+  }
+  let original = load_document(source)?;
+  let prev = last_startxref(source)
+      .ok_or_else(|| PdfError::new(ErrorCode::InvalidPdf, "Invalid PDF: no startxref"))?;
+
+  let mut update = Document::with_version(document.version.clone());
+  for (id, object) in document.objects.iter() {
+    let unchanged = original.objects.get(id).is_some_and(|before| same_object(before, object));
+    if !unchanged && !is_container(object) {
+      update.objects.insert(*id, object.clone());
+    }
+  }
+  // Removed objects become free entries, with the generation to use if the number is reused
+  let freed = original
+      .objects
+      .iter()
+      .filter(|(id, object)| !document.objects.contains_key(id) && !is_container(object))
+      .map(|(&(id, generation), _)| (id, generation.saturating_add(1)))
+      .collect::<Vec<_>>();
+  let trailer_changed = !same_object(
+    &Object::Dictionary(original.trailer.clone()),
+    &Object::Dictionary(document.trailer.clone()),
+  );
+  if update.objects.is_empty() && freed.is_empty() && !trailer_changed {
+    return Ok(source.to_vec());
+  }
+  // The update is a rewrite like any other and names the producer too. The stamp keeps the Info
+  // dictionary in an object of its own, added when the trailer had none
+  producer::stamp(document, &save.producer)?;
+  if let Ok(info_id) = document.trailer.get(b"Info").and_then(Object::as_reference) {
+    if let Ok(info) = document.get_object(info_id) {
+      update.objects.insert(info_id, info.clone());
+    }
+  }
+  // Only the new streams are compressed, the others must keep their original bytes
+  if !save.no_compression {
+    update.compress();
+  }
+
+  // The trailer of the update carries the document entries and points back at the original section.
+  // When the original used a cross-reference stream, its stream entries don't belong in a classic trailer.
+  let mut trailer = document.trailer.clone();
+  for key in XREF_STREAM_KEYS.iter() {
+    trailer.remove(key);
+  }
+  trailer.set("Prev", prev as i64);
+  update.trailer = trailer;
+  let size = original.trailer.get(b"Size").and_then(Object::as_i64).unwrap_or(0).max(i64::from(document.max_id) + 1);
+  update.trailer.set("Size", size);
+
+  let mut target = source.to_vec();
+  if !target.ends_with(b"\n") {
+    target.push(b'\n');
+  }
+  let mut entries = BTreeMap::new();
+  for (&(id, generation), object) in update.objects.iter() {
+    let offset = write_indirect_object(&mut target, (id, generation), object)?;
+    entries.insert(id, (offset, generation, 'n'));
+  }
+  for (id, generation) in freed {
+    entries.insert(id, (0, generation, 'f'));
+  }
+  // A section needs an entry, restate the head of the free list when only the trailer changed
+  if entries.is_empty() {
+    entries.insert(0, (0, 65535, 'f'));
+  }
+
+  let new_xref = target.len();
+  target.extend_from_slice(b"xref\n");
+  let entries = entries.into_iter().collect::<Vec<(u32, (usize, u16, char))>>();
+  for run in entries.chunk_by(|(a, _), (b, _)| a + 1 == *b) {
+    target.extend_from_slice(format!("{} {}\n", run[0].0, run.len()).as_bytes());
+    for (_, (offset, generation, kind)) in run {
+      target.extend_from_slice(format!("{:>010} {:>05} {} \n", offset, generation, kind).as_bytes());
+    }
+  }
+  target.extend_from_slice(b"trailer\n");
+  write_object(&mut target, &Object::Dictionary(update.trailer))?;
+  target.extend_from_slice(format!("\nstartxref\n{}\n%%EOF\n", new_xref).as_bytes());
+  Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+  use lopdf::Dictionary;
+
+  use super::*;
+  use crate::producer::ProducerInfo;
+  use crate::test_utils;
+  use crate::utils::{decode_text_string, refuse_certified, save_document};
+
+  fn bytes(document: &mut Document) -> Vec<u8> {
+    let mut bytes = vec![];
+    document.save_to(&mut bytes).unwrap();
+    bytes
+  }
+
+  /// A document certified with the DocMDP permissions `p`
+  fn certified(p: i64) -> Document {
+    let mut document = test_utils::document(1);
+    let mut params = Dictionary::new();
+    params.set("P", p);
+    let mut reference = Dictionary::new();
+    reference.set("TransformMethod", Object::Name(b"DocMDP".to_vec()));
+    reference.set("TransformParams", params);
+    let mut signature = Dictionary::new();
+    signature.set("Type", Object::Name(b"Sig".to_vec()));
+    signature.set("Reference", vec![Object::Dictionary(reference)]);
+    let signature_id = document.add_object(signature);
+    let mut perms = Dictionary::new();
+    perms.set("DocMDP", signature_id);
+    test_utils::catalog_mut(&mut document).set("Perms", perms);
+    document
+  }
+
+  /// Change the text of the first page
+  fn edit(document: &mut Document) {
+    let page_id = document.get_pages()[&1];
+    document.change_page_content(page_id, b"BT /F1 24 Tf 72 700 Td (Edited) Tj ET".to_vec()).unwrap();
+  }
+
+  #[test]
+  fn appends_the_changed_objects_to_the_original_bytes() {
+    let source = bytes(&mut test_utils::document(2));
+    let mut document = load_document(&source).unwrap();
+    edit(&mut document);
+    let updated = save_incremental(&source, &mut document, SaveOptions::default()).unwrap();
+    assert_eq!(&updated[..source.len()], &source[..]);
+
+    let update = &updated[source.len()..];
+    // The content stream and the Info dictionary naming the producer are the only changed objects
+    assert_eq!(update.windows(4).filter(|window| window == b" obj").count(), 2);
+    let reloaded = Document::load_mem(&updated).unwrap();
+    let pages = reloaded.get_pages();
+    assert_eq!(test_utils::page_text(&reloaded, pages[&1]), "Edited");
+    assert_eq!(test_utils::page_text(&reloaded, pages[&2]), "Page 2");
+    let prev = format!("/Prev {}", last_startxref(&source).unwrap());
+    assert!(update.windows(prev.len()).any(|window| window == prev.as_bytes()));
+  }
+
+  #[test]
+  fn points_the_xref_entries_at_the_written_objects() {
+    let source = bytes(&mut test_utils::document(1));
+    let mut document = load_document(&source).unwrap();
+    edit(&mut document);
+    let font_id = document.add_object(Dictionary::new());
+    let updated = save_incremental(&source, &mut document, SaveOptions::default()).unwrap();
+    let xref = last_startxref(&updated).unwrap();
+    let table = std::str::from_utf8(&updated[xref..]).unwrap();
+    let mut lines = table.lines().skip(1);
+    while let Some(header) = lines.next() {
+      if header == "trailer" {
+        break;
+      }
+      let (first, count) = header.split_once(' ').unwrap();
+      for id in first.parse::<u32>().unwrap()..first.parse::<u32>().unwrap() + count.parse::<u32>().unwrap() {
+        let entry = lines.next().unwrap();
+        let offset = entry[..10].parse::<usize>().unwrap();
+        assert!(updated[offset..].starts_with(format!("{} 0 obj", id).as_bytes()), "entry of {}", id);
+      }
+    }
+    let reloaded = Document::load_mem(&updated).unwrap();
+    assert!(reloaded.get_dictionary(font_id).is_ok());
+  }
+
+  #[test]
+  fn names_the_producer_in_the_update() {
+    let source = bytes(&mut test_utils::document(1));
+    let mut document = load_document(&source).unwrap();
+    edit(&mut document);
+    let updated = save_incremental(&source, &mut document, SaveOptions::default()).unwrap();
+    let reloaded = Document::load_mem(&updated).unwrap();
+    let info_id = reloaded.trailer.get(b"Info").and_then(Object::as_reference).unwrap();
+    let info = reloaded.get_dictionary(info_id).unwrap();
+    let producer = info.get(b"Producer").and_then(Object::as_str).unwrap();
+    assert_eq!(decode_text_string(producer), producer::producer(&ProducerInfo::default()));
+  }
+
+  #[test]
+  fn returns_the_source_when_nothing_changed() {
+    let source = bytes(&mut test_utils::document(1));
+    let mut document = load_document(&source).unwrap();
+    assert_eq!(save_incremental(&source, &mut document, SaveOptions::default()).unwrap(), source);
+  }
+
+  #[test]
+  fn updates_a_certified_document_only_incrementally() {
+    let source = bytes(&mut certified(2));
+    let mut document = load_document(&source).unwrap();
+    edit(&mut document);
+    assert_eq!(refuse_certified(&document).unwrap_err().code, ErrorCode::CertifiedDocument);
+    assert!(save_document(&mut document.clone(), SaveOptions::default()).is_err());
+    let updated = save_incremental(&source, &mut document, SaveOptions::default()).unwrap();
+    assert_eq!(&updated[..source.len()], &source[..]);
+    assert_eq!(docmdp_permissions(&Document::load_mem(&updated).unwrap()), Some(2));
+
+    // No change at all is allowed
+    let source = bytes(&mut certified(1));
+    let mut document = load_document(&source).unwrap();
+    edit(&mut document);
+    let err = save_incremental(&source, &mut document, SaveOptions::default()).unwrap_err();
+    assert_eq!(err.code, ErrorCode::CertifiedDocument);
+  }
+}
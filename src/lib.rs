@@ -3,200 +3,1655 @@
 #[macro_use]
 extern crate napi_derive;
 
-use std::collections::BTreeMap;
-use lopdf::{Document, Object, ObjectId};
-use napi::{CallContext, Env, JsNumber, JsObject, Result, Task, JsBuffer};
-
-#[cfg(all(
-unix,
-not(target_env = "musl"),
-not(target_arch = "aarch64"),
-not(target_arch = "arm"),
-not(debug_assertions)
-))]
-#[global_allocator]
-static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
-
-#[cfg(all(windows, target_arch = "x86_64"))]
-#[global_allocator]
-static ALLOC: mimalloc::MiMalloc = mimalloc::MiMalloc;
-
-struct AsyncTask(u32);
-
-impl Task for AsyncTask {
-  type Output = u32;
-  type JsValue = JsNumber;
+mod acro_form;
+mod additional_actions;
+mod allocator;
+mod alt_text;
+mod annotations;
+mod append;
+mod black;
+mod content;
+mod crypto;
+mod dedupe;
+mod destinations;
+mod error;
+mod extract;
+mod filters;
+mod fingerprint;
+mod font;
+mod fonts;
+mod form;
+mod header_footer;
+mod incremental;
+mod metadata;
+mod name_trees;
+mod names;
+mod object_map;
+mod object_streams;
+mod open_action;
+mod outline;
+mod page;
+mod page_labels;
+mod page_size;
+mod page_tree;
+mod pipeline;
+mod portfolio;
+mod poster;
+mod probe;
+mod producer;
+mod qr_code;
+mod raw_object;
+mod repair;
+mod rewrite;
+mod rotate;
+mod sanitize;
+mod split;
+mod stats;
+mod stream;
+mod structure;
+#[cfg(test)]
+mod test_utils;
+mod text_box;
+mod text_pdf;
+mod thumbnails;
+mod toc;
+mod transparency;
+mod unload;
+mod utils;
+mod validate;
+mod version;
+mod web_hint;
+mod xmp;
 
-  fn compute(&mut self) -> Result<Self::Output> {
-    use std::thread::sleep;
-    use std::time::Duration;
-    sleep(Duration::from_millis(self.0 as u64));
-    Ok(self.0 * 2)
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use napi::{
+  CallContext, Env, Error, JsBuffer, JsFunction, JsNumber, JsObject, JsUndefined, JsUnknown, Result, Status, Task,
+  ValueType,
+};
+
+use crate::acro_form::AcroForms;
+use crate::destinations::{DestinationConflicts, Destinations};
+use crate::error::{ErrorCode, OrThrow, PdfError};
+use crate::extract::BrokenLink;
+use crate::name_trees::{NameTreeConflicts, NameTrees};
+use crate::outline::OutlineItem;
+use crate::page_labels::PageLabelRange;
+use crate::portfolio::{Collections, PortfolioMode};
+use crate::rotate::PageFit;
+use crate::stream::{read_streams, WritableWriter};
+use crate::structure::StructureTrees;
+use crate::toc::TocEntry;
+use crate::utils::{
+  buffer_value, integer_property, output_document, prepare_document, renumber_objects_as, replace_references,
+  save_document, SaveOptions, MAX_OBJECT_ID,
+};
+use crate::xmp::SourceInfo;
+
+#[module_exports]
+fn init(mut exports: JsObject, env: Env) -> Result<()> {
+  unload::prevent_unload();
+  exports.create_named_method("allocatorInfo", allocator::allocator_info)?;
+  exports.create_named_method("version", version::version)?;
+  exports.create_named_method("setProducerInfo", producer::set_producer_info)?;
+  exports.create_named_method("mergePdf", merge_documents)?;
+  exports.create_named_method("mergePdfToStream", merge_documents_to_stream)?;
+  exports.create_named_method("mergePdfFromStreams", merge_documents_from_streams)?;
+  exports.create_named_method("mergePdfBounded", merge_documents_bounded)?;
+  exports.create_named_method("addHeaderFooter", header_footer::add_header_footer)?;
+  exports.create_named_method("textToPdf", text_pdf::text_to_pdf)?;
+  exports.create_named_method("dedupeObjects", dedupe::dedupe_objects)?;
+  exports.create_named_method("dedupePages", dedupe::dedupe_pages)?;
+  exports.create_named_method("getFormFields", form::get_form_fields)?;
+  exports.create_named_method("flattenFields", form::flatten_fields)?;
+  exports.create_named_method("setFieldReadOnly", form::set_field_read_only)?;
+  exports.create_named_method("renameField", form::rename_field)?;
+  exports.create_named_method("setNeedAppearances", form::set_need_appearances)?;
+  exports.create_named_method("flattenAnnotations", annotations::flatten_annotations)?;
+  exports.create_named_method("validate", validate::validate)?;
+  exports.create_named_method("tryLoad", validate::try_load)?;
+  exports.create_named_method("probe", probe::probe)?;
+  exports.create_named_method("fixPageTree", page_tree::fix_page_tree)?;
+  exports.create_named_method("getDefaultMediaBox", page_tree::get_default_media_box)?;
+  exports.create_named_method("setDefaultMediaBox", page_tree::set_default_media_box)?;
+  exports.create_named_method("stats", stats::stats)?;
+  exports.create_named_method("objectMap", object_map::object_map)?;
+  exports.create_named_method("rotateRange", rotate::rotate_range)?;
+  exports.create_named_method("getPageRotations", rotate::get_page_rotations)?;
+  exports.create_named_method("bakeRotation", rotate::bake_rotation)?;
+  exports.create_named_method("uniformOrientation", rotate::uniform_orientation)?;
+  exports.create_named_method("extractPages", extract::extract_pages)?;
+  exports.create_named_method("extractVisible", extract::extract_visible)?;
+  exports.create_named_method("extractRangeToFile", extract::extract_range_to_file)?;
+  exports.create_named_method("appendPage", append::append_page)?;
+  exports.create_named_method("insertBlankPage", append::insert_blank_page)?;
+  exports.create_named_method("posterize", poster::posterize)?;
+  exports.create_named_method("contentFingerprint", fingerprint::content_fingerprint)?;
+  exports.create_named_method("splitPdfToFiles", split::split_pdf_to_files)?;
+  exports.create_named_method("setOpenAction", open_action::set_open_action)?;
+  exports.create_named_method("expandObjectStreams", object_streams::expand_object_streams)?;
+  exports.create_named_method("setWebOptimizedHint", web_hint::set_web_optimized_hint)?;
+  exports.create_named_method("rewrite", rewrite::rewrite)?;
+  exports.create_named_method("addQrCode", qr_code::add_qr_code)?;
+  exports.create_named_method("addTextBox", text_box::add_text_box)?;
+  exports.create_named_method("getMetadata", metadata::get_metadata)?;
+  exports.create_named_method("setDates", metadata::set_dates)?;
+  exports.create_named_method("pdfaHints", xmp::pdfa_hints)?;
+  exports.create_named_method("detachXmp", xmp::detach_xmp)?;
+  exports.create_named_method("attachXmp", xmp::attach_xmp)?;
+  exports.create_named_method("getOutline", outline::get_outline)?;
+  exports.create_named_method("setOutline", outline::set_outline)?;
+  exports.create_named_method("renderThumbnails", thumbnails::render_thumbnails)?;
+  exports.create_named_method("setImageAltText", alt_text::set_image_alt_text)?;
+  exports.create_named_method("sanitize", sanitize::sanitize)?;
+  exports.create_named_method("getAdditionalActions", additional_actions::get_additional_actions)?;
+  exports.create_named_method("removeAdditionalActions", additional_actions::remove_additional_actions)?;
+  exports.create_named_method("getPageLabels", page_labels::get_page_labels)?;
+  exports.create_named_method("setPageLabels", page_labels::set_page_labels)?;
+  exports.create_named_method("resolveLabelToIndex", page_labels::resolve_label_to_index)?;
+  exports.create_named_method("resolveIndexToLabel", page_labels::resolve_index_to_label)?;
+  exports.create_named_method("getPageContent", content::get_page_content)?;
+  exports.create_named_method("setPageContent", content::set_page_content)?;
+  exports.create_named_method("getObject", raw_object::get_object)?;
+  exports.create_named_method("getTrailer", raw_object::get_trailer)?;
+  exports.create_named_method("setObject", raw_object::set_object)?;
+  exports.create_named_method("setTrailerEntry", raw_object::set_trailer_entry)?;
+  exports.create_named_method("flattenTransparency", transparency::flatten_transparency)?;
+  exports.create_named_method("normalizeBlack", black::normalize_black)?;
+  exports.create_named_method("listFonts", fonts::list_fonts)?;
+  exports.create_named_method("subsetFonts", fonts::subset_fonts)?;
+  exports.set_named_property("PdfPipeline", pipeline::define_pipeline(&env)?)?;
+  Ok(())
+}
+
+/// Catalog-level settings taken from the `metadataFrom` document instead of the merged catalog
+const CATALOG_METADATA_KEYS: [&[u8]; 5] = [b"Lang", b"ViewerPreferences", b"Metadata", b"PageLayout", b"OpenAction"];
+
+/// What becomes of the bookmarks of the merged documents
+#[derive(Clone, Copy, PartialEq, Default)]
+enum OutlineMode {
+  /// No outline
+  #[default]
+  Drop,
+  /// The outlines of all documents one after the other, pointing at the merged pages
+  Merge,
+  /// One bookmark per document, to its first page
+  PerFile,
+}
+
+impl OutlineMode {
+  /// Read the `outlines` option, `perFile` with a table of contents and `drop` otherwise
+  fn from_js(options: &JsObject, table_of_contents: bool) -> Result<Self> {
+    match options.get_named_property::<Option<String>>("outlines")?.as_deref() {
+      None if table_of_contents => Ok(OutlineMode::PerFile),
+      None | Some("drop") => Ok(OutlineMode::Drop),
+      Some("merge") => Ok(OutlineMode::Merge),
+      Some("perFile") => Ok(OutlineMode::PerFile),
+      Some(other) => Err(Error::new(
+        Status::InvalidArg,
+        format!("outlines must be 'drop', 'merge' or 'perFile', got '{}'", other),
+      )),
+    }
+  }
+}
+
+#[derive(Default)]
+struct MergeOptions {
+  /// Index of the source document whose catalog settings win
+  metadata_from: usize,
+  /// Return `{ buffer, warnings }` listing what the merge dropped or altered
+  report: bool,
+  /// Leave out the documents that don't parse instead of failing the merge
+  skip_invalid: bool,
+  /// Rebuild the cross-reference table of the documents that don't parse or show no pages
+  repair_invalid: bool,
+  /// Keep the article threads of every document instead of removing them
+  preserve_threads: bool,
+  /// Start with table of contents pages and a bookmark for every document
+  table_of_contents: bool,
+  outlines: OutlineMode,
+  /// Width and height of the table of contents pages, the first document's page size by default
+  table_of_contents_page_size: Option<(f64, f64)>,
+  /// Fail instead of warning when a page tree's `/Count` doesn't match its pages
+  strict_page_count: bool,
+  /// Replace the XMP with a packet recording the merged documents and the producer
+  provenance: bool,
+  /// Record the Info title and author of every document in the provenance
+  source_info: bool,
+  /// What to do with a named destination defined by several documents
+  destination_conflicts: DestinationConflicts,
+  /// What to do with a name of the other catalog name trees defined by several documents
+  name_tree_conflicts: NameTreeConflicts,
+  portfolios: PortfolioMode,
+  /// Add a blank page after every document with an odd number of pages, so each starts on a recto
+  pad_each_source: bool,
+  /// Add a blank page after the documents when their pages add up to an odd number, and after an
+  /// odd table of contents, so the whole document is even
+  pad_to_even: bool,
+  /// Limits on the pages and objects of all the documents, checked once they are parsed
+  max_pages: Option<u32>,
+  max_objects: Option<u32>,
+  /// When the merge gives up with `Timeout`, from `timeoutMs`
+  deadline: Option<Instant>,
+  /// Number of the first object of the merged document, 1 when unset
+  start_object_id: Option<u32>,
+  /// Whether `remapObjectId` was given, which only `mergePdf` can call
+  remaps_object_ids: bool,
+  /// The new id of every object, by position of the document in the merge, from `remapObjectId`.
+  /// Replaces the sequential numbering when not empty.
+  remapped_ids: Vec<BTreeMap<ObjectId, ObjectId>>,
+  /// Keep a single copy of the pages that display the same, the others pointing to it
+  dedupe_pages: bool,
+  /// Return a `MergePlan` of what the merge would give instead of merging, from `dryRun`
+  dry_run: bool,
+  /// Redraw every page on the MediaBox of the first page, from `normalizeToFirst` and `normalizeFit`
+  normalize_to_first: Option<PageFit>,
+  out_path: Option<String>,
+  save: SaveOptions,
+}
+
+impl MergeOptions {
+  fn from_js(options: Option<JsObject>) -> Result<Self> {
+    let mut merge_options = MergeOptions::default();
+    if let Some(options) = options {
+      if let Some(metadata_from) = options.get_named_property::<Option<u32>>("metadataFrom")? {
+        merge_options.metadata_from = metadata_from as usize;
+      }
+      merge_options.report = options.get_named_property::<Option<bool>>("report")?.unwrap_or(false);
+      merge_options.skip_invalid = options.get_named_property::<Option<bool>>("skipInvalid")?.unwrap_or(false);
+      merge_options.repair_invalid = options.get_named_property::<Option<bool>>("repairInvalid")?.unwrap_or(false);
+      merge_options.preserve_threads = options.get_named_property::<Option<bool>>("preserveThreads")?.unwrap_or(false);
+      merge_options.table_of_contents =
+          options.get_named_property::<Option<bool>>("tableOfContents")?.unwrap_or(false);
+      merge_options.outlines = OutlineMode::from_js(&options, merge_options.table_of_contents)?;
+      if let Some(name) = options.get_named_property::<Option<String>>("tableOfContentsPageSize")? {
+        merge_options.table_of_contents_page_size = Some(page_size::page_size(&name)?);
+      }
+      merge_options.strict_page_count =
+          options.get_named_property::<Option<bool>>("strictPageCount")?.unwrap_or(false);
+      merge_options.source_info = options.get_named_property::<Option<bool>>("sourceInfo")?.unwrap_or(false);
+      merge_options.provenance = merge_options.source_info
+          || options.get_named_property::<Option<bool>>("provenance")?.unwrap_or(false);
+      merge_options.destination_conflicts = DestinationConflicts::from_js(&options)?;
+      merge_options.name_tree_conflicts = NameTreeConflicts::from_js(&options)?;
+      merge_options.portfolios = PortfolioMode::from_js(&options)?;
+      merge_options.pad_each_source = options.get_named_property::<Option<bool>>("padEachSource")?.unwrap_or(false);
+      merge_options.pad_to_even = options.get_named_property::<Option<bool>>("padToEven")?.unwrap_or(false);
+      merge_options.max_pages = options.get_named_property::<Option<u32>>("maxPages")?;
+      merge_options.max_objects = options.get_named_property::<Option<u32>>("maxObjects")?;
+      if let Some(timeout) = options.get_named_property::<Option<f64>>("timeoutMs")? {
+        if !(timeout.is_finite() && timeout > 0.0) {
+          return Err(Error::new(Status::InvalidArg, format!("timeoutMs must be positive and finite, got {}", timeout)));
+        }
+        // Past what `Instant` can represent, on some platforms only a few centuries away
+        let deadline = Duration::try_from_secs_f64(timeout / 1000.0)
+            .ok()
+            .and_then(|timeout| Instant::now().checked_add(timeout))
+            .ok_or_else(|| Error::new(Status::InvalidArg, format!("timeoutMs {:e} is too long", timeout)))?;
+        merge_options.deadline = Some(deadline);
+      }
+      merge_options.start_object_id = integer_property(&options, "startObjectId", 1, MAX_OBJECT_ID)?;
+      merge_options.remaps_object_ids =
+          options.get_named_property::<JsUnknown>("remapObjectId")?.get_type()? == ValueType::Function;
+      if merge_options.remaps_object_ids && merge_options.start_object_id.is_some() {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "startObjectId and remapObjectId can't be used together".to_owned(),
+        ));
+      }
+      merge_options.dedupe_pages = options.get_named_property::<Option<bool>>("dedupePages")?.unwrap_or(false);
+      merge_options.dry_run = options.get_named_property::<Option<bool>>("dryRun")?.unwrap_or(false);
+      if options.get_named_property::<Option<bool>>("normalizeToFirst")?.unwrap_or(false) {
+        let fit = match options.get_named_property::<Option<String>>("normalizeFit")?.as_deref() {
+          None | Some("scale") => PageFit::Scale,
+          Some("center") => PageFit::Center,
+          Some(other) => {
+            return Err(Error::new(
+              Status::InvalidArg,
+              format!("normalizeFit must be 'scale' or 'center', got '{}'", other),
+            ))
+          }
+        };
+        merge_options.normalize_to_first = Some(fit);
+      }
+      merge_options.out_path = options.get_named_property::<Option<String>>("outPath")?;
+      merge_options.save = SaveOptions::from_js(&options)?;
+    }
+    Ok(merge_options)
+  }
+
+  /// Check `metadataFrom` against the number of merged documents
+  fn validate(&self, document_count: usize) -> Result<()> {
+    if self.metadata_from >= document_count {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!(
+          "metadataFrom {} is out of range for {} documents",
+          self.metadata_from, document_count
+        ),
+      ));
+    }
+    Ok(())
   }
 
-  fn resolve(self, env: Env, output: Self::Output) -> Result<Self::JsValue> {
-    env.create_uint32(output)
+  /// Refuse `remapObjectId` in the merges that run off the JS thread, or in the pipeline
+  fn reject_remap(&self, function: &str) -> Result<()> {
+    if self.remaps_object_ids {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("{} doesn't support remapObjectId, only mergePdf does", function),
+      ));
+    }
+    Ok(())
+  }
+
+  /// Fail with `Timeout` once the deadline has passed, the merge checks it between steps
+  fn check_deadline(&self) -> error::Result<()> {
+    match self.deadline {
+      Some(deadline) if Instant::now() >= deadline => {
+        Err(PdfError::new(ErrorCode::Timeout, "The merge ran past its timeoutMs".to_owned()))
+      }
+      _ => Ok(()),
+    }
   }
 }
 
-#[module_exports]
-fn init(mut exports: JsObject) -> Result<()> {
-  exports.create_named_method("mergePdf", merge_documents)?;
+/// The pages of a source to merge, all of them unless one of the options is set
+#[derive(Default)]
+struct PageSelection {
+  /// 1-based pages in merge order, which win over the counts
+  pages: Option<Vec<u32>>,
+  first_pages: Option<u32>,
+  last_pages: Option<u32>,
+}
+
+impl PageSelection {
+  fn from_js(source: &JsObject) -> Result<Self> {
+    Ok(PageSelection {
+      pages: source.get_named_property::<Option<Vec<u32>>>("pages")?,
+      first_pages: source.get_named_property::<Option<u32>>("firstPages")?,
+      last_pages: source.get_named_property::<Option<u32>>("lastPages")?,
+    })
+  }
+
+  /// The pages to keep of a document with `page_count` pages, `None` to keep them all. The counts
+  /// are clamped to the pages there are, a page both first and last is kept once.
+  fn resolve(&self, page_count: u32) -> Option<Vec<u32>> {
+    if let Some(pages) = &self.pages {
+      return Some(pages.clone());
+    }
+    if self.first_pages.is_none() && self.last_pages.is_none() {
+      return None;
+    }
+    let first = self.first_pages.unwrap_or(0).min(page_count);
+    let last = self.last_pages.unwrap_or(0).min(page_count);
+    let mut pages = (1..=first).collect::<Vec<_>>();
+    pages.extend((page_count - last + 1..=page_count).filter(|&page| page > first));
+    Some(pages)
+  }
+}
+
+/// A document to merge along with its per-document options
+#[derive(Clone)]
+struct MergeSource {
+  document: Document,
+  /// Extra clockwise rotation applied to every page of the document
+  rotate: i64,
+  /// Position in the merge input, which warnings and `metadataFrom` refer to
+  index: usize,
+  /// Title of the document in the table of contents
+  title: Option<String>,
+}
+
+impl MergeSource {
+  /// Read either a plain `Buffer` or a `{ buffer, rotate? }` object, along with whether the buffer
+  /// had to be repaired (see `repair::load_or_repair`). A malformed argument fails the outer result,
+  /// a buffer that doesn't parse the inner one.
+  fn from_js(value: JsUnknown, index: usize, repair: bool) -> Result<error::Result<(Self, bool)>> {
+    if value.is_buffer()? {
+      let buffer = buffer_value(unsafe { value.cast::<JsBuffer>() })?;
+      return Ok(repair::load_or_repair(&buffer, repair).map(|(document, repaired)| {
+        let source = MergeSource {
+          document,
+          rotate: 0,
+          index,
+          title: None,
+        };
+        (source, repaired)
+      }));
+    }
+    let source = value.coerce_to_object()?;
+    let buffer = buffer_value(source.get_named_property::<JsBuffer>("buffer")?)?;
+    let rotate = source.get_named_property::<Option<i64>>("rotate")?.unwrap_or(0);
+    if rotate % 90 != 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("rotate must be a multiple of 90, got {}", rotate),
+      ));
+    }
+    let title = source.get_named_property::<Option<String>>("title")?;
+    let selection = PageSelection::from_js(&source)?;
+    Ok(repair::load_or_repair(&buffer, repair).and_then(|(mut document, repaired)| {
+      if let Some(pages) = selection.resolve(document.get_pages().len() as u32) {
+        // The tags of the pages left out would point at nothing
+        extract::extract_pages_in(&mut document, &pages, BrokenLink::Remove, true)?;
+      }
+      let source = MergeSource {
+        document,
+        rotate,
+        index,
+        title,
+      };
+      Ok((source, repaired))
+    }))
+  }
+}
+
+/// Read the `Array<Buffer | MergeSource>` argument, numbering the sources from `first_index`.
+/// With `skipInvalid` the buffers that don't parse are left out and listed in `warnings`.
+fn merge_sources_from_js(
+  env: &Env, buffers: JsObject, first_index: usize, options: &MergeOptions, warnings: &mut Vec<String>,
+) -> Result<Vec<MergeSource>> {
+  let length = buffers.get_array_length()? as usize;
+  let mut sources = Vec::with_capacity(length);
+  for position in 0..length {
+    let index = first_index + position;
+    match MergeSource::from_js(buffers.get_element::<JsUnknown>(position as u32)?, index, options.repair_invalid)? {
+      Ok((source, repaired)) => {
+        if repaired {
+          warnings.push(format!("Document {}: repaired, its cross-reference table was rebuilt", index));
+        }
+        sources.push(source);
+      }
+      Err(err) if options.skip_invalid => warnings.push(format!("Document {}: skipped, {}", index, err.message)),
+      Err(err) => return Err(err.throw(env)),
+    }
+  }
+  Ok(sources)
+}
+
+/// Fail with `LimitExceeded` when the sources add up to more pages or objects than allowed, so
+/// oversized inputs are refused before the merge does any work on them
+fn check_limits(sources: &[MergeSource], options: &MergeOptions) -> error::Result<()> {
+  let page_count = sources.iter().map(|source| source.document.get_pages().len()).sum::<usize>();
+  let object_count = sources.iter().map(|source| source.document.objects.len()).sum::<usize>();
+  let totals = [
+    ("pages", "maxPages", options.max_pages, page_count),
+    ("objects", "maxObjects", options.max_objects, object_count),
+  ];
+  for (what, option, limit, total) in totals {
+    let limit = match limit {
+      Some(limit) => limit as usize,
+      None => continue,
+    };
+    if total > limit {
+      return Err(PdfError::new(
+        ErrorCode::LimitExceeded,
+        format!("The documents have {} {}, over {} {}", total, what, option, limit),
+      ));
+    }
+  }
   Ok(())
 }
 
-#[js_function(1)]
-fn merge_documents(ctx: CallContext) -> Result<JsBuffer> {
-  // Should read `Array<Buffer>`
+/// Replace the portfolios among the sources by the PDFs they embed, themselves flattened, keeping
+/// the portfolio's index and rotation. A portfolio without readable PDFs keeps its cover pages.
+fn flatten_portfolios(sources: Vec<MergeSource>, warnings: &mut Vec<String>) -> Vec<MergeSource> {
+  let mut flattened = Vec::with_capacity(sources.len());
+  for source in sources {
+    if !portfolio::is_portfolio(&source.document) {
+      flattened.push(source);
+      continue;
+    }
+    let (documents, others) = portfolio::embedded_documents(&source.document);
+    if documents.is_empty() {
+      warnings.push(format!(
+        "Document {}: the portfolio embeds no readable PDF, its cover pages were kept",
+        source.index
+      ));
+      flattened.push(source);
+      continue;
+    }
+    warnings.push(format!(
+      "Document {}: the portfolio was replaced by its {} embedded PDFs, {} other files were dropped",
+      source.index,
+      documents.len(),
+      others
+    ));
+    let embedded = documents
+        .into_iter()
+        .map(|embedded| MergeSource {
+          document: embedded.document,
+          rotate: source.rotate,
+          index: source.index,
+          title: Some(embedded.title),
+        })
+        .collect();
+    flattened.extend(flatten_portfolios(embedded, warnings));
+  }
+  flattened
+}
+
+/// Ask `remap` for the new number of every object of the sources, given the document index and
+/// the object number. The numbers must be positive integers, each given to a single object.
+fn remapped_ids(env: &Env, remap: &JsFunction, sources: &[MergeSource]) -> Result<Vec<BTreeMap<ObjectId, ObjectId>>> {
+  let mut owners = BTreeMap::new();
+  let mut remapped = Vec::with_capacity(sources.len());
+  for source in sources {
+    let mut ids = BTreeMap::new();
+    for &(number, generation) in source.document.objects.keys() {
+      let args = [env.create_uint32(source.index as u32)?, env.create_uint32(number)?];
+      let value = remap.call(None, &args)?;
+      let new_number = match value.get_type()? {
+        ValueType::Number => JsNumber::try_from(value)?.get_double()?,
+        _ => f64::NAN,
+      };
+      if !(1.0..=MAX_OBJECT_ID as f64).contains(&new_number) || new_number.fract() != 0.0 {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "remapObjectId returned no integer from 1 to {} for object {} of document {}",
+            MAX_OBJECT_ID, number, source.index
+          ),
+        ));
+      }
+      let new_number = new_number as u32;
+      if let Some((index, other)) = owners.insert(new_number, (source.index, number)) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!(
+            "remapObjectId gave {} to both object {} of document {} and object {} of document {}",
+            new_number, other, index, number, source.index
+          ),
+        ));
+      }
+      ids.insert((number, generation), (new_number, 0));
+    }
+    remapped.push(ids);
+  }
+  Ok(remapped)
+}
+
+/// Fail a merge whose documents were all skipped, rather than producing a document without pages
+fn require_documents(input_count: usize, count: usize) -> error::Result<()> {
+  if count == 0 && input_count > 0 {
+    return Err(PdfError::new(ErrorCode::InvalidPdf, "None of the documents could be read"));
+  }
+  Ok(())
+}
+
+#[js_function(2)]
+fn merge_documents(ctx: CallContext) -> Result<JsUnknown> {
+  let mut options = MergeOptions::from_js(ctx.get::<Option<JsObject>>(1)?)?;
+  let buffers = ctx.get::<JsObject>(0)?;
+  let input_count = buffers.get_array_length()? as usize;
+  options.validate(input_count)?;
+  let mut warnings = vec![];
+  let mut doc_buffers = merge_sources_from_js(ctx.env, buffers, 0, &options, &mut warnings)?;
+  require_documents(input_count, doc_buffers.len()).or_throw(ctx.env)?;
+  // Flattened before counting the pages of every source, `merge_sources` then has nothing to flatten
+  if options.portfolios == PortfolioMode::Flatten {
+    doc_buffers = flatten_portfolios(doc_buffers, &mut warnings);
+  }
+  if options.dry_run {
+    let sizes = input_sizes(&ctx.get::<JsObject>(0)?)?;
+    let plan = plan_merge(&doc_buffers, &options, &mut warnings).or_throw(ctx.env)?;
+    let mut report = ctx.env.create_object()?;
+    report.set_named_property("pageCount", ctx.env.create_uint32(plan.page_count)?)?;
+    report.set_named_property("version", ctx.env.create_string(&plan.version)?)?;
+    let estimated_size = doc_buffers.iter().map(|source| sizes.get(source.index).copied().unwrap_or(0)).sum::<usize>();
+    report.set_named_property("estimatedSize", ctx.env.create_int64(estimated_size as i64)?)?;
+    let mut list = ctx.env.create_array_with_length(warnings.len())?;
+    for (index, warning) in warnings.iter().enumerate() {
+      list.set_element(index as u32, ctx.env.create_string(warning)?)?;
+    }
+    report.set_named_property("warnings", list)?;
+    let mut sources = ctx.env.create_array_with_length(plan.sources.len())?;
+    for (position, planned) in plan.sources.iter().enumerate() {
+      let mut range = ctx.env.create_object()?;
+      range.set_named_property("source", ctx.env.create_uint32(planned.index as u32)?)?;
+      range.set_named_property("startPage", ctx.env.create_uint32(planned.start_page)?)?;
+      range.set_named_property("endPage", ctx.env.create_uint32(planned.end_page)?)?;
+      range.set_named_property("outlines", ctx.env.get_boolean(planned.outlines)?)?;
+      sources.set_element(position as u32, range)?;
+    }
+    report.set_named_property("sources", sources)?;
+    return Ok(report.into_unknown());
+  }
+  if options.remaps_object_ids {
+    let remap = ctx
+        .get::<JsObject>(1)?
+        .get_named_property::<JsFunction>("remapObjectId")?;
+    options.remapped_ids = remapped_ids(ctx.env, &remap, &doc_buffers)?;
+  }
+  let page_counts = doc_buffers
+      .iter()
+      .map(|source| (source.index, source.document.get_pages().len() as u32))
+      .collect::<Vec<_>>();
+  let mut collapsed = BTreeMap::new();
+  let mut document =
+      merge_sources_counting(doc_buffers, &options, &mut warnings, &mut collapsed).or_throw(ctx.env)?;
+  // Pages a source takes in the merged document, its blank page included and its collapsed pages not
+  let merged_count = |index: usize, page_count: u32| {
+    let padded = if options.pad_each_source {
+      page_count + page_count % 2
+    } else {
+      page_count
+    };
+    padded - collapsed.get(&index).copied().unwrap_or(0)
+  };
+  let source_pages = page_counts.iter().map(|&(index, count)| merged_count(index, count)).sum::<u32>();
+  let trailing_pages = (options.pad_to_even && source_pages % 2 == 1) as u32;
+  // Pages inserted ahead of the documents, such as the table of contents
+  let leading_pages = document.get_pages().len() as u32 - source_pages - trailing_pages;
+  let output = output_document(ctx.env, &mut document, options.out_path.clone(), options.save.clone())?;
+  if !options.report {
+    return Ok(output);
+  }
+  let mut report = ctx.env.create_object()?;
+  if options.out_path.is_none() {
+    report.set_named_property("buffer", output)?;
+  }
+  let mut list = ctx.env.create_array_with_length(warnings.len())?;
+  for (index, warning) in warnings.iter().enumerate() {
+    list.set_element(index as u32, ctx.env.create_string(warning)?)?;
+  }
+  report.set_named_property("warnings", list)?;
+  let page_counts = page_counts
+      .into_iter()
+      .map(|(index, page_count)| (index, page_count, page_count - collapsed.get(&index).copied().unwrap_or(0)))
+      .filter(|(_, _, kept_count)| *kept_count > 0)
+      .collect::<Vec<_>>();
+  let mut sources = ctx.env.create_array_with_length(page_counts.len())?;
+  let mut start_page = leading_pages + 1;
+  for (position, (index, page_count, kept_count)) in page_counts.into_iter().enumerate() {
+    let mut range = ctx.env.create_object()?;
+    range.set_named_property("source", ctx.env.create_uint32(index as u32)?)?;
+    range.set_named_property("startPage", ctx.env.create_uint32(start_page)?)?;
+    range.set_named_property("endPage", ctx.env.create_uint32(start_page + kept_count - 1)?)?;
+    sources.set_element(position as u32, range)?;
+    start_page += merged_count(index, page_count);
+  }
+  report.set_named_property("sources", sources)?;
+  Ok(report.into_unknown())
+}
+
+/// The byte length of every input of the merge, by index
+fn input_sizes(buffers: &JsObject) -> Result<Vec<usize>> {
+  let length = buffers.get_array_length()?;
+  let mut sizes = Vec::with_capacity(length as usize);
+  for position in 0..length {
+    let value = buffers.get_element::<JsUnknown>(position)?;
+    let buffer = if value.is_buffer()? {
+      unsafe { value.cast::<JsBuffer>() }
+    } else {
+      value.coerce_to_object()?.get_named_property::<JsBuffer>("buffer")?
+    };
+    sizes.push(buffer_value(buffer)?.len());
+  }
+  Ok(sizes)
+}
+
+/// Where a source's pages would land in the merged document
+struct PlannedSource {
+  index: usize,
+  start_page: u32,
+  end_page: u32,
+  /// Whether the document has bookmarks
+  outlines: bool,
+}
+
+/// What `mergePdf` would produce, from `dryRun`
+struct MergePlan {
+  page_count: u32,
+  version: String,
+  sources: Vec<PlannedSource>,
+}
+
+/// Work out the pages and version of the merge of `sources` from the parsed documents alone,
+/// adding the warnings their catalogs and page trees give. Nothing is renumbered or copied, so the
+/// warnings of merging the name trees and forms, and the pages `dedupePages` would collapse, are
+/// only known from a real merge.
+fn plan_merge(sources: &[MergeSource], options: &MergeOptions, warnings: &mut Vec<String>) -> error::Result<MergePlan> {
+  check_limits(sources, options)?;
+  let mut version = "1.5".to_owned();
+  let mut planned = vec![];
+  let mut source_pages = 0;
+  let last_position = sources.len().saturating_sub(1);
+  for (position, source) in sources.iter().enumerate() {
+    options.check_deadline()?;
+    let document = &source.document;
+    if version_number(&document.version) > version_number(&version) {
+      version = document.version.clone();
+    }
+    warnings.extend(catalog_warnings(document, source.index, position == last_position, options));
+    let mut page_count = document.get_pages().len() as u32;
+    if let Some(count) = page_count_mismatch(document, page_count as usize) {
+      let message = format!(
+        "Document {}: the page tree /Count is {} but {} pages were found",
+        source.index, count, page_count
+      );
+      if options.strict_page_count {
+        return Err(PdfError::new(ErrorCode::InvalidPdf, message));
+      }
+      warnings.push(message);
+    }
+    if page_count == 0 {
+      continue;
+    }
+    planned.push(PlannedSource {
+      index: source.index,
+      start_page: source_pages + 1,
+      end_page: source_pages + page_count,
+      outlines: document.catalog().is_ok_and(|catalog| catalog.has(b"Outlines")),
+    });
+    if options.pad_each_source && page_count % 2 == 1 {
+      page_count += 1;
+      warnings.push(format!("Document {}: a blank page was added after its odd number of pages", source.index));
+    }
+    source_pages += page_count;
+  }
+  if options.pad_to_even && source_pages % 2 == 1 {
+    warnings.push(format!("A blank page was added after the {} merged pages to make them even", source_pages));
+    source_pages += 1;
+  }
+  let mut toc_pages = 0;
+  let first_page = sources.iter().find_map(|source| {
+    let page_id = source.document.get_pages().into_values().next()?;
+    Some((&source.document, page_id))
+  });
+  if let (true, Some((document, page_id))) = (options.table_of_contents, first_page) {
+    let media_box = match options.table_of_contents_page_size {
+      Some((width, height)) => [0.0, 0.0, width, height],
+      None => page::media_box(document, page_id),
+    };
+    toc_pages = toc::page_count(media_box, planned.len(), options.pad_each_source || options.pad_to_even);
+  }
+  for source in planned.iter_mut() {
+    source.start_page += toc_pages;
+    source.end_page += toc_pages;
+  }
+  Ok(MergePlan {
+    page_count: toc_pages + source_pages,
+    version,
+    sources: planned,
+  })
+}
+
+#[js_function(3)]
+fn merge_documents_bounded(ctx: CallContext) -> Result<JsUnknown> {
   let buffers = ctx.get::<JsObject>(0)?;
-  let len_arr = vec![0; buffers.get_array_length()? as usize]; // Create the array iter
-  let doc_buffers = len_arr.iter()
-      .enumerate() // Add the index to the element
-      .map(|(i, _)| {
-        let buffer = &mut buffers
-            .get_named_property::<JsBuffer>(i.to_string().as_str())
-            .unwrap()
-            .into_value()
-            .unwrap()
-            .to_vec();
-        // Load the pdf by memory
-        Document::load_mem(&buffer).unwrap()
-      })
-      .collect::<Vec<Document>>();
-  // Add buffer to target
-  let mut target: Vec<u8> = vec![];
-  merge_documents_to(&doc_buffers, &mut target);
-  Ok(ctx.env.create_buffer_with_data(target).unwrap().into_raw())
+  let max_bytes = ctx.get::<f64>(1)?;
+  if max_bytes < 1.0 {
+    return Err(Error::new(Status::InvalidArg, format!("maxBytes must be positive, got {}", max_bytes)));
+  }
+  let options = MergeOptions::from_js(ctx.get::<Option<JsObject>>(2)?)?;
+  options.reject_remap("mergePdfBounded")?;
+  if options.out_path.is_some() {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "mergePdfBounded returns its parts, outPath isn't supported".to_owned(),
+    ));
+  }
+  let input_count = buffers.get_array_length()? as usize;
+  options.validate(input_count)?;
+  let mut warnings = vec![];
+  let sources = merge_sources_from_js(ctx.env, buffers, 0, &options, &mut warnings)?;
+  require_documents(input_count, sources.len()).or_throw(ctx.env)?;
+  let parts = merge_bounded(sources, max_bytes as usize, &options, &mut warnings).or_throw(ctx.env)?;
+  let mut list = ctx.env.create_array_with_length(parts.len())?;
+  for (index, part) in parts.into_iter().enumerate() {
+    list.set_element(index as u32, ctx.env.create_buffer_with_data(part)?.into_raw())?;
+  }
+  if !options.report {
+    return Ok(list.into_unknown());
+  }
+  let mut report = ctx.env.create_object()?;
+  report.set_named_property("buffers", list)?;
+  let mut list = ctx.env.create_array_with_length(warnings.len())?;
+  for (index, warning) in warnings.iter().enumerate() {
+    list.set_element(index as u32, ctx.env.create_string(warning)?)?;
+  }
+  report.set_named_property("warnings", list)?;
+  Ok(report.into_unknown())
+}
+
+/// Merge the sources in order into as few parts as fit under `max_bytes` each. The parts are
+/// planned from the size of every document saved on its own, then each part is merged for real and
+/// gives its last documents to the next part while it is still too large. A document over the cap
+/// on its own is a part by itself.
+fn merge_bounded(
+  sources: Vec<MergeSource>, max_bytes: usize, options: &MergeOptions, warnings: &mut Vec<String>,
+) -> error::Result<Vec<Vec<u8>>> {
+  // Parts are checked on their own when merged, the limits are on the whole input
+  check_limits(&sources, options)?;
+  let mut pending = VecDeque::with_capacity(sources.len());
+  for source in sources {
+    let size = save_document(&mut source.document.clone(), options.save.clone())?.len();
+    pending.push_back((source, size));
+  }
+  let mut parts = vec![];
+  while !pending.is_empty() {
+    let mut group = vec![];
+    let mut estimate = 0;
+    while let Some((_, size)) = pending.front() {
+      if !group.is_empty() && estimate + size > max_bytes {
+        break;
+      }
+      estimate += size;
+      group.push(pending.pop_front().unwrap());
+    }
+    loop {
+      let mut group_warnings = vec![];
+      let sources = group.iter().map(|(source, _)| source.clone()).collect();
+      let mut document = merge_sources(sources, options, &mut group_warnings)?;
+      let part = save_document(&mut document, options.save.clone())?;
+      if part.len() > max_bytes && group.len() > 1 {
+        pending.push_front(group.pop().unwrap());
+        continue;
+      }
+      warnings.extend(group_warnings);
+      if part.len() > max_bytes {
+        warnings.push(format!(
+          "Document {}: {} bytes on its own, over maxBytes {}",
+          group[0].0.index,
+          part.len(),
+          max_bytes
+        ));
+      }
+      parts.push(part);
+      break;
+    }
+  }
+  Ok(parts)
+}
+
+/// Merge on the threadpool and stream the result into a Node Writable
+struct MergeToStream {
+  sources: Vec<MergeSource>,
+  options: MergeOptions,
+  writer: Option<WritableWriter>,
+  /// Keeps the code of a failure until the promise is rejected
+  error: Option<PdfError>,
 }
 
+impl MergeToStream {
+  fn write(&mut self) -> error::Result<()> {
+    let sources = std::mem::take(&mut self.sources);
+    let mut document = merge_sources(sources, &self.options, &mut vec![])?;
+    self.options.check_deadline()?;
+    prepare_document(&mut document, &self.options.save)?;
+    // Dropping the writer releases the stream once everything is written
+    let mut writer = self.writer.take().unwrap();
+    document.save_to(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+  }
+}
+
+impl Task for MergeToStream {
+  type Output = ();
+  type JsValue = JsUndefined;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    self.write().map_err(|err| {
+      let error = Error::from_reason(err.message.clone());
+      self.error = Some(err);
+      error
+    })
+  }
+
+  fn resolve(&mut self, env: Env, _output: Self::Output) -> Result<Self::JsValue> {
+    env.get_undefined()
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+    match self.error.take() {
+      Some(error) => Err(error.into_napi(&env)),
+      None => Err(err),
+    }
+  }
+}
+
+#[js_function(3)]
+fn merge_documents_to_stream(ctx: CallContext) -> Result<JsObject> {
+  let options = MergeOptions::from_js(ctx.get::<Option<JsObject>>(2)?)?;
+  options.reject_remap("mergePdfToStream")?;
+  let buffers = ctx.get::<JsObject>(0)?;
+  let input_count = buffers.get_array_length()? as usize;
+  options.validate(input_count)?;
+  let sources = merge_sources_from_js(ctx.env, buffers, 0, &options, &mut vec![])?;
+  require_documents(input_count, sources.len()).or_throw(ctx.env)?;
+  let writer = WritableWriter::new(ctx.env, ctx.get::<JsObject>(1)?)?;
+  let task = MergeToStream {
+    sources,
+    options,
+    writer: Some(writer),
+    error: None,
+  };
+  Ok(ctx.env.spawn(task)?.promise_object())
+}
+
+/// Resolves the promise of `mergePdfFromStreams` on the JS thread
+type MergeResolver = Box<dyn FnOnce(Env) -> Result<JsBuffer> + Send>;
+
+#[js_function(2)]
+fn merge_documents_from_streams(ctx: CallContext) -> Result<JsObject> {
+  let options = MergeOptions::from_js(ctx.get::<Option<JsObject>>(1)?)?;
+  options.reject_remap("mergePdfFromStreams")?;
+  let streams = ctx.get::<Vec<JsObject>>(0)?;
+  options.validate(streams.len())?;
+  let (deferred, promise) = ctx.env.create_deferred::<JsBuffer, MergeResolver>()?;
+  read_streams(ctx.env, streams, move |_, buffers| {
+    let buffers = match buffers {
+      Ok(buffers) => buffers,
+      Err(err) => return deferred.reject(err),
+    };
+    // Parse and merge off the JS thread
+    std::thread::spawn(move || {
+      let merged = buffers
+          .iter()
+          .enumerate()
+          .map(|(index, buffer)| {
+            options.check_deadline()?;
+            repair::load_or_repair(buffer, options.repair_invalid).map(|(document, _)| MergeSource {
+              document,
+              rotate: 0,
+              index,
+              title: None,
+            })
+          })
+          .filter(|source| {
+            let invalid = source.as_ref().is_err_and(|err| err.code != ErrorCode::Timeout);
+            !(options.skip_invalid && invalid)
+          })
+          .collect::<error::Result<Vec<_>>>()
+          .and_then(|sources| require_documents(buffers.len(), sources.len()).map(|_| sources))
+          .and_then(|sources| merge_sources(sources, &options, &mut vec![]))
+          .and_then(|mut document| {
+            options.check_deadline()?;
+            save_document(&mut document, options.save)
+          });
+      deferred.resolve(Box::new(move |env| match merged {
+        Ok(target) => Ok(env.create_buffer_with_data(target)?.into_raw()),
+        Err(err) => Err(err.into_napi(&env)),
+      }));
+    });
+  })?;
+  Ok(promise)
+}
+
+/// Catalog entries merge_sources combines across documents instead of taking them from one catalog
+const MERGED_CATALOG_KEYS: [&[u8]; 9] = [
+  b"Type",
+  b"Pages",
+  b"AcroForm",
+  b"Dests",
+  b"Outlines",
+  b"PageLabels",
+  b"Threads",
+  b"StructTreeRoot",
+  b"MarkInfo",
+];
+
+/// Describe the catalog entries of a source document the merge drops. The merged catalog
+/// extends the catalog of the last document, `is_base`.
+fn catalog_warnings(
+  document: &Document, index: usize, is_base: bool, options: &MergeOptions,
+) -> Vec<String> {
+  let metadata_from = options.metadata_from;
+  // The collection and the files of portfolios are kept in the merged collection
+  let keep_collection = options.portfolios == PortfolioMode::Preserve && portfolio::is_portfolio(document);
+  let mut warnings = vec![];
+  let catalog = match document.catalog() {
+    Ok(catalog) => catalog,
+    Err(_) => return warnings,
+  };
+  if catalog.has(b"Outlines") && options.outlines != OutlineMode::Merge {
+    warnings.push(format!("Document {}: the outlines (bookmarks) were dropped", index));
+  }
+  for (key, _) in catalog.iter() {
+    let key = key.as_slice();
+    if CATALOG_METADATA_KEYS.contains(&key) {
+      if index != metadata_from {
+        warnings.push(format!(
+          "Document {}: /{} was dropped, catalog settings come from document {}",
+          index,
+          String::from_utf8_lossy(key),
+          metadata_from
+        ));
+      }
+    } else if key == b"Collection" && keep_collection {
+      continue;
+    } else if key == b"Names" {
+      // The known name trees are merged, others come from the base catalog
+      if is_base {
+        continue;
+      }
+      if let Ok((_, Object::Dictionary(names))) = catalog.get(b"Names").and_then(|names| document.dereference(names)) {
+        let kept = |tree: &[u8]| tree == b"Dests" || name_trees::MERGED_NAME_TREES.contains(&tree);
+        for (tree, _) in names.iter().filter(|(tree, _)| !kept(tree)) {
+          warnings.push(format!("Document {}: the /{} name tree was dropped", index, String::from_utf8_lossy(tree)));
+        }
+      }
+    } else if !is_base && !MERGED_CATALOG_KEYS.contains(&key) {
+      warnings.push(format!("Document {}: the catalog entry /{} was dropped", index, String::from_utf8_lossy(key)));
+    }
+  }
+  warnings
+}
+
+/// The thread references of a document's catalog `/Threads` and the ids of the threads and the
+/// beads its pages list in `/B`
+fn article_threads(document: &Document) -> (Vec<Object>, BTreeSet<ObjectId>) {
+  let threads = match document.catalog().and_then(|catalog| catalog.get(b"Threads")) {
+    Ok(threads) => match document.dereference(threads) {
+      Ok((_, Object::Array(threads))) => threads.clone(),
+      _ => vec![],
+    },
+    Err(_) => vec![],
+  };
+  let mut ids = threads.iter().filter_map(|thread| thread.as_reference().ok()).collect::<BTreeSet<_>>();
+  for page_id in document.get_pages().into_values() {
+    if let Ok(Object::Array(beads)) = document.get_dictionary(page_id).and_then(|page| page.get(b"B")) {
+      ids.extend(beads.iter().filter_map(|bead| bead.as_reference().ok()));
+    }
+  }
+  (threads, ids)
+}
+
+/// The `/Count` of the page tree root when it differs from the number of pages found in the tree
+fn page_count_mismatch(document: &Document, page_count: usize) -> Option<i64> {
+  let root = document
+      .catalog()
+      .and_then(|catalog| catalog.get(b"Pages"))
+      .and_then(|pages| document.dereference(pages))
+      .and_then(|(_, pages)| pages.as_dict())
+      .ok()?;
+  let count = root.get(b"Count").and_then(Object::as_i64).ok()?;
+  if count == page_count as i64 {
+    None
+  } else {
+    Some(count)
+  }
+}
+
+/// Move the targets of outline items `offset` pages further
+fn shift_outline(items: &mut [OutlineItem], offset: u32) {
+  for item in items {
+    item.page = item.page.map(|page| page + offset);
+    shift_outline(&mut item.children, offset);
+  }
+}
+
+/// Remove the `duplicates` from the merged pages, renumbering what refers to pages by position.
+/// Bookmarks to a collapsed page go to its copy, label ranges start at the next kept page.
+fn collapse_pages(
+  kids: &mut Vec<ObjectId>,
+  duplicates: &BTreeMap<ObjectId, ObjectId>,
+  outline: &mut [OutlineItem],
+  page_labels: &mut Vec<PageLabelRange>,
+  contents: &mut [TocEntry],
+) {
+  // For every page before collapsing, its position after and the number of kept pages before it
+  let mut positions = vec![];
+  let mut kept_before = vec![];
+  let mut kept_positions = HashMap::new();
+  for page_id in kids.iter() {
+    kept_before.push(kept_positions.len() as u32);
+    match duplicates.get(page_id) {
+      Some(copy_id) => positions.push(kept_positions[copy_id]),
+      None => {
+        let position = kept_positions.len() as u32 + 1;
+        kept_positions.insert(*page_id, position);
+        positions.push(position);
+      }
+    }
+  }
+  renumber_outline(outline, &positions);
+  for range in page_labels.iter_mut() {
+    range.start_page = kept_before.get(range.start_page as usize - 1).map_or(u32::MAX, |kept| kept + 1);
+  }
+  page_labels.retain(|range| range.start_page as usize <= kept_positions.len());
+  // Of the ranges now starting on the same page, the last one labels it
+  page_labels.reverse();
+  page_labels.dedup_by_key(|range| range.start_page);
+  page_labels.reverse();
+  for entry in contents.iter_mut() {
+    if let Some(copy_id) = duplicates.get(&entry.page_id) {
+      entry.page_id = *copy_id;
+    }
+  }
+  kids.retain(|page_id| !duplicates.contains_key(page_id));
+}
+
+fn renumber_outline(items: &mut [OutlineItem], positions: &[u32]) {
+  for item in items {
+    item.page = item
+        .page
+        .map(|page| positions.get((page as usize).saturating_sub(1)).copied().unwrap_or(page));
+    renumber_outline(&mut item.children, positions);
+  }
+}
+
+/// Major and minor numbers of a `%PDF-` header version, for comparing versions
+fn version_number(version: &str) -> (u32, u32) {
+  let mut parts = version.trim().splitn(2, '.').map(|part| part.parse().unwrap_or(0));
+  (parts.next().unwrap_or(0), parts.next().unwrap_or(0))
+}
+
+/// Merge the documents, describing in `warnings` what was dropped or altered on the way
 #[inline]
-fn merge_documents_to(documents: &Vec<Document>, target: &mut Vec<u8>) {
-  let documents = documents.clone();
+fn merge_sources(
+  documents: Vec<MergeSource>,
+  options: &MergeOptions,
+  warnings: &mut Vec<String>,
+) -> error::Result<Document> {
+  merge_sources_counting(documents, options, warnings, &mut BTreeMap::new())
+}
+
+/// `merge_sources`, adding to `collapsed` the number of pages of every document that `dedupePages`
+/// collapsed, by document index
+fn merge_sources_counting(
+  documents: Vec<MergeSource>,
+  options: &MergeOptions,
+  warnings: &mut Vec<String>,
+  collapsed: &mut BTreeMap<usize, u32>,
+) -> error::Result<Document> {
+  let documents = match options.portfolios {
+    PortfolioMode::Flatten => flatten_portfolios(documents, warnings),
+    PortfolioMode::Preserve => documents,
+  };
+  check_limits(&documents, options)?;
   // Define a starting max_id (will be used as start index for object_ids)
   let mut max_id = 1;
-  // Collect all Documents Objects grouped by a map
-  let mut documents_pages = BTreeMap::new();
-  let mut documents_objects = BTreeMap::new();
-  for mut document in documents {
-    document.renumber_objects_with(max_id);
+  // Objects are moved from each document straight into the merged one, so every object is only
+  // visited a constant number of times however many documents are merged
+  let mut merged = Document::with_version("1.5");
+  // Leaf pages of all documents, in order, and the index of the document of each
+  let mut kids = vec![];
+  let mut kid_sources = vec![];
+  // The first "Catalog" id is kept with the dictionary of the last one
+  let mut catalog_object: Option<(ObjectId, Dictionary)> = None;
+  // The id of the first "Pages" is reused for the merged page tree root
+  let mut pages_id: Option<ObjectId> = None;
+  // Catalog settings of the document chosen by `metadataFrom`
+  let mut metadata_catalog: Option<Dictionary> = None;
+  let mut destinations = Destinations::new(options.destination_conflicts);
+  let mut name_trees = NameTrees::new(options.name_tree_conflicts.clone());
+  let mut acro_forms = AcroForms::default();
+  let mut collections = Collections::default();
+  let mut structure_trees = StructureTrees::default();
+  // Label ranges of every document, shifted to the document's first merged page
+  let mut page_labels = vec![];
+  let mut labeled = false;
+  // Threads kept with `preserveThreads`, otherwise the thread and bead objects to leave out
+  let mut threads = vec![];
+  let mut stripped_threads = BTreeSet::new();
+  // Blank pages added by `padEachSource`, which `dedupePages` leaves alone
+  let mut blank_pages = BTreeSet::new();
+  let mut contents = vec![];
+  // Bookmarks of the merged document, numbered as the merged pages before the table of contents
+  let mut outline = vec![];
+  let source_count = documents.len();
+  let mut titles = vec![];
+  let mut source_info = vec![];
+  let last_position = documents.len().saturating_sub(1);
+  // Objects the merge adds are numbered after all the ids `remapObjectId` chose
+  let remapped_max_id = options.remapped_ids.iter().flat_map(BTreeMap::values).map(|id| id.0).max();
+  for (position, source) in documents.into_iter().enumerate() {
+    let MergeSource {
+      mut document,
+      rotate,
+      index,
+      title,
+    } = source;
+    options.check_deadline()?;
+    match (options.remapped_ids.get(position), remapped_max_id) {
+      (Some(ids), Some(remapped_max_id)) => {
+        renumber_objects_as(&mut document, ids);
+        document.max_id = remapped_max_id.max(max_id);
+      }
+      _ => document.renumber_objects_with(max_id),
+    }
     max_id = document.max_id + 1;
-    documents_pages.extend(
-      document
-          .get_pages()
-          .into_iter()
-          .map(|(_, object_id)| {
-            (
-              object_id,
-              document.get_object(object_id).unwrap().to_owned(),
-            )
-          })
-          .collect::<BTreeMap<ObjectId, Object>>(),
-    );
-    documents_objects.extend(document.objects);
-  }
-  // Initialize a new empty document
-  let mut document = Document::with_version("1.5");
-  // Catalog and Pages are mandatory
-  let mut catalog_object: Option<(ObjectId, Object)> = None;
-  let mut pages_object: Option<(ObjectId, Object)> = None;
-  // Process all objects except "Page" type
-  for (object_id, object) in documents_objects.iter() {
-    // We have to ignore "Page" (as are processed later), "Outlines" and "Outline" objects
-    // All other objects should be collected and inserted into the main Document
-    match object.type_name().unwrap_or("") {
-      "Catalog" => {
-        // Collect a first "Catalog" object and use it for the future "Pages"
-        catalog_object = Some((
-          if let Some((id, _)) = catalog_object {
-            id
-          } else {
-            *object_id
-          },
-          object.clone(),
+    // The merged document may use the features of any of them
+    if version_number(&document.version) > version_number(&merged.version) {
+      merged.version = document.version.clone();
+    }
+    warnings.extend(catalog_warnings(&document, index, position == last_position, options));
+    let mut pages = document.get_pages();
+    // The merged tree is rebuilt from the leaves, a wrong count usually means a damaged tree
+    if let Some(count) = page_count_mismatch(&document, pages.len()) {
+      let message = format!(
+        "Document {}: the page tree /Count is {} but {} pages were found",
+        index,
+        count,
+        pages.len()
+      );
+      if options.strict_page_count {
+        return Err(PdfError::new(ErrorCode::InvalidPdf, message));
+      }
+      warnings.push(message);
+    }
+    for (page_number, page_id) in pages.iter() {
+      options.check_deadline()?;
+      // Pages are moved under one merged `Pages` node, so they can't inherit from their old ancestors
+      page::copy_inherited_attributes(&mut document, *page_id)?;
+      if page::inherited_attribute(&document, *page_id, b"MediaBox")
+          .and_then(page::rect_from_object)
+          .is_none()
+      {
+        let media_box = page::DEFAULT_MEDIA_BOX.iter().map(|&value| value.into()).collect::<Vec<Object>>();
+        document
+            .get_object_mut(*page_id)
+            .and_then(Object::as_dict_mut)?
+            .set("MediaBox", media_box);
+        warnings.push(format!(
+          "Document {}: page {} has no valid /MediaBox, defaulted to US Letter",
+          index, page_number
         ));
       }
-      "Pages" => {
-        // Collect and update a first "Pages" object and use it for the future "Catalog"
-        // We have also to merge all dictionaries of the old and the new "Pages" object
-        if let Ok(dictionary) = object.as_dict() {
-          let mut dictionary = dictionary.clone();
-          if let Some((_, ref object)) = pages_object {
-            if let Ok(old_dictionary) = object.as_dict() {
-              dictionary.extend(old_dictionary);
+      if rotate != 0 {
+        rotate::rotate_page(&mut document, *page_id, rotate)?;
+      }
+    }
+    for (name, new_name) in destinations.add_document(&mut document, index)? {
+      warnings.push(match new_name {
+        Some(new_name) => format!(
+          "Document {}: the named destination '{}' was renamed to '{}'",
+          index,
+          String::from_utf8_lossy(&name),
+          String::from_utf8_lossy(&new_name)
+        ),
+        None => format!(
+          "Document {}: the named destination '{}' was dropped, an earlier document defines it",
+          index,
+          String::from_utf8_lossy(&name)
+        ),
+      });
+    }
+    for (tree, name, new_name) in name_trees.add_document(&document, index)? {
+      let (tree, name) = (String::from_utf8_lossy(&tree), String::from_utf8_lossy(&name));
+      warnings.push(match new_name {
+        Some(new_name) => format!(
+          "Document {}: the name '{}' of the /{} name tree was renamed to '{}'",
+          index,
+          name,
+          tree,
+          String::from_utf8_lossy(&new_name)
+        ),
+        None => format!(
+          "Document {}: the name '{}' of the /{} name tree was dropped, an earlier document defines it",
+          index, name, tree
+        ),
+      });
+    }
+    for (name, new_name) in acro_forms.add_document(&mut document) {
+      warnings.push(format!(
+        "Document {}: the form font /{} was renamed to /{}",
+        index,
+        String::from_utf8_lossy(&name),
+        String::from_utf8_lossy(&new_name)
+      ));
+    }
+    for id in structure_trees.add_document(&mut document) {
+      warnings.push(format!(
+        "Document {}: the structure element ID '{}' is used by an earlier document, it was left out of the IDTree",
+        index,
+        String::from_utf8_lossy(&id)
+      ));
+    }
+    if options.portfolios == PortfolioMode::Preserve && portfolio::is_portfolio(&document) {
+      let count = collections.add_document(&document);
+      warnings.push(format!(
+        "Document {}: a portfolio, its {} embedded files were added to the merged collection",
+        index, count
+      ));
+    }
+    // Taken after renumbering and renaming, so an `/OpenAction` still targets the right page
+    if index == options.metadata_from {
+      metadata_catalog = document.catalog().ok().cloned();
+    }
+    let (document_threads, thread_objects) = article_threads(&document);
+    if options.preserve_threads {
+      threads.extend(document_threads);
+    } else if !thread_objects.is_empty() {
+      stripped_threads.extend(thread_objects);
+      warnings.push(format!("Document {}: the article threads were removed", index));
+    }
+    let ranges = page_labels::read_page_labels(&document);
+    labeled |= !ranges.is_empty();
+    let offset = kids.len() as u32;
+    // Outline items need no /Type, so they are found from the root rather than by type below
+    let outline_ids = outline::outline_object_ids(&document);
+    if options.outlines == OutlineMode::Merge {
+      let mut items = outline::read_outline(&document);
+      shift_outline(&mut items, offset);
+      outline.extend(items);
+    }
+    if ranges.is_empty() {
+      // Without labels the pages of the document are numbered from 1, as they were on their own
+      page_labels.push(PageLabelRange {
+        start_page: offset + 1,
+        style: Some("D".to_owned()),
+        prefix: None,
+        start: None,
+      });
+    }
+    for range in ranges.into_iter().filter(|range| range.start_page as usize <= pages.len()) {
+      page_labels.push(PageLabelRange {
+        start_page: range.start_page + offset,
+        ..range
+      });
+    }
+    titles.extend(title.clone());
+    if options.source_info {
+      source_info.push(SourceInfo {
+        index,
+        title: metadata::info_text(&document, b"Title"),
+        author: metadata::info_text(&document, b"Author"),
+      });
+    }
+    if let Some(first_page_id) = pages.values().next() {
+      let title = title.unwrap_or_else(|| format!("Document {}", index + 1));
+      if options.outlines == OutlineMode::PerFile {
+        outline.push(OutlineItem {
+          title: title.clone(),
+          page: Some(offset + 1),
+          structure: None,
+          children: vec![],
+        });
+      }
+      contents.push(TocEntry {
+        title,
+        page_id: *first_page_id,
+      });
+    }
+    if options.pad_each_source && pages.len() % 2 == 1 {
+      if let Some(&last_page_id) = pages.values().last() {
+        let blank_id = document.add_object(page::blank_page(&document, last_page_id));
+        max_id = document.max_id + 1;
+        pages.insert(pages.len() as u32 + 1, blank_id);
+        blank_pages.insert(blank_id);
+        warnings.push(format!("Document {}: a blank page was added after its odd number of pages", index));
+      }
+    }
+    let mut leaves = pages.values().copied().collect::<BTreeSet<_>>();
+    // Template pages aren't in the page tree but the merged `/Templates` tree refers to them
+    leaves.extend(name_trees::template_pages(&document));
+    kid_sources.resize(kid_sources.len() + pages.len(), index);
+    kids.extend(pages.into_values());
+    for (object_id, object) in document.objects {
+      // We have to ignore "Outlines" and "Outline" objects, and "Page" objects outside the page tree.
+      // All other objects should be collected and inserted into the main Document
+      let type_name = object.type_name().unwrap_or("").to_owned();
+      match (type_name.as_str(), object) {
+        ("Catalog", Object::Dictionary(dictionary)) => {
+          let id = catalog_object.map(|(id, _)| id).unwrap_or(object_id);
+          catalog_object = Some((id, dictionary));
+        }
+        // Page trees are dropped, intermediate nodes included, their leaves were collected in `kids`
+        // and carry what they inherited
+        ("Pages", _) => {
+          pages_id.get_or_insert(object_id);
+        }
+        ("Page", mut object) => {
+          if leaves.contains(&object_id) {
+            if let (false, Object::Dictionary(page)) = (options.preserve_threads, &mut object) {
+              page.remove(b"B");
             }
+            merged.objects.insert(object_id, object);
+          }
+        }
+        // The outline items were read above for `outlines: 'merge'`, and `set_outline_in` writes them
+        // again at the end pointing at the merged pages, so the originals, typed or in
+        // `outline_ids`, aren't copied
+        ("Catalog", _) | ("Outlines", _) | ("Outline", _) => {}
+        (_, object) => {
+          if !stripped_threads.contains(&object_id) && !outline_ids.contains(&object_id) {
+            merged.objects.insert(object_id, object);
           }
-          pages_object = Some((
-            if let Some((id, _)) = pages_object {
-              id
-            } else {
-              *object_id
-            },
-            Object::Dictionary(dictionary),
-          ));
         }
       }
-      "Page" => {}     // Ignored, processed later and separately
-      "Outlines" => {} // Ignored, not supported yet
-      "Outline" => {}  // Ignored, not supported yet
-      _ => {
-        document.objects.insert(*object_id, object.clone());
+    }
+  }
+  // New objects must not collide with the inserted ones
+  merged.max_id = max_id;
+  if let (Some(fit), Some(&first_page_id)) = (options.normalize_to_first, kids.first()) {
+    let target = rotate::upright_media_box(&merged, first_page_id);
+    let mut transformed = BTreeSet::new();
+    let mut resized = 0;
+    for page_id in kids.iter() {
+      if rotate::fit_page(&mut merged, *page_id, target, fit, &mut transformed)? {
+        resized += 1;
       }
     }
+    if resized > 0 {
+      warnings.push(format!("{} pages were redrawn at the size of the first page", resized));
+    }
   }
-  // If no "Pages" found abort
-  if pages_object.is_none() {
-    println!("Pages root not found.");
-    return;
+  // Collapsed pages, whose references are pointed to the kept copy once everything is written
+  let mut duplicates = BTreeMap::new();
+  if options.dedupe_pages {
+    duplicates = dedupe::duplicate_pages(&merged, &kids, &blank_pages);
+    if !duplicates.is_empty() {
+      for (page_id, index) in kids.iter().zip(kid_sources) {
+        if duplicates.contains_key(page_id) {
+          *collapsed.entry(index).or_insert(0) += 1;
+        }
+      }
+      collapse_pages(&mut kids, &duplicates, &mut outline, &mut page_labels, &mut contents);
+      for page_id in duplicates.keys() {
+        merged.objects.remove(page_id);
+      }
+      warnings.push(format!("{} duplicate pages were collapsed into their first copy", duplicates.len()));
+    }
   }
-  // Iter over all "Page" and collect with the parent "Pages" created before
-  for (object_id, object) in documents_pages.iter() {
-    if let Ok(dictionary) = object.as_dict() {
-      let mut dictionary = dictionary.clone();
-      dictionary.set("Parent", pages_object.as_ref().unwrap().0);
-      document
-          .objects
-          .insert(*object_id, Object::Dictionary(dictionary));
+  if options.pad_to_even && kids.len() % 2 == 1 {
+    if let Some(&last_page_id) = kids.last() {
+      let blank_id = merged.add_object(page::blank_page(&merged, last_page_id));
+      warnings.push(format!("A blank page was added after the {} merged pages to make them even", kids.len()));
+      kids.push(blank_id);
     }
   }
+  // If no "Pages" found abort
+  let pages_id = pages_id.ok_or_else(|| PdfError::new(ErrorCode::NoPagesRoot, "Pages root not found"))?;
   // If no "Catalog" found abort
-  if catalog_object.is_none() {
-    println!("Catalog root not found.");
-    return;
-  }
-  let catalog_object = catalog_object.unwrap();
-  let pages_object = pages_object.unwrap();
-  // Build a new "Pages" with updated fields
-  if let Ok(dictionary) = pages_object.1.as_dict() {
-    let mut dictionary = dictionary.clone();
-    // Set new pages count
-    dictionary.set("Count", documents_pages.len() as u32);
-    // Set new "Kids" list (collected from documents pages) for "Pages"
-    dictionary.set(
-      "Kids",
-      documents_pages
-          .into_iter()
-          .map(|(object_id, _)| Object::Reference(object_id))
-          .collect::<Vec<_>>(),
-    );
-    document
-        .objects
-        .insert(pages_object.0, Object::Dictionary(dictionary));
+  let (catalog_id, mut catalog_dictionary) = catalog_object
+      .ok_or_else(|| PdfError::new(ErrorCode::InvalidPdf, "Catalog root not found"))?;
+  // Attach every page to a single flat "Pages" root
+  for page_id in kids.iter() {
+    if let Ok(page) = merged.get_object_mut(*page_id).and_then(Object::as_dict_mut) {
+      page.set("Parent", pages_id);
+    }
   }
+  let mut pages_dictionary = Dictionary::new();
+  pages_dictionary.set("Type", Object::Name(b"Pages".to_vec()));
+  // Set new pages count
+  pages_dictionary.set("Count", kids.len() as u32);
+  // Set new "Kids" list (collected from documents pages) for "Pages"
+  pages_dictionary.set("Kids", kids.into_iter().map(Object::Reference).collect::<Vec<_>>());
+  merged.objects.insert(pages_id, Object::Dictionary(pages_dictionary));
   // Build a new "Catalog" with updated fields
-  if let Ok(dictionary) = catalog_object.1.as_dict() {
-    let mut dictionary = dictionary.clone();
-    dictionary.set("Pages", pages_object.0);
-    dictionary.remove(b"Outlines"); // Outlines not supported in merged PDFs
-    document
-        .objects
-        .insert(catalog_object.0, Object::Dictionary(dictionary));
-  }
-  document.trailer.set("Root", catalog_object.0);
-  // Update the max internal ID as wasn't updated before due to direct objects insertion
-  document.max_id = document.objects.len() as u32;
-  // Reorder all new Document objects
-  document.renumber_objects();
-  document.compress();
-  // Save the merged PDF
-  document.save_to(target).unwrap();
+  catalog_dictionary.set("Pages", pages_id);
+  // The chosen catalog's /Outlines points at outline objects left out above. The merged outline,
+  // when `outlines` isn't `drop`, is rebuilt from every document by `set_outline_in` below
+  catalog_dictionary.remove(b"Outlines");
+  destinations.apply(&mut merged, &mut catalog_dictionary);
+  acro_forms.apply(&mut merged, &mut catalog_dictionary);
+  name_trees.apply(&mut merged, &mut catalog_dictionary);
+  collections.apply(&mut catalog_dictionary);
+  structure_trees.apply(&mut merged, &mut catalog_dictionary);
+  catalog_dictionary.remove(b"Threads");
+  if !threads.is_empty() {
+    catalog_dictionary.set("Threads", threads);
+  }
+  catalog_dictionary.remove(b"PageLabels");
+  if labeled {
+    catalog_dictionary.set("PageLabels", page_labels::page_labels_tree(&page_labels));
+  }
+  // Catalog settings come from the chosen document, even when it doesn't define them
+  if let Some(ref metadata_catalog) = metadata_catalog {
+    for key in CATALOG_METADATA_KEYS.iter() {
+      match metadata_catalog.get(key) {
+        Ok(value) => catalog_dictionary.set(key.to_vec(), value.clone()),
+        Err(_) => {
+          catalog_dictionary.remove(key);
+        }
+      }
+    }
+  }
+  if options.provenance && catalog_dictionary.has(b"Metadata") {
+    warnings.push(format!(
+      "Document {}: the XMP metadata was replaced with the merge provenance",
+      options.metadata_from
+    ));
+  }
+  merged.objects.insert(catalog_id, Object::Dictionary(catalog_dictionary));
+  merged.trailer.set("Root", catalog_id);
+  if options.provenance {
+    let producer = producer::producer(&options.save.producer);
+    let packet = xmp::provenance_packet(&producer, source_count, &titles, &source_info);
+    xmp::set_provenance(&mut merged, &producer, packet)?;
+  }
+  let mut toc_pages = 0;
+  if options.table_of_contents {
+    toc_pages = toc::add_table_of_contents(
+      &mut merged,
+      &contents,
+      options.table_of_contents_page_size,
+      options.pad_each_source || options.pad_to_even,
+    )?;
+    if labeled {
+      // The contents pages are numbered apart, in lowercase roman
+      let mut ranges = vec![PageLabelRange {
+        start_page: 1,
+        style: Some("r".to_owned()),
+        prefix: None,
+        start: None,
+      }];
+      ranges.extend(page_labels.into_iter().map(|range| PageLabelRange {
+        start_page: range.start_page + toc_pages,
+        ..range
+      }));
+      page_labels::write_page_labels(&mut merged, &ranges)?;
+    }
+  }
+  if options.outlines != OutlineMode::Drop {
+    shift_outline(&mut outline, toc_pages);
+    outline::set_outline_in(&mut merged, &outline)?;
+  }
+  if !duplicates.is_empty() {
+    for object in merged.objects.values_mut() {
+      replace_references(object, &duplicates);
+    }
+    // The contents and annotations of the collapsed pages are left unreferenced
+    merged.prune_objects();
+  }
+  // Reorder all new Document objects in a single pass, unless the caller chose the ids
+  if options.remapped_ids.is_empty() {
+    let start = options.start_object_id.unwrap_or(1);
+    // The objects are numbered from `start` to `start + count - 1`, leaving room for the document
+    // information dictionary the producer stamp adds when the result is written
+    if start.checked_add(merged.objects.len() as u32).is_none_or(|end| end > MAX_OBJECT_ID) {
+      return Err(PdfError::new(
+        ErrorCode::LimitExceeded,
+        format!(
+          "The {} objects of the result can't be numbered from startObjectId {} without going past {}",
+          merged.objects.len(),
+          start,
+          MAX_OBJECT_ID
+        ),
+      ));
+    }
+    merged.renumber_objects_with(start);
+  }
+  Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils;
+
+  fn source(document: Document, index: usize) -> MergeSource {
+    MergeSource {
+      document,
+      rotate: 0,
+      index,
+      title: None,
+    }
+  }
+
+  fn viewer_preferences(hide_toolbar: bool) -> Dictionary {
+    let mut preferences = Dictionary::new();
+    preferences.set("HideToolbar", hide_toolbar);
+    preferences
+  }
+
+  #[test]
+  fn merges_the_pages_in_order_under_one_root() {
+    let sources = vec![
+      source(test_utils::document_with_label(2, "First"), 0),
+      source(test_utils::document_with_label(3, "Second"), 1),
+    ];
+    let merged = merge_sources(sources, &MergeOptions::default(), &mut vec![]).unwrap();
+    let texts = merged
+        .get_pages()
+        .values()
+        .map(|&page_id| test_utils::page_text(&merged, page_id))
+        .collect::<Vec<_>>();
+    assert_eq!(texts, ["First 1", "First 2", "Second 1", "Second 2", "Second 3"]);
+    let root = merged.catalog().and_then(|catalog| catalog.get(b"Pages")).and_then(Object::as_reference).unwrap();
+    let count = merged.get_dictionary(root).and_then(|pages| pages.get(b"Count")).and_then(Object::as_i64);
+    assert_eq!(count.unwrap(), 5);
+  }
+
+  #[test]
+  fn metadata_from_picks_the_catalog_settings() {
+    let mut first = test_utils::document(1);
+    test_utils::catalog_mut(&mut first).set("ViewerPreferences", viewer_preferences(false));
+    test_utils::catalog_mut(&mut first).set("PageLayout", Object::Name(b"SinglePage".to_vec()));
+    let mut second = test_utils::document(1);
+    test_utils::catalog_mut(&mut second).set("ViewerPreferences", viewer_preferences(true));
+    test_utils::catalog_mut(&mut second).set("Lang", Object::string_literal("fr-FR"));
+
+    let options = MergeOptions {
+      metadata_from: 1,
+      ..MergeOptions::default()
+    };
+    let sources = vec![source(first.clone(), 0), source(second.clone(), 1)];
+    let merged = merge_sources(sources, &options, &mut vec![]).unwrap();
+    let catalog = merged.catalog().unwrap();
+    let preferences = catalog.get(b"ViewerPreferences").and_then(Object::as_dict).unwrap();
+    assert!(preferences.get(b"HideToolbar").and_then(Object::as_bool).unwrap());
+    assert_eq!(catalog.get(b"Lang").and_then(Object::as_str).unwrap(), b"fr-FR");
+    // Settings only the other document has aren't kept
+    assert!(!catalog.has(b"PageLayout"));
+
+    let sources = vec![source(first, 0), source(second, 1)];
+    let merged = merge_sources(sources, &MergeOptions::default(), &mut vec![]).unwrap();
+    let catalog = merged.catalog().unwrap();
+    let preferences = catalog.get(b"ViewerPreferences").and_then(Object::as_dict).unwrap();
+    assert!(!preferences.get(b"HideToolbar").and_then(Object::as_bool).unwrap());
+    assert!(!catalog.has(b"Lang"));
+  }
+
+  #[test]
+  fn merges_hundreds_of_documents_within_a_time_budget() {
+    let sources = (0..200)
+        .map(|index| source(test_utils::document_with_label(2, &format!("Document {}", index)), index))
+        .collect::<Vec<_>>();
+    let start = Instant::now();
+    let merged = merge_sources(sources, &MergeOptions::default(), &mut vec![]).unwrap();
+    // Generous for an unoptimized build, a merge quadratic in the documents takes far longer
+    assert!(start.elapsed() < Duration::from_secs(20), "took {:?}", start.elapsed());
+    let pages = merged.get_pages();
+    assert_eq!(pages.len(), 400);
+    assert_eq!(test_utils::page_text(&merged, pages[&400]), "Document 199 2");
+  }
 }
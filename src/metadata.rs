@@ -0,0 +1,216 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use lopdf::{Dictionary, Document, Object};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status, ValueType};
+
+use crate::error::{self, OrThrow};
+use crate::utils::{buffer_value, decode_text_string, load_document, output_update, Output};
+
+/// Text entries of the Info dictionary, with their `getMetadata` property names
+const TEXT_KEYS: [(&str, &str); 6] = [
+  ("Title", "title"),
+  ("Author", "author"),
+  ("Subject", "subject"),
+  ("Keywords", "keywords"),
+  ("Creator", "creator"),
+  ("Producer", "producer"),
+];
+
+/// Date entries of the Info dictionary, with their property names
+const DATE_KEYS: [(&str, &str); 2] = [("CreationDate", "creationDate"), ("ModDate", "modDate")];
+
+/// A change to one Info dictionary entry
+#[derive(Clone)]
+pub enum InfoUpdate<T> {
+  /// `undefined`, the entry is left alone
+  Keep,
+  /// `null`, the entry is removed
+  Clear,
+  Set(T),
+}
+
+/// Read an optional property where `null` means removing the entry
+pub fn info_update(options: &JsObject, name: &str) -> Result<InfoUpdate<String>> {
+  let value = options.get_named_property::<JsUnknown>(name)?;
+  match value.get_type()? {
+    ValueType::Undefined => Ok(InfoUpdate::Keep),
+    ValueType::Null => Ok(InfoUpdate::Clear),
+    ValueType::String => Ok(InfoUpdate::Set(value.coerce_to_string()?.into_utf8()?.into_owned()?)),
+    _ => Err(Error::new(
+      Status::InvalidArg,
+      format!("{} must be a string or null", name),
+    )),
+  }
+}
+
+/// Parse an ISO-8601 date. Without an offset the time is taken as UTC, a bare date is midnight.
+fn parse_iso_date(name: &str, value: &str) -> Result<DateTime<FixedOffset>> {
+  if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+    return Ok(date);
+  }
+  let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+      .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M"))
+      .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|date| date.and_time(NaiveTime::MIN)));
+  match naive {
+    Ok(naive) => Ok(Utc.from_utc_datetime(&naive).fixed_offset()),
+    Err(_) => Err(Error::new(
+      Status::InvalidArg,
+      format!("{} must be an ISO-8601 date, got '{}'", name, value),
+    )),
+  }
+}
+
+/// Format a date as a PDF date string, `D:YYYYMMDDHHmmSSOHH'mm'`
+pub fn format_pdf_date(date: &DateTime<FixedOffset>) -> String {
+  let offset = date.offset().local_minus_utc();
+  let zone = if offset == 0 {
+    "Z".to_owned()
+  } else {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let minutes = offset.abs() / 60;
+    format!("{}{:02}'{:02}'", sign, minutes / 60, minutes % 60)
+  };
+  format!("D:{}{}", date.format("%Y%m%d%H%M%S"), zone)
+}
+
+/// Parse a PDF date string into an ISO-8601 date. Only the year is mandatory,
+/// a missing offset is taken as UTC.
+pub fn parse_pdf_date(value: &str) -> Option<String> {
+  let value = value.trim();
+  let value = value.strip_prefix("D:").unwrap_or(value);
+  let digits = value.chars().take_while(char::is_ascii_digit).count();
+  if digits < 4 || digits % 2 != 0 || digits > 14 {
+    return None;
+  }
+  let field = |start: usize, default: u32| -> Option<u32> {
+    if start + 2 <= digits {
+      value[start..start + 2].parse().ok()
+    } else {
+      Some(default)
+    }
+  };
+  let year = value[..4].parse().ok()?;
+  let date = NaiveDate::from_ymd_opt(year, field(4, 1)?, field(6, 1)?)?
+      .and_hms_opt(field(8, 0)?, field(10, 0)?, field(12, 0)?)?;
+  let zone = &value[digits..];
+  let offset = match zone.chars().next() {
+    Some(sign @ ('+' | '-')) => {
+      let parts = zone[1..]
+          .split('\'')
+          .filter(|part| !part.is_empty())
+          .map(|part| part.parse::<i32>().ok())
+          .collect::<Option<Vec<_>>>()?;
+      let seconds = parts.first().copied().unwrap_or(0) * 3600 + parts.get(1).copied().unwrap_or(0) * 60;
+      FixedOffset::east_opt(if sign == '-' { -seconds } else { seconds })?
+    }
+    _ => FixedOffset::east_opt(0)?,
+  };
+  let date = offset.from_local_datetime(&date).single()?;
+  Some(date.to_rfc3339())
+}
+
+/// A text entry of the Info dictionary, decoded
+pub fn info_text(document: &Document, key: &[u8]) -> Option<String> {
+  let (_, info) = document.dereference(document.trailer.get(b"Info").ok()?).ok()?;
+  match document.dereference(info.as_dict().ok()?.get(key).ok()?).ok()? {
+    (_, Object::String(bytes, _)) => Some(decode_text_string(bytes)),
+    _ => None,
+  }
+}
+
+/// The Info dictionary, created and referenced from the trailer when missing
+pub fn info_dictionary_mut(document: &mut Document) -> error::Result<&mut Dictionary> {
+  let info_id = match document.trailer.get(b"Info") {
+    Ok(Object::Reference(id)) if document.objects.contains_key(id) => *id,
+    // A direct dictionary is kept, but moved into an object
+    info => {
+      let info = info.and_then(Object::as_dict).cloned().unwrap_or_default();
+      let id = document.add_object(info);
+      document.trailer.set("Info", id);
+      id
+    }
+  };
+  Ok(document.get_object_mut(info_id).and_then(Object::as_dict_mut)?)
+}
+
+pub struct DatesOptions {
+  creation_date: InfoUpdate<DateTime<FixedOffset>>,
+  mod_date: InfoUpdate<DateTime<FixedOffset>>,
+  output: Output,
+}
+
+impl DatesOptions {
+  pub fn from_js(options: JsObject) -> Result<Self> {
+    let date = |name: &str| -> Result<InfoUpdate<DateTime<FixedOffset>>> {
+      Ok(match info_update(&options, name)? {
+        InfoUpdate::Keep => InfoUpdate::Keep,
+        InfoUpdate::Clear => InfoUpdate::Clear,
+        InfoUpdate::Set(value) => InfoUpdate::Set(parse_iso_date(name, &value)?),
+      })
+    };
+    Ok(DatesOptions {
+      creation_date: date("creationDate")?,
+      mod_date: date("modDate")?,
+      output: Output::from_js(&options)?,
+    })
+  }
+}
+
+#[js_function(2)]
+pub fn set_dates(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let options = DatesOptions::from_js(ctx.get::<JsObject>(1)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  set_dates_in(&mut document, &options).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &options.output)
+}
+
+pub fn set_dates_in(document: &mut Document, options: &DatesOptions) -> error::Result<()> {
+  let info = info_dictionary_mut(document)?;
+  let updates = [
+    ("CreationDate", &options.creation_date),
+    ("ModDate", &options.mod_date),
+  ];
+  for (key, update) in updates.iter() {
+    match update {
+      InfoUpdate::Keep => {}
+      InfoUpdate::Clear => {
+        info.remove(key.as_bytes());
+      }
+      InfoUpdate::Set(date) => info.set(*key, Object::string_literal(format_pdf_date(date))),
+    }
+  }
+  Ok(())
+}
+
+#[js_function(1)]
+pub fn get_metadata(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  let mut result = ctx.env.create_object()?;
+  let info = document
+      .trailer
+      .get(b"Info")
+      .and_then(|info| document.dereference(info))
+      .and_then(|(_, info)| info.as_dict());
+  let info = match info {
+    Ok(info) => info,
+    Err(_) => return Ok(result),
+  };
+  let text = |key: &str| match info.get(key.as_bytes()).and_then(|value| document.dereference(value)) {
+    Ok((_, Object::String(bytes, _))) => Some(decode_text_string(bytes)),
+    _ => None,
+  };
+  for (key, name) in TEXT_KEYS.iter() {
+    if let Some(value) = text(key) {
+      result.set_named_property(name, ctx.env.create_string(&value)?)?;
+    }
+  }
+  // Dates that aren't valid PDF dates are returned as written
+  for (key, name) in DATE_KEYS.iter() {
+    if let Some(value) = text(key) {
+      let value = parse_pdf_date(&value).unwrap_or(value);
+      result.set_named_property(name, ctx.env.create_string(&value)?)?;
+    }
+  }
+  Ok(result)
+}
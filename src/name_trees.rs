@@ -0,0 +1,136 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use napi::{JsObject, Result};
+
+use crate::destinations::{merge_entries, DestinationConflicts};
+use crate::error::{self, ErrorCode, PdfError};
+use crate::names::{name_tree, name_tree_entries};
+
+/// The catalog `/Names` trees merged across documents, besides `/Dests` which `Destinations` merges
+pub const MERGED_NAME_TREES: [&[u8]; 9] = [
+  b"AP",
+  b"JavaScript",
+  b"Pages",
+  b"Templates",
+  b"IDS",
+  b"URLS",
+  b"EmbeddedFiles",
+  b"AlternatePresentations",
+  b"Renditions",
+];
+
+/// What to do with a name of each tree already defined by an earlier document
+#[derive(Clone, Default)]
+pub struct NameTreeConflicts(BTreeMap<Vec<u8>, DestinationConflicts>);
+
+impl NameTreeConflicts {
+  /// Read the `nameTreeConflicts` option, an object keyed by tree name, `rename` for every tree
+  /// it leaves out
+  pub fn from_js(options: &JsObject) -> Result<Self> {
+    let mut conflicts = BTreeMap::new();
+    if let Some(policies) = options.get_named_property::<Option<JsObject>>("nameTreeConflicts")? {
+      for tree in MERGED_NAME_TREES.iter() {
+        let tree_name = String::from_utf8_lossy(tree);
+        if let Some(value) = policies.get_named_property::<Option<String>>(&tree_name)? {
+          let option = format!("nameTreeConflicts.{}", tree_name);
+          conflicts.insert(tree.to_vec(), DestinationConflicts::parse(&option, Some(&value))?);
+        }
+      }
+    }
+    Ok(NameTreeConflicts(conflicts))
+  }
+
+  fn get(&self, tree: &[u8]) -> DestinationConflicts {
+    self.0.get(tree).copied().unwrap_or_default()
+  }
+}
+
+/// A name a document shares with an earlier one: the tree, the name, and its new name or `None`
+/// when it was dropped
+pub type NameConflict = (Vec<u8>, Vec<u8>, Option<Vec<u8>>);
+
+/// The catalog `/Names` dictionary of a document
+fn names_dictionary(document: &Document) -> Option<&Dictionary> {
+  document
+      .catalog()
+      .and_then(|catalog| catalog.get(b"Names"))
+      .and_then(|names| document.dereference(names))
+      .and_then(|(_, names)| names.as_dict())
+      .ok()
+}
+
+/// The pages of the `/Templates` tree, which sit outside the page tree
+pub fn template_pages(document: &Document) -> BTreeSet<ObjectId> {
+  names_dictionary(document)
+      .and_then(|names| names.get(b"Templates").ok())
+      .map(|tree| name_tree_entries(document, tree))
+      .unwrap_or_default()
+      .into_iter()
+      .filter_map(|(_, page)| page.as_reference().ok())
+      .collect()
+}
+
+/// Name trees collected from all merged documents
+#[derive(Default)]
+pub struct NameTrees {
+  conflicts: NameTreeConflicts,
+  trees: BTreeMap<Vec<u8>, BTreeMap<Vec<u8>, Object>>,
+}
+
+impl NameTrees {
+  pub fn new(conflicts: NameTreeConflicts) -> Self {
+    NameTrees {
+      conflicts,
+      ..NameTrees::default()
+    }
+  }
+
+  /// Collect the name trees of a (renumbered) document, the `index`-th of the merge. Names an
+  /// earlier document defined are renamed, or dropped, or fail the merge, depending on the policy
+  /// of their tree. Nothing refers to these names within the document, so nothing else changes.
+  pub fn add_document(&mut self, document: &Document, index: usize) -> error::Result<Vec<NameConflict>> {
+    let names = match names_dictionary(document) {
+      Some(names) => names,
+      None => return Ok(vec![]),
+    };
+    let mut collided = vec![];
+    for tree in MERGED_NAME_TREES.iter() {
+      let entries = match names.get(tree) {
+        Ok(root) => name_tree_entries(document, root),
+        Err(_) => continue,
+      };
+      let merged = self.trees.entry(tree.to_vec()).or_default();
+      let conflicts = merge_entries(merged, entries, self.conflicts.get(tree)).map_err(|name| {
+        PdfError::new(
+          ErrorCode::GenericFailure,
+          format!(
+            "Document {}: the name '{}' of the /{} name tree is also defined by an earlier document",
+            index,
+            String::from_utf8_lossy(&name),
+            String::from_utf8_lossy(tree)
+          ),
+        )
+      })?;
+      collided.extend(conflicts.into_iter().map(|(name, new_name)| (tree.to_vec(), name, new_name)));
+    }
+    Ok(collided)
+  }
+
+  /// Write the merged trees into the catalog of the merged document
+  pub fn apply(&self, document: &mut Document, catalog: &mut Dictionary) {
+    if self.trees.is_empty() {
+      return;
+    }
+    let mut names = catalog
+        .get(b"Names")
+        .and_then(|names| document.dereference(names))
+        .and_then(|(_, names)| names.as_dict())
+        .cloned()
+        .unwrap_or_default();
+    for (tree, entries) in self.trees.iter().filter(|(_, entries)| !entries.is_empty()) {
+      names.set(tree.clone(), document.add_object(name_tree(entries)));
+    }
+    catalog.set("Names", names);
+  }
+}
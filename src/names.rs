@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+/// Collect the `(key, value)` entries of a name or number tree, whose leaves hold them in `leaf_key`
+fn tree_entries(document: &Document, root: &Object, leaf_key: &[u8]) -> Vec<(Object, Object)> {
+  fn walk(
+    document: &Document, node: &Object, leaf_key: &[u8], visited: &mut BTreeSet<ObjectId>,
+    entries: &mut Vec<(Object, Object)>,
+  ) {
+    if let Object::Reference(id) = node {
+      if !visited.insert(*id) {
+        return;
+      }
+    }
+    let node = match document.dereference(node).ok().and_then(|(_, node)| node.as_dict().ok()) {
+      Some(node) => node,
+      None => return,
+    };
+    if let Ok(leaves) = node.get(leaf_key).and_then(Object::as_array) {
+      for pair in leaves.chunks(2) {
+        if let [key, value] = pair {
+          entries.push((key.clone(), value.clone()));
+        }
+      }
+    }
+    if let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) {
+      for kid in kids {
+        walk(document, kid, leaf_key, visited, entries);
+      }
+    }
+  }
+  let mut entries = vec![];
+  walk(document, root, leaf_key, &mut BTreeSet::new(), &mut entries);
+  entries
+}
+
+/// Collect the `(key, value)` entries of a name tree, walking `Kids` recursively
+pub fn name_tree_entries(document: &Document, root: &Object) -> Vec<(Vec<u8>, Object)> {
+  tree_entries(document, root, b"Names")
+      .into_iter()
+      .filter_map(|(key, value)| Some((key.as_str().ok()?.to_vec(), value)))
+      .collect()
+}
+
+/// Collect the `(key, value)` entries of a number tree, walking `Kids` recursively
+pub fn number_tree_entries(document: &Document, root: &Object) -> Vec<(i64, Object)> {
+  tree_entries(document, root, b"Nums")
+      .into_iter()
+      .filter_map(|(key, value)| Some((key.as_i64().ok()?, value)))
+      .collect()
+}
+
+/// Ids of the indirect nodes of a name or number tree, to remove once its entries are rewritten
+pub fn tree_node_ids(document: &Document, root: &Object) -> BTreeSet<ObjectId> {
+  fn walk(document: &Document, node: &Object, ids: &mut BTreeSet<ObjectId>) {
+    if let Object::Reference(id) = node {
+      if !ids.insert(*id) {
+        return;
+      }
+    }
+    if let Ok(kids) = document
+        .dereference(node)
+        .and_then(|(_, node)| node.as_dict())
+        .and_then(|node| node.get(b"Kids"))
+        .and_then(Object::as_array)
+    {
+      for kid in kids {
+        walk(document, kid, ids);
+      }
+    }
+  }
+  let mut ids = BTreeSet::new();
+  walk(document, root, &mut ids);
+  ids
+}
+
+/// Build a single-node name tree from sorted entries
+pub fn name_tree(entries: &BTreeMap<Vec<u8>, Object>) -> Dictionary {
+  let mut names = Vec::with_capacity(entries.len() * 2);
+  for (key, value) in entries {
+    names.push(Object::string_literal(key.clone()));
+    names.push(value.clone());
+  }
+  let mut tree = Dictionary::new();
+  tree.set("Names", names);
+  tree
+}
+
+/// Build a single-node number tree from sorted entries
+pub fn number_tree(entries: &BTreeMap<i64, Object>) -> Dictionary {
+  let mut nums = Vec::with_capacity(entries.len() * 2);
+  for (key, value) in entries {
+    nums.push(Object::Integer(*key));
+    nums.push(value.clone());
+  }
+  let mut tree = Dictionary::new();
+  tree.set("Nums", nums);
+  tree
+}
+
+/// Derive a unique name from `name` by appending `_2`, `_3`, ... until it's not taken
+pub fn unique_name<F: Fn(&[u8]) -> bool>(name: &[u8], taken: F) -> Vec<u8> {
+  let mut index = 2;
+  loop {
+    let mut candidate = name.to_vec();
+    candidate.extend_from_slice(format!("_{}", index).as_bytes());
+    if !taken(&candidate) {
+      return candidate;
+    }
+    index += 1;
+  }
+}
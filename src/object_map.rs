@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use lopdf::{Document, Object};
+use napi::{CallContext, JsBuffer, JsObject, Result};
+
+use crate::error::OrThrow;
+use crate::repair;
+use crate::utils::{buffer_value, load_document};
+
+/// The kind of an object without a `/Type`
+fn kind(object: &Object) -> &'static str {
+  match object {
+    Object::Null => "null",
+    Object::Boolean(_) => "boolean",
+    Object::Integer(_) => "integer",
+    Object::Real(_) => "real",
+    Object::Name(_) => "name",
+    Object::String(_, _) => "string",
+    Object::Array(_) => "array",
+    Object::Dictionary(_) => "dictionary",
+    Object::Stream(_) => "stream",
+    Object::Reference(_) => "reference",
+  }
+}
+
+fn find(data: &[u8], from: usize, keyword: &[u8]) -> Option<usize> {
+  data.get(from..)?
+      .windows(keyword.len())
+      .position(|window| window == keyword)
+      .map(|position| from + position)
+}
+
+/// The length of the object written at `offset`, up to and including its `endobj`. The data of a
+/// stream is skipped by its length, as it may contain the keyword, except for object streams which
+/// lopdf decompressed when loading.
+fn object_length(data: &[u8], offset: usize, object: Option<&Object>) -> Option<usize> {
+  let mut from = offset;
+  if let Some(Object::Stream(stream)) = object.filter(|object| object.type_name().ok() != Some("ObjStm")) {
+    let keyword = (offset..data.len()).find(|&index| {
+      data[index..].starts_with(b"stream")
+          && !data[..index].ends_with(b"end")
+          && matches!(data.get(index + 6), Some(b'\r') | Some(b'\n'))
+    })?;
+    let start = keyword + if data[keyword + 6..].starts_with(b"\r\n") { 8 } else { 7 };
+    from = start + stream.content.len();
+  }
+  find(data, from, b"endobj").map(|end| end + b"endobj".len() - offset)
+}
+
+/// The object stream holding each compressed object, and the object's index in it
+fn compressed_objects(document: &Document) -> BTreeMap<u32, (u32, u32)> {
+  let mut compressed = BTreeMap::new();
+  for (&(container, _), object) in document.objects.iter() {
+    let stream = match object {
+      Object::Stream(stream) if stream.dict.type_is(b"ObjStm") => stream,
+      _ => continue,
+    };
+    let first = stream.dict.get(b"First").and_then(Object::as_i64).unwrap_or(0).max(0) as usize;
+    let header = String::from_utf8_lossy(stream.content.get(..first).unwrap_or(&[]));
+    let numbers = header.split_ascii_whitespace().step_by(2).filter_map(|number| number.parse().ok());
+    for (index, number) in numbers.enumerate() {
+      compressed.entry(number).or_insert((container, index as u32));
+    }
+  }
+  compressed
+}
+
+#[js_function(1)]
+pub fn object_map(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  // The object headers in the file, standing in for the offsets lopdf keeps private
+  let offsets = repair::object_offsets(&buffer);
+  let compressed = compressed_objects(&document);
+  let located = |number: u32| -> Option<(usize, usize)> {
+    let &(generation, offset) = offsets.get(&number)?;
+    let length = object_length(&buffer, offset, document.objects.get(&(number, generation)))?;
+    Some((offset, length))
+  };
+  let mut result = ctx.env.create_array_with_length(0)?;
+  let mut index = 0;
+  for (&(number, generation), object) in document.objects.iter() {
+    // Whichever of the file the newest cross-reference section points at
+    let in_stream = document
+        .reference_table
+        .get(number)
+        .map_or(!offsets.contains_key(&number), |entry| entry.is_compressed());
+    let (location, container) = match compressed.get(&number) {
+      Some(&(container, stream_index)) if in_stream => (located(container), Some((container, stream_index))),
+      _ => (located(number), None),
+    };
+    let (offset, length) = match location {
+      Some(location) => location,
+      None => continue,
+    };
+    let mut item = ctx.env.create_object()?;
+    item.set_named_property("id", ctx.env.create_uint32(number)?)?;
+    item.set_named_property("gen", ctx.env.create_uint32(generation as u32)?)?;
+    item.set_named_property("offset", ctx.env.create_int64(offset as i64)?)?;
+    item.set_named_property("length", ctx.env.create_int64(length as i64)?)?;
+    // The `/Type` of a dictionary or stream, otherwise the kind of object
+    item.set_named_property("type", ctx.env.create_string(object.type_name().unwrap_or_else(|_| kind(object)))?)?;
+    if let Some((container, stream_index)) = container {
+      item.set_named_property("container", ctx.env.create_uint32(container)?)?;
+      item.set_named_property("index", ctx.env.create_uint32(stream_index)?)?;
+    }
+    result.set_element(index, item)?;
+    index += 1;
+  }
+  Ok(result)
+}
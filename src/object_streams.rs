@@ -0,0 +1,39 @@
+use lopdf::{Document, Object};
+use napi::{CallContext, JsBuffer, JsObject, JsUnknown, Result};
+
+use crate::error::OrThrow;
+use crate::utils::{buffer_value, load_document, output, output_update};
+
+/// Trailer entries only meaningful in a cross-reference stream dictionary
+pub const XREF_STREAM_KEYS: [&[u8]; 8] = [
+  b"Type",
+  b"W",
+  b"Index",
+  b"Prev",
+  b"XRefStm",
+  b"Filter",
+  b"DecodeParms",
+  b"Length",
+];
+
+#[js_function(2)]
+pub fn expand_object_streams(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(1)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  expand_object_streams_in(&mut document);
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Drop the object and cross-reference streams. lopdf has already unpacked the objects they hold
+/// and always writes a classic xref table, so what's left are the containers themselves.
+pub fn expand_object_streams_in(document: &mut Document) {
+  document.objects.retain(|_, object| match object {
+    Object::Stream(stream) => !stream.dict.type_is(b"ObjStm") && !stream.dict.type_is(b"XRef"),
+    _ => true,
+  });
+  // A trailer read from an xref stream keeps the entries of the stream dictionary
+  for key in XREF_STREAM_KEYS.iter() {
+    document.trailer.remove(key);
+  }
+}
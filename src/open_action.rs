@@ -0,0 +1,123 @@
+use lopdf::{Dictionary, Document, Object};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status, ValueType};
+
+use crate::error::{self, ErrorCode, OrThrow, PdfError};
+use crate::page;
+use crate::utils::{buffer_value, load_document, output, output_update};
+
+/// How a `GoTo` open action displays the page
+pub enum Zoom {
+  /// Keep the viewer's current zoom
+  Inherit,
+  /// A magnification factor, 1 is 100%
+  Factor(f64),
+  /// Fit the whole page in the window
+  Fit,
+  /// Fit the page width in the window
+  FitWidth,
+}
+
+/// What the viewer does when the document is opened
+pub enum OpenAction {
+  /// Jump to a 1-based page
+  GoTo { page: u32, zoom: Zoom },
+  /// Open a URI
+  Uri(String),
+}
+
+impl OpenAction {
+  pub fn from_js(action: JsObject) -> Result<Self> {
+    let page = action.get_named_property::<Option<u32>>("page")?;
+    let url = action.get_named_property::<Option<String>>("url")?;
+    let zoom = action.get_named_property::<JsUnknown>("zoom")?;
+    let zoom = match zoom.get_type()? {
+      ValueType::Undefined => Zoom::Inherit,
+      ValueType::Number => {
+        let factor = zoom.coerce_to_number()?.get_double()?;
+        if factor <= 0.0 {
+          return Err(Error::new(Status::InvalidArg, "zoom must be positive".to_owned()));
+        }
+        Zoom::Factor(factor)
+      }
+      ValueType::String => match zoom.coerce_to_string()?.into_utf8()?.as_str()? {
+        "fit" => Zoom::Fit,
+        "fitWidth" => Zoom::FitWidth,
+        other => {
+          return Err(Error::new(
+            Status::InvalidArg,
+            format!("zoom must be a number, 'fit' or 'fitWidth', got '{}'", other),
+          ))
+        }
+      },
+      _ => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "zoom must be a number, 'fit' or 'fitWidth'".to_owned(),
+        ))
+      }
+    };
+    match (page, url) {
+      (Some(0), None) => Err(Error::new(Status::InvalidArg, "page is 1-based".to_owned())),
+      (Some(page), None) => Ok(OpenAction::GoTo { page, zoom }),
+      (None, Some(url)) => Ok(OpenAction::Uri(url)),
+      _ => Err(Error::new(
+        Status::InvalidArg,
+        "The open action needs exactly one of page or url".to_owned(),
+      )),
+    }
+  }
+}
+
+#[js_function(3)]
+pub fn set_open_action(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let action = OpenAction::from_js(ctx.get::<JsObject>(1)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  set_open_action_in(&mut document, &action).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Replace the catalog `/OpenAction`
+pub fn set_open_action_in(document: &mut Document, action: &OpenAction) -> error::Result<()> {
+  let value = match action {
+    OpenAction::GoTo { page, zoom } => {
+      let page_id = page::page_ids(document, &[*page])?[0];
+      let mut destination = vec![page_id.into()];
+      match zoom {
+        Zoom::Inherit => destination.extend(vec![
+          Object::Name(b"XYZ".to_vec()),
+          Object::Null,
+          Object::Null,
+          Object::Null,
+        ]),
+        Zoom::Factor(factor) => destination.extend(vec![
+          Object::Name(b"XYZ".to_vec()),
+          Object::Null,
+          Object::Null,
+          (*factor).into(),
+        ]),
+        Zoom::Fit => destination.push(Object::Name(b"Fit".to_vec())),
+        Zoom::FitWidth => destination.extend(vec![Object::Name(b"FitH".to_vec()), Object::Null]),
+      }
+      // A bare destination array is a valid `/OpenAction`, and what most writers produce
+      Object::Array(destination)
+    }
+    OpenAction::Uri(url) => {
+      let mut uri = Dictionary::new();
+      uri.set("S", Object::Name(b"URI".to_vec()));
+      uri.set("URI", Object::string_literal(url.as_str()));
+      Object::Dictionary(uri)
+    }
+  };
+  let root_id = document
+      .trailer
+      .get(b"Root")
+      .and_then(Object::as_reference)
+      .map_err(|_| PdfError::new(ErrorCode::InvalidPdf, "The trailer has no /Root"))?;
+  document
+      .get_object_mut(root_id)
+      .and_then(Object::as_dict_mut)?
+      .set("OpenAction", value);
+  Ok(())
+}
@@ -0,0 +1,343 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use napi::{CallContext, Env, Error, JsBuffer, JsObject, JsUnknown, Result, Status};
+
+use crate::error::{self, ErrorCode, OrThrow, PdfError};
+use crate::names::name_tree_entries;
+use crate::page;
+use crate::utils::{buffer_value, decode_text_string, encode_text_string, load_document, output, output_update};
+
+/// One bookmark and the bookmarks nested under it
+pub struct OutlineItem {
+  pub title: String,
+  /// 1-based target page, `None` when the item has no destination in the document
+  pub page: Option<u32>,
+  /// Structure destination (`/SD`) of the item, an array starting with the target element, kept
+  /// along with the page for readers navigating the structure tree
+  pub structure: Option<Object>,
+  pub children: Vec<OutlineItem>,
+}
+
+/// Deepest nesting of bookmarks, which are read and written recursively
+pub const MAX_OUTLINE_DEPTH: usize = 256;
+
+impl OutlineItem {
+  /// Read an item nested `depth` levels deep, 1 at the top of the outline
+  fn from_js(item: JsObject, depth: usize) -> Result<Self> {
+    let page = item.get_named_property::<Option<u32>>("page")?;
+    if page == Some(0) {
+      return Err(Error::new(Status::InvalidArg, "page is 1-based".to_owned()));
+    }
+    let children = match item.get_named_property::<Option<JsObject>>("children")? {
+      Some(_) if depth == MAX_OUTLINE_DEPTH => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("Bookmarks can be nested at most {} levels deep", MAX_OUTLINE_DEPTH),
+        ));
+      }
+      Some(children) => items_from_js(children, depth + 1)?,
+      None => vec![],
+    };
+    Ok(OutlineItem {
+      title: item.get_named_property::<String>("title")?,
+      page,
+      structure: None,
+      children,
+    })
+  }
+}
+
+fn items_from_js(items: JsObject, depth: usize) -> Result<Vec<OutlineItem>> {
+  (0..items.get_array_length()?)
+      .map(|index| OutlineItem::from_js(items.get_element::<JsObject>(index)?, depth))
+      .collect()
+}
+
+/// Read an `OutlineItem[]` argument
+pub fn outline_from_js(items: JsObject) -> Result<Vec<OutlineItem>> {
+  items_from_js(items, 1)
+}
+
+/// Resolves outline destinations to page numbers
+struct Destinations<'a> {
+  document: &'a Document,
+  page_numbers: BTreeMap<ObjectId, u32>,
+  /// Entries of the `/Names /Dests` name tree
+  named: BTreeMap<Vec<u8>, Object>,
+}
+
+impl<'a> Destinations<'a> {
+  fn new(document: &'a Document) -> Self {
+    let page_numbers = document.get_pages().into_iter().map(|(number, id)| (id, number)).collect();
+    let named = document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Names"))
+        .and_then(|names| document.dereference(names))
+        .and_then(|(_, names)| names.as_dict())
+        .and_then(|names| names.get(b"Dests"))
+        .map(|dests| name_tree_entries(document, dests).into_iter().collect())
+        .unwrap_or_default();
+    Destinations {
+      document,
+      page_numbers,
+      named,
+    }
+  }
+
+  /// Page of an explicit destination array, or of a named destination (a name in the catalog `/Dests`
+  /// dictionary, or a string in the `/Names` tree) whose value is the array or a `<< /D array >>` dictionary
+  fn page(&self, destination: &Object) -> Option<u32> {
+    let destination = match self.document.dereference(destination).ok()?.1 {
+      Object::Name(name) => self
+          .document
+          .catalog()
+          .and_then(|catalog| catalog.get(b"Dests"))
+          .and_then(|dests| self.document.dereference(dests))
+          .and_then(|(_, dests)| dests.as_dict())
+          .and_then(|dests| dests.get(name))
+          .ok()?,
+      Object::String(name, _) => self.named.get(name)?,
+      destination => destination,
+    };
+    let destination = match self.document.dereference(destination).ok()?.1 {
+      Object::Dictionary(dictionary) => self.document.dereference(dictionary.get(b"D").ok()?).ok()?.1,
+      destination => destination,
+    };
+    match destination.as_array().ok()?.first()? {
+      Object::Reference(page_id) => self.page_numbers.get(page_id).copied(),
+      // Some writers use a 0-based page index, as in remote destinations
+      Object::Integer(index) => u32::try_from(*index).ok().map(|index| index + 1),
+      _ => None,
+    }
+  }
+
+  /// Target page of an outline item, from `/Dest` or a `GoTo` action
+  fn item_page(&self, item: &Dictionary) -> Option<u32> {
+    if let Ok(destination) = item.get(b"Dest") {
+      return self.page(destination);
+    }
+    let (_, action) = self.document.dereference(item.get(b"A").ok()?).ok()?;
+    let action = action.as_dict().ok()?;
+    if action.get(b"S").and_then(Object::as_name).ok() != Some(b"GoTo") {
+      return None;
+    }
+    self.page(action.get(b"D").ok()?)
+  }
+
+  /// Structure destination of an outline item, from its `/SD` or that of its `GoTo` action
+  fn item_structure(&self, item: &Dictionary) -> Option<Object> {
+    let destination = match item.get(b"SD") {
+      Ok(destination) => destination,
+      Err(_) => {
+        let (_, action) = self.document.dereference(item.get(b"A").ok()?).ok()?;
+        let action = action.as_dict().ok()?;
+        if action.get(b"S").and_then(Object::as_name).ok() != Some(b"GoTo") {
+          return None;
+        }
+        action.get(b"SD").ok()?
+      }
+    };
+    let destination = self.document.dereference(destination).ok()?.1;
+    match destination.as_array().ok()?.first()? {
+      Object::Reference(_) => Some(destination.clone()),
+      _ => None,
+    }
+  }
+}
+
+/// Read the outline tree, following `First`/`Next` links and ignoring items seen before and those
+/// nested deeper than `MAX_OUTLINE_DEPTH`
+pub fn read_outline(document: &Document) -> Vec<OutlineItem> {
+  fn children(
+    destinations: &Destinations, node: &Dictionary, visited: &mut BTreeSet<ObjectId>, depth: usize,
+  ) -> Vec<OutlineItem> {
+    let mut items = vec![];
+    if depth > MAX_OUTLINE_DEPTH {
+      return items;
+    }
+    let mut next = node.get(b"First").and_then(Object::as_reference).ok();
+    while let Some(item_id) = next {
+      if !visited.insert(item_id) {
+        break;
+      }
+      let item = match destinations.document.get_dictionary(item_id) {
+        Ok(item) => item,
+        Err(_) => break,
+      };
+      let title = match item.get(b"Title").and_then(|title| destinations.document.dereference(title)) {
+        Ok((_, Object::String(title, _))) => decode_text_string(title),
+        _ => String::new(),
+      };
+      items.push(OutlineItem {
+        title,
+        page: destinations.item_page(item),
+        structure: destinations.item_structure(item),
+        children: children(destinations, item, visited, depth + 1),
+      });
+      next = item.get(b"Next").and_then(Object::as_reference).ok();
+    }
+    items
+  }
+  let root = document
+      .catalog()
+      .and_then(|catalog| catalog.get(b"Outlines"))
+      .and_then(|outlines| document.dereference(outlines))
+      .and_then(|(_, outlines)| outlines.as_dict());
+  match root {
+    Ok(root) => children(&Destinations::new(document), root, &mut BTreeSet::new(), 1),
+    Err(_) => vec![],
+  }
+}
+
+fn outline_to_js(env: &Env, items: &[OutlineItem]) -> Result<JsObject> {
+  let mut result = env.create_array_with_length(items.len())?;
+  for (index, item) in items.iter().enumerate() {
+    let mut object = env.create_object()?;
+    object.set_named_property("title", env.create_string(&item.title)?)?;
+    if let Some(page) = item.page {
+      object.set_named_property("page", env.create_uint32(page)?)?;
+    }
+    object.set_named_property("children", outline_to_js(env, &item.children)?)?;
+    result.set_element(index as u32, object)?;
+  }
+  Ok(result)
+}
+
+#[js_function(1)]
+pub fn get_outline(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  outline_to_js(ctx.env, &read_outline(&document))
+}
+
+#[js_function(3)]
+pub fn set_outline(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let outline = outline_from_js(ctx.get::<JsObject>(1)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  set_outline_in(&mut document, &outline).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Ids of the outline root and all its items
+pub fn outline_object_ids(document: &Document) -> BTreeSet<ObjectId> {
+  let mut ids = BTreeSet::new();
+  let root_id = match document.catalog().and_then(|catalog| catalog.get(b"Outlines")).and_then(Object::as_reference) {
+    Ok(root_id) => root_id,
+    Err(_) => return ids,
+  };
+  // The first item of every list of siblings left to walk, kept on the heap as a document can
+  // nest its items arbitrarily deep
+  let mut lists = vec![root_id];
+  while let Some(first_id) = lists.pop() {
+    let mut next = Some(first_id);
+    while let Some(item_id) = next {
+      if !ids.insert(item_id) {
+        break;
+      }
+      let item = match document.get_dictionary(item_id) {
+        Ok(item) => item,
+        Err(_) => break,
+      };
+      if let Ok(first) = item.get(b"First").and_then(Object::as_reference) {
+        lists.push(first);
+      }
+      // The root has no siblings
+      next = if item_id == root_id && item.type_is(b"Outlines") {
+        None
+      } else {
+        item.get(b"Next").and_then(Object::as_reference).ok()
+      };
+    }
+  }
+  ids
+}
+
+/// Add the items under `parent_id` and return the first and last item ids and the number of
+/// items in the subtree. Every item is open, so the `/Count` of a node is all its descendants.
+fn add_items(
+  document: &mut Document, items: &[OutlineItem], parent_id: ObjectId, pages: &BTreeMap<u32, ObjectId>,
+) -> (ObjectId, ObjectId, i64) {
+  let ids = items.iter().map(|_| document.new_object_id()).collect::<Vec<_>>();
+  let mut count = 0;
+  for (index, item) in items.iter().enumerate() {
+    let mut dictionary = Dictionary::new();
+    dictionary.set("Title", encode_text_string(&item.title));
+    dictionary.set("Parent", parent_id);
+    if index > 0 {
+      dictionary.set("Prev", ids[index - 1]);
+    }
+    if let Some(next) = ids.get(index + 1) {
+      dictionary.set("Next", *next);
+    }
+    if let Some(page_id) = item.page.and_then(|page| pages.get(&page)) {
+      let destination = vec![(*page_id).into(), Object::Name(b"Fit".to_vec())];
+      match &item.structure {
+        // The page stays the destination of readers ignoring structure destinations
+        Some(structure) => {
+          let mut action = Dictionary::new();
+          action.set("S", Object::Name(b"GoTo".to_vec()));
+          action.set("D", destination);
+          action.set("SD", structure.clone());
+          dictionary.set("A", action);
+        }
+        None => dictionary.set("Dest", destination),
+      }
+    }
+    if !item.children.is_empty() {
+      let (first, last, descendants) = add_items(document, &item.children, ids[index], pages);
+      dictionary.set("First", first);
+      dictionary.set("Last", last);
+      dictionary.set("Count", descendants);
+      count += descendants;
+    }
+    document.objects.insert(ids[index], Object::Dictionary(dictionary));
+    count += 1;
+  }
+  (ids[0], ids[ids.len() - 1], count)
+}
+
+/// Replace the bookmarks with `outline`, removing the document outline when it is empty
+pub fn set_outline_in(document: &mut Document, outline: &[OutlineItem]) -> error::Result<()> {
+  fn collect_pages(items: &[OutlineItem], pages: &mut Vec<u32>) {
+    for item in items {
+      pages.extend(item.page);
+      collect_pages(&item.children, pages);
+    }
+  }
+  // Check every page before touching the document
+  let mut page_numbers = vec![];
+  collect_pages(outline, &mut page_numbers);
+  let page_ids = page::page_ids(document, &page_numbers)?;
+  let pages = page_numbers.into_iter().zip(page_ids).collect::<BTreeMap<_, _>>();
+
+  let catalog_id = document
+      .trailer
+      .get(b"Root")
+      .and_then(Object::as_reference)
+      .map_err(|_| PdfError::new(ErrorCode::InvalidPdf, "The trailer has no /Root"))?;
+
+  for id in outline_object_ids(document) {
+    document.objects.remove(&id);
+  }
+  if outline.is_empty() {
+    document.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?.remove(b"Outlines");
+    return Ok(());
+  }
+  let root_id = document.new_object_id();
+  let (first, last, count) = add_items(document, outline, root_id, &pages);
+  let mut root = Dictionary::new();
+  root.set("Type", Object::Name(b"Outlines".to_vec()));
+  root.set("First", first);
+  root.set("Last", last);
+  root.set("Count", count);
+  document.objects.insert(root_id, Object::Dictionary(root));
+  document
+      .get_object_mut(catalog_id)
+      .and_then(Object::as_dict_mut)?
+      .set("Outlines", root_id);
+  Ok(())
+}
@@ -0,0 +1,452 @@
+use std::collections::BTreeSet;
+
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use napi::{Error, JsObject, JsUnknown, Status, ValueType};
+
+use crate::error::{ErrorCode, PdfError, Result};
+use crate::filters;
+
+/// Default page size (US Letter) used when no MediaBox can be found
+pub const DEFAULT_MEDIA_BOX: [f64; 4] = [0.0, 0.0, 612.0, 792.0];
+
+/// Keys a page inherits from its ancestors in the page tree. `/UserUnit` is a page-only key,
+/// but some writers put it on a `Pages` node and viewers honor it there.
+const INHERITABLE_KEYS: [&[u8]; 5] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate", b"UserUnit"];
+
+/// Raw (possibly a reference) value of a page attribute, following the `Parent` chain
+fn inherited_value<'a>(document: &'a Document, page_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+  let mut node_id = Some(page_id);
+  // Guard against a cyclic page tree
+  let mut depth = 0;
+  while let Some(id) = node_id {
+    let node = document.get_dictionary(id).ok()?;
+    if let Ok(value) = node.get(key) {
+      return Some(value);
+    }
+    depth += 1;
+    if depth > 64 {
+      return None;
+    }
+    node_id = node.get(b"Parent").and_then(Object::as_reference).ok();
+  }
+  None
+}
+
+/// Look up a page attribute, following the `Parent` chain for inheritable keys
+pub fn inherited_attribute<'a>(document: &'a Document, page_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+  inherited_value(document, page_id, key)
+      .and_then(|value| document.dereference(value).ok())
+      .map(|(_, object)| object)
+}
+
+/// Copy the attributes a page inherits onto the page itself, so it can be moved to another parent
+pub fn copy_inherited_attributes(document: &mut Document, page_id: ObjectId) -> Result<()> {
+  for key in INHERITABLE_KEYS.iter() {
+    if document.get_dictionary(page_id)?.has(key) {
+      continue;
+    }
+    if let Some(value) = inherited_value(document, page_id, key).cloned() {
+      document
+          .get_object_mut(page_id)
+          .and_then(Object::as_dict_mut)?
+          .set(key.to_vec(), value);
+    }
+  }
+  Ok(())
+}
+
+/// Object ids of the given 1-based pages
+pub fn page_ids(document: &Document, page_numbers: &[u32]) -> Result<Vec<ObjectId>> {
+  let pages = document.get_pages();
+  page_numbers
+      .iter()
+      .map(|page_number| {
+        pages.get(page_number).copied().ok_or_else(|| {
+          PdfError::new(
+            ErrorCode::PageOutOfRange,
+            format!("Page {} is out of range for {} pages", page_number, pages.len()),
+          )
+        })
+      })
+      .collect()
+}
+
+/// Read a rectangle as `[llx, lly, urx, ury]`, normalizing the corners
+pub fn rect_from_object(object: &Object) -> Option<[f64; 4]> {
+  let values = object
+      .as_array()
+      .ok()?
+      .iter()
+      .map(|value| value.as_float().ok())
+      .collect::<Option<Vec<f64>>>()?;
+  if values.len() != 4 {
+    return None;
+  }
+  Some([
+    values[0].min(values[2]),
+    values[1].min(values[3]),
+    values[0].max(values[2]),
+    values[1].max(values[3]),
+  ])
+}
+
+/// Effective MediaBox of a page
+pub fn media_box(document: &Document, page_id: ObjectId) -> [f64; 4] {
+  inherited_attribute(document, page_id, b"MediaBox")
+      .and_then(rect_from_object)
+      .unwrap_or(DEFAULT_MEDIA_BOX)
+}
+
+/// Effective `/Rotate` of a page, normalized to 0, 90, 180 or 270
+pub fn rotation(document: &Document, page_id: ObjectId) -> i64 {
+  inherited_attribute(document, page_id, b"Rotate")
+      .and_then(|value| value.as_i64().ok())
+      .map(|rotate| rotate.rem_euclid(360) / 90 * 90)
+      .unwrap_or(0)
+}
+
+/// Page size as it is displayed, i.e. with `/Rotate` applied
+pub fn display_size(document: &Document, page_id: ObjectId) -> (f64, f64) {
+  let [llx, lly, urx, ury] = media_box(document, page_id);
+  match rotation(document, page_id) {
+    90 | 270 => (ury - lly, urx - llx),
+    _ => (urx - llx, ury - lly),
+  }
+}
+
+/// Matrix mapping display coordinates (origin at the displayed bottom-left) to user space
+pub fn display_matrix(document: &Document, page_id: ObjectId) -> [f64; 6] {
+  rect_display_matrix(media_box(document, page_id), rotation(document, page_id))
+}
+
+/// `display_matrix` for the area `rect` of a page turned by `rotate`
+pub fn rect_display_matrix(rect: [f64; 4], rotate: i64) -> [f64; 6] {
+  let [llx, lly, urx, ury] = rect;
+  match rotate {
+    90 => [0.0, 1.0, -1.0, 0.0, urx, lly],
+    180 => [-1.0, 0.0, 0.0, -1.0, urx, ury],
+    270 => [0.0, -1.0, 1.0, 0.0, llx, ury],
+    _ => [1.0, 0.0, 0.0, 1.0, llx, lly],
+  }
+}
+
+/// An empty page with the size and rotation of `like`, without its `/Parent`
+pub fn blank_page(document: &Document, like: ObjectId) -> Dictionary {
+  let mut page = Dictionary::new();
+  page.set("Type", Object::Name(b"Page".to_vec()));
+  page.set("MediaBox", media_box(document, like).iter().map(|&value| value.into()).collect::<Vec<Object>>());
+  let rotate = rotation(document, like);
+  if rotate != 0 {
+    page.set("Rotate", rotate);
+  }
+  page.set("Resources", Dictionary::new());
+  page
+}
+
+/// How the positions given to the stamping operations are measured
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum CoordinateSpace {
+  /// From the bottom-left of the page as displayed, with `/Rotate` applied, and drawn upright
+  #[default]
+  Display,
+  /// In the default user space of the page, the coordinates of its content, ignoring `/Rotate`
+  Media,
+}
+
+impl CoordinateSpace {
+  /// Read the `coordinateSpace` option, `display` by default
+  pub fn from_js(options: &JsObject) -> napi::Result<Self> {
+    match options.get_named_property::<Option<String>>("coordinateSpace")?.as_deref() {
+      None | Some("display") => Ok(CoordinateSpace::Display),
+      Some("media") => Ok(CoordinateSpace::Media),
+      Some(other) => Err(Error::new(
+        Status::InvalidArg,
+        format!("coordinateSpace must be 'display' or 'media', got '{}'", other),
+      )),
+    }
+  }
+
+  /// Matrix mapping positions in this space to the user space of the page
+  pub fn matrix(self, document: &Document, page_id: ObjectId) -> [f64; 6] {
+    match self {
+      CoordinateSpace::Display => display_matrix(document, page_id),
+      CoordinateSpace::Media => [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+    }
+  }
+
+  /// Size of the page in this space, and the matrix mapping positions from its bottom-left corner
+  /// to user space, for what is laid out against the page edges
+  pub fn frame(self, document: &Document, page_id: ObjectId) -> ((f64, f64), [f64; 6]) {
+    match self {
+      CoordinateSpace::Display => (display_size(document, page_id), display_matrix(document, page_id)),
+      CoordinateSpace::Media => {
+        let media_box = media_box(document, page_id);
+        let [llx, lly, urx, ury] = media_box;
+        ((urx - llx, ury - lly), rect_display_matrix(media_box, 0))
+      }
+    }
+  }
+}
+
+/// Which pages the stamping operations apply to
+#[derive(Clone, PartialEq, Default)]
+pub enum PageSelector {
+  #[default]
+  All,
+  Odd,
+  Even,
+  /// 1-based page numbers
+  List(Vec<u32>),
+  /// 1-based inclusive range, up to the last page when `to` is unset
+  Range { from: u32, to: Option<u32> },
+}
+
+impl PageSelector {
+  /// Read the `pages` option: `'all'` (the default), `'odd'`, `'even'`, an array of page numbers or
+  /// a `{ from, to }` range
+  pub fn from_js(options: &JsObject) -> napi::Result<Self> {
+    let pages = options.get_named_property::<JsUnknown>("pages")?;
+    let invalid = || {
+      Error::new(
+        Status::InvalidArg,
+        "pages must be 'all', 'odd', 'even', an array of page numbers or a { from, to } range".to_owned(),
+      )
+    };
+    let selector = match pages.get_type()? {
+      ValueType::Undefined => PageSelector::All,
+      ValueType::String => match pages.coerce_to_string()?.into_utf8()?.as_str()? {
+        "all" => PageSelector::All,
+        "odd" => PageSelector::Odd,
+        "even" => PageSelector::Even,
+        other => {
+          return Err(Error::new(
+            Status::InvalidArg,
+            format!("pages must be 'all', 'odd' or 'even' when a string, got '{}'", other),
+          ))
+        }
+      },
+      ValueType::Object if pages.is_array()? => {
+        let list = pages.coerce_to_object()?;
+        let page_numbers = (0..list.get_array_length()?)
+            .map(|index| list.get_element::<JsUnknown>(index)?.coerce_to_number()?.get_uint32())
+            .collect::<napi::Result<Vec<_>>>()?;
+        PageSelector::List(page_numbers)
+      }
+      ValueType::Object => {
+        let range = pages.coerce_to_object()?;
+        PageSelector::Range {
+          from: range.get_named_property::<Option<u32>>("from")?.ok_or_else(invalid)?,
+          to: range.get_named_property::<Option<u32>>("to")?,
+        }
+      }
+      _ => return Err(invalid()),
+    };
+    let valid = match &selector {
+      PageSelector::List(page_numbers) => !page_numbers.contains(&0),
+      PageSelector::Range { from, to } => *from > 0 && to.is_none_or(|to| to >= *from),
+      _ => true,
+    };
+    if !valid {
+      return Err(Error::new(Status::InvalidArg, "pages are 1-based, ranges from <= to".to_owned()));
+    }
+    Ok(selector)
+  }
+
+  /// Read the `pages` option along with `page`, its single page form
+  pub fn from_js_with_page(options: &JsObject) -> napi::Result<Self> {
+    let pages = PageSelector::from_js(options)?;
+    match options.get_named_property::<Option<u32>>("page")? {
+      Some(0) => Err(Error::new(Status::InvalidArg, "page is 1-based".to_owned())),
+      Some(page_number) if pages == PageSelector::All => Ok(PageSelector::List(vec![page_number])),
+      Some(_) => Err(Error::new(Status::InvalidArg, "Use either page or pages".to_owned())),
+      None => Ok(pages),
+    }
+  }
+
+  /// The selected pages as their 1-based number and id, in page order. Explicit page numbers and
+  /// range bounds past the last page fail with `PageOutOfRange`.
+  pub fn select(&self, document: &Document) -> Result<Vec<(u32, ObjectId)>> {
+    let pages = document.get_pages();
+    let out_of_range = |page_number: u32| {
+      PdfError::new(
+        ErrorCode::PageOutOfRange,
+        format!("Page {} is out of range for {} pages", page_number, pages.len()),
+      )
+    };
+    let last = pages.len() as u32;
+    // The bounds are checked before the pages are listed, a range can span billions of pages
+    let selected: BTreeSet<u32> = match self {
+      PageSelector::All => (1..=last).collect(),
+      PageSelector::Odd => (1..=last).step_by(2).collect(),
+      PageSelector::Even => (2..=last).step_by(2).collect(),
+      PageSelector::List(page_numbers) => match page_numbers.iter().find(|&&page_number| page_number > last) {
+        Some(&page_number) => return Err(out_of_range(page_number)),
+        None => page_numbers.iter().copied().collect(),
+      },
+      &PageSelector::Range { from, to } => {
+        let to = to.unwrap_or(last);
+        if let Some(bound) = [from, to].iter().copied().find(|&bound| bound > last) {
+          return Err(out_of_range(bound));
+        }
+        (from..=to).collect()
+      }
+    };
+    selected
+        .into_iter()
+        .map(|page_number| {
+          let page_id = pages.get(&page_number).ok_or_else(|| out_of_range(page_number))?;
+          Ok((page_number, *page_id))
+        })
+        .collect()
+  }
+}
+
+/// Make sure the page owns a direct `/Resources` dictionary and return it.
+/// Inherited resources are copied onto the page, referenced ones are resolved in place.
+fn page_resources_mut(document: &mut Document, page_id: ObjectId) -> lopdf::Result<&mut Dictionary> {
+  let resources = match document.get_dictionary(page_id)?.get(b"Resources") {
+    Ok(Object::Reference(id)) => Some(*id),
+    Ok(_) => None,
+    Err(_) => {
+      let inherited = inherited_attribute(document, page_id, b"Resources")
+          .and_then(|object| object.as_dict().ok())
+          .cloned()
+          .unwrap_or_default();
+      document
+          .get_object_mut(page_id)
+          .and_then(Object::as_dict_mut)?
+          .set("Resources", inherited);
+      None
+    }
+  };
+  match resources {
+    Some(id) => document.get_object_mut(id).and_then(Object::as_dict_mut),
+    None => document
+        .get_object_mut(page_id)
+        .and_then(Object::as_dict_mut)?
+        .get_mut(b"Resources")
+        .and_then(Object::as_dict_mut),
+  }
+}
+
+/// Register `object_id` in a resource category (`Font`, `XObject`, ...) of the page under a
+/// name starting with `prefix` that doesn't collide with existing entries. Returns the name.
+pub fn add_resource(
+  document: &mut Document,
+  page_id: ObjectId,
+  category: &[u8],
+  prefix: &str,
+  object_id: ObjectId,
+) -> Result<Vec<u8>> {
+  // The category itself may be an indirect dictionary
+  let category_id = page_resources_mut(document, page_id)?
+      .get(category)
+      .and_then(Object::as_reference)
+      .ok();
+  let entries = match category_id {
+    Some(id) => document.get_object_mut(id).and_then(Object::as_dict_mut),
+    None => {
+      let resources = page_resources_mut(document, page_id)?;
+      if resources.get(category).and_then(Object::as_dict).is_err() {
+        resources.set(category.to_vec(), Dictionary::new());
+      }
+      resources.get_mut(category).and_then(Object::as_dict_mut)
+    }
+  }?;
+  // Reuse an existing entry pointing at the same object
+  if let Some((name, _)) = entries
+      .iter()
+      .find(|(_, value)| value.as_reference().ok() == Some(object_id))
+  {
+    return Ok(name.clone());
+  }
+  let mut index = 1;
+  let name = loop {
+    let name = format!("{}{}", prefix, index).into_bytes();
+    if !entries.has(&name) {
+      break name;
+    }
+    index += 1;
+  };
+  entries.set(name.clone(), object_id);
+  Ok(name)
+}
+
+/// The content streams of a page, `Contents` may itself be a reference to an array of streams
+fn content_streams(document: &Document, page_id: ObjectId) -> Result<Vec<Object>> {
+  Ok(match document.get_dictionary(page_id)?.get(b"Contents") {
+    Ok(object) => match document.dereference(object) {
+      Ok((_, Object::Array(streams))) => streams.clone(),
+      _ => vec![object.clone()],
+    },
+    Err(_) => vec![],
+  })
+}
+
+/// The unfiltered bytes of a page's content streams, joined as if they were one
+pub fn page_content_data(document: &Document, page_id: ObjectId) -> Result<Vec<u8>> {
+  let mut data = vec![];
+  for stream in content_streams(document, page_id)? {
+    if let Ok((_, Object::Stream(stream))) = document.dereference(&stream) {
+      data.extend(filters::decoded_content(stream));
+      // Streams may end in the middle of a token only if nothing separates them
+      data.push(b'\n');
+    }
+  }
+  Ok(data)
+}
+
+/// Decode the whole content of a page, its streams joined as if they were one
+pub fn page_content(document: &Document, page_id: ObjectId) -> Result<Content> {
+  Ok(Content::decode(&page_content_data(document, page_id)?)?)
+}
+
+/// Replace the content of a page with a single stream, removing the old streams no other page uses
+pub fn set_content(document: &mut Document, page_id: ObjectId, content: Vec<u8>) -> Result<()> {
+  let old_streams = content_streams(document, page_id)?;
+  let stream_id = document.add_object(Stream::new(Dictionary::new(), content));
+  document
+      .get_object_mut(page_id)
+      .and_then(Object::as_dict_mut)?
+      .set("Contents", stream_id);
+  let mut used = BTreeSet::new();
+  for other_id in document.get_pages().into_values() {
+    for stream in content_streams(document, other_id)? {
+      used.extend(stream.as_reference().ok());
+    }
+  }
+  for stream in old_streams {
+    if let Ok(id) = stream.as_reference() {
+      if !used.contains(&id) {
+        document.objects.remove(&id);
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Stamp content over the existing page content, whether `/Contents` is one stream or an array.
+/// Both are put between `q` and `Q`: the stamp starts from the initial graphics state whatever
+/// transformation or colors the page leaves set, and its own don't leak into content appended later.
+pub fn append_content(document: &mut Document, page_id: ObjectId, content: Vec<u8>) -> Result<()> {
+  let mut after = b"\nQ\nq\n".to_vec();
+  after.extend(content);
+  after.extend_from_slice(b"\nQ\n");
+  wrap_content(document, page_id, b"q\n".to_vec(), after)
+}
+
+/// Put the page content between `before` and `after`, each a stream of its own so the existing
+/// streams are left as they are
+pub fn wrap_content(document: &mut Document, page_id: ObjectId, before: Vec<u8>, after: Vec<u8>) -> Result<()> {
+  let before_id = document.add_object(Stream::new(Dictionary::new(), before));
+  let after_id = document.add_object(Stream::new(Dictionary::new(), after));
+  let mut streams = vec![Object::Reference(before_id)];
+  streams.extend(content_streams(document, page_id)?);
+  streams.push(Object::Reference(after_id));
+  document
+      .get_object_mut(page_id)
+      .and_then(Object::as_dict_mut)?
+      .set("Contents", streams);
+  Ok(())
+}
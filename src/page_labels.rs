@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use lopdf::{Dictionary, Document, Object};
+use napi::{CallContext, Env, Error, JsBuffer, JsNumber, JsObject, JsString, JsUnknown, Result, Status};
+
+use crate::error::{self, ErrorCode, OrThrow, PdfError};
+use crate::names::{number_tree, number_tree_entries};
+use crate::page;
+use crate::utils::{
+  buffer_value, decode_text_string, encode_text_string, integer_in, integer_property, load_document, output,
+  output_update, MAX_OBJECT_ID,
+};
+
+/// Numbering styles of the `/S` entry
+const STYLES: [&str; 5] = ["D", "r", "R", "a", "A"];
+
+/// Highest number of the first page of a range. Letters repeat every 26 numbers, a letter label
+/// starting there is already near 4000 characters long
+const MAX_START: u32 = 100_000;
+
+/// Labels of the pages from `start_page` up to the next range
+#[derive(Clone)]
+pub struct PageLabelRange {
+  /// 1-based first page of the range
+  pub start_page: u32,
+  /// Numbering style, `None` labels the pages with the prefix only
+  pub style: Option<String>,
+  pub prefix: Option<String>,
+  /// Number of the first page, 1 by default
+  pub start: Option<u32>,
+}
+
+impl PageLabelRange {
+  pub fn from_js(range: JsObject) -> Result<Self> {
+    let start_page = integer_in(range.get_named_property::<f64>("startPage")?, "startPage", 1, MAX_OBJECT_ID)?;
+    let style = range.get_named_property::<Option<String>>("style")?;
+    if let Some(style) = &style {
+      if !STYLES.contains(&style.as_str()) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("Unknown page label style {}, expected one of D, r, R, a, A", style),
+        ));
+      }
+    }
+    Ok(PageLabelRange {
+      start_page,
+      style,
+      prefix: range.get_named_property::<Option<String>>("prefix")?,
+      start: integer_property(&range, "start", 1, MAX_START)?,
+    })
+  }
+
+  fn from_dictionary(document: &Document, start_page: u32, label: &Dictionary) -> Self {
+    let style = label
+        .get(b"S")
+        .and_then(Object::as_name_str)
+        .ok()
+        .filter(|style| STYLES.contains(style))
+        .map(str::to_owned);
+    let prefix = match label.get(b"P").and_then(|prefix| document.dereference(prefix)) {
+      Ok((_, Object::String(prefix, _))) => Some(decode_text_string(prefix)),
+      _ => None,
+    };
+    // A start outside the bounds `from_js` checks numbers from 1, as when absent
+    let start = label
+        .get(b"St")
+        .and_then(Object::as_i64)
+        .ok()
+        .and_then(|start| u32::try_from(start).ok())
+        .filter(|start| (1..=MAX_START).contains(start));
+    PageLabelRange {
+      start_page,
+      style,
+      prefix,
+      start,
+    }
+  }
+
+  pub fn to_dictionary(&self) -> Dictionary {
+    let mut label = Dictionary::new();
+    if let Some(style) = &self.style {
+      label.set("S", Object::Name(style.as_bytes().to_vec()));
+    }
+    if let Some(prefix) = &self.prefix {
+      label.set("P", encode_text_string(prefix));
+    }
+    if let Some(start) = self.start {
+      label.set("St", start as i64);
+    }
+    label
+  }
+}
+
+/// Read a `PageLabelRange[]` argument
+pub fn page_labels_from_js(ranges: JsObject) -> Result<Vec<PageLabelRange>> {
+  (0..ranges.get_array_length()?)
+      .map(|index| PageLabelRange::from_js(ranges.get_element::<JsObject>(index)?))
+      .collect()
+}
+
+/// The label ranges of the catalog `/PageLabels` number tree, ordered by first page
+pub fn read_page_labels(document: &Document) -> Vec<PageLabelRange> {
+  let tree = match document.catalog().and_then(|catalog| catalog.get(b"PageLabels")) {
+    Ok(tree) => tree,
+    Err(_) => return vec![],
+  };
+  let mut entries = number_tree_entries(document, tree);
+  entries.sort_by_key(|(index, _)| *index);
+  entries
+      .into_iter()
+      .filter_map(|(index, label)| {
+        let start_page = u32::try_from(index).ok()?.checked_add(1)?;
+        let (_, label) = document.dereference(&label).ok()?;
+        Some(PageLabelRange::from_dictionary(document, start_page, label.as_dict().ok()?))
+      })
+      .collect()
+}
+
+fn page_labels_to_js(env: &Env, ranges: &[PageLabelRange]) -> Result<JsObject> {
+  let mut result = env.create_array_with_length(ranges.len())?;
+  for (index, range) in ranges.iter().enumerate() {
+    let mut object = env.create_object()?;
+    object.set_named_property("startPage", env.create_uint32(range.start_page)?)?;
+    if let Some(style) = &range.style {
+      object.set_named_property("style", env.create_string(style)?)?;
+    }
+    if let Some(prefix) = &range.prefix {
+      object.set_named_property("prefix", env.create_string(prefix)?)?;
+    }
+    if let Some(start) = range.start {
+      object.set_named_property("start", env.create_uint32(start)?)?;
+    }
+    result.set_element(index as u32, object)?;
+  }
+  Ok(result)
+}
+
+#[js_function(1)]
+pub fn get_page_labels(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  page_labels_to_js(ctx.env, &read_page_labels(&document))
+}
+
+#[js_function(3)]
+pub fn set_page_labels(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let ranges = page_labels_from_js(ctx.get::<JsObject>(1)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  set_page_labels_in(&mut document, &ranges).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Write `ranges` as the catalog `/PageLabels`, without checking them against the pages
+pub fn write_page_labels(document: &mut Document, ranges: &[PageLabelRange]) -> error::Result<()> {
+  let catalog_id = document
+      .trailer
+      .get(b"Root")
+      .and_then(Object::as_reference)
+      .map_err(|_| PdfError::new(ErrorCode::InvalidPdf, "The trailer has no /Root"))?;
+  // The tree may be an object of its own, it's replaced by an inline one
+  if let Ok(tree_id) = document.get_dictionary(catalog_id)?.get(b"PageLabels").and_then(Object::as_reference) {
+    document.objects.remove(&tree_id);
+  }
+  let catalog = document.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?;
+  if ranges.is_empty() {
+    catalog.remove(b"PageLabels");
+    return Ok(());
+  }
+  catalog.set("PageLabels", page_labels_tree(ranges));
+  Ok(())
+}
+
+/// The `/PageLabels` number tree of `ranges`, keyed by 0-based page index
+pub fn page_labels_tree(ranges: &[PageLabelRange]) -> Dictionary {
+  let entries = ranges
+      .iter()
+      .map(|range| (range.start_page as i64 - 1, Object::Dictionary(range.to_dictionary())))
+      .collect::<BTreeMap<_, _>>();
+  number_tree(&entries)
+}
+
+/// Write `number` in a numbering style: decimal, roman or letters (`a` to `z`, then `aa` to `zz`...)
+fn format_number(style: &str, number: u32) -> String {
+  match style {
+    "R" | "r" => {
+      const NUMERALS: [(u32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+      ];
+      let mut rest = number;
+      let mut roman = String::new();
+      for (value, numeral) in NUMERALS.iter() {
+        while rest >= *value {
+          roman.push_str(numeral);
+          rest -= value;
+        }
+      }
+      if style == "r" {
+        roman.to_lowercase()
+      } else {
+        roman
+      }
+    }
+    "A" | "a" => {
+      let base = if style == "A" { b'A' } else { b'a' };
+      let letter = (base + ((number - 1) % 26) as u8) as char;
+      letter.to_string().repeat(((number - 1) / 26 + 1) as usize)
+    }
+    _ => number.to_string(),
+  }
+}
+
+/// The label of every page. Pages before the first range, or of a document without labels, are
+/// labeled with their 1-based page number.
+pub fn page_labels_list(document: &Document) -> Vec<String> {
+  let ranges = read_page_labels(document);
+  (1..=document.get_pages().len() as u32)
+      .map(|page_number| match ranges.iter().rev().find(|range| range.start_page <= page_number) {
+        Some(range) => {
+          let number = range.start.unwrap_or(1) + (page_number - range.start_page);
+          let prefix = range.prefix.as_deref().unwrap_or("");
+          match &range.style {
+            Some(style) => format!("{}{}", prefix, format_number(style, number)),
+            None => prefix.to_owned(),
+          }
+        }
+        None => page_number.to_string(),
+      })
+      .collect()
+}
+
+/// 0-based index of the first page labeled `label`
+pub fn resolve_label_to_index_in(document: &Document, label: &str) -> error::Result<u32> {
+  page_labels_list(document)
+      .iter()
+      .position(|page_label| page_label == label)
+      .map(|index| index as u32)
+      .ok_or_else(|| PdfError::new(ErrorCode::GenericFailure, format!("No page is labeled '{}'", label)))
+}
+
+/// Label of the page at the 0-based `index`
+pub fn resolve_index_to_label_in(document: &Document, index: u32) -> error::Result<String> {
+  let labels = page_labels_list(document);
+  labels.get(index as usize).cloned().ok_or_else(|| {
+    PdfError::new(
+      ErrorCode::PageOutOfRange,
+      format!("Page index {} is out of range for {} pages", index, labels.len()),
+    )
+  })
+}
+
+#[js_function(2)]
+pub fn resolve_label_to_index(ctx: CallContext) -> Result<JsNumber> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let label = ctx.get::<String>(1)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  ctx.env.create_uint32(resolve_label_to_index_in(&document, &label).or_throw(ctx.env)?)
+}
+
+#[js_function(2)]
+pub fn resolve_index_to_label(ctx: CallContext) -> Result<JsString> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let index = integer_in(ctx.get::<f64>(1)?, "index", 0, u32::MAX)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  ctx.env.create_string(&resolve_index_to_label_in(&document, index).or_throw(ctx.env)?)
+}
+
+/// Replace the page labels with `ranges`, removing them when it is empty. The first range must
+/// start on page 1 and the following ones on increasing pages.
+pub fn set_page_labels_in(document: &mut Document, ranges: &[PageLabelRange]) -> error::Result<()> {
+  let start_pages = ranges.iter().map(|range| range.start_page).collect::<Vec<_>>();
+  page::page_ids(document, &start_pages)?;
+  if matches!(start_pages.first(), Some(start_page) if *start_page != 1) {
+    return Err(PdfError::new(
+      ErrorCode::GenericFailure,
+      "The first page label range must start on page 1",
+    ));
+  }
+  if start_pages.windows(2).any(|pair| pair[0] >= pair[1]) {
+    return Err(PdfError::new(
+      ErrorCode::GenericFailure,
+      "Page label ranges must be ordered by increasing startPage",
+    ));
+  }
+  write_page_labels(document, ranges)
+}
@@ -0,0 +1,33 @@
+use napi::{Error, Result, Status};
+
+/// Portrait width and height in points of the named paper sizes
+const PAGE_SIZES: [(&str, f64, f64); 5] = [
+  ("A3", 841.89, 1190.55),
+  ("A4", 595.28, 841.89),
+  ("A5", 419.53, 595.28),
+  ("Letter", 612.0, 792.0),
+  ("Legal", 612.0, 1008.0),
+];
+
+/// Resolve a paper size name such as `A4` or `Letter-landscape` to its width and height in
+/// points. Names are case-insensitive and portrait unless they end in `-landscape`.
+pub fn page_size(name: &str) -> Result<(f64, f64)> {
+  let lower = name.to_ascii_lowercase();
+  let (paper, landscape) = match lower.rsplit_once('-') {
+    Some((paper, "landscape")) => (paper, true),
+    Some((paper, "portrait")) => (paper, false),
+    _ => (lower.as_str(), false),
+  };
+  match PAGE_SIZES.iter().find(|(known, _, _)| known.eq_ignore_ascii_case(paper)) {
+    Some(&(_, width, height)) if landscape => Ok((height, width)),
+    Some(&(_, width, height)) => Ok((width, height)),
+    None => Err(Error::new(
+      Status::InvalidArg,
+      format!(
+        "Unknown page size '{}', expected one of {} with an optional -portrait or -landscape",
+        name,
+        PAGE_SIZES.iter().map(|(known, _, _)| *known).collect::<Vec<_>>().join(", ")
+      ),
+    )),
+  }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeSet;
+
+use lopdf::{Document, Object, ObjectId};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status};
+
+use crate::error::{self, ErrorCode, OrThrow, PdfError};
+use crate::page;
+use crate::utils::{buffer_value, load_document, output, output_update};
+
+#[js_function(2)]
+pub fn fix_page_tree(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(1)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  fix_page_tree_in(&mut document).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+fn pages_root_id(document: &Document) -> error::Result<ObjectId> {
+  document
+      .catalog()
+      .and_then(|catalog| catalog.get(b"Pages"))
+      .and_then(Object::as_reference)
+      .map_err(|_| PdfError::new(ErrorCode::NoPagesRoot, "Pages root not found"))
+}
+
+/// Recompute `/Count` of every `Pages` node and point each kid's `/Parent` at its node
+pub fn fix_page_tree_in(document: &mut Document) -> error::Result<()> {
+  let pages_id = pages_root_id(document)?;
+  let mut visited = BTreeSet::new();
+  visited.insert(pages_id);
+  fix_node(document, pages_id, &mut visited);
+  Ok(())
+}
+
+/// Fix the subtree below `node_id`, returning the number of pages in it. Kids listed twice, or
+/// pointing back up the tree, are removed from `/Kids`.
+fn fix_node(document: &mut Document, node_id: ObjectId, visited: &mut BTreeSet<ObjectId>) -> i64 {
+  let kids = match document.get_dictionary(node_id) {
+    Ok(node) => match (node.type_name().ok(), node.get(b"Kids").and_then(Object::as_array)) {
+      (Some("Page"), _) => return 1,
+      (_, Ok(kids)) => kids.clone(),
+      // A `Pages` node without kids holds no page, a leaf without `/Type` is still a page
+      (Some("Pages"), Err(_)) => {
+        if let Ok(node) = document.get_object_mut(node_id).and_then(Object::as_dict_mut) {
+          node.set("Kids", vec![]);
+          node.set("Count", 0);
+        }
+        return 0;
+      }
+      (_, Err(_)) => return 1,
+    },
+    Err(_) => return 0,
+  };
+  let mut count = 0;
+  let mut kept = Vec::with_capacity(kids.len());
+  for kid in kids {
+    let kid_id = match kid.as_reference() {
+      Ok(kid_id) => kid_id,
+      Err(_) => continue,
+    };
+    if !visited.insert(kid_id) {
+      continue;
+    }
+    if let Ok(kid) = document.get_object_mut(kid_id).and_then(Object::as_dict_mut) {
+      kid.set("Parent", node_id);
+    }
+    count += fix_node(document, kid_id, visited);
+    kept.push(kid);
+  }
+  if let Ok(node) = document.get_object_mut(node_id).and_then(Object::as_dict_mut) {
+    node.set("Kids", kept);
+    node.set("Count", count);
+  }
+  count
+}
+
+/// Read a `[llx, lly, urx, ury]` argument enclosing some area
+pub fn media_box_from_js(values: Vec<f64>) -> Result<[f64; 4]> {
+  match values[..] {
+    [llx, lly, urx, ury] if values.iter().all(|value| value.is_finite()) && llx != urx && lly != ury => {
+      Ok([llx.min(urx), lly.min(ury), llx.max(urx), lly.max(ury)])
+    }
+    _ => Err(Error::new(
+      Status::InvalidArg,
+      "The MediaBox must be [llx, lly, urx, ury] enclosing some area".to_owned(),
+    )),
+  }
+}
+
+#[js_function(1)]
+pub fn get_default_media_box(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  let pages_id = pages_root_id(&document).or_throw(ctx.env)?;
+  let media_box = document
+      .get_dictionary(pages_id)
+      .and_then(|pages| pages.get(b"MediaBox"))
+      .and_then(|media_box| document.dereference(media_box))
+      .ok()
+      .and_then(|(_, media_box)| page::rect_from_object(media_box));
+  match media_box {
+    Some(media_box) => {
+      let mut values = ctx.env.create_array_with_length(4)?;
+      for (index, value) in media_box.iter().enumerate() {
+        values.set_element(index as u32, ctx.env.create_double(*value)?)?;
+      }
+      Ok(values.into_unknown())
+    }
+    None => Ok(ctx.env.get_null()?.into_unknown()),
+  }
+}
+
+#[js_function(3)]
+pub fn set_default_media_box(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let media_box = media_box_from_js(ctx.get::<Vec<f64>>(1)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  set_default_media_box_in(&mut document, media_box).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Set the `/MediaBox` of the root `Pages` node, the size of the pages that inherit it. Pages, and
+/// intermediate nodes, with their own MediaBox keep it.
+pub fn set_default_media_box_in(document: &mut Document, media_box: [f64; 4]) -> error::Result<()> {
+  let pages_id = pages_root_id(document)?;
+  let media_box = media_box.iter().map(|&value| value.into()).collect::<Vec<Object>>();
+  document
+      .get_object_mut(pages_id)
+      .and_then(Object::as_dict_mut)?
+      .set("MediaBox", media_box);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use lopdf::Dictionary;
+
+  use super::*;
+  use crate::test_utils;
+
+  fn pages_id(document: &Document) -> ObjectId {
+    pages_root_id(document).unwrap()
+  }
+
+  fn pages_mut(document: &mut Document) -> &mut Dictionary {
+    let pages_id = pages_id(document);
+    document.get_object_mut(pages_id).and_then(Object::as_dict_mut).unwrap()
+  }
+
+  #[test]
+  fn recomputes_a_wrong_count_and_the_parents() {
+    let mut document = test_utils::document(3);
+    let pages_id = pages_id(&document);
+    pages_mut(&mut document).set("Count", 7);
+    let first_id = document.get_pages()[&1];
+    document.get_object_mut(first_id).and_then(Object::as_dict_mut).unwrap().remove(b"Parent");
+    fix_page_tree_in(&mut document).unwrap();
+    assert_eq!(pages_mut(&mut document).get(b"Count").unwrap().as_i64().unwrap(), 3);
+    let parent = document.get_dictionary(first_id).unwrap().get(b"Parent").unwrap();
+    assert_eq!(parent.as_reference().unwrap(), pages_id);
+  }
+
+  #[test]
+  fn removes_kids_listed_twice() {
+    let mut document = test_utils::document(2);
+    let pages = pages_mut(&mut document);
+    let mut kids = pages.get(b"Kids").and_then(Object::as_array).unwrap().clone();
+    kids.push(kids[0].clone());
+    pages.set("Kids", kids);
+    fix_page_tree_in(&mut document).unwrap();
+    let pages = pages_mut(&mut document);
+    assert_eq!(pages.get(b"Kids").and_then(Object::as_array).unwrap().len(), 2);
+    assert_eq!(pages.get(b"Count").unwrap().as_i64().unwrap(), 2);
+  }
+
+  #[test]
+  fn counts_no_page_in_a_pages_node_without_kids() {
+    let mut document = test_utils::document(2);
+    let mut empty = Dictionary::new();
+    empty.set("Type", Object::Name(b"Pages".to_vec()));
+    empty.set("Count", 4);
+    let empty_id = document.add_object(empty);
+    let pages = pages_mut(&mut document);
+    let mut kids = pages.get(b"Kids").and_then(Object::as_array).unwrap().clone();
+    kids.push(Object::Reference(empty_id));
+    pages.set("Kids", kids);
+    fix_page_tree_in(&mut document).unwrap();
+    assert_eq!(pages_mut(&mut document).get(b"Count").unwrap().as_i64().unwrap(), 2);
+    let empty = document.get_dictionary(empty_id).unwrap();
+    assert_eq!(empty.get(b"Count").unwrap().as_i64().unwrap(), 0);
+  }
+}
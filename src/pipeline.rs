@@ -0,0 +1,370 @@
+// `#[js_function(0)]` expands to a zero-length argument array
+#![allow(clippy::zero_repeat_side_effects)]
+
+use lopdf::Document;
+use napi::{CallContext, Env, JsBuffer, JsFunction, JsObject, JsUndefined, JsUnknown, Property, Result};
+
+use crate::additional_actions::{remove_additional_actions_in, ActionScope};
+use crate::alt_text::{alt_texts_from_js, set_image_alt_text_in};
+use crate::annotations::{flatten_annotations_in, FlattenAnnotationsOptions};
+use crate::append::{append_page_in, blank_page_size_from_js, insert_blank_page_in};
+use crate::black::{normalize_black_in, tolerance_from_js};
+use crate::content::set_page_content_in;
+use crate::dedupe::dedupe_document;
+use crate::error::OrThrow;
+use crate::extract::{extract_pages_in, extract_visible_in, ExtractOptions};
+use crate::fonts::subset_fonts_in;
+use crate::form::{flatten_fields_in, rename_field_in, set_field_read_only_in, set_need_appearances_in};
+use crate::header_footer::{add_header_footer_to, HeaderFooterOptions};
+use crate::metadata::{set_dates_in, DatesOptions};
+use crate::open_action::{set_open_action_in, OpenAction};
+use crate::outline::{outline_from_js, set_outline_in};
+use crate::page_labels::{page_labels_from_js, set_page_labels_in};
+use crate::page_tree::{fix_page_tree_in, media_box_from_js, set_default_media_box_in};
+use crate::poster::{posterize_in, PosterGrid};
+use crate::qr_code::{self, add_qr_code_to, QrCodeOptions};
+use crate::raw_object::{object_from_js, object_id, set_object_in, set_trailer_entry_in, trailer_key};
+use crate::rotate::{bake_rotation_in, rotate_pages_in, uniform_orientation_in, validate_range, Orientation};
+use crate::sanitize::{sanitize_in, SanitizeOptions};
+use crate::text_box::{add_text_box_to, TextBoxOptions};
+use crate::transparency::flatten_transparency_in;
+use crate::utils::{buffer_value, load_document, save_document, save_document_to_file, SaveOptions};
+use crate::{merge_sources, merge_sources_from_js, MergeOptions, MergeSource};
+
+/// Define the `PdfPipeline` class, which keeps one parsed document across several operations
+pub fn define_pipeline(env: &Env) -> Result<JsFunction> {
+  env.define_class(
+    "PdfPipeline",
+    constructor,
+    &[
+      Property::new("merge")?.with_method(merge),
+      Property::new("addHeaderFooter")?.with_method(add_header_footer),
+      Property::new("dedupeObjects")?.with_method(dedupe_objects),
+      Property::new("flattenFields")?.with_method(flatten_fields),
+      Property::new("setFieldReadOnly")?.with_method(set_field_read_only),
+      Property::new("renameField")?.with_method(rename_field),
+      Property::new("setNeedAppearances")?.with_method(set_need_appearances),
+      Property::new("flattenAnnotations")?.with_method(flatten_annotations),
+      Property::new("rotateRange")?.with_method(rotate_range),
+      Property::new("bakeRotation")?.with_method(bake_rotation),
+      Property::new("uniformOrientation")?.with_method(uniform_orientation),
+      Property::new("extractPages")?.with_method(extract_pages),
+      Property::new("extractVisible")?.with_method(extract_visible),
+      Property::new("appendPage")?.with_method(append_page),
+      Property::new("insertBlankPage")?.with_method(insert_blank_page),
+      Property::new("posterize")?.with_method(posterize),
+      Property::new("fixPageTree")?.with_method(fix_page_tree),
+      Property::new("setDefaultMediaBox")?.with_method(set_default_media_box),
+      Property::new("setOpenAction")?.with_method(set_open_action),
+      Property::new("addQrCode")?.with_method(add_qr_code),
+      Property::new("addTextBox")?.with_method(add_text_box),
+      Property::new("setDates")?.with_method(set_dates),
+      Property::new("setOutline")?.with_method(set_outline),
+      Property::new("setImageAltText")?.with_method(set_image_alt_text),
+      Property::new("sanitize")?.with_method(sanitize),
+      Property::new("removeAdditionalActions")?.with_method(remove_additional_actions),
+      Property::new("setPageLabels")?.with_method(set_page_labels),
+      Property::new("setObject")?.with_method(set_object),
+      Property::new("setTrailerEntry")?.with_method(set_trailer_entry),
+      Property::new("flattenTransparency")?.with_method(flatten_transparency),
+      Property::new("normalizeBlack")?.with_method(normalize_black),
+      Property::new("subsetFonts")?.with_method(subset_fonts),
+      Property::new("setPageContent")?.with_method(set_page_content),
+      Property::new("toBuffer")?.with_method(to_buffer),
+      Property::new("toFile")?.with_method(to_file),
+    ],
+  )
+}
+
+/// The document wrapped by `this`
+fn document<'a>(ctx: &'a CallContext) -> Result<&'a mut Document> {
+  let this = ctx.this_unchecked::<JsObject>();
+  ctx.env.unwrap::<Document>(&this)
+}
+
+#[js_function(1)]
+fn constructor(ctx: CallContext) -> Result<JsUndefined> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  let mut this = ctx.this_unchecked::<JsObject>();
+  ctx.env.wrap(&mut this, document)?;
+  ctx.env.get_undefined()
+}
+
+/// Append other documents after the current one, `metadataFrom` 0 is the pipeline document
+#[js_function(2)]
+fn merge(ctx: CallContext) -> Result<JsObject> {
+  let options = MergeOptions::from_js(ctx.get::<Option<JsObject>>(1)?)?;
+  options.reject_remap("The pipeline merge")?;
+  let buffers = ctx.get::<JsObject>(0)?;
+  options.validate(buffers.get_array_length()? as usize + 1)?;
+  let others = merge_sources_from_js(ctx.env, buffers, 1, &options, &mut vec![])?;
+  let document = document(&ctx)?;
+  // Merge a copy, so the pipeline stays usable when the merge fails
+  let mut sources = vec![MergeSource {
+    document: document.clone(),
+    rotate: 0,
+    index: 0,
+    title: None,
+  }];
+  sources.extend(others);
+  *document = merge_sources(sources, &options, &mut vec![]).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn add_header_footer(ctx: CallContext) -> Result<JsObject> {
+  let options = HeaderFooterOptions::from_js(ctx.get::<JsObject>(0)?)?;
+  add_header_footer_to(document(&ctx)?, &options).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(0)]
+fn dedupe_objects(ctx: CallContext) -> Result<JsObject> {
+  dedupe_document(document(&ctx)?);
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn flatten_fields(ctx: CallContext) -> Result<JsObject> {
+  let field_names = ctx.get::<Vec<String>>(0)?;
+  flatten_fields_in(document(&ctx)?, &field_names).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(2)]
+fn set_field_read_only(ctx: CallContext) -> Result<JsObject> {
+  let field_names = ctx.get::<Vec<String>>(0)?;
+  let read_only = ctx.get::<bool>(1)?;
+  set_field_read_only_in(document(&ctx)?, &field_names, read_only).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(2)]
+fn rename_field(ctx: CallContext) -> Result<JsObject> {
+  let old_name = ctx.get::<String>(0)?;
+  let new_name = ctx.get::<String>(1)?;
+  rename_field_in(document(&ctx)?, &old_name, &new_name).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn set_need_appearances(ctx: CallContext) -> Result<JsObject> {
+  let value = ctx.get::<bool>(0)?;
+  set_need_appearances_in(document(&ctx)?, value).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn flatten_annotations(ctx: CallContext) -> Result<JsObject> {
+  let options = FlattenAnnotationsOptions::from_js(&ctx.get::<Option<JsObject>>(0)?)?;
+  flatten_annotations_in(document(&ctx)?, &options).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(3)]
+fn rotate_range(ctx: CallContext) -> Result<JsObject> {
+  let from = ctx.get::<u32>(0)?;
+  let to = ctx.get::<u32>(1)?;
+  let degrees = ctx.get::<i64>(2)?;
+  validate_range(from, to, degrees)?;
+  rotate_pages_in(document(&ctx)?, &(from..=to).collect::<Vec<_>>(), degrees).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(0)]
+fn bake_rotation(ctx: CallContext) -> Result<JsObject> {
+  bake_rotation_in(document(&ctx)?).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn uniform_orientation(ctx: CallContext) -> Result<JsObject> {
+  let target = Orientation::from_js(&ctx.get::<String>(0)?)?;
+  uniform_orientation_in(document(&ctx)?, target).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(2)]
+fn extract_pages(ctx: CallContext) -> Result<JsObject> {
+  let page_numbers = ctx.get::<Vec<u32>>(0)?;
+  let options = ExtractOptions::from_js(ctx.get::<Option<JsObject>>(1)?)?;
+  extract_pages_in(document(&ctx)?, &page_numbers, options.on_broken_link, options.preserve_structure)
+      .or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(2)]
+fn extract_visible(ctx: CallContext) -> Result<JsObject> {
+  let page_numbers = ctx.get::<Vec<u32>>(0)?;
+  let options = ExtractOptions::from_js(ctx.get::<Option<JsObject>>(1)?)?;
+  extract_visible_in(document(&ctx)?, &page_numbers, &options).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn append_page(ctx: CallContext) -> Result<JsObject> {
+  let page = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let source = load_document(&page).or_throw(ctx.env)?;
+  append_page_in(document(&ctx)?, source).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(2)]
+fn insert_blank_page(ctx: CallContext) -> Result<JsObject> {
+  let position = ctx.get::<u32>(0)?;
+  let size = blank_page_size_from_js(&ctx.get::<Option<JsObject>>(1)?)?;
+  insert_blank_page_in(document(&ctx)?, position, size).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(2)]
+fn posterize(ctx: CallContext) -> Result<JsObject> {
+  let page_number = ctx.get::<u32>(0)?;
+  let grid = PosterGrid::from_js(&ctx.get::<JsObject>(1)?)?;
+  posterize_in(document(&ctx)?, page_number, grid).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(0)]
+fn fix_page_tree(ctx: CallContext) -> Result<JsObject> {
+  fix_page_tree_in(document(&ctx)?).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn set_default_media_box(ctx: CallContext) -> Result<JsObject> {
+  let media_box = media_box_from_js(ctx.get::<Vec<f64>>(0)?)?;
+  set_default_media_box_in(document(&ctx)?, media_box).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn set_open_action(ctx: CallContext) -> Result<JsObject> {
+  let action = OpenAction::from_js(ctx.get::<JsObject>(0)?)?;
+  set_open_action_in(document(&ctx)?, &action).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(2)]
+fn add_qr_code(ctx: CallContext) -> Result<JsObject> {
+  let code = qr_code::encode(&ctx.get::<String>(0)?)?;
+  let options = QrCodeOptions::from_js(ctx.get::<JsObject>(1)?)?;
+  add_qr_code_to(document(&ctx)?, &code, &options).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn add_text_box(ctx: CallContext) -> Result<JsObject> {
+  let options = TextBoxOptions::from_js(ctx.get::<JsObject>(0)?)?;
+  add_text_box_to(document(&ctx)?, &options).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn set_dates(ctx: CallContext) -> Result<JsObject> {
+  let options = DatesOptions::from_js(ctx.get::<JsObject>(0)?)?;
+  set_dates_in(document(&ctx)?, &options).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn set_image_alt_text(ctx: CallContext) -> Result<JsObject> {
+  let alt_texts = alt_texts_from_js(ctx.get::<JsObject>(0)?)?;
+  set_image_alt_text_in(document(&ctx)?, &alt_texts).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn set_page_labels(ctx: CallContext) -> Result<JsObject> {
+  let ranges = page_labels_from_js(ctx.get::<JsObject>(0)?)?;
+  set_page_labels_in(document(&ctx)?, &ranges).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(3)]
+fn set_object(ctx: CallContext) -> Result<JsObject> {
+  let id = object_id(&ctx, 0)?;
+  let object = object_from_js(ctx.get::<JsUnknown>(2)?)?;
+  set_object_in(document(&ctx)?, id, object)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(2)]
+fn set_trailer_entry(ctx: CallContext) -> Result<JsObject> {
+  let key = trailer_key(&ctx.get::<String>(0)?)?;
+  let value = object_from_js(ctx.get::<JsUnknown>(1)?)?;
+  set_trailer_entry_in(document(&ctx)?, &key, value).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(0)]
+fn flatten_transparency(ctx: CallContext) -> Result<JsObject> {
+  flatten_transparency_in(document(&ctx)?);
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn normalize_black(ctx: CallContext) -> Result<JsObject> {
+  let tolerance = tolerance_from_js(&ctx.get::<Option<JsObject>>(0)?)?;
+  normalize_black_in(document(&ctx)?, tolerance).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(0)]
+fn subset_fonts(ctx: CallContext) -> Result<JsObject> {
+  subset_fonts_in(document(&ctx)?);
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(2)]
+fn set_page_content(ctx: CallContext) -> Result<JsObject> {
+  let page_number = ctx.get::<u32>(0)?;
+  let content = buffer_value(ctx.get::<JsBuffer>(1)?)?.to_vec();
+  set_page_content_in(document(&ctx)?, page_number, content).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn sanitize(ctx: CallContext) -> Result<JsObject> {
+  let options = SanitizeOptions::from_js(ctx.get::<Option<JsObject>>(0)?)?;
+  sanitize_in(document(&ctx)?, &options).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn remove_additional_actions(ctx: CallContext) -> Result<JsObject> {
+  let scope = ActionScope::filter_from_js(&ctx.get::<String>(0)?)?;
+  remove_additional_actions_in(document(&ctx)?, scope);
+  Ok(ctx.this_unchecked())
+}
+
+#[js_function(1)]
+fn set_outline(ctx: CallContext) -> Result<JsObject> {
+  let outline = outline_from_js(ctx.get::<JsObject>(0)?)?;
+  set_outline_in(document(&ctx)?, &outline).or_throw(ctx.env)?;
+  Ok(ctx.this_unchecked())
+}
+
+/// Read the optional `{ noObjectStreams, noCompression }` argument
+fn save_options(options: Option<JsObject>) -> Result<SaveOptions> {
+  match options {
+    Some(options) => SaveOptions::from_js(&options),
+    None => Ok(SaveOptions::default()),
+  }
+}
+
+#[js_function(1)]
+fn to_buffer(ctx: CallContext) -> Result<JsBuffer> {
+  let save = save_options(ctx.get::<Option<JsObject>>(0)?)?;
+  let target = save_document(document(&ctx)?, save).or_throw(ctx.env)?;
+  Ok(ctx.env.create_buffer_with_data(target)?.into_raw())
+}
+
+#[js_function(2)]
+fn to_file(ctx: CallContext) -> Result<JsUndefined> {
+  let path = ctx.get::<String>(0)?;
+  let save = save_options(ctx.get::<Option<JsObject>>(1)?)?;
+  save_document_to_file(document(&ctx)?, path, save).or_throw(ctx.env)?;
+  ctx.env.get_undefined()
+}
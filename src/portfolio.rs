@@ -0,0 +1,123 @@
+use lopdf::{Dictionary, Document, Object};
+use napi::{Error, JsObject, Result, Status};
+
+use crate::filters;
+use crate::names::name_tree_entries;
+use crate::utils::{decode_text_string, load_document};
+
+/// What `mergePdf` does with a portfolio, a document whose catalog `/Collection` makes its
+/// embedded files the content and its pages a cover
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum PortfolioMode {
+  /// Keep the cover pages and gather the embedded files of every portfolio in one collection
+  #[default]
+  Preserve,
+  /// Merge the PDFs embedded in the portfolio instead of its cover pages
+  Flatten,
+}
+
+impl PortfolioMode {
+  /// Read the `portfolios` option, `preserve` by default
+  pub fn from_js(options: &JsObject) -> Result<Self> {
+    match options.get_named_property::<Option<String>>("portfolios")?.as_deref() {
+      None | Some("preserve") => Ok(PortfolioMode::Preserve),
+      Some("flatten") => Ok(PortfolioMode::Flatten),
+      Some(other) => Err(Error::new(
+        Status::InvalidArg,
+        format!("portfolios must be 'preserve' or 'flatten', got '{}'", other),
+      )),
+    }
+  }
+}
+
+/// The catalog `/Collection` of a portfolio
+fn collection(document: &Document) -> Option<&Object> {
+  document.catalog().and_then(|catalog| catalog.get(b"Collection")).ok()
+}
+
+pub fn is_portfolio(document: &Document) -> bool {
+  collection(document).is_some()
+}
+
+/// Entries of the `/Names /EmbeddedFiles` name tree, file specifications keyed by name
+fn embedded_files(document: &Document) -> Vec<(Vec<u8>, Object)> {
+  document
+      .catalog()
+      .and_then(|catalog| catalog.get(b"Names"))
+      .and_then(|names| document.dereference(names))
+      .and_then(|(_, names)| names.as_dict())
+      .and_then(|names| names.get(b"EmbeddedFiles"))
+      .map(|tree| name_tree_entries(document, tree))
+      .unwrap_or_default()
+}
+
+/// A PDF embedded in a portfolio, titled with its file name
+pub struct EmbeddedDocument {
+  pub title: String,
+  pub document: Document,
+}
+
+/// The embedded files of a portfolio that are PDFs the merge can read, in name tree order, and
+/// the number of other files
+pub fn embedded_documents(document: &Document) -> (Vec<EmbeddedDocument>, usize) {
+  let mut documents = vec![];
+  let mut others = 0;
+  for (key, file_spec) in embedded_files(document) {
+    let file_spec = match document.dereference(&file_spec) {
+      Ok((_, Object::Dictionary(file_spec))) => file_spec,
+      _ => {
+        others += 1;
+        continue;
+      }
+    };
+    let text = |name: &[u8]| match file_spec.get(name).and_then(|value| document.dereference(value)) {
+      Ok((_, Object::String(value, _))) => Some(decode_text_string(value)),
+      _ => None,
+    };
+    let title = text(b"UF").or_else(|| text(b"F")).unwrap_or_else(|| decode_text_string(&key));
+    let embedded = file_spec
+        .get(b"EF")
+        .and_then(|streams| document.dereference(streams))
+        .and_then(|(_, streams)| streams.as_dict())
+        .and_then(|streams| streams.get(b"UF").or_else(|_| streams.get(b"F")))
+        .and_then(|stream| document.dereference(stream));
+    let data = match embedded {
+      Ok((_, Object::Stream(stream))) => filters::decoded_content(stream),
+      _ => {
+        others += 1;
+        continue;
+      }
+    };
+    match load_document(&data) {
+      Ok(document) => documents.push(EmbeddedDocument { title, document }),
+      Err(_) => others += 1,
+    }
+  }
+  (documents, others)
+}
+
+/// The collection of the merged portfolios, whose embedded files `NameTrees` merges like those of
+/// any document
+#[derive(Default)]
+pub struct Collections {
+  /// The `/Collection` of the first portfolio, which sets how viewers present the files
+  collection: Option<Object>,
+}
+
+impl Collections {
+  /// Take the collection of a portfolio, unless an earlier one was taken. Returns the number of
+  /// files it embeds.
+  pub fn add_document(&mut self, document: &Document) -> usize {
+    if let Some(collection) = collection(document) {
+      self.collection.get_or_insert_with(|| collection.clone());
+    }
+    embedded_files(document).len()
+  }
+
+  /// Make the merged document a portfolio, when there were portfolios
+  pub fn apply(&self, catalog: &mut Dictionary) {
+    if let Some(collection) = &self.collection {
+      catalog.set("Collection", collection.clone());
+    }
+  }
+}
@@ -0,0 +1,127 @@
+use lopdf::{Document, Object};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status};
+
+use crate::error::{self, ErrorCode, OrThrow, PdfError};
+use crate::page;
+use crate::page_tree::fix_page_tree_in;
+use crate::utils::{buffer_value, load_document, output, output_update};
+
+/// Keys a tile doesn't keep: the boxes would reach outside it and the thumbnail shows the whole page
+const TILE_REMOVED_KEYS: [&[u8]; 4] = [b"BleedBox", b"TrimBox", b"ArtBox", b"Thumb"];
+
+/// Keys only the first tile keeps, as an annotation, bead or structure element belongs to one page
+const FIRST_TILE_KEYS: [&[u8]; 3] = [b"Annots", b"B", b"StructParents"];
+
+/// Grid of a poster, read from the `{ cols, rows, overlap? }` options
+#[derive(Clone, Copy)]
+pub struct PosterGrid {
+  pub cols: u32,
+  pub rows: u32,
+  /// Points every tile shares with its neighbours, to glue them
+  pub overlap: f64,
+}
+
+impl PosterGrid {
+  pub fn from_js(options: &JsObject) -> Result<Self> {
+    let cols = options.get_named_property::<u32>("cols")?;
+    let rows = options.get_named_property::<u32>("rows")?;
+    if cols == 0 || rows == 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("cols and rows must be at least 1, got {} by {}", cols, rows),
+      ));
+    }
+    let overlap = options.get_named_property::<Option<f64>>("overlap")?.unwrap_or(0.0);
+    if !overlap.is_finite() || overlap < 0.0 {
+      return Err(Error::new(Status::InvalidArg, format!("overlap can't be negative, got {}", overlap)));
+    }
+    Ok(PosterGrid { cols, rows, overlap })
+  }
+}
+
+#[js_function(3)]
+pub fn posterize(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let page_number = ctx.get::<u32>(1)?;
+  let options = ctx.get::<JsObject>(2)?;
+  let grid = PosterGrid::from_js(&options)?;
+  let output = output(&Some(options))?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  posterize_in(&mut document, page_number, grid).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Replace the page by `cols` × `rows` tiles of its visible area, left to right from the top row as
+/// displayed. Every tile shows the same content through its own MediaBox and CropBox, overlapping
+/// its neighbours by `overlap` points. The first tile keeps the page's id, so bookmarks, links and
+/// annotations stay on it.
+pub fn posterize_in(document: &mut Document, page_number: u32, grid: PosterGrid) -> error::Result<()> {
+  let page_id = page::page_ids(document, &[page_number])?[0];
+  page::copy_inherited_attributes(document, page_id)?;
+  let media_box = page::media_box(document, page_id);
+  let visible = match page::inherited_attribute(document, page_id, b"CropBox").and_then(page::rect_from_object) {
+    Some(crop_box) => [
+      crop_box[0].max(media_box[0]),
+      crop_box[1].max(media_box[1]),
+      crop_box[2].min(media_box[2]),
+      crop_box[3].min(media_box[3]),
+    ],
+    None => media_box,
+  };
+  let rotate = page::rotation(document, page_id);
+  let (width, height) = match rotate {
+    90 | 270 => (visible[3] - visible[1], visible[2] - visible[0]),
+    _ => (visible[2] - visible[0], visible[3] - visible[1]),
+  };
+  let overlap = grid.overlap;
+  if (grid.cols > 1 && overlap >= width) || (grid.rows > 1 && overlap >= height) {
+    return Err(PdfError::new(
+      ErrorCode::GenericFailure,
+      format!("An overlap of {} leaves nothing of the {} × {} page to tile", overlap, width, height),
+    ));
+  }
+  let tile_width = (width + overlap * (grid.cols - 1) as f64) / grid.cols as f64;
+  let tile_height = (height + overlap * (grid.rows - 1) as f64) / grid.rows as f64;
+  let [a, b, c, d, e, f] = page::rect_display_matrix(visible, rotate);
+  let parent_id = document.get_dictionary(page_id)?.get(b"Parent").and_then(Object::as_reference)?;
+  let page = document.get_dictionary(page_id)?.clone();
+  let mut tile_ids = vec![];
+  for row in 0..grid.rows {
+    for col in 0..grid.cols {
+      let x = col as f64 * (tile_width - overlap);
+      let y = height - tile_height - row as f64 * (tile_height - overlap);
+      let corners = [(x, y), (x + tile_width, y + tile_height)].map(|(x, y)| (a * x + c * y + e, b * x + d * y + f));
+      let [(x0, y0), (x1, y1)] = corners;
+      let tile_box = [x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)]
+          .iter()
+          .map(|&value| value.into())
+          .collect::<Vec<Object>>();
+      let mut tile = page.clone();
+      for key in TILE_REMOVED_KEYS.iter() {
+        tile.remove(key);
+      }
+      if !tile_ids.is_empty() {
+        for key in FIRST_TILE_KEYS.iter() {
+          tile.remove(key);
+        }
+      }
+      tile.set("MediaBox", tile_box.clone());
+      tile.set("CropBox", tile_box);
+      if tile_ids.is_empty() {
+        document.objects.insert(page_id, Object::Dictionary(tile));
+        tile_ids.push(page_id);
+      } else {
+        tile_ids.push(document.add_object(tile));
+      }
+    }
+  }
+  let parent = document.get_object_mut(parent_id).and_then(Object::as_dict_mut)?;
+  let mut kids = parent.get(b"Kids").and_then(Object::as_array).cloned().unwrap_or_default();
+  let position = kids
+      .iter()
+      .position(|kid| kid.as_reference().ok() == Some(page_id))
+      .ok_or_else(|| PdfError::new(ErrorCode::InvalidPdf, "The page isn't among the kids of its parent"))?;
+  kids.splice(position + 1..position + 1, tile_ids[1..].iter().map(|&id| Object::Reference(id)));
+  parent.set("Kids", kids);
+  fix_page_tree_in(document)
+}
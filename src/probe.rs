@@ -0,0 +1,246 @@
+use std::convert::TryInto;
+
+use lopdf::{Dictionary, Document, Object};
+use napi::{CallContext, JsBuffer, JsObject, Result};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::crypto::{aes128_cbc_encrypt, md5, rc4};
+use crate::error::{ErrorCode, OrThrow, PdfError};
+use crate::{form, repair};
+use crate::utils::buffer_value;
+
+/// Pads passwords to 32 bytes in the standard security handler
+const PASSWORD_PADDING: [u8; 32] = [
+  0x28, 0xbf, 0x4e, 0x5e, 0x4e, 0x75, 0x8a, 0x41, 0x64, 0x00, 0x4e, 0x56, 0xff, 0xfa, 0x01, 0x08, 0x2e, 0x2e, 0x00,
+  0xb6, 0xd0, 0x68, 0x3e, 0x80, 0x2f, 0x0c, 0xa9, 0xfe, 0x64, 0x53, 0x69, 0x7a,
+];
+
+/// A linearization dictionary must be the first object, within the first kilobyte
+const LINEARIZED_WINDOW: usize = 1024;
+
+#[js_function(1)]
+pub fn probe(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  // Parsed without `load_document`, which refuses encrypted documents
+  let mut document = Document::load_mem(&buffer)
+      .map_err(|err| PdfError::new(ErrorCode::InvalidPdf, format!("Invalid PDF: {}", err)))
+      .or_throw(ctx.env)?;
+  repair::recover_hybrid_objects(&buffer, &mut document);
+  let encryption = document
+      .trailer
+      .get(b"Encrypt")
+      .and_then(|encrypt| document.dereference(encrypt))
+      .ok()
+      .map(|(_, encrypt)| encrypt.as_dict().cloned().unwrap_or_default());
+  let linearized = buffer[..buffer.len().min(LINEARIZED_WINDOW)]
+      .windows(b"/Linearized".len())
+      .any(|window| window == b"/Linearized");
+  let mut result = ctx.env.create_object()?;
+  result.set_named_property("pageCount", ctx.env.create_uint32(document.get_pages().len() as u32)?)?;
+  result.set_named_property("encrypted", ctx.env.get_boolean(encryption.is_some())?)?;
+  let needs_password = encryption.is_some_and(|encrypt| !opens_without_password(&document, &encrypt));
+  result.set_named_property("needsPassword", ctx.env.get_boolean(needs_password)?)?;
+  result.set_named_property("version", ctx.env.create_string(&document.version)?)?;
+  result.set_named_property("linearized", ctx.env.get_boolean(linearized)?)?;
+  result.set_named_property("xfaOnly", ctx.env.get_boolean(form::is_xfa_only(&document))?)?;
+  Ok(result)
+}
+
+fn bytes<'a>(dictionary: &'a Dictionary, key: &[u8]) -> &'a [u8] {
+  match dictionary.get(key) {
+    Ok(Object::String(bytes, _)) => bytes,
+    _ => &[],
+  }
+}
+
+/// Whether the empty user password opens the document, as readers try before asking for one.
+/// Only the standard security handler has passwords, others (certificates) always need something.
+fn opens_without_password(document: &Document, encrypt: &Dictionary) -> bool {
+  if encrypt.get(b"Filter").and_then(Object::as_name).ok() != Some(b"Standard") {
+    return false;
+  }
+  let revision = encrypt.get(b"R").and_then(Object::as_i64).unwrap_or(0);
+  let user = bytes(encrypt, b"U");
+  match revision {
+    2..=4 => {
+      let id = match document.trailer.get(b"ID").and_then(Object::as_array) {
+        Ok(id) => match id.first() {
+          Some(Object::String(id, _)) => id.as_slice(),
+          _ => &[],
+        },
+        Err(_) => &[],
+      };
+      let key = file_key(encrypt, revision, id);
+      if revision == 2 {
+        user.get(..32) == Some(&rc4(&key, &PASSWORD_PADDING)[..])
+      } else {
+        let mut hash = rc4(&key, &md5(&[&PASSWORD_PADDING[..], id].concat()));
+        for round in 1..=19_u8 {
+          hash = rc4(&key.iter().map(|byte| byte ^ round).collect::<Vec<_>>(), &hash);
+        }
+        user.get(..16) == Some(&hash[..])
+      }
+    }
+    5 | 6 if user.len() >= 40 => {
+      let salt = &user[32..40];
+      let hash = if revision == 5 {
+        Sha256::digest(salt).to_vec()
+      } else {
+        hardened_hash(salt)
+      };
+      user[..32] == hash[..32]
+    }
+    _ => false,
+  }
+}
+
+/// The RC4 key computed from the empty user password (algorithm 2 of ISO 32000)
+fn file_key(encrypt: &Dictionary, revision: i64, id: &[u8]) -> Vec<u8> {
+  let length = if revision == 2 {
+    5
+  } else {
+    (encrypt.get(b"Length").and_then(Object::as_i64).unwrap_or(40) / 8).clamp(5, 16) as usize
+  };
+  let permissions = encrypt.get(b"P").and_then(Object::as_i64).unwrap_or(0) as u32;
+  let mut input = [&PASSWORD_PADDING[..], bytes(encrypt, b"O"), &permissions.to_le_bytes(), id].concat();
+  if revision >= 4 && encrypt.get(b"EncryptMetadata").and_then(Object::as_bool).ok() == Some(false) {
+    input.extend_from_slice(&[0xff; 4]);
+  }
+  let mut hash = md5(&input);
+  if revision >= 3 {
+    for _ in 0..50 {
+      hash = md5(&hash[..length]);
+    }
+  }
+  hash[..length].to_vec()
+}
+
+/// The hash of the empty password of revision 6 (algorithm 2.B of ISO 32000-2)
+fn hardened_hash(salt: &[u8]) -> Vec<u8> {
+  let mut hash = Sha256::digest(salt).to_vec();
+  let mut round = 0;
+  loop {
+    // With an empty password and no user key, each repetition is just the hash
+    let repeated = hash.repeat(64);
+    let key: [u8; 16] = hash[..16].try_into().unwrap();
+    let iv: [u8; 16] = hash[16..32].try_into().unwrap();
+    let encrypted = aes128_cbc_encrypt(&key, &iv, &repeated);
+    hash = match encrypted[..16].iter().map(|&byte| byte as u32).sum::<u32>() % 3 {
+      0 => Sha256::digest(&encrypted).to_vec(),
+      1 => Sha384::digest(&encrypted).to_vec(),
+      _ => Sha512::digest(&encrypted).to_vec(),
+    };
+    round += 1;
+    if round >= 64 && u32::from(encrypted[encrypted.len() - 1]) <= round - 32 {
+      return hash[..32].to_vec();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use lopdf::StringFormat;
+
+  use super::*;
+
+  fn unhex(text: &str) -> Object {
+    let bytes = (0..text.len()).step_by(2).map(|index| u8::from_str_radix(&text[index..index + 2], 16).unwrap());
+    Object::String(bytes.collect(), StringFormat::Hexadecimal)
+  }
+
+  /// A document whose first file identifier is the bytes 0 to 15
+  fn identified() -> Document {
+    let mut document = Document::with_version("1.7");
+    let id = unhex("000102030405060708090a0b0c0d0e0f");
+    document.trailer.set("ID", vec![id.clone(), id]);
+    document
+  }
+
+  /// A standard security handler dictionary with the owner password `owner`, `/P` -3904
+  fn standard(revision: i64, owner: &str, user: &str) -> Dictionary {
+    let mut encrypt = Dictionary::new();
+    encrypt.set("Filter", Object::Name(b"Standard".to_vec()));
+    encrypt.set("V", if revision == 2 { 1 } else { revision.min(5) });
+    encrypt.set("R", revision);
+    encrypt.set("Length", if revision == 2 { 40 } else { 128 });
+    encrypt.set("P", -3904);
+    encrypt.set("O", unhex(owner));
+    encrypt.set("U", unhex(user));
+    encrypt
+  }
+
+  // The O and U entries below were computed with an independent implementation of the algorithms
+  // of ISO 32000, for the empty user password and for `secret`
+
+  #[test]
+  fn opens_rc4_documents_with_an_empty_user_password() {
+    let document = identified();
+    let cases = [
+      (
+        2,
+        "c92422687facee686e373f10b5c7d04738053152f7e2ee30e11c69ec442576ab",
+        "0535ff2ff4a1427af498e09e89c6c3b14139d497977277f0f05fcf04febf5d0d",
+        "92fe0f4454ad4c9644693f33c07cb54f587dce1e2682fe9ecea6107a1ef630dd",
+        "3469e05f8c1a9b6eb41405f5c363753ec8a16fa771efe097052974ef5c7a0120",
+      ),
+      (
+        3,
+        "566fa873ee33c797cd3b904fdadf814afa34df9a38f6ed41b984e2c6da2aa6f5",
+        "6a377f14504be9f1aba99e13933760ac00000000000000000000000000000000",
+        "0db5855fc5326569e765906caf64e4429a4c20d6e996fdef963e9b5080f9e083",
+        "07dd1598955b4a3c04b41ce38b601e3d00000000000000000000000000000000",
+      ),
+    ];
+    for (revision, owner, user, locked_owner, locked_user) in cases.iter() {
+      assert!(opens_without_password(&document, &standard(*revision, owner, user)), "R{}", revision);
+      assert!(!opens_without_password(&document, &standard(*revision, locked_owner, locked_user)), "R{}", revision);
+    }
+  }
+
+  #[test]
+  fn keys_revision_4_on_unencrypted_metadata() {
+    let document = identified();
+    let owner = "566fa873ee33c797cd3b904fdadf814afa34df9a38f6ed41b984e2c6da2aa6f5";
+    let mut encrypt = standard(4, owner, "0d2f357f373bcc901f31cfa3223f521800000000000000000000000000000000");
+    encrypt.set("EncryptMetadata", false);
+    assert!(opens_without_password(&document, &encrypt));
+    // The same entries with the metadata encrypted give another key
+    encrypt.remove(b"EncryptMetadata");
+    assert!(!opens_without_password(&document, &encrypt));
+  }
+
+  #[test]
+  fn opens_aes_256_documents_with_an_empty_user_password() {
+    let document = identified();
+    // The validation salt is the bytes 0 to 7 and the key salt 8 to 15
+    let salts = "000102030405060708090a0b0c0d0e0f";
+    let cases = [
+      (
+        5,
+        "8a851ff82ee7048ad09ec3847f1ddf44944104d2cbd17ef4e3db22c6785a0d45",
+        "9407a397f39fd21ffe27f6faa71ed3f0b2cf77f4319ad49b0c3be4f1d8e34491",
+      ),
+      (
+        6,
+        "1403c04eb647d2e60452dfc4eb0a5e0cf322e8a83a759eabbd17d498a93ba041",
+        "952a028e406d92accedad37501d7f8ffe7e3d9582c35336d4e434b580de7de76",
+      ),
+    ];
+    let owner = "00".repeat(48);
+    for (revision, user, locked) in cases.iter() {
+      let opened = standard(*revision, &owner, &format!("{}{}", user, salts));
+      assert!(opens_without_password(&document, &opened), "R{}", revision);
+      let locked = standard(*revision, &owner, &format!("{}{}", locked, salts));
+      assert!(!opens_without_password(&document, &locked), "R{}", revision);
+    }
+  }
+
+  #[test]
+  fn needs_a_password_for_other_handlers() {
+    let mut encrypt = Dictionary::new();
+    encrypt.set("Filter", Object::Name(b"Adobe.PubSec".to_vec()));
+    assert!(!opens_without_password(&identified(), &encrypt));
+    // An unknown revision of the standard handler
+    assert!(!opens_without_password(&identified(), &standard(7, "", "")));
+  }
+}
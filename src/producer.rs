@@ -0,0 +1,114 @@
+use std::sync::{PoisonError, RwLock};
+
+use lopdf::Document;
+use napi::{CallContext, JsObject, JsUndefined, Result};
+
+use crate::error;
+use crate::metadata::{info_dictionary_mut, info_update, InfoUpdate};
+use crate::utils::encode_text_string;
+
+/// What every rewritten document gets in its Info dictionary, set with `setProducerInfo`
+#[derive(Clone)]
+struct ProducerSettings {
+  /// Write `/Producer`, on by default
+  enabled: bool,
+  /// Name of the calling application, written in `/Producer` before this package
+  application: Option<String>,
+  /// Written in `/Creator`, which is left alone unless set
+  creator: Option<String>,
+}
+
+impl ProducerSettings {
+  fn apply(&mut self, info: &ProducerInfo) {
+    if let Some(enabled) = info.enabled {
+      self.enabled = enabled;
+    }
+    for (setting, update) in [(&mut self.application, &info.application), (&mut self.creator, &info.creator)] {
+      match update {
+        InfoUpdate::Keep => {}
+        InfoUpdate::Clear => *setting = None,
+        InfoUpdate::Set(value) => *setting = Some(value.clone()),
+      }
+    }
+  }
+}
+
+/// The only state kept between calls. The addon is loaded once per process, so every worker thread
+/// reads the same settings: a worker calling `setProducerInfo` changes them for all the others. The
+/// lock only keeps the change safe while others are rewriting, the `producerInfo` save option is
+/// the way to name a producer for one call.
+static SETTINGS: RwLock<ProducerSettings> = RwLock::new(ProducerSettings {
+  enabled: true,
+  application: None,
+  creator: None,
+});
+
+/// A `ProducerInfo` object: changes to the settings, for the whole process with `setProducerInfo`
+/// or for a single call with the `producerInfo` save option
+#[derive(Clone)]
+pub struct ProducerInfo {
+  enabled: Option<bool>,
+  application: InfoUpdate<String>,
+  creator: InfoUpdate<String>,
+}
+
+impl Default for ProducerInfo {
+  fn default() -> Self {
+    ProducerInfo {
+      enabled: None,
+      application: InfoUpdate::Keep,
+      creator: InfoUpdate::Keep,
+    }
+  }
+}
+
+impl ProducerInfo {
+  pub fn from_js(options: &JsObject) -> Result<Self> {
+    Ok(ProducerInfo {
+      enabled: options.get_named_property::<Option<bool>>("producer")?,
+      application: info_update(options, "application")?,
+      creator: info_update(options, "creator")?,
+    })
+  }
+}
+
+/// The settings of the process with the changes of `info`
+fn settings(info: &ProducerInfo) -> ProducerSettings {
+  let mut settings = SETTINGS.read().unwrap_or_else(PoisonError::into_inner).clone();
+  settings.apply(info);
+  settings
+}
+
+/// Name and version of the addon, after the application name when one was set
+pub fn producer(info: &ProducerInfo) -> String {
+  let addon = format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+  match settings(info).application {
+    Some(application) => format!("{} ({})", application, addon),
+    None => addon,
+  }
+}
+
+#[js_function(1)]
+pub fn set_producer_info(ctx: CallContext) -> Result<JsUndefined> {
+  let info = ProducerInfo::from_js(&ctx.get::<JsObject>(0)?)?;
+  SETTINGS.write().unwrap_or_else(PoisonError::into_inner).apply(&info);
+  ctx.env.get_undefined()
+}
+
+/// Write the `/Producer` and, when set, the `/Creator` of the settings changed by `info` into the
+/// Info dictionary
+pub fn stamp(document: &mut Document, info: &ProducerInfo) -> error::Result<()> {
+  let ProducerSettings { enabled, creator, .. } = settings(info);
+  if !enabled && creator.is_none() {
+    return Ok(());
+  }
+  let producer = producer(info);
+  let info = info_dictionary_mut(document)?;
+  if enabled {
+    info.set("Producer", encode_text_string(&producer));
+  }
+  if let Some(creator) = creator {
+    info.set("Creator", encode_text_string(&creator));
+  }
+  Ok(())
+}
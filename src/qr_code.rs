@@ -0,0 +1,109 @@
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, Stream};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status};
+use qrcode::{Color, QrCode};
+
+use crate::error::{self, OrThrow};
+use crate::page;
+use crate::utils::{buffer_value, load_document, output_update, Output};
+
+/// Light modules around the code, as required by the QR specification
+const QUIET_ZONE: usize = 4;
+
+pub struct QrCodeOptions {
+  pages: page::PageSelector,
+  /// Lower-left corner in points, measured in `coordinate_space`
+  x: f64,
+  y: f64,
+  coordinate_space: page::CoordinateSpace,
+  /// Side length in points, quiet zone included
+  size: f64,
+  output: Output,
+}
+
+impl QrCodeOptions {
+  pub fn from_js(options: JsObject) -> Result<Self> {
+    let size = options.get_named_property::<f64>("size")?;
+    if size <= 0.0 {
+      return Err(Error::new(Status::InvalidArg, "size must be positive".to_owned()));
+    }
+    Ok(QrCodeOptions {
+      pages: page::PageSelector::from_js_with_page(&options)?,
+      x: options.get_named_property::<f64>("x")?,
+      y: options.get_named_property::<f64>("y")?,
+      coordinate_space: page::CoordinateSpace::from_js(&options)?,
+      size,
+      output: Output::from_js(&options)?,
+    })
+  }
+}
+
+/// Encode the data, failing with `InvalidArg` when it doesn't fit in a QR code
+pub fn encode(data: &str) -> Result<QrCode> {
+  QrCode::new(data).map_err(|err| Error::new(Status::InvalidArg, format!("Can't encode the QR code: {}", err)))
+}
+
+#[js_function(3)]
+pub fn add_qr_code(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let code = encode(&ctx.get::<String>(1)?)?;
+  let options = QrCodeOptions::from_js(ctx.get::<JsObject>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  add_qr_code_to(&mut document, &code, &options).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &options.output)
+}
+
+/// A 1-bit image of the code with one pixel per module, so viewers can scale it without blurring
+fn qr_image(code: &QrCode) -> Stream {
+  let modules = code.width();
+  let colors = code.to_colors();
+  let width = modules + 2 * QUIET_ZONE;
+  let row_bytes = width.div_ceil(8);
+  // In DeviceGray a set bit is white
+  let mut data = vec![0xff; row_bytes * width];
+  for y in 0..modules {
+    for x in 0..modules {
+      if colors[y * modules + x] == Color::Dark {
+        let (row, column) = (y + QUIET_ZONE, x + QUIET_ZONE);
+        data[row * row_bytes + column / 8] &= !(0x80 >> (column % 8));
+      }
+    }
+  }
+  let mut dictionary = Dictionary::new();
+  dictionary.set("Type", Object::Name(b"XObject".to_vec()));
+  dictionary.set("Subtype", Object::Name(b"Image".to_vec()));
+  dictionary.set("Width", width as i64);
+  dictionary.set("Height", width as i64);
+  dictionary.set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+  dictionary.set("BitsPerComponent", 1);
+  dictionary.set("Interpolate", false);
+  Stream::new(dictionary, data)
+}
+
+pub fn add_qr_code_to(document: &mut Document, code: &QrCode, options: &QrCodeOptions) -> error::Result<()> {
+  let pages = options.pages.select(document)?;
+  let image_id = document.add_object(qr_image(code));
+  for (_, page_id) in pages {
+    let image_name = page::add_resource(document, page_id, b"XObject", "QR", image_id)?;
+    // In display space the code keeps its position and reads upright on rotated pages
+    let matrix = options.coordinate_space.matrix(document, page_id);
+    let operations = vec![
+      Operation::new("cm", matrix.iter().map(|&value| value.into()).collect()),
+      Operation::new(
+        "cm",
+        vec![
+          options.size.into(),
+          0.into(),
+          0.into(),
+          options.size.into(),
+          options.x.into(),
+          options.y.into(),
+        ],
+      ),
+      Operation::new("Do", vec![Object::Name(image_name)]),
+    ];
+    let content = Content { operations }.encode()?;
+    page::append_content(document, page_id, content)?;
+  }
+  Ok(())
+}
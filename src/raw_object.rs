@@ -0,0 +1,244 @@
+use std::convert::TryFrom;
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use napi::{CallContext, Env, Error, JsBuffer, JsObject, JsUnknown, Result, Status, ValueType};
+
+use crate::error::{self, ErrorCode, OrThrow, PdfError};
+use crate::utils::{
+  buffer_value, decode_text_string, encode_text_string, integer_in, load_document, output, output_update, MAX_OBJECT_ID,
+};
+
+// Objects cross to JS in the notation of qpdf's JSON output: names are `/Name` strings, strings are
+// `u:text` or `b:hex`, references `12 0 R`, dictionary keys keep their `/` and a stream is
+// `{ stream: { dict, data } }`. Numbers and booleans are plain JS values.
+
+/// Whether a string is text that survives a `u:` round trip
+fn is_text(bytes: &[u8]) -> bool {
+  bytes.starts_with(&[0xfe, 0xff]) || bytes.iter().all(|&byte| (0x20..0x7f).contains(&byte) || b"\t\n\r".contains(&byte))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+  if !hex.len().is_multiple_of(2) {
+    return None;
+  }
+  (0..hex.len())
+      .step_by(2)
+      .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+      .collect()
+}
+
+fn dictionary_to_js(env: &Env, dictionary: &Dictionary) -> Result<JsObject> {
+  let mut result = env.create_object()?;
+  for (key, value) in dictionary.iter() {
+    let key = format!("/{}", String::from_utf8_lossy(key));
+    result.set_named_property(&key, object_to_js(env, value)?)?;
+  }
+  Ok(result)
+}
+
+/// Convert a PDF object to its JS form
+pub fn object_to_js(env: &Env, object: &Object) -> Result<JsUnknown> {
+  Ok(match object {
+    Object::Null => env.get_null()?.into_unknown(),
+    Object::Boolean(value) => env.get_boolean(*value)?.into_unknown(),
+    Object::Integer(value) => env.create_int64(*value)?.into_unknown(),
+    Object::Real(value) => env.create_double(*value)?.into_unknown(),
+    Object::Name(name) => env.create_string(&format!("/{}", String::from_utf8_lossy(name)))?.into_unknown(),
+    Object::String(bytes, _) if is_text(bytes) => {
+      env.create_string(&format!("u:{}", decode_text_string(bytes)))?.into_unknown()
+    }
+    Object::String(bytes, _) => env.create_string(&format!("b:{}", to_hex(bytes)))?.into_unknown(),
+    Object::Reference((number, generation)) => {
+      env.create_string(&format!("{} {} R", number, generation))?.into_unknown()
+    }
+    Object::Array(array) => {
+      let mut result = env.create_array_with_length(array.len())?;
+      for (index, item) in array.iter().enumerate() {
+        result.set_element(index as u32, object_to_js(env, item)?)?;
+      }
+      result.into_unknown()
+    }
+    Object::Dictionary(dictionary) => dictionary_to_js(env, dictionary)?.into_unknown(),
+    Object::Stream(stream) => {
+      let mut body = env.create_object()?;
+      body.set_named_property("dict", dictionary_to_js(env, &stream.dict)?)?;
+      body.set_named_property("data", env.create_buffer_with_data(stream.content.clone())?.into_raw())?;
+      let mut result = env.create_object()?;
+      result.set_named_property("stream", body)?;
+      result.into_unknown()
+    }
+  })
+}
+
+fn invalid(message: String) -> Error {
+  Error::new(Status::InvalidArg, message)
+}
+
+fn dictionary_from_js(dictionary: &JsObject) -> Result<Dictionary> {
+  let keys = dictionary.get_property_names()?;
+  let mut result = Dictionary::new();
+  for index in 0..keys.get_array_length()? {
+    let key = keys.get_element::<JsUnknown>(index)?.coerce_to_string()?.into_utf8()?.into_owned()?;
+    let name = key
+        .strip_prefix('/')
+        .ok_or_else(|| invalid(format!("Dictionary keys are names starting with '/', got '{}'", key)))?;
+    result.set(name.as_bytes().to_vec(), object_from_js(dictionary.get_named_property::<JsUnknown>(&key)?)?);
+  }
+  Ok(result)
+}
+
+/// Convert the JS form of an object back to a PDF object
+pub fn object_from_js(value: JsUnknown) -> Result<Object> {
+  match value.get_type()? {
+    ValueType::Null | ValueType::Undefined => Ok(Object::Null),
+    ValueType::Boolean => Ok(Object::Boolean(value.coerce_to_bool()?.get_value()?)),
+    ValueType::Number => {
+      let number = value.coerce_to_number()?.get_double()?;
+      if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+        Ok(Object::Integer(number as i64))
+      } else {
+        Ok(Object::Real(number))
+      }
+    }
+    ValueType::String => {
+      let text = value.coerce_to_string()?.into_utf8()?.into_owned()?;
+      if let Some(name) = text.strip_prefix('/') {
+        return Ok(Object::Name(name.as_bytes().to_vec()));
+      }
+      if let Some(text) = text.strip_prefix("u:") {
+        return Ok(encode_text_string(text));
+      }
+      if let Some(hex) = text.strip_prefix("b:") {
+        let bytes = from_hex(hex).ok_or_else(|| invalid(format!("'{}' isn't valid hexadecimal", hex)))?;
+        return Ok(Object::String(bytes, StringFormat::Hexadecimal));
+      }
+      let reference = match text.split(' ').collect::<Vec<_>>().as_slice() {
+        [number, generation, "R"] => number.parse::<u32>().ok().zip(generation.parse::<u16>().ok()),
+        _ => None,
+      };
+      reference.map(Object::Reference).ok_or_else(|| {
+        invalid(format!(
+          "Strings are '/Name', 'u:text', 'b:hex' or '12 0 R' references, got '{}'",
+          text
+        ))
+      })
+    }
+    ValueType::Object => {
+      let object = value.coerce_to_object()?;
+      if object.is_array()? {
+        return (0..object.get_array_length()?)
+            .map(|index| object_from_js(object.get_element::<JsUnknown>(index)?))
+            .collect::<Result<Vec<_>>>()
+            .map(Object::Array);
+      }
+      if !object.has_named_property("stream")? {
+        return dictionary_from_js(&object).map(Object::Dictionary);
+      }
+      let body = object.get_named_property::<JsObject>("stream")?;
+      let dict = dictionary_from_js(&body.get_named_property::<JsObject>("dict")?)?;
+      let data = buffer_value(body.get_named_property::<JsBuffer>("data")?)?.to_vec();
+      Ok(Object::Stream(Stream::new(dict, data)))
+    }
+    other => Err(invalid(format!("{:?} values have no PDF equivalent", other))),
+  }
+}
+
+/// How far past the highest object number `setObject` may add one
+const MAX_NEW_OBJECT_GAP: u32 = 1024;
+
+/// Read the object id arguments at `index` and `index + 1`, the generation defaulting to 0
+pub fn object_id(ctx: &CallContext, index: usize) -> Result<ObjectId> {
+  let number = integer_in(ctx.get::<f64>(index)?, "objNum", 1, MAX_OBJECT_ID)?;
+  let generation = ctx.get::<Option<u32>>(index + 1)?.unwrap_or(0);
+  let generation = u16::try_from(generation).map_err(|_| invalid(format!("Invalid generation {}", generation)))?;
+  Ok((number, generation))
+}
+
+#[js_function(3)]
+pub fn get_object(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let id = object_id(&ctx, 1)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  let object = document
+      .get_object(id)
+      .map_err(|_| PdfError::new(ErrorCode::GenericFailure, format!("Object {} {} doesn't exist", id.0, id.1)))
+      .or_throw(ctx.env)?;
+  object_to_js(ctx.env, object)
+}
+
+#[js_function(1)]
+pub fn get_trailer(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  dictionary_to_js(ctx.env, &document.trailer)
+}
+
+#[js_function(5)]
+pub fn set_object(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let id = object_id(&ctx, 1)?;
+  let object = object_from_js(ctx.get::<JsUnknown>(3)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(4)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  set_object_in(&mut document, id, object)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Replace or add an indirect object, `null` deletes it. A new object may be numbered at most
+/// `MAX_NEW_OBJECT_GAP` past the highest number, every number below it gets an xref entry on save.
+pub fn set_object_in(document: &mut Document, id: ObjectId, object: Object) -> Result<()> {
+  let highest = document.max_id.saturating_add(MAX_NEW_OBJECT_GAP);
+  if id.0 > highest && !document.objects.contains_key(&id) {
+    return Err(invalid(format!(
+      "objNum {} is more than {} past the highest object number {}",
+      id.0, MAX_NEW_OBJECT_GAP, document.max_id
+    )));
+  }
+  if let Object::Null = object {
+    document.objects.remove(&id);
+    return Ok(());
+  }
+  document.objects.insert(id, object);
+  document.max_id = document.max_id.max(id.0);
+  Ok(())
+}
+
+/// Read a trailer key argument, a name like `/ID`
+pub fn trailer_key(key: &str) -> Result<Vec<u8>> {
+  key
+      .strip_prefix('/')
+      .map(|name| name.as_bytes().to_vec())
+      .ok_or_else(|| invalid(format!("Trailer keys are names starting with '/', got '{}'", key)))
+}
+
+#[js_function(4)]
+pub fn set_trailer_entry(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let key = trailer_key(&ctx.get::<String>(1)?)?;
+  let value = object_from_js(ctx.get::<JsUnknown>(2)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(3)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  set_trailer_entry_in(&mut document, &key, value).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Set a trailer entry, `null` removes it. `/Root` must reference a dictionary of the document.
+/// `/Size` is rewritten on save whatever it is set to.
+pub fn set_trailer_entry_in(document: &mut Document, key: &[u8], value: Object) -> error::Result<()> {
+  if key == b"Root" && !value.as_reference().is_ok_and(|id| document.get_dictionary(id).is_ok()) {
+    return Err(PdfError::new(
+      ErrorCode::GenericFailure,
+      "/Root must reference a dictionary of the document",
+    ));
+  }
+  if let Object::Null = value {
+    document.trailer.remove(key);
+  } else {
+    document.trailer.set(key.to_vec(), value);
+  }
+  Ok(())
+}
@@ -0,0 +1,150 @@
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+use lopdf::Document;
+
+use crate::error::{ErrorCode, Result};
+use crate::utils::load_document;
+
+/// Read `N G R` at the start of `data`, after optional whitespace
+fn reference(data: &[u8]) -> Option<(u32, u16)> {
+  let text = std::str::from_utf8(&data[..data.len().min(32)]).unwrap_or_else(|err| {
+    std::str::from_utf8(&data[..err.valid_up_to()]).unwrap_or("")
+  });
+  let mut parts = text.split_ascii_whitespace();
+  let number = parts.next()?.parse().ok()?;
+  let generation = parts.next()?.parse().ok()?;
+  if !parts.next()?.starts_with('R') {
+    return None;
+  }
+  Some((number, generation))
+}
+
+/// The reference last written after `key` in the file, as the newest trailer holds it
+fn last_reference(data: &[u8], key: &[u8]) -> Option<(u32, u16)> {
+  (0..data.len().saturating_sub(key.len()))
+      .rev()
+      .filter(|&start| data[start..].starts_with(key))
+      .find_map(|start| reference(&data[start + key.len()..]))
+}
+
+/// Offsets of the `N G obj` headers found in the file, the last one of each object number when an
+/// object was rewritten by incremental updates
+pub fn object_offsets(data: &[u8]) -> BTreeMap<u32, (u16, usize)> {
+  let is_space = |byte: u8| byte.is_ascii_whitespace() || byte == 0;
+  // The digits ending right before the whitespace that precedes `end`, as their start and end
+  let digits_before = |end: usize| {
+    let digits_end = (0..end).rev().find(|&index| !is_space(data[index]))? + 1;
+    let digits_start = (0..digits_end)
+        .rev()
+        .find(|&index| !data[index].is_ascii_digit())
+        .map_or(0, |index| index + 1);
+    Some((digits_start, digits_end)).filter(|_| digits_end < end && digits_start < digits_end)
+  };
+  fn parse<T: std::str::FromStr>(data: &[u8], (start, end): (usize, usize)) -> Option<T> {
+    std::str::from_utf8(&data[start..end]).ok()?.parse().ok()
+  }
+  let mut offsets = BTreeMap::new();
+  for keyword in 0..data.len().saturating_sub(2) {
+    if !data[keyword..].starts_with(b"obj") || data.get(keyword + 3).is_some_and(u8::is_ascii_alphanumeric) {
+      continue;
+    }
+    let generation = match digits_before(keyword) {
+      Some(generation) => generation,
+      None => continue,
+    };
+    let number = match digits_before(generation.0) {
+      Some(number) if number.0 == 0 || is_space(data[number.0 - 1]) => number,
+      _ => continue,
+    };
+    if let (Some(object_number), Some(generation)) = (parse(data, number), parse(data, generation)) {
+      offsets.insert(object_number, (generation, number.0));
+    }
+  }
+  offsets
+}
+
+/// Append a cross-reference table listing the objects found by scanning the file, and a trailer
+/// pointing at the last `/Root` (a catalog found among the objects when there is none). Objects
+/// only stored in object streams are lost if the cross-reference stream locating them is damaged.
+fn rebuild_xref(data: &[u8]) -> Option<Vec<u8>> {
+  let offsets = object_offsets(data);
+  let root = last_reference(data, b"/Root").or_else(|| {
+    // An object spans at most up to the next one
+    let mut starts = offsets.values().map(|&(_, start)| start).collect::<Vec<_>>();
+    starts.sort_unstable();
+    offsets.iter().find_map(|(&number, &(generation, start))| {
+      let end = starts.iter().copied().find(|&next| next > start).unwrap_or(data.len());
+      let object = &data[start..end];
+      let is_catalog = (0..object.len()).any(|index| object[index..].starts_with(b"/Catalog"));
+      Some((number, generation)).filter(|_| is_catalog)
+    })
+  })?;
+  let size = offsets.keys().next_back()? + 1;
+  let mut repaired = data.to_vec();
+  repaired.push(b'\n');
+  let xref_offset = repaired.len();
+  repaired.extend(format!("xref\n0 {}\n", size).into_bytes());
+  for number in 0..size {
+    let entry = match offsets.get(&number) {
+      Some(&(generation, start)) => format!("{:010} {:05} n \n", start, generation),
+      None => "0000000000 65535 f \n".to_owned(),
+    };
+    repaired.extend(entry.into_bytes());
+  }
+  let mut trailer = format!("trailer\n<< /Size {} /Root {} {} R", size, root.0, root.1);
+  for key in ["Info", "Encrypt"] {
+    if let Some((number, generation)) = last_reference(data, format!("/{}", key).as_bytes()) {
+      trailer.push_str(&format!(" /{} {} {} R", key, number, generation));
+    }
+  }
+  repaired.extend(format!("{} >>\nstartxref\n{}\n%%EOF\n", trailer, xref_offset).into_bytes());
+  Some(repaired)
+}
+
+/// Add the objects lopdf missed in a hybrid-reference file, whose classic table leaves out objects
+/// only listed by the cross-reference stream of its trailer's `/XRefStm` (object streams and the
+/// objects in them). lopdf reads that stream only from the newest trailer of a file with updates,
+/// so they are taken from a load with a rebuilt table.
+pub fn recover_hybrid_objects(buffer: &[u8], document: &mut Document) {
+  if !is_hybrid(buffer) {
+    return;
+  }
+  // Points into the original file, meaningless once the document is written again
+  document.trailer.remove(b"XRefStm");
+  let rebuilt = match rebuild_xref(buffer).and_then(|repaired| Document::load_mem(&repaired).ok()) {
+    Some(rebuilt) => rebuilt,
+    None => return,
+  };
+  for (id, object) in rebuilt.objects {
+    if let Entry::Vacant(entry) = document.objects.entry(id) {
+      entry.insert(object);
+    }
+  }
+  document.max_id = document.max_id.max(rebuilt.max_id);
+}
+
+/// Whether a trailer of the file points at a cross-reference stream besides its classic table
+pub fn is_hybrid(buffer: &[u8]) -> bool {
+  buffer.windows(b"/XRefStm".len()).any(|window| window == b"/XRefStm")
+}
+
+/// Load a document, and when `repair` is set and it doesn't parse or no page can be read, load it
+/// again with a cross-reference table rebuilt from the objects in the file. Returns whether the
+/// document had to be repaired.
+pub fn load_or_repair(buffer: &[u8], repair: bool) -> Result<(Document, bool)> {
+  let loaded = load_document(buffer);
+  let damaged = match &loaded {
+    Ok(document) => document.get_pages().is_empty(),
+    Err(err) => err.code == ErrorCode::InvalidPdf,
+  };
+  if repair && damaged {
+    if let Some(document) = rebuild_xref(buffer)
+        .and_then(|repaired| load_document(&repaired).ok())
+        .filter(|document| !document.get_pages().is_empty())
+    {
+      return Ok((document, true));
+    }
+  }
+  loaded.map(|document| (document, false))
+}
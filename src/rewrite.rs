@@ -0,0 +1,28 @@
+use lopdf::Document;
+use napi::{CallContext, JsBuffer, JsObject, JsUnknown, Result};
+
+use crate::error::OrThrow;
+use crate::utils::{buffer_value, load_document, output, output_document};
+
+#[js_function(2)]
+pub fn rewrite(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let options = ctx.get::<Option<JsObject>>(1)?;
+  let garbage_collect = match &options {
+    Some(options) => options.get_named_property::<Option<bool>>("garbageCollect")?.unwrap_or(true),
+    None => true,
+  };
+  let output = output(&options)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  if garbage_collect {
+    collect_garbage(&mut document);
+  }
+  output_document(ctx.env, &mut document, output.path, output.save)
+}
+
+/// Drop the objects nothing refers to from the trailer down, the containers of object streams
+/// lopdf already unpacked among them, and number the others from 1 without gaps
+pub fn collect_garbage(document: &mut Document) {
+  document.prune_objects();
+  document.renumber_objects();
+}
@@ -0,0 +1,374 @@
+use std::collections::BTreeSet;
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status};
+
+use crate::error::{self, OrThrow};
+use crate::form::annotation_ids;
+use crate::page;
+use crate::utils::{buffer_value, load_document, output, output_update};
+
+#[js_function(5)]
+pub fn rotate_range(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let from = ctx.get::<u32>(1)?;
+  let to = ctx.get::<u32>(2)?;
+  let degrees = ctx.get::<i64>(3)?;
+  let output = output(&ctx.get::<Option<JsObject>>(4)?)?;
+  validate_range(from, to, degrees)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  rotate_pages_in(&mut document, &(from..=to).collect::<Vec<_>>(), degrees).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Check the arguments of `rotateRange`, the page count is only checked once the document is loaded
+pub fn validate_range(from: u32, to: u32, degrees: i64) -> Result<()> {
+  if from == 0 || from > to {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("Invalid page range {}-{}", from, to),
+    ));
+  }
+  if degrees % 90 != 0 {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("degrees must be a multiple of 90, got {}", degrees),
+    ));
+  }
+  Ok(())
+}
+
+#[js_function(1)]
+pub fn get_page_rotations(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  let pages = document.get_pages();
+  let mut rotations = ctx.env.create_array_with_length(pages.len())?;
+  for (index, page_id) in pages.into_values().enumerate() {
+    rotations.set_element(index as u32, ctx.env.create_int64(page::rotation(&document, page_id))?)?;
+  }
+  Ok(rotations)
+}
+
+/// Add a clockwise rotation to the given 1-based pages
+pub fn rotate_pages_in(document: &mut Document, page_numbers: &[u32], degrees: i64) -> error::Result<()> {
+  // Resolve every page first, so nothing is rotated when a page is out of range
+  for page_id in page::page_ids(document, page_numbers)? {
+    rotate_page(document, page_id, degrees)?;
+  }
+  Ok(())
+}
+
+/// Add a clockwise rotation to a page, composing with its (possibly inherited) `/Rotate`
+pub fn rotate_page(document: &mut Document, page_id: ObjectId, degrees: i64) -> error::Result<()> {
+  let rotation = (page::rotation(document, page_id) + degrees).rem_euclid(360);
+  document
+      .get_object_mut(page_id)
+      .and_then(Object::as_dict_mut)?
+      .set("Rotate", rotation);
+  Ok(())
+}
+
+/// Orientation of a page as displayed, after its `/Rotate`
+#[derive(Clone, Copy, PartialEq)]
+pub enum Orientation {
+  Portrait,
+  Landscape,
+}
+
+impl Orientation {
+  pub fn from_js(target: &str) -> Result<Self> {
+    match target {
+      "portrait" => Ok(Orientation::Portrait),
+      "landscape" => Ok(Orientation::Landscape),
+      other => Err(Error::new(
+        Status::InvalidArg,
+        format!("target must be 'portrait' or 'landscape', got '{}'", other),
+      )),
+    }
+  }
+}
+
+#[js_function(3)]
+pub fn uniform_orientation(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let target = Orientation::from_js(&ctx.get::<String>(1)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  uniform_orientation_in(&mut document, target).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Turn the pages displayed in the other orientation a quarter clockwise, without scaling them.
+/// Square pages and pages already in the `target` orientation are left alone.
+pub fn uniform_orientation_in(document: &mut Document, target: Orientation) -> error::Result<()> {
+  for page_id in document.get_pages().into_values() {
+    let (width, height) = page::display_size(document, page_id);
+    let orientation = if width > height {
+      Orientation::Landscape
+    } else if height > width {
+      Orientation::Portrait
+    } else {
+      continue;
+    };
+    if orientation != target {
+      rotate_page(document, page_id, 90)?;
+    }
+  }
+  Ok(())
+}
+
+/// Page boxes besides the MediaBox, which are moved along with the content
+const PAGE_BOXES: [&[u8]; 4] = [b"CropBox", b"BleedBox", b"TrimBox", b"ArtBox"];
+
+/// Annotation entries listing `x y` pairs
+const ANNOTATION_POINTS: [&[u8]; 3] = [b"QuadPoints", b"L", b"Vertices"];
+
+const IDENTITY: [f64; 6] = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Annotation flag keeping the annotation upright whatever the page rotation
+const NO_ROTATE: i64 = 1 << 4;
+
+#[js_function(2)]
+pub fn bake_rotation(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(1)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  bake_rotation_in(&mut document).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+fn matrix_from_object(object: &Object) -> Option<[f64; 6]> {
+  match object.as_array().ok()?.iter().map(|value| value.as_float().ok()).collect::<Option<Vec<f64>>>()?[..] {
+    [a, b, c, d, e, f] => Some([a, b, c, d, e, f]),
+    _ => None,
+  }
+}
+
+fn invert(matrix: [f64; 6]) -> [f64; 6] {
+  let [a, b, c, d, e, f] = matrix;
+  let det = a * d - b * c;
+  [d / det, -b / det, -c / det, a / det, (c * f - d * e) / det, (b * e - a * f) / det]
+}
+
+fn multiply(first: [f64; 6], second: [f64; 6]) -> [f64; 6] {
+  let [a, b, c, d, e, f] = first;
+  let [a2, b2, c2, d2, e2, f2] = second;
+  [
+    a * a2 + b * c2,
+    a * b2 + b * d2,
+    c * a2 + d * c2,
+    c * b2 + d * d2,
+    e * a2 + f * c2 + e2,
+    e * b2 + f * d2 + f2,
+  ]
+}
+
+fn apply(matrix: [f64; 6], x: f64, y: f64) -> (f64, f64) {
+  let [a, b, c, d, e, f] = matrix;
+  (a * x + c * y + e, b * x + d * y + f)
+}
+
+fn rect_object(rect: [f64; 4]) -> Object {
+  Object::Array(rect.iter().map(|&value| value.into()).collect())
+}
+
+fn transform_rect(matrix: [f64; 6], rect: [f64; 4]) -> [f64; 4] {
+  let (x1, y1) = apply(matrix, rect[0], rect[1]);
+  let (x2, y2) = apply(matrix, rect[2], rect[3]);
+  [x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)]
+}
+
+/// Transform a flat list of `x y` pairs, leaving it alone if it holds anything but numbers
+fn transform_points(matrix: [f64; 6], points: &[Object]) -> Option<Vec<Object>> {
+  let values = points.iter().map(|value| value.as_float().ok()).collect::<Option<Vec<f64>>>()?;
+  if !values.len().is_multiple_of(2) {
+    return None;
+  }
+  Some(
+    values
+        .chunks(2)
+        .flat_map(|pair| {
+          let (x, y) = apply(matrix, pair[0], pair[1]);
+          vec![x.into(), y.into()]
+        })
+        .collect(),
+  )
+}
+
+/// Streams of an appearance dictionary, its entries being streams or dictionaries of state streams
+fn appearance_stream_ids(document: &Document, annotation: &Dictionary) -> Vec<ObjectId> {
+  let appearances = match annotation.get(b"AP").and_then(|appearances| document.dereference(appearances)) {
+    Ok((_, Object::Dictionary(appearances))) => appearances,
+    _ => return vec![],
+  };
+  let mut ids = vec![];
+  for (_, appearance) in appearances.iter() {
+    match appearance {
+      Object::Reference(id) if matches!(document.get_object(*id), Ok(Object::Stream(_))) => ids.push(*id),
+      _ => {
+        if let Ok((_, Object::Dictionary(states))) = document.dereference(appearance) {
+          ids.extend(states.iter().filter_map(|(_, state)| state.as_reference().ok()));
+        }
+      }
+    }
+  }
+  ids
+}
+
+/// Move an annotation along with the page content. Its appearances get the rotation in their
+/// `/Matrix`, unless the annotation doesn't rotate with the page: it then stays upright, pinned
+/// at its top-left corner as viewers draw it.
+fn bake_annotation(
+  document: &mut Document, annotation_id: ObjectId, matrix: [f64; 6], rotated: &mut BTreeSet<ObjectId>,
+) -> error::Result<()> {
+  let annotation = document.get_dictionary(annotation_id)?;
+  let rect = match annotation.get(b"Rect").ok().and_then(page::rect_from_object) {
+    Some(rect) => rect,
+    None => return Ok(()),
+  };
+  let no_rotate = annotation.get(b"F").and_then(Object::as_i64).unwrap_or(0) & NO_ROTATE != 0;
+  let appearances = appearance_stream_ids(document, annotation);
+  let annotation = document.get_object_mut(annotation_id).and_then(Object::as_dict_mut)?;
+  if no_rotate {
+    let (x, y) = apply(matrix, rect[0], rect[3]);
+    let (width, height) = (rect[2] - rect[0], rect[3] - rect[1]);
+    annotation.set("Rect", rect_object([x, y - height, x + width, y]));
+    return Ok(());
+  }
+  annotation.set("Rect", rect_object(transform_rect(matrix, rect)));
+  for key in ANNOTATION_POINTS.iter() {
+    let points = annotation.get(key).and_then(Object::as_array).ok();
+    if let Some(points) = points.and_then(|points| transform_points(matrix, points)) {
+      annotation.set(key.to_vec(), points);
+    }
+  }
+  if let Ok(Object::Array(ink)) = annotation.get(b"InkList") {
+    let ink = ink
+        .iter()
+        .map(|path| match path.as_array().ok().and_then(|path| transform_points(matrix, path)) {
+          Some(path) => Object::Array(path),
+          None => path.clone(),
+        })
+        .collect::<Vec<_>>();
+    annotation.set("InkList", ink);
+  }
+  // Only the rotation matters, the appearance box is fitted to the new `/Rect` anyway
+  let rotation = [matrix[0], matrix[1], matrix[2], matrix[3], 0.0, 0.0];
+  for stream_id in appearances {
+    if !rotated.insert(stream_id) {
+      continue;
+    }
+    if let Ok(Object::Stream(stream)) = document.get_object_mut(stream_id) {
+      let current = stream.dict.get(b"Matrix").ok().and_then(matrix_from_object).unwrap_or(IDENTITY);
+      let baked = multiply(current, rotation);
+      stream.dict.set("Matrix", baked.iter().map(|&value| value.into()).collect::<Vec<Object>>());
+    }
+  }
+  Ok(())
+}
+
+/// Apply the `/Rotate` of every page to its content, so the page displays the same with
+/// `/Rotate 0`: the content is drawn through the rotation, the page boxes and annotations are
+/// moved with it and the MediaBox starts at the origin with its sides swapped for quarter turns.
+pub fn bake_rotation_in(document: &mut Document) -> error::Result<()> {
+  let mut rotated = BTreeSet::new();
+  for page_id in document.get_pages().into_values() {
+    if page::rotation(document, page_id) == 0 {
+      continue;
+    }
+    // User space to display space, which becomes the new user space
+    let matrix = invert(page::display_matrix(document, page_id));
+    let media_box = transform_rect(matrix, page::media_box(document, page_id));
+    redraw_page(document, page_id, matrix, media_box, &mut rotated)?;
+  }
+  Ok(())
+}
+
+/// Draw the page content through `matrix` on a page of `media_box` with `/Rotate 0`, moving the
+/// other page boxes and the annotations along. `transformed` tracks the appearance streams
+/// already changed, which pages may share.
+fn redraw_page(
+  document: &mut Document,
+  page_id: ObjectId,
+  matrix: [f64; 6],
+  media_box: [f64; 4],
+  transformed: &mut BTreeSet<ObjectId>,
+) -> error::Result<()> {
+  let boxes = PAGE_BOXES
+      .iter()
+      .filter_map(|key| {
+        let rect = page::inherited_attribute(document, page_id, key).and_then(page::rect_from_object)?;
+        Some((key.to_vec(), transform_rect(matrix, rect)))
+      })
+      .collect::<Vec<_>>();
+  // Adding 0 turns the -0 of negated zeros into 0
+  let before = format!(
+    "q {} cm\n",
+    matrix.iter().map(|value| (value + 0.0).to_string()).collect::<Vec<_>>().join(" ")
+  );
+  page::wrap_content(document, page_id, before.into_bytes(), b"\nQ".to_vec())?;
+  for annotation_id in annotation_ids(document, page_id) {
+    bake_annotation(document, annotation_id, matrix, transformed)?;
+  }
+  let page = document.get_object_mut(page_id).and_then(Object::as_dict_mut)?;
+  page.set("MediaBox", rect_object(media_box));
+  for (key, rect) in boxes {
+    page.set(key, rect_object(rect));
+  }
+  page.set("Rotate", 0);
+  Ok(())
+}
+
+/// How `fit_page` places the content of a page of another size
+#[derive(Clone, Copy, PartialEq)]
+pub enum PageFit {
+  /// Scaled to fit, keeping its proportions, and centered
+  Scale,
+  /// Centered at its own size, cut off where it is larger
+  Center,
+}
+
+/// The MediaBox of the page as it is displayed: unchanged when upright, otherwise starting at the
+/// origin with the displayed width and height
+pub fn upright_media_box(document: &Document, page_id: ObjectId) -> [f64; 4] {
+  if page::rotation(document, page_id) == 0 {
+    return page::media_box(document, page_id);
+  }
+  let (width, height) = page::display_size(document, page_id);
+  [0.0, 0.0, width, height]
+}
+
+/// Redraw the page as displayed on a `target` MediaBox with `/Rotate 0`, its content placed
+/// according to `fit`. Pages already upright on the target box are left alone, returns whether
+/// the page was redrawn.
+pub fn fit_page(
+  document: &mut Document,
+  page_id: ObjectId,
+  target: [f64; 4],
+  fit: PageFit,
+  transformed: &mut BTreeSet<ObjectId>,
+) -> error::Result<bool> {
+  let (width, height) = page::display_size(document, page_id);
+  let upright = page::rotation(document, page_id) == 0;
+  if (upright && page::media_box(document, page_id) == target) || width <= 0.0 || height <= 0.0 {
+    return Ok(false);
+  }
+  let (target_width, target_height) = (target[2] - target[0], target[3] - target[1]);
+  let scale = match fit {
+    PageFit::Scale => (target_width / width).min(target_height / height),
+    PageFit::Center => 1.0,
+  };
+  let placement = [
+    scale,
+    0.0,
+    0.0,
+    scale,
+    target[0] + (target_width - width * scale) / 2.0,
+    target[1] + (target_height - height * scale) / 2.0,
+  ];
+  // User space to display space, then onto the target box
+  let matrix = multiply(invert(page::display_matrix(document, page_id)), placement);
+  redraw_page(document, page_id, matrix, target, transformed)?;
+  Ok(true)
+}
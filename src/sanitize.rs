@@ -0,0 +1,188 @@
+use std::collections::BTreeSet;
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use napi::{CallContext, JsBuffer, JsObject, JsUnknown, Result};
+
+use crate::error::{self, ErrorCode, OrThrow, PdfError};
+use crate::form::{annotation_ids, remove_from_array};
+use crate::utils::{buffer_value, load_document, output_update, Output};
+
+/// Which active content `sanitize` removes, everything but embedded files by default
+pub struct SanitizeOptions {
+  /// JavaScript actions anywhere and the document-level `/Names /JavaScript` scripts
+  javascript: bool,
+  /// The catalog `/OpenAction`
+  open_action: bool,
+  /// Every `/AA` additional-actions dictionary, run on page, field or document events
+  additional_actions: bool,
+  /// `Launch` actions anywhere, and `URI` actions that run without a click
+  launch_actions: bool,
+  /// `GoToR` and `GoToE` actions, which open another PDF file. `URI` and `GoTo` links are kept.
+  remote_go_to_actions: bool,
+  /// Embedded files, file attachment annotations and associated files
+  embedded_files: bool,
+  output: Output,
+}
+
+impl SanitizeOptions {
+  pub fn from_js(options: Option<JsObject>) -> Result<Self> {
+    let flag = |name: &str, default: bool| -> Result<bool> {
+      match &options {
+        Some(options) => Ok(options.get_named_property::<Option<bool>>(name)?.unwrap_or(default)),
+        None => Ok(default),
+      }
+    };
+    Ok(SanitizeOptions {
+      javascript: flag("javascript", true)?,
+      open_action: flag("openAction", true)?,
+      additional_actions: flag("additionalActions", true)?,
+      launch_actions: flag("launchActions", true)?,
+      remote_go_to_actions: flag("remoteGoToActions", true)?,
+      embedded_files: flag("embeddedFiles", false)?,
+      output: match &options {
+        Some(options) => Output::from_js(options)?,
+        None => Output::default(),
+      },
+    })
+  }
+}
+
+#[js_function(2)]
+pub fn sanitize(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let options = SanitizeOptions::from_js(ctx.get::<Option<JsObject>>(1)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  sanitize_in(&mut document, &options).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &options.output)
+}
+
+/// Whether `action` is removed. `automatic` actions run without user interaction (open and additional actions).
+fn is_removed_action(document: &Document, action: &Object, automatic: bool, options: &SanitizeOptions) -> bool {
+  let action = match document.dereference(action).and_then(|(_, action)| action.as_dict()) {
+    Ok(action) => action,
+    Err(_) => return false,
+  };
+  match action.get(b"S").and_then(Object::as_name) {
+    Ok(b"JavaScript") => options.javascript,
+    Ok(b"Launch") => options.launch_actions,
+    Ok(b"URI") => options.launch_actions && automatic,
+    Ok(b"GoToR") | Ok(b"GoToE") => options.remote_go_to_actions,
+    _ => false,
+  }
+}
+
+/// Remove the unwanted entries of a dictionary and of the dictionaries nested in it.
+/// `additional_actions` is set for an `/AA` dictionary stored as an object of its own.
+fn sanitize_dictionary(
+  document: &Document, dictionary: &mut Dictionary, additional_actions: bool, options: &SanitizeOptions,
+) {
+  let mut removed = vec![];
+  for (key, value) in dictionary.iter() {
+    let remove = match key.as_slice() {
+      // Every entry of an additional-actions dictionary runs automatically
+      _ if additional_actions => is_removed_action(document, value, true, options),
+      b"A" | b"Next" => is_removed_action(document, value, false, options),
+      b"OpenAction" => options.open_action || is_removed_action(document, value, true, options),
+      b"AA" => options.additional_actions,
+      b"AF" => options.embedded_files,
+      _ => false,
+    };
+    if remove {
+      removed.push(key.clone());
+    }
+  }
+  for key in removed {
+    dictionary.remove(&key);
+  }
+  for (key, value) in dictionary.iter_mut() {
+    let nested_actions = key.as_slice() == b"AA";
+    sanitize_object(document, value, nested_actions, options);
+  }
+}
+
+fn sanitize_object(document: &Document, object: &mut Object, additional_actions: bool, options: &SanitizeOptions) {
+  match object {
+    Object::Dictionary(dictionary) => sanitize_dictionary(document, dictionary, additional_actions, options),
+    Object::Stream(stream) => sanitize_dictionary(document, &mut stream.dict, false, options),
+    Object::Array(array) => {
+      for item in array.iter_mut() {
+        sanitize_object(document, item, false, options);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// Remove JavaScript, automatic actions and optionally embedded files, according to `options`
+pub fn sanitize_in(document: &mut Document, options: &SanitizeOptions) -> error::Result<()> {
+  let catalog_id = document
+      .trailer
+      .get(b"Root")
+      .and_then(Object::as_reference)
+      .map_err(|_| PdfError::new(ErrorCode::InvalidPdf, "The trailer has no /Root"))?;
+
+  // Additional-actions dictionaries stored as objects of their own, found through the `/AA` references
+  let additional_actions_ids = document
+      .objects
+      .values()
+      .filter_map(|object| match object {
+        Object::Dictionary(dictionary) => dictionary.get(b"AA").and_then(Object::as_reference).ok(),
+        _ => None,
+      })
+      .collect::<BTreeSet<ObjectId>>();
+  let ids = document.objects.keys().copied().collect::<Vec<_>>();
+  for id in ids {
+    // Take the object out so the rest of the document can be read while it is changed
+    if let Some(mut object) = document.objects.remove(&id) {
+      sanitize_object(document, &mut object, additional_actions_ids.contains(&id), options);
+      document.objects.insert(id, object);
+    }
+  }
+
+  let mut name_trees: Vec<&[u8]> = vec![];
+  if options.javascript {
+    name_trees.push(b"JavaScript");
+  }
+  if options.embedded_files {
+    name_trees.push(b"EmbeddedFiles");
+  }
+  let names_id = match document.get_dictionary(catalog_id)?.get(b"Names") {
+    Ok(Object::Reference(names_id)) => Some(*names_id),
+    _ => None,
+  };
+  let names = match names_id {
+    Some(names_id) => document.get_object_mut(names_id).and_then(Object::as_dict_mut).ok(),
+    None => document
+        .get_object_mut(catalog_id)
+        .and_then(Object::as_dict_mut)?
+        .get_mut(b"Names")
+        .and_then(Object::as_dict_mut)
+        .ok(),
+  };
+  if let Some(names) = names {
+    for key in name_trees {
+      names.remove(key);
+    }
+  }
+
+  if options.embedded_files {
+    let catalog = document.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?;
+    catalog.remove(b"Collection");
+    for page_id in document.get_pages().into_values() {
+      for annotation_id in annotation_ids(document, page_id) {
+        let is_attachment = document
+            .get_dictionary(annotation_id)
+            .and_then(|annotation| annotation.get(b"Subtype"))
+            .and_then(Object::as_name)
+            .ok()
+            == Some(b"FileAttachment");
+        if is_attachment {
+          remove_from_array(document, page_id, b"Annots", annotation_id)?;
+        }
+      }
+    }
+  }
+  // Removed scripts and files must not stay in the file as unreferenced objects
+  document.prune_objects();
+  Ok(())
+}
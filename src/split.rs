@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::{Component, Path};
+
+use lopdf::Document;
+use napi::{CallContext, Error, JsBuffer, JsObject, JsString, Result, Status};
+
+use crate::error::{self, OrThrow, PdfError};
+use crate::extract::{extract_pages_in, BrokenLink};
+use crate::utils::{buffer_value, load_document, save_document_to_file, SaveOptions};
+
+/// File name of each page when no template is given
+const DEFAULT_TEMPLATE: &str = "page-{n}.pdf";
+
+#[derive(Default)]
+pub struct SplitOptions {
+  pub on_broken_link: BrokenLink,
+  /// Restrict the structure tree to each page instead of removing it
+  pub preserve_structure: bool,
+  pub save: SaveOptions,
+}
+
+impl SplitOptions {
+  pub fn from_js(options: Option<JsObject>) -> Result<Self> {
+    let options = match options {
+      Some(options) => options,
+      None => return Ok(SplitOptions::default()),
+    };
+    Ok(SplitOptions {
+      on_broken_link: BrokenLink::from_js(&options)?,
+      preserve_structure: options.get_named_property::<Option<bool>>("preserveStructure")?.unwrap_or(false),
+      save: SaveOptions::from_js(&options)?,
+    })
+  }
+}
+
+#[js_function(4)]
+pub fn split_pdf_to_files(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let out_dir = ctx.get::<String>(1)?;
+  let template = ctx.get::<Option<String>>(2)?.unwrap_or_else(|| DEFAULT_TEMPLATE.to_owned());
+  if !template.contains("{n}") {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("The template must contain {{n}} for the page number, got '{}'", template),
+    ));
+  }
+  // The files are written in `outDir` and nowhere else
+  let mut components = Path::new(&template).components();
+  if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("The template must be a file name, without directories, got '{}'", template),
+    ));
+  }
+  let options = SplitOptions::from_js(ctx.get::<Option<JsObject>>(3)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  let paths = split_to_files(&document, Path::new(&out_dir), &template, &options).or_throw(ctx.env)?;
+  let mut list = ctx.env.create_array_with_length(paths.len())?;
+  for (index, path) in paths.iter().enumerate() {
+    list.set_element::<JsString>(index as u32, ctx.env.create_string(path)?)?;
+  }
+  Ok(list)
+}
+
+/// Write every page to a document of its own in `out_dir`, created when missing, naming the files
+/// by replacing `{n}` in `template` with the 1-based page number. Only one page document is held
+/// in memory at a time.
+pub fn split_to_files(
+  document: &Document, out_dir: &Path, template: &str, options: &SplitOptions,
+) -> error::Result<Vec<String>> {
+  fs::create_dir_all(out_dir).map_err(PdfError::from)?;
+  let page_count = document.get_pages().len() as u32;
+  let mut paths = Vec::with_capacity(page_count as usize);
+  for page_number in 1..=page_count {
+    let mut page = document.clone();
+    extract_pages_in(&mut page, &[page_number], options.on_broken_link, options.preserve_structure)?;
+    let path = out_dir.join(template.replace("{n}", &page_number.to_string()));
+    save_document_to_file(&mut page, &path, options.save.clone())?;
+    paths.push(path.to_string_lossy().into_owned());
+  }
+  Ok(paths)
+}
@@ -0,0 +1,33 @@
+use lopdf::Object;
+use napi::{CallContext, JsBuffer, JsObject, Result};
+
+use crate::error::OrThrow;
+use crate::repair;
+use crate::utils::{buffer_value, load_document};
+
+#[js_function(1)]
+pub fn stats(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  let stream_object_count = document
+      .objects
+      .values()
+      .filter(|object| matches!(object, Object::Stream(_)))
+      .count();
+  // Objects read from object streams only show up as compressed xref entries,
+  // lopdf has already unpacked them into `objects`
+  let compressed_xref = document.reference_table.entries.values().any(|entry| entry.is_compressed())
+      || document.objects.values().any(|object| {
+        object
+            .as_stream()
+            .map(|stream| stream.dict.type_is(b"XRef"))
+            .unwrap_or(false)
+      });
+  let mut result = ctx.env.create_object()?;
+  result.set_named_property("objectCount", ctx.env.create_uint32(document.objects.len() as u32)?)?;
+  result.set_named_property("maxObjectId", ctx.env.create_uint32(document.max_id)?)?;
+  result.set_named_property("streamObjectCount", ctx.env.create_uint32(stream_object_count as u32)?)?;
+  result.set_named_property("compressedXref", ctx.env.get_boolean(compressed_xref)?)?;
+  result.set_named_property("hybridXref", ctx.env.get_boolean(repair::is_hybrid(&buffer))?)?;
+  Ok(result)
+}
@@ -0,0 +1,167 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::sync::mpsc::channel;
+
+use napi::bindgen_prelude::{Buffer, FromNapiValue};
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{
+  sys, CallContext, Env, Error, JsBoolean, JsBuffer, JsFunction, JsObject, JsUndefined, JsUnknown, NapiRaw, Result,
+  Status,
+};
+
+use crate::utils::buffer_value;
+
+/// Size of the chunks handed to `writable.write`
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// `io::Write` forwarding the bytes in chunks to the `write` method of a Node Writable.
+/// Only one chunk is in flight at a time: writing blocks until JS has taken the previous one.
+pub struct WritableWriter {
+  write: ThreadsafeFunction<Buffer, ErrorStrategy::Fatal>,
+  buffer: Vec<u8>,
+}
+
+impl WritableWriter {
+  pub fn new(env: &Env, writable: JsObject) -> Result<Self> {
+    // Fail early on something that isn't a stream
+    writable.get_named_property::<JsFunction>("write")?;
+    // Threadsafe functions are called without `this`, so bind the stream as first argument
+    let write = env.create_function("writeChunk", write_chunk)?;
+    let write = unsafe { write.into_unknown().cast::<JsObject>() };
+    let bind = write.get_named_property::<JsFunction>("bind")?;
+    let bound = bind.call(Some(&write), &[env.get_null()?.into_unknown(), writable.into_unknown()])?;
+    Ok(WritableWriter {
+      write: unsafe { ThreadsafeFunction::from_napi_value(env.raw(), bound.raw()) }?,
+      buffer: Vec::with_capacity(CHUNK_SIZE),
+    })
+  }
+
+  fn send(&mut self) -> io::Result<()> {
+    if self.buffer.is_empty() {
+      return Ok(());
+    }
+    let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE));
+    let (sender, receiver) = channel();
+    self.write.call_with_return_value(
+      Buffer::from(chunk),
+      ThreadsafeFunctionCallMode::Blocking,
+      move |written: bool| {
+        sender.send(written).ok();
+        Ok(())
+      },
+    );
+    match receiver.recv() {
+      Ok(true) => Ok(()),
+      _ => Err(io::Error::new(io::ErrorKind::BrokenPipe, "Writing to the stream failed")),
+    }
+  }
+}
+
+/// Call `writable.write(chunk)`, returning whether it succeeded instead of throwing
+#[js_function(2)]
+fn write_chunk(ctx: CallContext) -> Result<JsBoolean> {
+  let writable = ctx.get::<JsObject>(0)?;
+  let chunk = ctx.get::<JsBuffer>(1)?;
+  let written = writable
+      .get_named_property::<JsFunction>("write")
+      .and_then(|write| write.call(Some(&writable), &[chunk]));
+  if written.is_err() {
+    // Swallow the exception, the merge promise is rejected instead
+    let mut exception = std::ptr::null_mut();
+    unsafe { sys::napi_get_and_clear_last_exception(ctx.env.raw(), &mut exception) };
+    return ctx.env.get_boolean(false);
+  }
+  ctx.env.get_boolean(true)
+}
+
+impl Write for WritableWriter {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.buffer.extend_from_slice(buf);
+    if self.buffer.len() >= CHUNK_SIZE {
+      self.send()?;
+    }
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.send()
+  }
+}
+
+/// Data read so far from a set of Readables
+struct ReadState<F> {
+  chunks: Vec<Vec<u8>>,
+  remaining: usize,
+  /// Taken by the first of the last `end` and any `error` event
+  done: Option<F>,
+}
+
+impl<F: FnOnce(Env, Result<Vec<Vec<u8>>>)> ReadState<F> {
+  fn finish(state: &RefCell<Self>, env: Env, result: Result<()>) {
+    let (done, chunks) = {
+      let mut state = state.borrow_mut();
+      (state.done.take(), std::mem::take(&mut state.chunks))
+    };
+    if let Some(done) = done {
+      done(env, result.map(|_| chunks));
+    }
+  }
+}
+
+/// Drain Node Readables emitting Buffers, calling `done` with the content of each stream
+/// once all of them have ended, or with the error of the first stream failing
+pub fn read_streams<F>(env: &Env, streams: Vec<JsObject>, done: F) -> Result<()>
+where
+  F: 'static + FnOnce(Env, Result<Vec<Vec<u8>>>),
+{
+  let state = Rc::new(RefCell::new(ReadState {
+    chunks: vec![vec![]; streams.len()],
+    remaining: streams.len(),
+    done: Some(done),
+  }));
+  if streams.is_empty() {
+    ReadState::finish(&state, *env, Ok(()));
+    return Ok(());
+  }
+  for (index, stream) in streams.into_iter().enumerate() {
+    let data_state = state.clone();
+    let on_data = env.create_function_from_closure("onData", move |ctx| -> Result<JsUndefined> {
+      let chunk = ctx.get::<JsUnknown>(0)?;
+      if chunk.is_buffer()? {
+        let chunk = buffer_value(unsafe { chunk.cast::<JsBuffer>() })?;
+        if let Some(chunks) = data_state.borrow_mut().chunks.get_mut(index) {
+          chunks.extend_from_slice(&chunk);
+        }
+      } else {
+        let error = Error::new(Status::InvalidArg, "Streams must emit Buffers".to_owned());
+        ReadState::finish(&data_state, *ctx.env, Err(error));
+      }
+      ctx.env.get_undefined()
+    })?;
+    let end_state = state.clone();
+    let on_end = env.create_function_from_closure("onEnd", move |ctx| -> Result<JsUndefined> {
+      let remaining = {
+        let mut state = end_state.borrow_mut();
+        state.remaining -= 1;
+        state.remaining
+      };
+      if remaining == 0 {
+        ReadState::finish(&end_state, *ctx.env, Ok(()));
+      }
+      ctx.env.get_undefined()
+    })?;
+    let error_state = state.clone();
+    let on_error = env.create_function_from_closure("onError", move |ctx| -> Result<JsUndefined> {
+      // Reject with the stream's own error
+      let error = Error::from(ctx.get::<JsUnknown>(0)?);
+      ReadState::finish(&error_state, *ctx.env, Err(error));
+      ctx.env.get_undefined()
+    })?;
+    let on = stream.get_named_property::<JsFunction>("on")?;
+    for (event, listener) in [("data", on_data), ("end", on_end), ("error", on_error)] {
+      on.call(Some(&stream), &[env.create_string(event)?.into_unknown(), listener.into_unknown()])?;
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,307 @@
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+use crate::error::{self, ErrorCode, PdfError};
+use crate::names::{name_tree, name_tree_entries, number_tree, number_tree_entries, tree_node_ids};
+
+/// Keys pointing from pages, annotations and forms into the `ParentTree`
+const STRUCT_PARENT_KEYS: [&[u8]; 2] = [b"StructParents", b"StructParent"];
+
+fn catalog_id(document: &Document) -> error::Result<ObjectId> {
+  document
+      .trailer
+      .get(b"Root")
+      .and_then(Object::as_reference)
+      .map_err(|_| PdfError::new(ErrorCode::InvalidPdf, "The trailer has no /Root"))
+}
+
+fn kids(element: &Dictionary) -> Vec<Object> {
+  match element.get(b"K") {
+    Ok(Object::Array(kids)) => kids.clone(),
+    Ok(kid) => vec![kid.clone()],
+    Err(_) => vec![],
+  }
+}
+
+/// Remove the structure tree along with the `ParentTree` keys of pages, annotations and forms,
+/// leaving an untagged document
+pub fn strip_structure(document: &mut Document) -> error::Result<()> {
+  let catalog = document.get_object_mut(catalog_id(document)?).and_then(Object::as_dict_mut)?;
+  catalog.remove(b"StructTreeRoot");
+  catalog.remove(b"MarkInfo");
+  for object in document.objects.values_mut() {
+    let dictionary = match object {
+      Object::Dictionary(dictionary) => dictionary,
+      Object::Stream(stream) => &mut stream.dict,
+      _ => continue,
+    };
+    for key in STRUCT_PARENT_KEYS.iter() {
+      dictionary.remove(key);
+    }
+  }
+  Ok(())
+}
+
+/// The outcome of pruning the structure elements
+#[derive(Default)]
+struct Pruned {
+  /// Elements with content left on the kept pages
+  kept: BTreeSet<ObjectId>,
+  /// New `/K` of every visited element
+  kids: Vec<(ObjectId, Vec<Object>)>,
+  visited: BTreeSet<ObjectId>,
+}
+
+/// Page of a marked-content or object reference, `None` for a dictionary of another type
+fn content_reference_page(document: &Document, kid: &Dictionary, page: Option<ObjectId>) -> Option<Option<ObjectId>> {
+  let object_page = || {
+    let (_, object) = document.dereference(kid.get(b"Obj").ok()?).ok()?;
+    object.as_dict().ok()?.get(b"P").and_then(Object::as_reference).ok()
+  };
+  if kid.type_is(b"MCR") {
+    Some(kid.get(b"Pg").and_then(Object::as_reference).ok().or(page))
+  } else if kid.type_is(b"OBJR") {
+    Some(kid.get(b"Pg").and_then(Object::as_reference).ok().or_else(object_page).or(page))
+  } else {
+    None
+  }
+}
+
+/// Keep the kids of an element whose content is on the kept pages, returning whether any is left
+fn prune_element(
+  document: &Document, element_id: ObjectId, page: Option<ObjectId>, pages: &BTreeSet<ObjectId>, pruned: &mut Pruned,
+) -> bool {
+  if !pruned.visited.insert(element_id) {
+    return pruned.kept.contains(&element_id);
+  }
+  let element = match document.get_dictionary(element_id) {
+    Ok(element) => element,
+    Err(_) => return false,
+  };
+  let page = element.get(b"Pg").and_then(Object::as_reference).ok().or(page);
+  let on_kept_page = |page: Option<ObjectId>| page.is_none_or(|page| pages.contains(&page));
+  let mut kept_kids = vec![];
+  for kid in kids(element) {
+    let keep = match &kid {
+      Object::Integer(_) => on_kept_page(page),
+      Object::Dictionary(reference) => content_reference_page(document, reference, page).is_some_and(on_kept_page),
+      Object::Reference(kid_id) => match document.get_dictionary(*kid_id) {
+        Ok(reference) => match content_reference_page(document, reference, page) {
+          Some(kid_page) => on_kept_page(kid_page),
+          None => prune_element(document, *kid_id, page, pages, pruned),
+        },
+        Err(_) => false,
+      },
+      _ => false,
+    };
+    if keep {
+      kept_kids.push(kid);
+    }
+  }
+  let keep = !kept_kids.is_empty();
+  if keep {
+    pruned.kept.insert(element_id);
+  }
+  pruned.kids.push((element_id, kept_kids));
+  keep
+}
+
+/// Restrict the structure tree to the content of `pages`: elements left without content are
+/// removed, as are the `ParentTree` and `IDTree` entries pointing at them. The `/StructParents`
+/// of the kept pages stay valid, their keys are unchanged.
+pub fn restrict_structure(document: &mut Document, pages: &BTreeSet<ObjectId>) -> error::Result<()> {
+  let catalog_id = catalog_id(document)?;
+  let root_id = match document.get_dictionary(catalog_id)?.get(b"StructTreeRoot") {
+    Ok(Object::Reference(root_id)) if document.get_dictionary(*root_id).is_ok() => *root_id,
+    Ok(_) => return strip_structure(document),
+    Err(_) => return Ok(()),
+  };
+  let root = document.get_dictionary(root_id)?;
+  let mut pruned = Pruned::default();
+  let root_kids = kids(root)
+      .into_iter()
+      .filter(|kid| match kid {
+        Object::Reference(kid_id) => prune_element(document, *kid_id, None, pages, &mut pruned),
+        _ => false,
+      })
+      .collect::<Vec<_>>();
+  let is_kept = |object: &Object| matches!(object, Object::Reference(id) if pruned.kept.contains(id));
+  let mut parent_tree = BTreeMap::new();
+  if let Ok(tree) = root.get(b"ParentTree") {
+    for (key, value) in number_tree_entries(document, tree) {
+      match document.dereference(&value) {
+        Ok((_, Object::Array(elements))) => {
+          let elements = elements
+              .iter()
+              .map(|element| if is_kept(element) { element.clone() } else { Object::Null })
+              .collect::<Vec<_>>();
+          if elements.iter().any(is_kept) {
+            parent_tree.insert(key, Object::Array(elements));
+          }
+        }
+        _ if is_kept(&value) => {
+          parent_tree.insert(key, value);
+        }
+        _ => {}
+      }
+    }
+  }
+  let id_tree = root.get(b"IDTree").ok().map(|tree| {
+    name_tree_entries(document, tree)
+        .into_iter()
+        .filter(|(_, element)| is_kept(element))
+        .collect::<BTreeMap<_, _>>()
+  });
+  for (element_id, kids) in std::mem::take(&mut pruned.kids) {
+    if !pruned.kept.contains(&element_id) {
+      continue;
+    }
+    let element = document.get_object_mut(element_id).and_then(Object::as_dict_mut)?;
+    element.set("K", kids);
+    // The kids left carry their own page, the element's would keep a removed page alive
+    if matches!(element.get(b"Pg"), Ok(Object::Reference(page_id)) if !pages.contains(page_id)) {
+      element.remove(b"Pg");
+    }
+  }
+  let root = document.get_object_mut(root_id).and_then(Object::as_dict_mut)?;
+  root.set("K", root_kids);
+  root.set("ParentTree", number_tree(&parent_tree));
+  if let Some(id_tree) = id_tree {
+    root.set("IDTree", name_tree(&id_tree));
+  }
+  Ok(())
+}
+
+/// Structure trees collected from the merged documents, combined under the root of the first
+/// tagged one
+#[derive(Default)]
+pub struct StructureTrees {
+  root: Option<(ObjectId, Dictionary)>,
+  /// Top-level elements of every tree, in document order
+  kids: Vec<Object>,
+  parent_tree: BTreeMap<i64, Object>,
+  /// First `ParentTree` key left for the next document
+  next_key: i64,
+  role_map: Dictionary,
+  class_map: Dictionary,
+  id_tree: BTreeMap<Vec<u8>, Object>,
+}
+
+impl StructureTrees {
+  /// Take the structure tree of a renumbered document, its `ParentTree` keys moved after those of
+  /// the documents before it. Role and class names keep their first definition, as do element IDs:
+  /// the IDs already taken are returned.
+  pub fn add_document(&mut self, document: &mut Document) -> Vec<Vec<u8>> {
+    let root_id = match document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"StructTreeRoot"))
+        .and_then(Object::as_reference)
+    {
+      Ok(root_id) => root_id,
+      Err(_) => return vec![],
+    };
+    let root = match document.get_dictionary(root_id) {
+      Ok(root) => root.clone(),
+      Err(_) => return vec![],
+    };
+    let merged_root_id = self.root.get_or_insert_with(|| (root_id, root.clone())).0;
+    let offset = self.next_key;
+    let mut next_key = root.get(b"ParentTreeNextKey").and_then(Object::as_i64).unwrap_or(0);
+    if let Ok(tree) = root.get(b"ParentTree") {
+      for (key, value) in number_tree_entries(document, tree) {
+        next_key = next_key.max(key + 1);
+        self.parent_tree.insert(key + offset, value);
+      }
+    }
+    self.next_key = offset + next_key;
+    if offset > 0 {
+      for object in document.objects.values_mut() {
+        let dictionary = match object {
+          Object::Dictionary(dictionary) => dictionary,
+          Object::Stream(stream) => &mut stream.dict,
+          _ => continue,
+        };
+        for key in STRUCT_PARENT_KEYS.iter() {
+          if let Ok(Object::Integer(value)) = dictionary.get_mut(key) {
+            *value += offset;
+          }
+        }
+      }
+    }
+    for kid in kids(&root) {
+      if let Object::Reference(kid_id) = kid {
+        if let Ok(element) = document.get_object_mut(kid_id).and_then(Object::as_dict_mut) {
+          element.set("P", merged_root_id);
+        }
+      }
+      self.kids.push(kid);
+    }
+    for (key, map) in [(b"RoleMap".as_slice(), &mut self.role_map), (b"ClassMap", &mut self.class_map)] {
+      if let Ok((_, Object::Dictionary(entries))) = root.get(key).and_then(|map| document.dereference(map)) {
+        for (name, value) in entries.iter() {
+          if !map.has(name) {
+            map.set(name.clone(), value.clone());
+          }
+        }
+      }
+    }
+    let mut taken = vec![];
+    if let Ok(tree) = root.get(b"IDTree") {
+      for (id, element) in name_tree_entries(document, tree) {
+        match self.id_tree.entry(id) {
+          Entry::Occupied(entry) => taken.push(entry.key().clone()),
+          Entry::Vacant(entry) => {
+            entry.insert(element);
+          }
+        }
+      }
+    }
+    // The trees are rewritten as a single node in the merged root
+    let mut removed = [b"ParentTree".as_slice(), b"IDTree"]
+        .iter()
+        .filter_map(|key| root.get(key).ok())
+        .flat_map(|tree| tree_node_ids(document, tree))
+        .collect::<BTreeSet<_>>();
+    if root_id != merged_root_id {
+      removed.insert(root_id);
+    }
+    for id in removed {
+      document.objects.remove(&id);
+    }
+    taken
+  }
+
+  /// Write the combined root, marking the merged document tagged, or remove `/StructTreeRoot` from
+  /// `catalog` when no document was tagged
+  pub fn apply(self, document: &mut Document, catalog: &mut Dictionary) {
+    let (root_id, mut root) = match self.root {
+      Some(root) => root,
+      None => {
+        catalog.remove(b"StructTreeRoot");
+        return;
+      }
+    };
+    root.set("K", self.kids);
+    root.set("ParentTree", number_tree(&self.parent_tree));
+    root.set("ParentTreeNextKey", self.next_key);
+    for (key, map) in [("RoleMap", self.role_map), ("ClassMap", self.class_map)] {
+      root.remove(key.as_bytes());
+      if !map.is_empty() {
+        root.set(key, map);
+      }
+    }
+    root.remove(b"IDTree");
+    if !self.id_tree.is_empty() {
+      root.set("IDTree", name_tree(&self.id_tree));
+    }
+    document.objects.insert(root_id, Object::Dictionary(root));
+    catalog.set("StructTreeRoot", root_id);
+    if !catalog.has(b"MarkInfo") {
+      let mut mark_info = Dictionary::new();
+      mark_info.set("Marked", true);
+      catalog.set("MarkInfo", mark_info);
+    }
+  }
+}
@@ -0,0 +1,76 @@
+//! Documents built in memory for the unit tests
+
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+
+/// A document of `page_count` US Letter pages, each showing `"{label} {n}"` in Helvetica
+pub fn document_with_label(page_count: usize, label: &str) -> Document {
+  let mut document = Document::with_version("1.4");
+  let pages_id = document.new_object_id();
+  let mut font = Dictionary::new();
+  font.set("Type", Object::Name(b"Font".to_vec()));
+  font.set("Subtype", Object::Name(b"Type1".to_vec()));
+  font.set("BaseFont", Object::Name(b"Helvetica".to_vec()));
+  let font_id = document.add_object(font);
+  let mut kids = vec![];
+  for page_number in 1..=page_count {
+    let content = Content {
+      operations: vec![
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec![Object::Name(b"F1".to_vec()), 24.into()]),
+        Operation::new("Td", vec![72.into(), 700.into()]),
+        Operation::new("Tj", vec![Object::string_literal(format!("{} {}", label, page_number))]),
+        Operation::new("ET", vec![]),
+      ],
+    };
+    let content_id = document.add_object(Stream::new(Dictionary::new(), content.encode().unwrap()));
+    let mut fonts = Dictionary::new();
+    fonts.set("F1", font_id);
+    let mut resources = Dictionary::new();
+    resources.set("Font", fonts);
+    let mut page = Dictionary::new();
+    page.set("Type", Object::Name(b"Page".to_vec()));
+    page.set("Parent", pages_id);
+    page.set("MediaBox", vec![0.into(), 0.into(), 612.into(), 792.into()]);
+    page.set("Contents", content_id);
+    page.set("Resources", resources);
+    kids.push(Object::Reference(document.add_object(page)));
+  }
+  let mut pages = Dictionary::new();
+  pages.set("Type", Object::Name(b"Pages".to_vec()));
+  pages.set("Count", page_count as i64);
+  pages.set("Kids", kids);
+  document.objects.insert(pages_id, Object::Dictionary(pages));
+  let mut catalog = Dictionary::new();
+  catalog.set("Type", Object::Name(b"Catalog".to_vec()));
+  catalog.set("Pages", pages_id);
+  let catalog_id = document.add_object(catalog);
+  document.trailer.set("Root", catalog_id);
+  document
+}
+
+/// `document_with_label` labelled `Page`
+pub fn document(page_count: usize) -> Document {
+  document_with_label(page_count, "Page")
+}
+
+pub fn catalog_id(document: &Document) -> ObjectId {
+  document.trailer.get(b"Root").and_then(Object::as_reference).unwrap()
+}
+
+pub fn catalog_mut(document: &mut Document) -> &mut Dictionary {
+  let catalog_id = catalog_id(document);
+  document.get_object_mut(catalog_id).and_then(Object::as_dict_mut).unwrap()
+}
+
+/// The text the page shows with `Tj`, decoded
+pub fn page_text(document: &Document, page_id: ObjectId) -> String {
+  let content = Content::decode(&document.get_page_content(page_id).unwrap()).unwrap();
+  content
+      .operations
+      .iter()
+      .filter(|operation| operation.operator == "Tj")
+      .filter_map(|operation| operation.operands.first()?.as_str().ok())
+      .map(|text| String::from_utf8_lossy(text).into_owned())
+      .collect()
+}
@@ -0,0 +1,151 @@
+use lopdf::content::{Content, Operation};
+use lopdf::{Document, Object};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status};
+
+use crate::error::{self, OrThrow};
+use crate::font::{Font, FontWriter};
+use crate::page;
+use crate::utils::{buffer_value, load_document, output_update, Output};
+
+/// Distance between baselines, relative to the font size
+const LINE_SPACING: f64 = 1.2;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Align {
+  Left,
+  Center,
+  Right,
+}
+
+pub struct TextBoxOptions {
+  pages: page::PageSelector,
+  /// `[llx, lly, urx, ury]` in `coordinate_space`
+  rect: [f64; 4],
+  coordinate_space: page::CoordinateSpace,
+  text: String,
+  font: Font,
+  font_size: f64,
+  align: Align,
+  output: Output,
+}
+
+impl TextBoxOptions {
+  pub fn from_js(options: JsObject) -> Result<Self> {
+    let values = options.get_named_property::<Vec<f64>>("rect")?;
+    let rect = match values[..] {
+      [llx, lly, urx, ury] if values.iter().all(|value| value.is_finite()) && llx != urx && lly != ury => {
+        [llx.min(urx), lly.min(ury), llx.max(urx), lly.max(ury)]
+      }
+      _ => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "rect must be [llx, lly, urx, ury] enclosing some area".to_owned(),
+        ))
+      }
+    };
+    let font_size = options.get_named_property::<Option<f64>>("fontSize")?.unwrap_or(12.0);
+    if font_size <= 0.0 {
+      return Err(Error::new(Status::InvalidArg, "fontSize must be positive".to_owned()));
+    }
+    let align = match options.get_named_property::<Option<String>>("align")?.as_deref() {
+      None | Some("left") => Align::Left,
+      Some("center") => Align::Center,
+      Some("right") => Align::Right,
+      Some(other) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          format!("align must be 'left', 'center' or 'right', got '{}'", other),
+        ))
+      }
+    };
+    Ok(TextBoxOptions {
+      pages: page::PageSelector::from_js_with_page(&options)?,
+      rect,
+      coordinate_space: page::CoordinateSpace::from_js(&options)?,
+      text: options.get_named_property::<String>("text")?,
+      font: Font::from_js(&options)?,
+      font_size,
+      align,
+      output: Output::from_js(&options)?,
+    })
+  }
+}
+
+#[js_function(2)]
+pub fn add_text_box(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let options = TextBoxOptions::from_js(ctx.get::<JsObject>(1)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  add_text_box_to(&mut document, &options).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &options.output)
+}
+
+/// Break `text` into lines no wider than `width`, at its newlines and between words. A word wider
+/// than the box is a line of its own, which the box clips.
+fn wrap(font: &mut FontWriter, text: &str, font_size: f64, width: f64) -> error::Result<Vec<(Object, f64)>> {
+  let mut lines = vec![];
+  for paragraph in text.lines() {
+    let mut line = String::new();
+    let mut encoded = font.encode("", font_size)?;
+    for word in paragraph.split_whitespace() {
+      let candidate = if line.is_empty() {
+        word.to_owned()
+      } else {
+        format!("{} {}", line, word)
+      };
+      let candidate_encoded = font.encode(&candidate, font_size)?;
+      if line.is_empty() || candidate_encoded.1 <= width {
+        line = candidate;
+        encoded = candidate_encoded;
+      } else {
+        lines.push(encoded);
+        encoded = font.encode(word, font_size)?;
+        line = word.to_owned();
+      }
+    }
+    lines.push(encoded);
+  }
+  Ok(lines)
+}
+
+/// Draw the text wrapped into the box, one line below the other from its top edge, clipped to the
+/// box. Lines whose baseline falls below the box are left out.
+pub fn add_text_box_to(document: &mut Document, options: &TextBoxOptions) -> error::Result<()> {
+  let pages = options.pages.select(document)?;
+  let [llx, lly, urx, ury] = options.rect;
+  let mut font = FontWriter::new(document, &options.font)?;
+  let lines = wrap(&mut font, &options.text, options.font_size, urx - llx)?;
+  let line_height = options.font_size * LINE_SPACING;
+  for (_, page_id) in pages {
+    let font_name = page::add_resource(document, page_id, b"Font", "FTB", font.id())?;
+    let matrix = options.coordinate_space.matrix(document, page_id);
+    let mut operations = vec![
+      Operation::new("cm", matrix.iter().map(|&value| value.into()).collect()),
+      Operation::new("re", vec![llx.into(), lly.into(), (urx - llx).into(), (ury - lly).into()]),
+      Operation::new("W", vec![]),
+      Operation::new("n", vec![]),
+    ];
+    let mut baseline = ury - options.font_size;
+    for (encoded, width) in lines.iter() {
+      if baseline < lly {
+        break;
+      }
+      let x = match options.align {
+        Align::Left => llx,
+        Align::Center => llx + (urx - llx - width) / 2.0,
+        Align::Right => urx - width,
+      };
+      operations.extend(vec![
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec![Object::Name(font_name.clone()), options.font_size.into()]),
+        Operation::new("Td", vec![x.into(), baseline.into()]),
+        Operation::new("Tj", vec![encoded.clone()]),
+        Operation::new("ET", vec![]),
+      ]);
+      baseline -= line_height;
+    }
+    let content = Content { operations }.encode()?;
+    page::append_content(document, page_id, content)?;
+  }
+  font.finish(document)
+}
@@ -0,0 +1,175 @@
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, Stream};
+use napi::{CallContext, Error, JsObject, JsString, JsUnknown, Result, Status};
+
+use crate::error::{self, OrThrow};
+use crate::font::{Font, FontWriter, StandardFont};
+use crate::page_size::page_size;
+use crate::utils::{output_document, SaveOptions};
+
+/// Baseline to baseline distance, as a multiple of the font size
+const LINE_SPACING: f64 = 1.2;
+/// Spaces a tab stands for
+const TAB: &str = "    ";
+
+pub struct TextToPdfOptions {
+  font: Font,
+  font_size: f64,
+  /// Width and height of the pages
+  page_size: (f64, f64),
+  /// Distance of the text from every edge of the page
+  margin: f64,
+  out_path: Option<String>,
+  save: SaveOptions,
+}
+
+impl TextToPdfOptions {
+  pub fn from_js(options: &Option<JsObject>) -> Result<Self> {
+    let options = match options {
+      Some(options) => options,
+      None => {
+        return Ok(TextToPdfOptions {
+          font: Font::Standard(StandardFont::Helvetica),
+          font_size: 12.0,
+          page_size: page_size("A4")?,
+          margin: 72.0,
+          out_path: None,
+          save: SaveOptions::default(),
+        })
+      }
+    };
+    let font_size = options.get_named_property::<Option<f64>>("fontSize")?.unwrap_or(12.0);
+    if font_size <= 0.0 {
+      return Err(Error::new(Status::InvalidArg, "fontSize must be positive".to_owned()));
+    }
+    let page_size = page_size(options.get_named_property::<Option<String>>("pageSize")?.as_deref().unwrap_or("A4"))?;
+    let margin = options.get_named_property::<Option<f64>>("margin")?.unwrap_or(72.0);
+    let (width, height) = page_size;
+    if margin < 0.0 || width - 2.0 * margin < font_size || height - 2.0 * margin < font_size * LINE_SPACING {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("margin must be positive and leave room for a line of text, got {}", margin),
+      ));
+    }
+    Ok(TextToPdfOptions {
+      font: Font::from_js(options)?,
+      font_size,
+      page_size,
+      margin,
+      out_path: options.get_named_property::<Option<String>>("outPath")?,
+      save: SaveOptions::from_js(options)?,
+    })
+  }
+}
+
+#[js_function(2)]
+pub fn text_to_pdf(ctx: CallContext) -> Result<JsUnknown> {
+  let text = ctx.get::<JsString>(0)?.into_utf8()?.into_owned()?;
+  let options = TextToPdfOptions::from_js(&ctx.get::<Option<JsObject>>(1)?)?;
+  let mut document = text_to_pdf_in(&text, &options).or_throw(ctx.env)?;
+  output_document(ctx.env, &mut document, options.out_path.clone(), options.save)
+}
+
+/// Break a line of text into lines no wider than `available` points, at spaces when possible and
+/// inside words longer than a line
+fn wrap_line(font: &mut FontWriter, line: &str, font_size: f64, available: f64) -> error::Result<Vec<String>> {
+  let mut lines = vec![];
+  let mut current = String::new();
+  for word in line.split(' ') {
+    let candidate = if current.is_empty() {
+      word.to_owned()
+    } else {
+      format!("{} {}", current, word)
+    };
+    if font.encode(&candidate, font_size)?.1 <= available {
+      current = candidate;
+      continue;
+    }
+    if !current.is_empty() {
+      lines.push(current);
+    }
+    // Cut the word where it overflows, keeping at least one character per line
+    current = String::new();
+    for c in word.chars() {
+      let candidate = format!("{}{}", current, c);
+      if !current.is_empty() && font.encode(&candidate, font_size)?.1 > available {
+        lines.push(current);
+        current = c.to_string();
+      } else {
+        current = candidate;
+      }
+    }
+  }
+  lines.push(current);
+  Ok(lines)
+}
+
+/// Lay out `text` in lines wrapped to the page width, over as many pages as it takes. Line breaks
+/// in the text are kept and a form feed starts a new page.
+pub fn text_to_pdf_in(text: &str, options: &TextToPdfOptions) -> error::Result<Document> {
+  let (width, height) = options.page_size;
+  let available = width - 2.0 * options.margin;
+  // Rounded, 12 * 1.2 would be written 14.399999999999999
+  let leading = (options.font_size * LINE_SPACING * 100.0).round() / 100.0;
+  let lines_per_page = ((height - 2.0 * options.margin - options.font_size) / leading).floor() as usize + 1;
+
+  let mut document = Document::with_version("1.5");
+  let pages_id = document.new_object_id();
+  let mut font = FontWriter::new(&mut document, &options.font)?;
+  let mut pages: Vec<Vec<String>> = vec![];
+  // A form feed ending the text doesn't start another page
+  for section in text.strip_suffix('\u{c}').unwrap_or(text).split('\u{c}') {
+    let mut lines = vec![];
+    for line in section.lines() {
+      let line = line.replace('\t', TAB);
+      lines.extend(wrap_line(&mut font, &line, options.font_size, available)?);
+    }
+    if lines.is_empty() {
+      pages.push(vec![]);
+    }
+    pages.extend(lines.chunks(lines_per_page).map(<[String]>::to_vec));
+  }
+
+  let mut fonts = Dictionary::new();
+  fonts.set("F1", font.id());
+  let mut resources = Dictionary::new();
+  resources.set("Font", fonts);
+  let resources_id = document.add_object(resources);
+  let mut kids = vec![];
+  for lines in pages {
+    let mut operations = vec![
+      Operation::new("BT", vec![]),
+      Operation::new("Tf", vec![Object::Name(b"F1".to_vec()), options.font_size.into()]),
+      Operation::new("TL", vec![leading.into()]),
+      Operation::new("Td", vec![options.margin.into(), (height - options.margin - options.font_size).into()]),
+    ];
+    for (index, line) in lines.iter().enumerate() {
+      if index > 0 {
+        operations.push(Operation::new("T*", vec![]));
+      }
+      operations.push(Operation::new("Tj", vec![font.encode(line, options.font_size)?.0]));
+    }
+    operations.push(Operation::new("ET", vec![]));
+    let content_id = document.add_object(Stream::new(Dictionary::new(), Content { operations }.encode()?));
+    let mut page = Dictionary::new();
+    page.set("Type", Object::Name(b"Page".to_vec()));
+    page.set("Parent", pages_id);
+    page.set("MediaBox", vec![0.into(), 0.into(), width.into(), height.into()]);
+    page.set("Resources", resources_id);
+    page.set("Contents", content_id);
+    kids.push(Object::Reference(document.add_object(page)));
+  }
+  font.finish(&mut document)?;
+
+  let mut pages = Dictionary::new();
+  pages.set("Type", Object::Name(b"Pages".to_vec()));
+  pages.set("Count", kids.len() as i64);
+  pages.set("Kids", kids);
+  document.objects.insert(pages_id, Object::Dictionary(pages));
+  let mut catalog = Dictionary::new();
+  catalog.set("Type", Object::Name(b"Catalog".to_vec()));
+  catalog.set("Pages", pages_id);
+  let catalog_id = document.add_object(catalog);
+  document.trailer.set("Root", catalog_id);
+  Ok(document)
+}
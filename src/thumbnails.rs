@@ -0,0 +1,137 @@
+use napi::{CallContext, Error, JsBuffer, JsObject, Result, Status};
+
+use crate::error::{self, OrThrow};
+#[cfg(not(feature = "thumbnails"))]
+use crate::error::{ErrorCode, PdfError};
+use crate::page;
+use crate::utils::{buffer_value, load_document};
+
+/// Resolution used when no `dpi` is given, one pixel per point
+const DEFAULT_DPI: f64 = 72.0;
+
+/// Largest width and height of an image. The renderer sizes its canvas in `u16` and panics when
+/// its tiles round up past `u16::MAX`, and an image this large already takes a gigabyte
+#[cfg(feature = "thumbnails")]
+const MAX_PIXELS_PER_SIDE: f32 = 16384.0;
+
+pub struct ThumbnailOptions {
+  dpi: f64,
+  /// 1-based pages to render, every page when absent
+  pages: Option<Vec<u32>>,
+}
+
+impl ThumbnailOptions {
+  pub fn from_js(options: Option<JsObject>) -> Result<Self> {
+    let mut thumbnail_options = ThumbnailOptions {
+      dpi: DEFAULT_DPI,
+      pages: None,
+    };
+    if let Some(options) = options {
+      if let Some(dpi) = options.get_named_property::<Option<f64>>("dpi")? {
+        if !(dpi.is_finite() && dpi > 0.0) {
+          return Err(Error::new(Status::InvalidArg, format!("dpi must be positive and finite, got {}", dpi)));
+        }
+        thumbnail_options.dpi = dpi;
+      }
+      thumbnail_options.pages = options.get_named_property::<Option<Vec<u32>>>("pages")?;
+    }
+    Ok(thumbnail_options)
+  }
+}
+
+#[js_function(2)]
+pub fn render_thumbnails(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let options = ThumbnailOptions::from_js(ctx.get::<Option<JsObject>>(1)?)?;
+  let images = render_pages(&buffer, &options).or_throw(ctx.env)?;
+  let mut result = ctx.env.create_array_with_length(images.len())?;
+  for (index, image) in images.into_iter().enumerate() {
+    result.set_element(index as u32, ctx.env.create_buffer_with_data(image)?.into_raw())?;
+  }
+  Ok(result)
+}
+
+/// The 0-based indices of the pages to render, checked against the document with lopdf so the
+/// errors match the other operations
+fn page_indices(buffer: &[u8], options: &ThumbnailOptions) -> error::Result<Vec<usize>> {
+  let document = load_document(buffer)?;
+  let page_count = document.get_pages().len() as u32;
+  let page_numbers = options.pages.clone().unwrap_or_else(|| (1..=page_count).collect());
+  page::page_ids(&document, &page_numbers)?;
+  Ok(page_numbers.into_iter().map(|number| number as usize - 1).collect())
+}
+
+/// Render the pages to PNG on a white background
+#[cfg(feature = "thumbnails")]
+pub fn render_pages(buffer: &[u8], options: &ThumbnailOptions) -> error::Result<Vec<Vec<u8>>> {
+  use hayro::hayro_interpret::InterpreterSettings;
+  use hayro::hayro_syntax::Pdf;
+  use hayro::vello_cpu::color::palette::css::WHITE;
+  use hayro::{PixmapSettings, RenderCache, RenderSettings};
+
+  use crate::error::{ErrorCode, PdfError};
+
+  let indices = page_indices(buffer, options)?;
+  let pdf = Pdf::new(buffer.to_vec())
+      .map_err(|err| PdfError::new(ErrorCode::InvalidPdf, format!("Invalid PDF: {:?}", err)))?;
+  let pages = pdf.pages();
+  let cache = RenderCache::new();
+  let scale = (options.dpi / 72.0) as f32;
+  let pixmap_settings = PixmapSettings {
+    x_scale: scale,
+    y_scale: scale,
+    bg_color: WHITE,
+  };
+  let pages = indices
+      .into_iter()
+      .map(|index| {
+        let page = pages.get(index).ok_or_else(|| {
+          PdfError::new(
+            ErrorCode::GenericFailure,
+            format!("Page {} couldn't be read by the renderer", index + 1),
+          )
+        })?;
+        // Checked for every page before rendering any, in whole pixels as the renderer truncates the size
+        let (width, height) = page.render_dimensions();
+        let (width, height) = ((width * scale).floor(), (height * scale).floor());
+        if !(1.0..=MAX_PIXELS_PER_SIDE).contains(&width) || !(1.0..=MAX_PIXELS_PER_SIDE).contains(&height) {
+          return Err(PdfError::new(
+            ErrorCode::LimitExceeded,
+            format!(
+              "Page {} would be {} x {} pixels at {} dpi, images are 1 to {} pixels per side",
+              index + 1,
+              width,
+              height,
+              options.dpi,
+              MAX_PIXELS_PER_SIDE
+            ),
+          ));
+        }
+        Ok(page)
+      })
+      .collect::<error::Result<Vec<_>>>()?;
+  pages
+      .into_iter()
+      .map(|page| {
+        let pixmap = hayro::render(
+          page,
+          &cache,
+          &InterpreterSettings::default(),
+          &RenderSettings::default(),
+          &pixmap_settings,
+        );
+        pixmap
+            .into_png()
+            .map_err(|err| PdfError::new(ErrorCode::GenericFailure, format!("Can't encode the PNG: {}", err)))
+      })
+      .collect()
+}
+
+#[cfg(not(feature = "thumbnails"))]
+pub fn render_pages(buffer: &[u8], options: &ThumbnailOptions) -> error::Result<Vec<Vec<u8>>> {
+  page_indices(buffer, options)?;
+  Err(PdfError::new(
+    ErrorCode::GenericFailure,
+    "renderThumbnails is unavailable, the addon was built without the `thumbnails` feature",
+  ))
+}
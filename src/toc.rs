@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+
+use crate::error::{self, ErrorCode, PdfError};
+use crate::font::{Font, FontWriter, StandardFont};
+use crate::page;
+
+const MARGIN: f64 = 72.0;
+const HEADING: &str = "Contents";
+const HEADING_SIZE: f64 = 18.0;
+const ENTRY_SIZE: f64 = 12.0;
+const LINE_HEIGHT: f64 = 20.0;
+
+/// A line of the table of contents
+pub struct TocEntry {
+  pub title: String,
+  /// First page of the entry, which the line and the bookmark link to
+  pub page_id: ObjectId,
+}
+
+fn text(font_name: &[u8], size: f64, x: f64, y: f64, encoded: Object) -> Vec<Operation> {
+  vec![
+    Operation::new("BT", vec![]),
+    Operation::new("Tf", vec![Object::Name(font_name.to_vec()), size.into()]),
+    Operation::new("Td", vec![x.into(), y.into()]),
+    Operation::new("Tj", vec![encoded]),
+    Operation::new("ET", vec![]),
+  ]
+}
+
+/// Encode a title, cut with `...` to fit in `available` points
+fn fit_title(font: &mut FontWriter, title: &str, available: f64) -> error::Result<Object> {
+  let (encoded, width) = font.encode(title, ENTRY_SIZE)?;
+  if width <= available {
+    return Ok(encoded);
+  }
+  let mut chars = title.chars().collect::<Vec<_>>();
+  while !chars.is_empty() {
+    chars.pop();
+    let (encoded, width) = font.encode(&format!("{}...", chars.iter().collect::<String>()), ENTRY_SIZE)?;
+    if width <= available {
+      return Ok(encoded);
+    }
+  }
+  Ok(font.encode("...", ENTRY_SIZE)?.0)
+}
+
+/// Number of entries listed on each page of `media_box`
+fn lines_per_page(media_box: [f64; 4]) -> usize {
+  let [_, bottom, _, top] = media_box;
+  let first_line_y = top - MARGIN - HEADING_SIZE - 2.0 * LINE_HEIGHT;
+  (((first_line_y - bottom - MARGIN) / LINE_HEIGHT).floor() as usize + 1).max(1)
+}
+
+/// Number of pages of `media_box` that list `entry_count` entries, `pad` making it even
+pub fn page_count(media_box: [f64; 4], entry_count: usize, pad: bool) -> u32 {
+  let pages = entry_count.div_ceil(lines_per_page(media_box)) as u32;
+  pages + (pad && pages % 2 == 1) as u32
+}
+
+/// Insert pages listing `entries` before the first page, `page_size` or else the size of the first
+/// entry's page, each line linking to its entry with the page number it ends up on. Returns the
+/// number of pages inserted, `pad` adding a blank one to make it even.
+pub fn add_table_of_contents(
+  document: &mut Document, entries: &[TocEntry], page_size: Option<(f64, f64)>, pad: bool,
+) -> error::Result<u32> {
+  let first = match entries.first() {
+    Some(first) => first,
+    None => return Ok(0),
+  };
+  let pages_id = document
+      .catalog()?
+      .get(b"Pages")
+      .and_then(Object::as_reference)
+      .map_err(|_| PdfError::new(ErrorCode::NoPagesRoot, "Pages root not found"))?;
+  let page_numbers = document.get_pages().into_iter().map(|(number, id)| (id, number)).collect::<BTreeMap<_, _>>();
+  let media_box = match page_size {
+    Some((width, height)) => [0.0, 0.0, width, height],
+    None => page::media_box(document, first.page_id),
+  };
+  let [left, _, right, top] = media_box;
+  let heading_y = top - MARGIN - HEADING_SIZE;
+  let first_line_y = heading_y - 2.0 * LINE_HEIGHT;
+  let chunks = entries.chunks(lines_per_page(media_box)).collect::<Vec<_>>();
+  let toc_pages = page_count(media_box, entries.len(), pad);
+  let blank = toc_pages as usize > chunks.len();
+
+  let regular = Font::Standard(StandardFont::Helvetica);
+  let bold = Font::Standard(StandardFont::HelveticaBold);
+  let mut regular_writer = FontWriter::new(document, &regular)?;
+  let mut bold_writer = FontWriter::new(document, &bold)?;
+  let mut font_resources = Dictionary::new();
+  font_resources.set("F1", regular_writer.id());
+  font_resources.set("F2", bold_writer.id());
+  let mut toc_ids = vec![];
+  for chunk in chunks {
+    let (heading, _) = bold_writer.encode(HEADING, HEADING_SIZE)?;
+    let mut operations = text(b"F2", HEADING_SIZE, left + MARGIN, heading_y, heading);
+    let mut annotations = vec![];
+    for (line, entry) in chunk.iter().enumerate() {
+      let y = first_line_y - line as f64 * LINE_HEIGHT;
+      let page_number = page_numbers.get(&entry.page_id).copied().unwrap_or(0) + toc_pages;
+      let (number, number_width) = regular_writer.encode(&page_number.to_string(), ENTRY_SIZE)?;
+      let available = right - left - 2.0 * MARGIN - number_width - ENTRY_SIZE;
+      let title = fit_title(&mut regular_writer, &entry.title, available)?;
+      operations.extend(text(b"F1", ENTRY_SIZE, left + MARGIN, y, title));
+      operations.extend(text(b"F1", ENTRY_SIZE, right - MARGIN - number_width, y, number));
+      let mut link = Dictionary::new();
+      link.set("Type", Object::Name(b"Annot".to_vec()));
+      link.set("Subtype", Object::Name(b"Link".to_vec()));
+      link.set(
+        "Rect",
+        vec![(left + MARGIN).into(), (y - 4.0).into(), (right - MARGIN).into(), (y + ENTRY_SIZE).into()],
+      );
+      link.set("Border", vec![0.into(), 0.into(), 0.into()]);
+      link.set("Dest", vec![entry.page_id.into(), Object::Name(b"Fit".to_vec())]);
+      annotations.push(Object::Reference(document.add_object(link)));
+    }
+    let content_id = document.add_object(Stream::new(Dictionary::new(), Content { operations }.encode()?));
+    let mut resources = Dictionary::new();
+    resources.set("Font", font_resources.clone());
+    let mut page = Dictionary::new();
+    page.set("Type", Object::Name(b"Page".to_vec()));
+    page.set("Parent", pages_id);
+    page.set("MediaBox", media_box.iter().map(|&value| value.into()).collect::<Vec<Object>>());
+    page.set("Resources", resources);
+    page.set("Contents", content_id);
+    page.set("Annots", annotations);
+    toc_ids.push(Object::Reference(document.add_object(page)));
+  }
+  if blank {
+    let mut page = Dictionary::new();
+    page.set("Type", Object::Name(b"Page".to_vec()));
+    page.set("Parent", pages_id);
+    page.set("MediaBox", media_box.iter().map(|&value| value.into()).collect::<Vec<Object>>());
+    page.set("Resources", Dictionary::new());
+    toc_ids.push(Object::Reference(document.add_object(page)));
+  }
+  regular_writer.finish(document)?;
+  bold_writer.finish(document)?;
+
+  let root = document.get_object_mut(pages_id).and_then(Object::as_dict_mut)?;
+  let mut kids = root.get(b"Kids").and_then(Object::as_array).cloned().unwrap_or_default();
+  let count = root.get(b"Count").and_then(Object::as_i64).unwrap_or(kids.len() as i64);
+  kids.splice(0..0, toc_ids);
+  root.set("Kids", kids);
+  root.set("Count", count + toc_pages as i64);
+  Ok(toc_pages)
+}
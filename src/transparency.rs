@@ -0,0 +1,123 @@
+use std::collections::BTreeSet;
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use napi::{CallContext, JsBuffer, JsObject, JsUnknown, Result};
+
+use crate::error::OrThrow;
+use crate::utils::{buffer_value, load_document, output, output_update};
+
+/// Graphics state entries that only matter to the transparency model
+const TRANSPARENCY_KEYS: [&[u8]; 6] = [b"CA", b"ca", b"SMask", b"BM", b"AIS", b"TK"];
+
+#[js_function(2)]
+pub fn flatten_transparency(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(1)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  flatten_transparency_in(&mut document);
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Ids of the graphics states listed by reference in `/ExtGState` resources
+fn graphics_state_ids(document: &Document) -> BTreeSet<ObjectId> {
+  fn walk(document: &Document, object: &Object, ids: &mut BTreeSet<ObjectId>) {
+    let dictionary = match object {
+      Object::Dictionary(dictionary) => dictionary,
+      Object::Stream(stream) => &stream.dict,
+      Object::Array(array) => {
+        for item in array {
+          walk(document, item, ids);
+        }
+        return;
+      }
+      _ => return,
+    };
+    for (key, value) in dictionary.iter() {
+      if key.as_slice() == b"ExtGState" {
+        if let Ok((_, Object::Dictionary(states))) = document.dereference(value) {
+          ids.extend(states.iter().filter_map(|(_, state)| state.as_reference().ok()));
+        }
+      }
+      walk(document, value, ids);
+    }
+  }
+  let mut ids = BTreeSet::new();
+  for object in document.objects.values() {
+    walk(document, object, &mut ids);
+  }
+  ids
+}
+
+fn strip_graphics_state(state: &mut Dictionary) {
+  for key in TRANSPARENCY_KEYS.iter() {
+    state.remove(key);
+  }
+}
+
+fn is_transparency_group(document: &Document, group: &Object) -> bool {
+  match document.dereference(group) {
+    Ok((_, Object::Dictionary(group))) => group.get(b"S").and_then(Object::as_name).ok() == Some(b"Transparency"),
+    _ => false,
+  }
+}
+
+fn flatten_dictionary(document: &Document, dictionary: &mut Dictionary) {
+  if dictionary.type_is(b"ExtGState") {
+    strip_graphics_state(dictionary);
+  }
+  if dictionary.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Image") {
+    dictionary.remove(b"SMask");
+    dictionary.remove(b"SMaskInData");
+  }
+  if dictionary.type_is(b"Annot") {
+    dictionary.remove(b"CA");
+    dictionary.remove(b"ca");
+  }
+  if matches!(dictionary.get(b"Group"), Ok(group) if is_transparency_group(document, group)) {
+    dictionary.remove(b"Group");
+  }
+  if let Ok(Object::Dictionary(states)) = dictionary.get_mut(b"ExtGState") {
+    for (_, state) in states.iter_mut() {
+      if let Object::Dictionary(state) = state {
+        strip_graphics_state(state);
+      }
+    }
+  }
+  for (_, value) in dictionary.iter_mut() {
+    flatten_object(document, value);
+  }
+}
+
+fn flatten_object(document: &Document, object: &mut Object) {
+  match object {
+    Object::Dictionary(dictionary) => flatten_dictionary(document, dictionary),
+    Object::Stream(stream) => flatten_dictionary(document, &mut stream.dict),
+    Object::Array(array) => {
+      for item in array.iter_mut() {
+        flatten_object(document, item);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// Make the document opaque for RIPs without PDF 1.4 transparency: constant alpha, blend modes and
+/// soft masks are removed from the graphics states, images lose their soft masks and pages, forms
+/// and annotations their transparency groups and opacity. Transparent objects are painted opaque,
+/// nothing is composited. Overprint settings predate transparency and are kept.
+pub fn flatten_transparency_in(document: &mut Document) {
+  let state_ids = graphics_state_ids(document);
+  let ids = document.objects.keys().copied().collect::<Vec<_>>();
+  for id in ids {
+    // Take the object out so the rest of the document can be read while it is changed
+    if let Some(mut object) = document.objects.remove(&id) {
+      if let (true, Object::Dictionary(state)) = (state_ids.contains(&id), &mut object) {
+        strip_graphics_state(state);
+      }
+      flatten_object(document, &mut object);
+      document.objects.insert(id, object);
+    }
+  }
+  // Soft mask images and groups are no longer referenced
+  document.prune_objects();
+}
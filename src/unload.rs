@@ -0,0 +1,19 @@
+/// Keep the addon loaded until the process exits. Node unloads an addon when the worker thread
+/// that loaded it exits, and the napi-rs module registration crashes the process when it is
+/// unloaded, so a worker requiring the package on its own brought the whole process down on exit.
+/// Opening the library again with `RTLD_NODELETE` makes the unload a no-op.
+#[cfg(unix)]
+pub fn prevent_unload() {
+  // SAFETY: `dladdr` only fills `info`, and the name it points to outlives the `dlopen` call
+  unsafe {
+    let mut info: libc::Dl_info = std::mem::zeroed();
+    if libc::dladdr(prevent_unload as *const libc::c_void, &mut info) != 0 && !info.dli_fname.is_null() {
+      // The handle is left open on purpose
+      libc::dlopen(info.dli_fname, libc::RTLD_NOW | libc::RTLD_NODELETE);
+    }
+  }
+}
+
+/// Without `dlopen` there is nothing to pin the library with
+#[cfg(not(unix))]
+pub fn prevent_unload() {}
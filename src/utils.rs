@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::mem::ManuallyDrop;
+use std::path::Path;
+
+use lopdf::content::{Content, Operation};
+use lopdf::{Document, Object, ObjectId};
+use napi::{Env, JsBuffer, JsBufferValue, JsObject, JsUnknown};
+
+use crate::error::{ErrorCode, OrThrow, PdfError, Result};
+use crate::producer::ProducerInfo;
+use crate::{incremental, object_streams, producer, repair};
+
+/// The bytes of a `Buffer`. napi gives a null data pointer for an empty buffer, which
+/// `JsBuffer::into_value` turns into a `Vec` and aborts the process on
+pub fn buffer_value(buffer: JsBuffer) -> napi::Result<JsBufferValue> {
+  let object = unsafe { buffer.into_unknown().cast::<JsObject>() };
+  let empty = object.get_named_property::<u32>("length")? == 0;
+  let buffer = unsafe { object.into_unknown().cast::<JsBuffer>() };
+  if empty {
+    return Ok(JsBufferValue::new(buffer, ManuallyDrop::new(vec![])));
+  }
+  buffer.into_value()
+}
+
+/// Highest object number written, the limit of the PDF 1.7 reference (appendix C). Past it readers
+/// may fail, lopdf writes an xref entry for every number below the highest and numbers the objects
+/// it adds after the highest without checking for overflow.
+pub const MAX_OBJECT_ID: u32 = 8_388_607;
+
+/// `value` as an integer, failing with `InvalidArg` outside `min..=max`. JS numbers read straight
+/// as `u32` wrap when negative and truncate fractions.
+pub fn integer_in(value: f64, name: &str, min: u32, max: u32) -> napi::Result<u32> {
+  if value.fract() != 0.0 || !(min as f64..=max as f64).contains(&value) {
+    return Err(napi::Error::new(
+      napi::Status::InvalidArg,
+      format!("{} must be an integer from {} to {}, got {}", name, min, max, value),
+    ));
+  }
+  Ok(value as u32)
+}
+
+/// The optional integer property `name`, checked with `integer_in`
+pub fn integer_property(object: &JsObject, name: &str, min: u32, max: u32) -> napi::Result<Option<u32>> {
+  object
+      .get_named_property::<Option<f64>>(name)?
+      .map(|value| integer_in(value, name, min, max))
+      .transpose()
+}
+
+/// Load the pdf by memory
+pub fn load_document(buffer: &[u8]) -> Result<Document> {
+  let mut document = Document::load_mem(buffer)
+      .map_err(|err| PdfError::new(ErrorCode::InvalidPdf, format!("Invalid PDF: {}", err)))?;
+  repair::recover_hybrid_objects(buffer, &mut document);
+  // lopdf can't decrypt, the strings and streams of an encrypted document would be garbage
+  if document.trailer.has(b"Encrypt") {
+    return Err(PdfError::new(
+      ErrorCode::EncryptedNoPassword,
+      "The document is encrypted",
+    ));
+  }
+  Ok(document)
+}
+
+/// How a document is serialized, from the `noObjectStreams`, `noCompression`, `verify` and
+/// `producerInfo` options
+#[derive(Clone)]
+pub struct SaveOptions {
+  /// Drop the object and cross-reference streams read from the source. lopdf writes their objects
+  /// and a classic xref table either way, so this only removes the containers.
+  pub no_object_streams: bool,
+  /// Write the streams decoded (images and filters lopdf can't decode excepted) instead of compressing them
+  pub no_compression: bool,
+  /// Parse the written document back with `verify_written` before handing it out, on by default
+  pub verify: bool,
+  /// Changes to the `setProducerInfo` settings for this call only
+  pub producer: ProducerInfo,
+}
+
+impl Default for SaveOptions {
+  fn default() -> Self {
+    SaveOptions {
+      no_object_streams: false,
+      no_compression: false,
+      verify: true,
+      producer: ProducerInfo::default(),
+    }
+  }
+}
+
+impl SaveOptions {
+  pub fn from_js(options: &JsObject) -> napi::Result<Self> {
+    Ok(SaveOptions {
+      no_object_streams: options.get_named_property::<Option<bool>>("noObjectStreams")?.unwrap_or(false),
+      no_compression: options.get_named_property::<Option<bool>>("noCompression")?.unwrap_or(false),
+      verify: options.get_named_property::<Option<bool>>("verify")?.unwrap_or(true),
+      producer: match options.get_named_property::<Option<JsObject>>("producerInfo")? {
+        Some(info) => ProducerInfo::from_js(&info)?,
+        None => ProducerInfo::default(),
+      },
+    })
+  }
+}
+
+/// Get a document ready to be written: refuse certified documents, write the producer and apply
+/// the save options
+pub fn prepare_document(document: &mut Document, save: &SaveOptions) -> Result<()> {
+  refuse_certified(document)?;
+  producer::stamp(document, &save.producer)?;
+  if save.no_object_streams {
+    object_streams::expand_object_streams_in(document);
+  }
+  if save.no_compression {
+    document.decompress();
+  } else {
+    document.compress();
+  }
+  Ok(())
+}
+
+/// Serialize a document back into a buffer
+pub fn save_document(document: &mut Document, save: SaveOptions) -> Result<Vec<u8>> {
+  prepare_document(document, &save)?;
+  let mut target: Vec<u8> = vec![];
+  document.save_to(&mut target)?;
+  if save.verify {
+    verify_written(document, &target)?;
+  }
+  Ok(target)
+}
+
+/// Serialize a document straight to the file at `path`, which is removed when it fails the check
+/// of `verify_written`
+pub fn save_document_to_file<P: AsRef<Path>>(document: &mut Document, path: P, save: SaveOptions) -> Result<()> {
+  prepare_document(document, &save)?;
+  document.save(&path)?;
+  if save.verify {
+    let verified = fs::read(&path).map_err(PdfError::from).and_then(|written| verify_written(document, &written));
+    if verified.is_err() {
+      fs::remove_file(&path).ok();
+    }
+    verified?;
+  }
+  Ok(())
+}
+
+/// Append an object in PDF syntax, as lopdf writes it. lopdf only exposes its object writer
+/// through content streams, an operation with the object as the only operand and no operator.
+pub fn write_object(target: &mut Vec<u8>, object: &Object) -> Result<()> {
+  let mut object = object.clone();
+  if let Object::Stream(ref mut stream) = object {
+    stream.dict.set("Length", stream.content.len() as i64);
+  }
+  let encoded = Content {
+    operations: vec![Operation::new("", vec![object])],
+  }
+  .encode()?;
+  // The operand is followed by a space and the end of line of the missing operator
+  target.extend_from_slice(&encoded[..encoded.len() - 2]);
+  Ok(())
+}
+
+/// Append an object as `N G obj ... endobj`, returning the offset it starts at
+pub fn write_indirect_object(target: &mut Vec<u8>, (id, generation): ObjectId, object: &Object) -> Result<usize> {
+  let offset = target.len();
+  target.extend_from_slice(format!("{} {} obj\n", id, generation).as_bytes());
+  write_object(target, object)?;
+  target.extend_from_slice(b"\nendobj\n");
+  Ok(offset)
+}
+
+/// Parse the bytes written for `document` back and check its catalog and pages are all there, so
+/// an object lopdf left out (it never writes objects typed `ObjStm`, `XRef` or `Linearized`) fails
+/// the operation instead of returning a broken file
+pub fn verify_written(document: &Document, written: &[u8]) -> Result<()> {
+  let broken = |reason: String| {
+    PdfError::new(ErrorCode::GenericFailure, format!("The written document is broken: {}", reason))
+  };
+  let reloaded = Document::load_mem(written).map_err(|err| broken(format!("it doesn't parse ({})", err)))?;
+  let pages_root = reloaded.catalog().and_then(|catalog| catalog.get(b"Pages")).and_then(Object::as_reference);
+  if pages_root.and_then(|id| reloaded.get_dictionary(id)).is_err() {
+    return Err(broken("its catalog or page tree is missing".to_owned()));
+  }
+  let (expected, found) = (document.get_pages().len(), reloaded.get_pages().len());
+  if found != expected {
+    return Err(broken(format!("it has {} pages instead of {}", found, expected)));
+  }
+  Ok(())
+}
+
+/// Fail when rewriting the document would break its certification signature
+pub fn refuse_certified(document: &Document) -> Result<()> {
+  if incremental::docmdp_permissions(document).is_some() {
+    return Err(PdfError::new(
+      ErrorCode::CertifiedDocument,
+      "The document is certified and rewriting it would break the certification, save it with `incremental: true`",
+    ));
+  }
+  Ok(())
+}
+
+/// Where and how an operation writes its result, from the output options shared by all operations
+#[derive(Default)]
+pub struct Output {
+  pub path: Option<String>,
+  /// Append the changes to the original bytes instead of rewriting the file
+  pub incremental: bool,
+  pub save: SaveOptions,
+}
+
+impl Output {
+  pub fn from_js(options: &JsObject) -> napi::Result<Self> {
+    Ok(Output {
+      path: options.get_named_property::<Option<String>>("outPath")?,
+      incremental: options.get_named_property::<Option<bool>>("incremental")?.unwrap_or(false),
+      save: SaveOptions::from_js(options)?,
+    })
+  }
+}
+
+/// Read the output options of an optional options argument
+pub fn output(options: &Option<JsObject>) -> napi::Result<Output> {
+  match options {
+    Some(options) => Output::from_js(options),
+    None => Ok(Output::default()),
+  }
+}
+
+/// Return the document to JS as a Buffer, or write it straight to `out_path` and return `undefined`
+pub fn output_document(
+  env: &Env, document: &mut Document, out_path: Option<String>, save: SaveOptions,
+) -> napi::Result<JsUnknown> {
+  match out_path {
+    Some(path) => {
+      save_document_to_file(document, path, save).or_throw(env)?;
+      Ok(env.get_undefined()?.into_unknown())
+    }
+    None => {
+      let target = save_document(document, save).or_throw(env)?;
+      Ok(env.create_buffer_with_data(target)?.into_raw().into_unknown())
+    }
+  }
+}
+
+/// Output a document loaded from `source`, as an incremental update of it when requested
+pub fn output_update(env: &Env, source: &[u8], document: &mut Document, output: &Output) -> napi::Result<JsUnknown> {
+  if !output.incremental {
+    return output_document(env, document, output.path.clone(), output.save.clone());
+  }
+  let target = incremental::save_incremental(source, document, output.save.clone()).or_throw(env)?;
+  if output.save.verify {
+    verify_written(document, &target).or_throw(env)?;
+  }
+  match &output.path {
+    Some(path) => {
+      fs::write(path, target).map_err(PdfError::from).or_throw(env)?;
+      Ok(env.get_undefined()?.into_unknown())
+    }
+    None => Ok(env.create_buffer_with_data(target)?.into_raw().into_unknown()),
+  }
+}
+
+/// Point every reference found in `object` at its replacement in `replace`
+pub fn replace_references(object: &mut Object, replace: &BTreeMap<ObjectId, ObjectId>) {
+  match object {
+    Object::Reference(id) => {
+      if let Some(new_id) = replace.get(id) {
+        *id = *new_id;
+      }
+    }
+    Object::Array(array) => {
+      for item in array.iter_mut() {
+        replace_references(item, replace);
+      }
+    }
+    Object::Dictionary(dictionary) => {
+      for (_, value) in dictionary.iter_mut() {
+        replace_references(value, replace);
+      }
+    }
+    Object::Stream(stream) => {
+      for (_, value) in stream.dict.iter_mut() {
+        replace_references(value, replace);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// Decode a PDF text string (UTF-16BE with BOM, otherwise PDFDocEncoding treated as Latin-1)
+pub fn decode_text_string(bytes: &[u8]) -> String {
+  if bytes.starts_with(&[0xfe, 0xff]) {
+    let units = bytes[2..]
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]))
+        .collect::<Vec<u16>>();
+    String::from_utf16_lossy(&units)
+  } else {
+    bytes.iter().map(|&byte| byte as char).collect()
+  }
+}
+
+/// Encode a PDF text string, UTF-16BE with BOM unless it is plain ASCII
+pub fn encode_text_string(text: &str) -> Object {
+  if text.is_ascii() {
+    return Object::string_literal(text);
+  }
+  let mut bytes = vec![0xfe, 0xff];
+  for unit in text.encode_utf16() {
+    bytes.extend_from_slice(&unit.to_be_bytes());
+  }
+  Object::string_literal(bytes)
+}
+
+/// Collect the objects referenced by `object`
+pub fn collect_references(object: &Object, references: &mut Vec<ObjectId>) {
+  match object {
+    Object::Reference(id) => references.push(*id),
+    Object::Array(array) => {
+      for item in array {
+        collect_references(item, references);
+      }
+    }
+    Object::Dictionary(dictionary) => {
+      for (_, value) in dictionary.iter() {
+        collect_references(value, references);
+      }
+    }
+    Object::Stream(stream) => {
+      for (_, value) in stream.dict.iter() {
+        collect_references(value, references);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// Give the objects of a document the ids of `ids`, keeping those it leaves out
+pub fn renumber_objects_as(document: &mut Document, ids: &BTreeMap<ObjectId, ObjectId>) {
+  let objects = std::mem::take(&mut document.objects);
+  for (id, mut object) in objects {
+    replace_references(&mut object, ids);
+    document.objects.insert(ids.get(&id).copied().unwrap_or(id), object);
+  }
+  for (_, value) in document.trailer.iter_mut() {
+    replace_references(value, ids);
+  }
+}
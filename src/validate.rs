@@ -0,0 +1,190 @@
+use std::collections::BTreeSet;
+
+use lopdf::{Document, Object, ObjectId};
+use napi::{CallContext, JsBoolean, JsBuffer, JsObject, Result};
+
+use crate::{page, repair};
+use crate::utils::{buffer_value, load_document};
+
+/// Readers look for the `%PDF-` header in the first kilobyte, after whatever junk precedes it
+const HEADER_WINDOW: usize = 1024;
+
+/// Whether the data starts like a PDF, however damaged the rest of it
+fn has_pdf_header(buffer: &[u8]) -> bool {
+  buffer[..buffer.len().min(HEADER_WINDOW)].windows(5).any(|window| window == b"%PDF-")
+}
+
+#[js_function(1)]
+pub fn try_load(ctx: CallContext) -> Result<JsBoolean> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  // Anything else isn't parsed at all
+  let openable = has_pdf_header(&buffer)
+      && load_document(&buffer).is_ok_and(|document| !document.get_pages().is_empty());
+  ctx.env.get_boolean(openable)
+}
+
+#[js_function(1)]
+pub fn validate(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let issues = match Document::load_mem(&buffer) {
+    Ok(mut document) => {
+      repair::recover_hybrid_objects(&buffer, &mut document);
+      validate_document(&document)
+    }
+    Err(err) => vec![format!("Invalid PDF: {}", err)],
+  };
+  let mut result = ctx.env.create_object()?;
+  result.set_named_property("ok", ctx.env.get_boolean(issues.is_empty())?)?;
+  result.set_named_property("isPdf", ctx.env.get_boolean(has_pdf_header(&buffer))?)?;
+  let mut list = ctx.env.create_array_with_length(issues.len())?;
+  for (index, issue) in issues.iter().enumerate() {
+    list.set_element(index as u32, ctx.env.create_string(issue)?)?;
+  }
+  result.set_named_property("issues", list)?;
+  Ok(result)
+}
+
+fn describe(id: ObjectId) -> String {
+  format!("{} {} R", id.0, id.1)
+}
+
+/// Check the structural invariants of a document, collecting every violation
+fn validate_document(document: &Document) -> Vec<String> {
+  let mut issues = vec![];
+  if document.trailer.has(b"Encrypt") {
+    issues.push("The document is encrypted".to_owned());
+  }
+  check_references(document, &mut issues);
+  let root_id = match document.trailer.get(b"Root").and_then(Object::as_reference) {
+    Ok(id) => id,
+    Err(_) => {
+      issues.push("The trailer has no /Root".to_owned());
+      return issues;
+    }
+  };
+  let pages_id = match document.get_dictionary(root_id) {
+    Ok(catalog) => match catalog.get(b"Pages").and_then(Object::as_reference) {
+      Ok(id) => id,
+      Err(_) => {
+        issues.push(format!("The catalog {} has no /Pages", describe(root_id)));
+        return issues;
+      }
+    },
+    Err(_) => {
+      issues.push(format!("The /Root {} is not a dictionary", describe(root_id)));
+      return issues;
+    }
+  };
+  let mut pages = vec![];
+  let mut visited = BTreeSet::new();
+  visited.insert(pages_id);
+  check_page_tree(document, pages_id, &mut visited, &mut pages, &mut issues);
+  for (index, page_id) in pages.into_iter().enumerate() {
+    let media_box = page::inherited_attribute(document, page_id, b"MediaBox").and_then(page::rect_from_object);
+    if media_box.is_none() {
+      issues.push(format!("Page {} ({}) has no valid /MediaBox", index + 1, describe(page_id)));
+    }
+  }
+  issues
+}
+
+/// Collect the references in `object` to objects missing from the document
+fn missing_references(document: &Document, object: &Object, missing: &mut BTreeSet<ObjectId>) {
+  match object {
+    Object::Reference(id) if !document.objects.contains_key(id) => {
+      missing.insert(*id);
+    }
+    Object::Array(array) => {
+      for item in array {
+        missing_references(document, item, missing);
+      }
+    }
+    Object::Dictionary(dictionary) => {
+      for (_, value) in dictionary.iter() {
+        missing_references(document, value, missing);
+      }
+    }
+    Object::Stream(stream) => {
+      for (_, value) in stream.dict.iter() {
+        missing_references(document, value, missing);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// Report dangling references in the objects and the trailer
+fn check_references(document: &Document, issues: &mut Vec<String>) {
+  for (id, object) in document.objects.iter() {
+    let mut missing = BTreeSet::new();
+    missing_references(document, object, &mut missing);
+    for missing_id in missing {
+      issues.push(format!("{} references the missing object {}", describe(*id), describe(missing_id)));
+    }
+  }
+  let mut missing = BTreeSet::new();
+  for (_, value) in document.trailer.iter() {
+    missing_references(document, value, &mut missing);
+  }
+  for missing_id in missing {
+    issues.push(format!("The trailer references the missing object {}", describe(missing_id)));
+  }
+}
+
+/// Walk a `Pages` node, checking its `/Kids` and `/Count`. Returns the number of pages below it.
+fn check_page_tree(
+  document: &Document,
+  node_id: ObjectId,
+  visited: &mut BTreeSet<ObjectId>,
+  pages: &mut Vec<ObjectId>,
+  issues: &mut Vec<String>,
+) -> i64 {
+  let node = match document.get_dictionary(node_id) {
+    Ok(node) => node,
+    Err(_) => {
+      issues.push(format!("The page tree node {} is not a dictionary", describe(node_id)));
+      return 0;
+    }
+  };
+  if node.type_name().ok() == Some("Page") || (!node.has(b"Kids") && node.type_name().is_err()) {
+    pages.push(node_id);
+    return 1;
+  }
+  let kids = match node.get(b"Kids").and_then(Object::as_array) {
+    Ok(kids) => kids,
+    Err(_) => {
+      issues.push(format!("The Pages node {} has no /Kids", describe(node_id)));
+      return 0;
+    }
+  };
+  let mut count = 0;
+  for kid in kids {
+    match kid.as_reference() {
+      Ok(kid_id) if !visited.insert(kid_id) => {
+        issues.push(format!("The page tree node {} appears more than once", describe(kid_id)));
+      }
+      Ok(kid_id) => {
+        let parent = document
+            .get_dictionary(kid_id)
+            .and_then(|kid| kid.get(b"Parent"))
+            .and_then(Object::as_reference);
+        if document.objects.contains_key(&kid_id) && parent.ok() != Some(node_id) {
+          issues.push(format!("The /Parent of {} doesn't point at {}", describe(kid_id), describe(node_id)));
+        }
+        count += check_page_tree(document, kid_id, visited, pages, issues);
+      }
+      Err(_) => issues.push(format!("The Pages node {} has a kid that isn't a reference", describe(node_id))),
+    }
+  }
+  match node.get(b"Count").and_then(Object::as_i64) {
+    Ok(declared) if declared == count => {}
+    Ok(declared) => issues.push(format!(
+      "The Pages node {} has /Count {} but {} pages",
+      describe(node_id),
+      declared,
+      count
+    )),
+    Err(_) => issues.push(format!("The Pages node {} has no /Count", describe(node_id))),
+  }
+  count
+}
@@ -0,0 +1,13 @@
+// `#[js_function(0)]` expands to a zero-length argument array
+#![allow(clippy::zero_repeat_side_effects)]
+
+use napi::{CallContext, JsObject, Result};
+
+#[js_function(0)]
+pub fn version(ctx: CallContext) -> Result<JsObject> {
+  let mut info = ctx.env.create_object()?;
+  info.set_named_property("crate", ctx.env.create_string(env!("CARGO_PKG_VERSION"))?)?;
+  // Read from Cargo.lock by the build script
+  info.set_named_property("lopdf", ctx.env.create_string(env!("LOPDF_VERSION"))?)?;
+  Ok(info)
+}
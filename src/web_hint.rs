@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use napi::{CallContext, Error, JsBuffer, JsObject, JsUnknown, Result, Status};
+
+use crate::error::{self, ErrorCode, OrThrow, PdfError};
+use crate::object_streams::expand_object_streams_in;
+use crate::repair;
+use crate::utils::{
+  buffer_value, collect_references, load_document, output, output_update, renumber_objects_as, save_document,
+  write_indirect_object, SaveOptions,
+};
+
+#[js_function(3)]
+pub fn set_web_optimized_hint(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let value = ctx.get::<bool>(1)?;
+  let output = output(&ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  remove_linearization(&buffer, &mut document);
+  if !value {
+    return output_update(ctx.env, &buffer, &mut document, &output);
+  }
+  if output.incremental {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "setWebOptimizedHint reorders the whole file, incremental isn't supported".to_owned(),
+    ));
+  }
+  let target = save_web_optimized(&mut document, output.save).or_throw(ctx.env)?;
+  match output.path {
+    Some(path) => {
+      fs::write(path, target).map_err(PdfError::from).or_throw(ctx.env)?;
+      Ok(ctx.env.get_undefined()?.into_unknown())
+    }
+    None => Ok(ctx.env.create_buffer_with_data(target)?.into_raw().into_unknown()),
+  }
+}
+
+/// Remove the linearization dictionary and the hint stream it locates by offset, which nothing
+/// references. Both describe the file as it was read, not as it will be written.
+fn remove_linearization(buffer: &[u8], document: &mut Document) {
+  let mut removed = vec![];
+  let mut hint_offsets = vec![];
+  for (id, object) in document.objects.iter() {
+    let dictionary = match object {
+      Object::Dictionary(dictionary) if dictionary.has(b"Linearized") => dictionary,
+      _ => continue,
+    };
+    removed.push(*id);
+    let hints = dictionary.get(b"H").and_then(Object::as_array).ok();
+    hint_offsets.extend(hints.and_then(|hints| hints.first()).and_then(|offset| offset.as_i64().ok()));
+  }
+  if !hint_offsets.is_empty() {
+    for (&number, &(generation, offset)) in repair::object_offsets(buffer).iter() {
+      let is_stream = document.get_object((number, generation)).is_ok_and(|hint| hint.as_stream().is_ok());
+      if is_stream && hint_offsets.contains(&(offset as i64)) {
+        removed.push((number, generation));
+      }
+    }
+  }
+  for id in removed {
+    document.objects.remove(&id);
+  }
+}
+
+/// The objects the first page needs, in the order it reaches them, without the page tree, the
+/// other pages or the catalog
+fn first_page_objects(document: &Document, page_id: ObjectId) -> Vec<ObjectId> {
+  let mut ordered = vec![];
+  let mut visited = BTreeSet::new();
+  let mut pending = vec![page_id];
+  while let Some(id) = pending.pop() {
+    let object = match document.get_object(id) {
+      Ok(object) => object,
+      Err(_) => continue,
+    };
+    let skipped = match object.type_name() {
+      Ok("Page") => id != page_id,
+      Ok("Pages") | Ok("Catalog") => true,
+      _ => false,
+    };
+    if skipped || !visited.insert(id) {
+      continue;
+    }
+    ordered.push(id);
+    let mut references = vec![];
+    collect_references(object, &mut references);
+    // Popped in the order they appear
+    pending.extend(references.into_iter().rev());
+  }
+  ordered
+}
+
+/// Write the document with a linearization dictionary first, then the catalog, the first page and
+/// what it uses, then everything else. There are no hint tables and a single cross-reference
+/// table, so viewers see the `/Linearized` marker but this isn't full Fast Web View.
+fn save_web_optimized(document: &mut Document, save: SaveOptions) -> error::Result<Vec<u8>> {
+  // Objects of object streams would be written ahead of their place
+  expand_object_streams_in(document);
+  let pages = document.get_pages();
+  let catalog_id = document.trailer.get(b"Root").and_then(Object::as_reference)?;
+  let mut order = vec![catalog_id];
+  if let Some(&page_id) = pages.get(&1) {
+    order.extend(first_page_objects(document, page_id));
+  }
+  let front = order.iter().copied().collect::<BTreeSet<_>>();
+  order.extend(document.objects.keys().copied().filter(|id| !front.contains(id)));
+  // Object 1 is left for the linearization dictionary
+  let ids = order
+      .iter()
+      .enumerate()
+      .map(|(index, id)| (*id, (index as u32 + 2, 0)))
+      .collect::<BTreeMap<_, _>>();
+  renumber_objects_as(document, &ids);
+  document.max_id = order.len() as u32 + 1;
+  let first_page = pages.get(&1).and_then(|page_id| ids.get(page_id)).map_or(0, |id| id.0);
+  let target = save_document(document, save)?;
+  // lopdf writes the objects by number, the first one after the first page's ends its section
+  insert_linearization(&target, first_page, front.len() as u32 + 2, pages.len())
+      .ok_or_else(|| PdfError::new(ErrorCode::GenericFailure, "Unexpected layout of the written file"))
+}
+
+/// Insert the linearization dictionary as object 1 right after the header of `file`, which lopdf
+/// wrote with object 1 free (it never writes a linearization dictionary itself), shifting the
+/// offsets of the cross-reference table. `/E` is where object `rest_start`, the first one after
+/// the first page's, starts and `/T` the end of line before the first cross-reference entry.
+fn insert_linearization(file: &[u8], first_page: u32, rest_start: u32, page_count: usize) -> Option<Vec<u8>> {
+  const ENTRY_LENGTH: usize = 20;
+  let header_end = file.iter().position(|&byte| byte == b'\n')? + 1;
+  let xref_start = file.windows(6).rposition(|window| window == b"\nxref\n")? + 1;
+  let subsection_end = xref_start + 5 + file[xref_start + 5..].iter().position(|&byte| byte == b'\n')? + 1;
+  let size = std::str::from_utf8(&file[xref_start + 5..subsection_end - 1])
+      .ok()?
+      .split_ascii_whitespace()
+      .nth(1)?
+      .parse::<usize>()
+      .ok()?;
+  let trailer_start = subsection_end + size * ENTRY_LENGTH;
+  let trailer_end = file.windows(9).rposition(|window| window == b"startxref")?;
+  if size < 2 || trailer_start > trailer_end {
+    return None;
+  }
+  let entries = file[subsection_end..trailer_start]
+      .chunks(ENTRY_LENGTH)
+      .map(|entry| Some((std::str::from_utf8(&entry[..10]).ok()?.parse::<usize>().ok()?, entry)))
+      .collect::<Option<Vec<_>>>()?;
+  // Offsets before the insertion
+  let first_page_end = match entries.get(rest_start as usize) {
+    Some(&(offset, entry)) if entry[17] == b'n' => offset,
+    _ => xref_start,
+  };
+  let first_entry = subsection_end - 1;
+  // `/L` is the length of the file and the offsets grow by the length of the object, which both
+  // depend on the digits written for them
+  let (mut length, mut shift) = (0, 0);
+  loop {
+    let mut linearization = Dictionary::new();
+    linearization.set("Linearized", 1);
+    linearization.set("L", length as i64);
+    linearization.set("O", first_page);
+    linearization.set("E", (first_page_end + shift) as i64);
+    linearization.set("N", page_count as i64);
+    linearization.set("T", (first_entry + shift) as i64);
+    let mut object = vec![];
+    write_indirect_object(&mut object, (1, 0), &Object::Dictionary(linearization)).ok()?;
+    if object.len() != shift {
+      shift = object.len();
+      continue;
+    }
+    let mut written = Vec::with_capacity(file.len() + shift);
+    written.extend_from_slice(&file[..header_end]);
+    written.extend_from_slice(&object);
+    written.extend_from_slice(&file[header_end..subsection_end]);
+    for (number, &(offset, entry)) in entries.iter().enumerate() {
+      match (number, entry[17]) {
+        (1, _) => written.extend(format!("{:010} 00000 n \n", header_end).into_bytes()),
+        (_, b'n') => {
+          written.extend(format!("{:010}", offset + shift).into_bytes());
+          written.extend_from_slice(&entry[10..]);
+        }
+        _ => written.extend_from_slice(entry),
+      }
+    }
+    written.extend_from_slice(&file[trailer_start..trailer_end]);
+    written.extend(format!("startxref\n{}\n%%EOF", xref_start + shift).into_bytes());
+    if written.len() == length {
+      return Some(written);
+    }
+    length = written.len();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils;
+
+  /// A file laid out as lopdf writes it, objects 2 to 4 with object 1 free
+  fn written() -> Vec<u8> {
+    let mut file = b"%PDF-1.4\n".to_vec();
+    let mut offsets = vec![];
+    let objects = [
+      (2, "<</Pages 3 0 R/Type/Catalog>>"),
+      (3, "<</Count 0/Kids[]/Type/Pages>>"),
+      (4, "(after the first page)"),
+    ];
+    for (number, body) in objects {
+      offsets.push(file.len());
+      file.extend(format!("{} 0 obj\n{}\nendobj\n", number, body).into_bytes());
+    }
+    let xref_start = file.len();
+    file.extend(b"xref\n0 5\n0000000000 65535 f \n0000000000 00000 f \n");
+    for offset in offsets {
+      file.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    file.extend(format!("trailer\n<</Root 2 0 R/Size 5>>\nstartxref\n{}\n%%EOF", xref_start).into_bytes());
+    file
+  }
+
+  fn linearization(file: &[u8]) -> Dictionary {
+    let document = Document::load_mem(file).unwrap();
+    document.get_dictionary((1, 0)).unwrap().clone()
+  }
+
+  fn entry(dictionary: &Dictionary, key: &[u8]) -> usize {
+    dictionary.get(key).and_then(Object::as_i64).unwrap() as usize
+  }
+
+  #[test]
+  fn inserts_the_linearization_dictionary_after_the_header() {
+    let file = insert_linearization(&written(), 3, 4, 1).unwrap();
+    assert!(file.starts_with(b"%PDF-1.4\n1 0 obj\n<</Linearized 1/L "));
+    let linearization = linearization(&file);
+    assert_eq!(entry(&linearization, b"L"), file.len());
+    assert_eq!(entry(&linearization, b"O"), 3);
+    assert_eq!(entry(&linearization, b"N"), 1);
+    assert!(!linearization.has(b"H"));
+    // The first page's section ends where object 4 starts
+    assert!(file[entry(&linearization, b"E")..].starts_with(b"4 0 obj\n"));
+    let first_entry = entry(&linearization, b"T");
+    assert!(file[..=first_entry].ends_with(b"xref\n0 5\n"));
+    assert!(file[first_entry + 1..].starts_with(b"0000000000 65535 f \n"));
+  }
+
+  #[test]
+  fn shifts_the_cross_reference_entries() {
+    let file = insert_linearization(&written(), 3, 4, 1).unwrap();
+    let offsets = repair::object_offsets(&file);
+    let document = Document::load_mem(&file).unwrap();
+    for (number, (generation, offset)) in offsets {
+      assert!(document.get_object((number, generation)).is_ok());
+      assert!(file[offset..].starts_with(format!("{} 0 obj\n", number).as_bytes()));
+    }
+    let xref_start = file.windows(6).rposition(|window| window == b"\nxref\n").unwrap() + 1;
+    assert!(file.ends_with(format!("startxref\n{}\n%%EOF", xref_start).as_bytes()));
+  }
+
+  #[test]
+  fn ends_the_first_page_at_the_xref_table_without_other_objects() {
+    let file = insert_linearization(&written(), 3, 5, 1).unwrap();
+    let linearization = linearization(&file);
+    assert!(file[entry(&linearization, b"E")..].starts_with(b"xref\n"));
+  }
+
+  #[test]
+  fn writes_the_first_page_objects_before_the_others() {
+    let mut document = test_utils::document(3);
+    let file = save_web_optimized(&mut document, SaveOptions::default()).unwrap();
+    let document = Document::load_mem(&file).unwrap();
+    let linearization = linearization(&file);
+    let pages = document.get_pages();
+    assert_eq!(entry(&linearization, b"O"), pages[&1].0 as usize);
+    assert_eq!(entry(&linearization, b"N"), 3);
+    // The first page, its content and its font come before `/E`, the other pages after
+    let offsets = repair::object_offsets(&file);
+    let end = entry(&linearization, b"E");
+    let first_page = document.get_dictionary(pages[&1]).unwrap();
+    let content_id = first_page.get(b"Contents").and_then(Object::as_reference).unwrap();
+    let font_id = first_page
+        .get(b"Resources")
+        .and_then(Object::as_dict)
+        .and_then(|resources| resources.get(b"Font"))
+        .and_then(Object::as_dict)
+        .and_then(|fonts| fonts.get(b"F1"))
+        .and_then(Object::as_reference)
+        .unwrap();
+    for id in [pages[&1], content_id, font_id, test_utils::catalog_id(&document)] {
+      assert!(offsets[&id.0].1 < end, "{:?}", id);
+    }
+    for id in [pages[&2], pages[&3]] {
+      assert!(offsets[&id.0].1 >= end, "{:?}", id);
+    }
+  }
+}
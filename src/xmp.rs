@@ -0,0 +1,192 @@
+use chrono::{SecondsFormat, Utc};
+use lopdf::{Dictionary, Document, Object, Stream};
+use napi::{CallContext, JsBuffer, JsObject, JsUnknown, Result, ValueType};
+
+use crate::error::{self, OrThrow};
+use crate::filters;
+use crate::metadata::info_dictionary_mut;
+use crate::utils::{buffer_value, encode_text_string, load_document, output, output_update};
+
+/// Namespace of the merge provenance properties
+const PROVENANCE_NAMESPACE: &str = "http://ns.vibes-pdf-utils/provenance/1.0/";
+
+/// Namespace of the PDF/A identification schema, `pdfaid:part` and `pdfaid:conformance`
+const PDFA_ID_NAMESPACE: &str = "http://www.aiim.org/pdfa/ns/id/";
+
+fn escape(text: &str) -> String {
+  text
+      .replace('&', "&amp;")
+      .replace('<', "&lt;")
+      .replace('>', "&gt;")
+      .replace('"', "&quot;")
+}
+
+/// The Info entries of a merged document, kept in the provenance with `sourceInfo`
+pub struct SourceInfo {
+  pub index: usize,
+  pub title: Option<String>,
+  pub author: Option<String>,
+}
+
+/// An XMP packet recording that `producer` merged the document from `source_count` documents, with
+/// the titles of those that have one and the Info entries of `sources`
+pub fn provenance_packet(producer: &str, source_count: usize, titles: &[String], sources: &[SourceInfo]) -> String {
+  let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+  let producer = escape(producer);
+  let mut packet = String::new();
+  packet.push_str("<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n");
+  packet.push_str("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n");
+  packet.push_str(" <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n");
+  packet.push_str("  <rdf:Description rdf:about=\"\"\n");
+  packet.push_str("    xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\"\n");
+  packet.push_str("    xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"\n");
+  packet.push_str(&format!("    xmlns:pdfu=\"{}\">\n", PROVENANCE_NAMESPACE));
+  packet.push_str(&format!("   <pdf:Producer>{}</pdf:Producer>\n", producer));
+  packet.push_str(&format!("   <xmp:CreatorTool>{}</xmp:CreatorTool>\n", producer));
+  packet.push_str(&format!("   <xmp:MetadataDate>{}</xmp:MetadataDate>\n", now));
+  packet.push_str(&format!("   <xmp:ModifyDate>{}</xmp:ModifyDate>\n", now));
+  packet.push_str(&format!("   <pdfu:SourceCount>{}</pdfu:SourceCount>\n", source_count));
+  if !titles.is_empty() {
+    packet.push_str("   <pdfu:Sources>\n    <rdf:Seq>\n");
+    for title in titles {
+      packet.push_str(&format!("     <rdf:li>{}</rdf:li>\n", escape(title)));
+    }
+    packet.push_str("    </rdf:Seq>\n   </pdfu:Sources>\n");
+  }
+  if !sources.is_empty() {
+    packet.push_str("   <pdfu:SourceInfo>\n    <rdf:Seq>\n");
+    for source in sources {
+      packet.push_str("     <rdf:li rdf:parseType=\"Resource\">\n");
+      packet.push_str(&format!("      <pdfu:Index>{}</pdfu:Index>\n", source.index));
+      for (property, value) in [("Title", &source.title), ("Author", &source.author)] {
+        if let Some(value) = value {
+          packet.push_str(&format!("      <pdfu:{0}>{1}</pdfu:{0}>\n", property, escape(value)));
+        }
+      }
+      packet.push_str("     </rdf:li>\n");
+    }
+    packet.push_str("    </rdf:Seq>\n   </pdfu:SourceInfo>\n");
+  }
+  packet.push_str("  </rdf:Description>\n </rdf:RDF>\n</x:xmpmeta>\n");
+  packet.push_str("<?xpacket end=\"w\"?>");
+  packet
+}
+
+/// Replace the document's XMP with `packet` and set the Info `/Producer` it declares, `producer`
+pub fn set_provenance(document: &mut Document, producer: &str, packet: String) -> error::Result<()> {
+  set_metadata_stream(document, packet)?;
+  info_dictionary_mut(document)?.set("Producer", encode_text_string(producer));
+  Ok(())
+}
+
+/// Point the catalog `/Metadata` at a new stream holding `packet`
+fn set_metadata_stream(document: &mut Document, packet: String) -> error::Result<()> {
+  let mut dictionary = Dictionary::new();
+  dictionary.set("Type", Object::Name(b"Metadata".to_vec()));
+  dictionary.set("Subtype", Object::Name(b"XML".to_vec()));
+  // Left uncompressed so tools scanning files for packets find it
+  let stream = Stream::new(dictionary, packet.into_bytes()).with_compression(false);
+  let metadata_id = document.add_object(stream);
+  let catalog_id = document.trailer.get(b"Root").and_then(Object::as_reference)?;
+  document
+      .get_object_mut(catalog_id)
+      .and_then(Object::as_dict_mut)?
+      .set("Metadata", metadata_id);
+  Ok(())
+}
+
+#[js_function(2)]
+pub fn detach_xmp(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let output = output(&ctx.get::<Option<JsObject>>(1)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  let packet = detach_xmp_in(&mut document).or_throw(ctx.env)?;
+  let pdf = output_update(ctx.env, &buffer, &mut document, &output)?;
+  let mut result = ctx.env.create_object()?;
+  if pdf.get_type()? != ValueType::Undefined {
+    result.set_named_property("pdf", pdf)?;
+  }
+  match packet {
+    Some(packet) => result.set_named_property("xmp", ctx.env.create_string(&packet)?)?,
+    None => result.set_named_property("xmp", ctx.env.get_null()?)?,
+  }
+  Ok(result)
+}
+
+/// Remove the catalog `/Metadata` and its stream, returning the packet it held
+pub fn detach_xmp_in(document: &mut Document) -> error::Result<Option<String>> {
+  let packet = metadata_packet(document);
+  let catalog_id = document.trailer.get(b"Root").and_then(Object::as_reference)?;
+  let catalog = document.get_object_mut(catalog_id).and_then(Object::as_dict_mut)?;
+  if let Some(Object::Reference(metadata_id)) = catalog.remove(b"Metadata") {
+    document.objects.remove(&metadata_id);
+  }
+  Ok(packet)
+}
+
+#[js_function(3)]
+pub fn attach_xmp(ctx: CallContext) -> Result<JsUnknown> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let packet = ctx.get::<String>(1)?;
+  let output = output(&ctx.get::<Option<JsObject>>(2)?)?;
+  let mut document = load_document(&buffer).or_throw(ctx.env)?;
+  attach_xmp_in(&mut document, packet).or_throw(ctx.env)?;
+  output_update(ctx.env, &buffer, &mut document, &output)
+}
+
+/// Make `packet` the document's XMP, replacing the stream of the current one
+pub fn attach_xmp_in(document: &mut Document, packet: String) -> error::Result<()> {
+  detach_xmp_in(document)?;
+  set_metadata_stream(document, packet)
+}
+
+#[js_function(1)]
+pub fn pdfa_hints(ctx: CallContext) -> Result<JsObject> {
+  let buffer = buffer_value(ctx.get::<JsBuffer>(0)?)?;
+  let document = load_document(&buffer).or_throw(ctx.env)?;
+  let mut result = ctx.env.create_object()?;
+  let packet = metadata_packet(&document).unwrap_or_default();
+  let part = pdfa_id_property(&packet, "part");
+  result.set_named_property("claimsPdfA", ctx.env.get_boolean(part.is_some())?)?;
+  if let Some(part) = part {
+    result.set_named_property("part", ctx.env.create_string(&part)?)?;
+    if let Some(conformance) = pdfa_id_property(&packet, "conformance") {
+      result.set_named_property("conformance", ctx.env.create_string(&conformance)?)?;
+    }
+  }
+  Ok(result)
+}
+
+/// The XMP packet of the catalog `/Metadata` stream, decompressed
+fn metadata_packet(document: &Document) -> Option<String> {
+  let catalog = document.catalog().ok()?;
+  let (_, metadata) = document.dereference(catalog.get(b"Metadata").ok()?).ok()?;
+  let stream = metadata.as_stream().ok()?;
+  let data = filters::decoded_content(stream);
+  Some(String::from_utf8_lossy(&data).into_owned())
+}
+
+/// A property of the PDF/A identification schema, written as an element or as an attribute of
+/// `rdf:Description`, under whichever prefix the packet binds to the namespace
+fn pdfa_id_property(packet: &str, name: &str) -> Option<String> {
+  let prefix = ["\"", "'"]
+      .iter()
+      .find_map(|quote| {
+        let binding = packet.find(&format!("={0}{1}{0}", quote, PDFA_ID_NAMESPACE))?;
+        let start = packet[..binding].rfind("xmlns:")? + "xmlns:".len();
+        Some(packet[start..binding].trim())
+      })
+      .unwrap_or("pdfaid");
+  let property = format!("{}:{}", prefix, name);
+  let element = format!("<{}>", property);
+  let value = if let Some(start) = packet.find(&element) {
+    let value = &packet[start + element.len()..];
+    &value[..value.find('<')?]
+  } else {
+    let start = packet.find(&format!("{}=", property))? + property.len() + 1;
+    let quote = packet[start..].chars().next()?;
+    let value = &packet[start + 1..];
+    &value[..value.find(quote)?]
+  };
+  Some(value.trim().to_owned()).filter(|value| !value.is_empty())
+}